@@ -0,0 +1,82 @@
+//! GitHub-style alerts (also known as callouts) e.g.
+//!
+//! ```markdown
+//! > [!NOTE]
+//! > Useful information that users should know
+//! ```
+//!
+//! Alerts are regular blockquotes where the first line is just the `[!TYPE]` marker. The marker
+//! gets swapped out for a title, and the quote's bar is drawn in the alert's color
+
+use crate::color::Theme;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Alert {
+    Note,
+    Tip,
+    Important,
+    Warning,
+    Caution,
+}
+
+impl Alert {
+    /// Parses a leading `[!TYPE]` marker (case-insensitive), returning the alert and the rest of
+    /// the text
+    ///
+    /// The marker has to be on its own line, so anything else on the same line means that this
+    /// isn't an alert
+    pub fn from_marker(text: &str) -> Option<(Self, &str)> {
+        let (marker, rest) = text.strip_prefix("[!")?.split_once(']')?;
+        let alert = match marker.to_ascii_lowercase().as_str() {
+            "note" => Self::Note,
+            "tip" => Self::Tip,
+            "important" => Self::Important,
+            "warning" => Self::Warning,
+            "caution" => Self::Caution,
+            _ => return None,
+        };
+        let rest = rest.trim_start_matches([' ', '\t']);
+        match rest.strip_prefix('\n') {
+            Some(rest) => Some((alert, rest)),
+            None => rest.is_empty().then_some((alert, rest)),
+        }
+    }
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Self::Note => "Note",
+            Self::Tip => "Tip",
+            Self::Important => "Important",
+            Self::Warning => "Warning",
+            Self::Caution => "Caution",
+        }
+    }
+
+    pub fn color(self, theme: &Theme) -> u32 {
+        match self {
+            Self::Note => theme.alert_note_color,
+            Self::Tip => theme.alert_tip_color,
+            Self::Important => theme.alert_important_color,
+            Self::Warning => theme.alert_warning_color,
+            Self::Caution => theme.alert_caution_color,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn markers() {
+        assert_eq!(Alert::from_marker("[!NOTE]"), Some((Alert::Note, "")));
+        assert_eq!(
+            Alert::from_marker("[!warning]  \nBe careful"),
+            Some((Alert::Warning, "Be careful"))
+        );
+        // Unknown types and extra text on the marker's line are regular quotes
+        assert_eq!(Alert::from_marker("[!FOO]"), None);
+        assert_eq!(Alert::from_marker("[!TIP] Not on its own line"), None);
+        assert_eq!(Alert::from_marker("[NOTE]"), None);
+    }
+}
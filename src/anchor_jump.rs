@@ -0,0 +1,222 @@
+//! Jumping to any header by picking it out of a list (`GoToAnchor`)
+//!
+//! The list holds every anchor that came from a header, in document order. Typing narrows it down
+//! to the headers whose text contains the query's characters in order (ignoring case)
+
+use std::collections::HashMap;
+
+use crate::keybindings::action::VertDirection;
+use crate::toc::Heading;
+
+/// Font size of the list's text (before hidpi scaling)
+pub const FONT_SIZE: f32 = 14.;
+/// Space above and below each row of the list (before hidpi scaling)
+pub const PADDING: f32 = 3.;
+/// The most headers that get listed at once
+pub const MAX_SHOWN: usize = 10;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    pub title: String,
+    pub y: f32,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AnchorJump {
+    pub query: String,
+    selected: usize,
+}
+
+impl AnchorJump {
+    /// The headers that match the query, in document order
+    pub fn entries(
+        &self,
+        anchors: &HashMap<String, f32>,
+        headings: &HashMap<String, Heading>,
+    ) -> Vec<Entry> {
+        let mut entries: Vec<_> = headings
+            .iter()
+            .filter(|(_, heading)| is_fuzzy_match(&heading.title, &self.query))
+            .filter_map(|(anchor, heading)| {
+                let y = *anchors.get(anchor)?;
+                Some(Entry {
+                    title: heading.title.clone(),
+                    y,
+                })
+            })
+            .collect();
+        entries.sort_by(|a, b| a.y.total_cmp(&b.y).then_with(|| a.title.cmp(&b.title)));
+        entries
+    }
+
+    /// The index of the selected entry out of `len` entries, if there are any
+    pub fn selected(&self, len: usize) -> Option<usize> {
+        (len > 0).then(|| self.selected.min(len - 1))
+    }
+
+    pub fn push(&mut self, c: char) {
+        self.query.push(c);
+        self.selected = 0;
+    }
+
+    pub fn pop(&mut self) {
+        self.query.pop();
+        self.selected = 0;
+    }
+
+    /// Moves the selection up or down through `len` entries, wrapping around at either end
+    pub fn move_selection(&mut self, direction: VertDirection, len: usize) {
+        let Some(selected) = self.selected(len) else {
+            return;
+        };
+        self.selected = match direction {
+            VertDirection::Up => (selected + len - 1) % len,
+            VertDirection::Down => (selected + 1) % len,
+        };
+    }
+
+    /// What the renderer needs to draw the list
+    pub fn list(
+        &self,
+        anchors: &HashMap<String, f32>,
+        headings: &HashMap<String, Heading>,
+    ) -> AnchorList {
+        let entries = self.entries(anchors, headings);
+        let selected = self.selected(entries.len());
+        // Scroll the list along to keep the selection in view
+        let start = selected.map_or(0, |selected| (selected + 1).saturating_sub(MAX_SHOWN));
+        AnchorList {
+            query: self.query.clone(),
+            titles: entries
+                .into_iter()
+                .skip(start)
+                .take(MAX_SHOWN)
+                .map(|entry| entry.title)
+                .collect(),
+            selected: selected.map(|selected| selected - start),
+        }
+    }
+}
+
+fn is_fuzzy_match(title: &str, query: &str) -> bool {
+    let mut title_chars = title.chars().flat_map(char::to_lowercase);
+    query
+        .chars()
+        .flat_map(char::to_lowercase)
+        .all(|c| title_chars.any(|title_c| title_c == c))
+}
+
+#[derive(Debug)]
+pub struct AnchorList {
+    pub query: String,
+    pub titles: Vec<String>,
+    /// Index of the selected title within `titles`
+    pub selected: Option<usize>,
+}
+
+impl AnchorList {
+    pub fn row_height(hidpi_scale: f32) -> f32 {
+        (FONT_SIZE * 1.1 + 2. * PADDING) * hidpi_scale
+    }
+
+    /// Height of the whole list including the row for the query
+    pub fn height(&self, hidpi_scale: f32) -> f32 {
+        (self.titles.len() + 1) as f32 * Self::row_height(hidpi_scale)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers() -> (HashMap<String, f32>, HashMap<String, Heading>) {
+        let headers = [
+            ("#install", "Install", 100.),
+            ("#usage", "Usage", 300.),
+            ("#config-file", "Config File", 500.),
+            ("#faq", "FAQ", 900.),
+        ];
+        let anchors = headers
+            .iter()
+            .map(|&(anchor, _, y)| (anchor.to_owned(), y))
+            // Anchors that aren't from headers don't get listed
+            .chain([("#fn-1".to_owned(), 1_000.)])
+            .collect();
+        let headings = headers
+            .iter()
+            .map(|&(anchor, title, _)| {
+                let title = title.to_owned();
+                (anchor.to_owned(), Heading { level: 2, title })
+            })
+            .collect();
+        (anchors, headings)
+    }
+
+    fn titles(entries: &[Entry]) -> Vec<&str> {
+        entries.iter().map(|entry| entry.title.as_str()).collect()
+    }
+
+    #[test]
+    fn filtering() {
+        let (anchors, headings) = headers();
+        let mut jump = AnchorJump::default();
+        let entries = jump.entries(&anchors, &headings);
+        assert_eq!(titles(&entries), ["Install", "Usage", "Config File", "FAQ"]);
+        assert_eq!(entries[2].y, 500.);
+
+        for c in "sa".chars() {
+            jump.push(c);
+        }
+        let entries = jump.entries(&anchors, &headings);
+        assert_eq!(titles(&entries), ["Install", "Usage"]);
+        jump.push('G');
+        let entries = jump.entries(&anchors, &headings);
+        assert_eq!(titles(&entries), ["Usage"]);
+
+        jump.query = "cf".into();
+        let entries = jump.entries(&anchors, &headings);
+        assert_eq!(titles(&entries), ["Config File"]);
+        jump.query = "xyz".into();
+        assert!(jump.entries(&anchors, &headings).is_empty());
+    }
+
+    #[test]
+    fn selection_wraps_and_resets() {
+        let mut jump = AnchorJump::default();
+        assert_eq!(jump.selected(0), None);
+        assert_eq!(jump.selected(4), Some(0));
+
+        jump.move_selection(VertDirection::Up, 4);
+        assert_eq!(jump.selected(4), Some(3));
+        jump.move_selection(VertDirection::Down, 4);
+        assert_eq!(jump.selected(4), Some(0));
+        jump.move_selection(VertDirection::Down, 4);
+        // Fewer matches than before still leaves something selected
+        assert_eq!(jump.selected(1), Some(0));
+
+        jump.push('a');
+        assert_eq!(jump.selected(4), Some(0));
+    }
+
+    #[test]
+    fn list_follows_selection() {
+        let anchors: HashMap<_, _> = (0..15).map(|i| (format!("#h-{i}"), i as f32)).collect();
+        let headings = (0..15)
+            .map(|i| {
+                let title = format!("H {i}");
+                (format!("#h-{i}"), Heading { level: 2, title })
+            })
+            .collect();
+        let mut jump = AnchorJump::default();
+
+        let list = jump.list(&anchors, &headings);
+        assert_eq!(list.titles.len(), MAX_SHOWN);
+        assert_eq!(list.titles[0], "H 0");
+        assert_eq!(list.selected, Some(0));
+
+        jump.move_selection(VertDirection::Up, 15);
+        let list = jump.list(&anchors, &headings);
+        assert_eq!(list.titles.last().unwrap(), "H 14");
+        assert_eq!(list.selected, Some(MAX_SHOWN - 1));
+    }
+}
@@ -38,10 +38,21 @@ pub struct Theme {
     pub text_color: u32,
     pub background_color: u32,
     pub code_color: u32,
+    pub code_border_color: u32,
+    pub line_number_color: u32,
+    pub kbd_background_color: u32,
+    pub kbd_border_color: u32,
     pub quote_block_color: u32,
     pub link_color: u32,
     pub select_color: u32,
     pub checkbox_color: u32,
+    pub search_highlight_color: u32,
+    pub scrollbar_color: u32,
+    pub alert_note_color: u32,
+    pub alert_tip_color: u32,
+    pub alert_important_color: u32,
+    pub alert_warning_color: u32,
+    pub alert_caution_color: u32,
     pub code_highlighter: SyntectTheme,
 }
 
@@ -56,10 +67,21 @@ impl Theme {
             text_color: 0x9DACBB,
             background_color: 0x1A1D22,
             code_color: 0xB38FAC,
+            code_border_color: 0x3E4451,
+            line_number_color: 0x5C6370,
+            kbd_background_color: 0x2D333B,
+            kbd_border_color: 0x444C56,
             quote_block_color: 0x1D2025,
             link_color: 0x4182EB,
             select_color: 0x3675CB,
             checkbox_color: 0x0A5301,
+            search_highlight_color: 0x6B5A12,
+            scrollbar_color: 0x5C6370,
+            alert_note_color: 0x4493F8,
+            alert_tip_color: 0x3FB950,
+            alert_important_color: 0xAB7DF8,
+            alert_warning_color: 0xD29922,
+            alert_caution_color: 0xF85149,
             code_highlighter,
         }
     }
@@ -74,14 +96,53 @@ impl Theme {
             text_color: 0x000000,
             background_color: 0xFFFFFF,
             code_color: 0x95114E,
+            code_border_color: 0xD0D7DE,
+            line_number_color: 0x8C959F,
+            kbd_background_color: 0xF6F8FA,
+            kbd_border_color: 0xD0D7DE,
             quote_block_color: 0xEEF9FE,
             link_color: 0x5466FF,
             select_color: 0xCDE8F0,
             checkbox_color: 0x96ECAE,
+            search_highlight_color: 0xFFE27A,
+            scrollbar_color: 0x959595,
+            alert_note_color: 0x0969DA,
+            alert_tip_color: 0x1A7F37,
+            alert_important_color: 0x8250DF,
+            alert_warning_color: 0x9A6700,
+            alert_caution_color: 0xD1242F,
             code_highlighter,
         }
     }
 
+    /// High contrast black on white that sticks to shades of gray
+    ///
+    /// Syntax highlighting colors get ignored with this theme, so the highlighter is only kept for
+    /// the sake of having one
+    pub fn mono_default() -> Self {
+        Self {
+            text_color: 0x000000,
+            background_color: 0xFFFFFF,
+            code_color: 0x000000,
+            code_border_color: 0x000000,
+            line_number_color: 0x595959,
+            kbd_background_color: 0xFFFFFF,
+            kbd_border_color: 0x000000,
+            quote_block_color: 0xEEEEEE,
+            link_color: 0x000000,
+            select_color: 0xBBBBBB,
+            checkbox_color: 0xBBBBBB,
+            search_highlight_color: 0xCCCCCC,
+            scrollbar_color: 0x595959,
+            alert_note_color: 0x000000,
+            alert_tip_color: 0x000000,
+            alert_important_color: 0x000000,
+            alert_warning_color: 0x000000,
+            alert_caution_color: 0x000000,
+            ..Self::light_default()
+        }
+    }
+
     pub fn code_highlighter(mut self, theme: SyntectTheme) -> Self {
         self.code_highlighter = theme;
         self
@@ -92,6 +153,9 @@ impl Theme {
 pub enum SyntaxTheme {
     Defaults(ThemeDefaults),
     Custom(ThemeCustom),
+    /// A name that doesn't match any of the defaults. Kept around so that it can be warned about
+    /// once the theme is used instead of failing the whole config
+    Unknown(String),
 }
 
 #[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
@@ -111,6 +175,16 @@ impl TryFrom<SyntaxTheme> for SyntectTheme {
     fn try_from(syntax_theme: SyntaxTheme) -> Result<Self, Self::Error> {
         match syntax_theme {
             SyntaxTheme::Defaults(default) => Ok(SyntectTheme::from(default)),
+            SyntaxTheme::Unknown(theme_name) => {
+                let variants = ThemeDefaults::kebab_pairs()
+                    .iter()
+                    .map(|(kebab, _)| format!("\"{kebab}\""))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                anyhow::bail!(
+                    "\"{theme_name}\" didn't match any of the expected variants: [{variants}]"
+                )
+            }
             SyntaxTheme::Custom(ThemeCustom { path }) => {
                 let mut reader = BufReader::new(File::open(&path).with_context(|| {
                     format!("Failed opening theme from path {}", path.display())
@@ -143,26 +217,13 @@ impl<'de> Deserialize<'de> for SyntaxTheme {
             ));
         };
 
-        match untagged {
-            // Unfortunately #[serde(untagged)] uses private internals to reuse a deserializer
-            // multiple times. We can't so now we have to fall back to other means to give a good
-            // error message ;-;
-            Untagged::Defaults(theme_name) => match ThemeDefaults::from_kebab(&theme_name) {
-                Some(theme) => Ok(Self::Defaults(theme)),
-                None => {
-                    let variants = ThemeDefaults::kebab_pairs()
-                        .iter()
-                        .map(|(kebab, _)| format!("\"{kebab}\""))
-                        .collect::<Vec<_>>()
-                        .join(", ");
-                    let msg = format!(
-                        "\"{theme_name}\" didn't match any of the expected variants: [{variants}]"
-                    );
-                    Err(serde::de::Error::custom(msg))
-                }
+        Ok(match untagged {
+            Untagged::Defaults(theme_name) => match ThemeDefaults::from_name(&theme_name) {
+                Some(theme) => Self::Defaults(theme),
+                None => Self::Unknown(theme_name),
             },
-            Untagged::Custom(custom) => Ok(Self::Custom(custom)),
-        }
+            Untagged::Custom(custom) => Self::Custom(custom),
+        })
     }
 }
 
@@ -222,10 +283,11 @@ impl ThemeDefaults {
         ]
     }
 
-    fn from_kebab(kebab: &str) -> Option<Self> {
-        Self::kebab_pairs()
-            .iter()
-            .find_map(|&(hay, var)| (kebab == hay).then_some(var))
+    /// Looks up a theme by either its kebab-case name or `syntect`'s own name for it
+    fn from_name(name: &str) -> Option<Self> {
+        Self::kebab_pairs().iter().find_map(|&(kebab, var)| {
+            (name == kebab || name == var.as_syntect_name()).then_some(var)
+        })
     }
 
     pub fn as_syntect_name(self) -> &'static str {
@@ -79,6 +79,7 @@ pub fn text(text: &Text, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         is_italic: bool,
         is_underlined: bool,
         is_striked: bool,
+        is_kbd: bool,
     }
 
     impl StyleWrapper {
@@ -88,9 +89,10 @@ pub fn text(text: &Text, f: &mut fmt::Formatter<'_>) -> fmt::Result {
                 is_italic,
                 is_underlined,
                 is_striked,
+                is_kbd,
             } = self;
 
-            ![is_bold, is_italic, is_underlined, is_striked].contains(&true)
+            ![is_bold, is_italic, is_underlined, is_striked, is_kbd].contains(&true)
         }
     }
 
@@ -101,6 +103,7 @@ pub fn text(text: &Text, f: &mut fmt::Formatter<'_>) -> fmt::Result {
                 is_italic,
                 is_underlined,
                 is_striked,
+                is_kbd,
             } = *self;
 
             if self.is_regular() {
@@ -118,6 +121,9 @@ pub fn text(text: &Text, f: &mut fmt::Formatter<'_>) -> fmt::Result {
                 if is_striked {
                     f.write_str("STRIKED ")?;
                 }
+                if is_kbd {
+                    f.write_str("KBD ")?;
+                }
             }
 
             Ok(())
@@ -132,6 +138,8 @@ pub fn text(text: &Text, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         is_italic,
         is_underlined,
         is_striked,
+        is_kbd,
+        script,
         font_family,
         // Globally consistent so avoid displaying as noise
         hidpi_scale: _,
@@ -158,10 +166,12 @@ pub fn text(text: &Text, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         is_italic: *is_italic,
         is_underlined: *is_underlined,
         is_striked: *is_striked,
+        is_kbd: *is_kbd,
     };
     if !style.is_regular() {
         debug.field("style", &style);
     }
+    debug_inline_some(&mut debug, "script", script);
     debug_inline_some(&mut debug, "link", link);
 
     debug.finish_non_exhaustive()
@@ -0,0 +1,150 @@
+//! Expanding and collapsing `<details>` sections in bulk
+//!
+//! Sections can be nested within rows and other sections, so everything here goes through
+//! [`walk`] to find them
+
+use crate::positioner::{Positioned, Section};
+use crate::Element;
+
+/// Visits every section in `elements`
+///
+/// `visit` returns whether to continue on into the section's contents
+pub fn walk<'a>(elements: &'a [Positioned<Element>], visit: &mut impl FnMut(&'a Section) -> bool) {
+    for element in elements {
+        match &element.inner {
+            Element::Row(row) => walk(&row.elements, visit),
+            Element::Section(section) => {
+                if visit(section) {
+                    walk(&section.elements, visit);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Expands every section if any of them are collapsed, otherwise collapses them all
+///
+/// Returns whether there were any sections to toggle
+pub fn toggle_all(elements: &[Positioned<Element>]) -> bool {
+    let mut sections = Vec::new();
+    walk(elements, &mut |section| {
+        sections.push(section);
+        true
+    });
+    let hide = sections.iter().all(|section| !*section.hidden.borrow());
+    for section in &sections {
+        *section.hidden.borrow_mut() = hide;
+    }
+    !sections.is_empty()
+}
+
+/// Toggles the visible section whose summary sits closest to `y`
+///
+/// Returns whether there was a section to toggle
+pub fn toggle_nearest(elements: &[Positioned<Element>], y: f32) -> bool {
+    let mut nearest: Option<(&Section, f32)> = None;
+    walk(elements, &mut |section| {
+        let summary_y = section
+            .summary
+            .as_ref()
+            .as_ref()
+            .and_then(|summary| summary.bounds.as_ref())
+            .map(|bounds| bounds.pos.1);
+        if let Some(summary_y) = summary_y {
+            let distance = (summary_y - y).abs();
+            if nearest.map_or(true, |(_, nearest)| distance < nearest) {
+                nearest = Some((section, distance));
+            }
+        }
+        // The contents of collapsed sections aren't visible
+        !*section.hidden.borrow()
+    });
+
+    match nearest {
+        Some((section, _)) => {
+            let mut hidden = section.hidden.borrow_mut();
+            *hidden = !*hidden;
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::positioner::Row;
+    use crate::text::{Text, TextBox};
+    use crate::utils::Rect;
+
+    fn section(summary_y: f32, hidden: bool, elements: Vec<Positioned<Element>>) -> Element {
+        let mut summary = Positioned::new(TextBox::new(
+            vec![Text::new("Summary".into(), 1., [0.; 4])],
+            1.,
+        ));
+        summary.bounds = Some(Rect::new((0., summary_y), (100., 10.)));
+        Element::Section(Section {
+            elements,
+            hidpi_scale: 1.,
+            hidden: hidden.into(),
+            summary: Box::new(Some(summary)),
+        })
+    }
+
+    fn hidden_states(elements: &[Positioned<Element>]) -> Vec<bool> {
+        let mut states = Vec::new();
+        walk(elements, &mut |section| {
+            states.push(*section.hidden.borrow());
+            true
+        });
+        states
+    }
+
+    fn doc() -> Vec<Positioned<Element>> {
+        let nested = section(50., true, Vec::new());
+        let in_row = Row {
+            elements: vec![Positioned::new(section(200., false, Vec::new()))],
+            hidpi_scale: 1.,
+            baseline: false,
+        };
+        vec![
+            Positioned::new(section(0., false, vec![Positioned::new(nested)])),
+            Positioned::new(Element::Row(in_row)),
+        ]
+    }
+
+    #[test]
+    fn toggle_all_sections() {
+        let elements = doc();
+        assert_eq!(hidden_states(&elements), [false, true, false]);
+
+        // Anything collapsed means that everything gets expanded, nested or not
+        assert!(toggle_all(&elements));
+        assert_eq!(hidden_states(&elements), [false, false, false]);
+        assert!(toggle_all(&elements));
+        assert_eq!(hidden_states(&elements), [true, true, true]);
+        assert!(toggle_all(&elements));
+        assert_eq!(hidden_states(&elements), [false, false, false]);
+
+        assert!(!toggle_all(&[]));
+    }
+
+    #[test]
+    fn toggle_nearest_section() {
+        let elements = doc();
+        // Nested sections are fair game while their parent is expanded
+        assert!(toggle_nearest(&elements, 45.));
+        assert_eq!(hidden_states(&elements), [false, false, false]);
+        assert!(toggle_nearest(&elements, 0.));
+        assert_eq!(hidden_states(&elements), [true, false, false]);
+
+        // but get skipped over once it's collapsed
+        assert!(toggle_nearest(&elements, 60.));
+        assert_eq!(hidden_states(&elements), [false, false, false]);
+        assert!(toggle_nearest(&elements, 150.));
+        assert_eq!(hidden_states(&elements), [false, false, true]);
+
+        assert!(!toggle_nearest(&[], 0.));
+    }
+}
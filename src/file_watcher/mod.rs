@@ -5,6 +5,7 @@ use std::path::{Path, PathBuf};
 use std::sync::mpsc;
 use std::time::Duration;
 
+use crate::tabs::TabId;
 use crate::InlyneEvent;
 
 use notify::event::{EventKind, ModifyKind};
@@ -16,16 +17,41 @@ use winit::event_loop::EventLoopProxy;
 
 trait Callback: Send + 'static {
     fn file_reload(&self);
+    /// The file was replaced (e.g. by an atomic save) and is being watched again
+    fn file_replaced(&self) {
+        self.file_reload();
+    }
     fn file_change(&self, contents: String);
 }
 
-impl Callback for EventLoopProxy<InlyneEvent> {
+/// Passes events along to the event loop, marked with the tab whose file they're for
+struct TabEvents {
+    event_proxy: EventLoopProxy<InlyneEvent>,
+    tab: TabId,
+}
+
+impl Callback for TabEvents {
     fn file_reload(&self) {
-        let _ = self.send_event(InlyneEvent::FileReload);
+        let tab = self.tab;
+        let _ = self.event_proxy.send_event(InlyneEvent::FileReload {
+            tab,
+            replaced: false,
+        });
+    }
+
+    fn file_replaced(&self) {
+        let tab = self.tab;
+        let _ = self.event_proxy.send_event(InlyneEvent::FileReload {
+            tab,
+            replaced: true,
+        });
     }
 
     fn file_change(&self, contents: String) {
-        let _ = self.send_event(InlyneEvent::FileChange { contents });
+        let tab = self.tab;
+        let _ = self
+            .event_proxy
+            .send_event(InlyneEvent::FileChange { tab, contents });
     }
 }
 
@@ -44,6 +70,8 @@ enum WatcherMsg {
     Action(DebouncerAction),
     // Sent by the event loop
     FileChange(FileChange),
+    // Sent when the watcher is dropped
+    Stop,
 }
 
 impl WatcherMsg {
@@ -92,16 +120,31 @@ impl DebounceEventHandler for MsgHandler {
     }
 }
 
-pub struct Watcher(mpsc::Sender<WatcherMsg>);
+enum Handle {
+    Watching(mpsc::Sender<WatcherMsg>),
+    // Nothing gets watched, but we still need to pass along file changes from navigating
+    Disabled(Box<dyn Callback>),
+}
+
+pub struct Watcher(Handle);
 
 impl Watcher {
-    pub fn spawn(event_proxy: EventLoopProxy<InlyneEvent>, file_path: PathBuf) -> Self {
-        Self::spawn_inner(event_proxy, file_path)
+    pub fn spawn(event_proxy: EventLoopProxy<InlyneEvent>, tab: TabId, file_path: PathBuf) -> Self {
+        Self::spawn_inner(TabEvents { event_proxy, tab }, file_path)
+    }
+
+    /// A watcher that never watches for changes, so reloads only happen on demand
+    pub fn disabled(event_proxy: EventLoopProxy<InlyneEvent>, tab: TabId) -> Self {
+        Self::disabled_inner(TabEvents { event_proxy, tab })
+    }
+
+    fn disabled_inner<C: Callback>(callback: C) -> Self {
+        Self(Handle::Disabled(Box::new(callback)))
     }
 
     fn spawn_inner<C: Callback>(reload_callback: C, file_path: PathBuf) -> Self {
         let (msg_tx, msg_rx) = mpsc::channel();
-        let watcher = Self(msg_tx.clone());
+        let watcher = Self(Handle::Watching(msg_tx.clone()));
 
         let notify_watcher =
             new_debouncer(Duration::from_millis(10), None, MsgHandler(msg_tx)).unwrap();
@@ -114,8 +157,22 @@ impl Watcher {
     }
 
     pub fn update_file(&self, new_path: &Path, contents: String) {
-        let msg = WatcherMsg::file_change(new_path.to_owned(), contents);
-        let _ = self.0.send(msg);
+        match &self.0 {
+            Handle::Watching(msg_tx) => {
+                let msg = WatcherMsg::file_change(new_path.to_owned(), contents);
+                let _ = msg_tx.send(msg);
+            }
+            Handle::Disabled(callback) => callback.file_change(contents),
+        }
+    }
+}
+
+// The debouncer keeps its own sender around, so the thread has to be told to wrap up
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        if let Handle::Watching(msg_tx) = &self.0 {
+            let _ = msg_tx.send(WatcherMsg::Stop);
+        }
     }
 }
 
@@ -148,7 +205,7 @@ fn endlessly_handle_messages<C: Callback>(
                 tracing::debug!("File may have been renamed/removed. Falling back to polling");
                 poll_registering_watcher(watcher, &file_path);
                 tracing::debug!("Successfully re-registered file watcher");
-                reload_callback.file_reload();
+                reload_callback.file_replaced();
             }
             WatcherMsg::Action(DebouncerAction::FileReload) => {
                 tracing::debug!("Reloading file");
@@ -161,6 +218,7 @@ fn endlessly_handle_messages<C: Callback>(
                 file_path = new_path;
                 reload_callback.file_change(contents);
             }
+            WatcherMsg::Stop => return,
         }
     }
 
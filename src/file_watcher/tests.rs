@@ -17,6 +17,40 @@ impl Callback for mpsc::Sender<()> {
     }
 }
 
+impl Callback for () {
+    fn file_reload(&self) {}
+
+    fn file_change(&self, _: String) {}
+}
+
+impl Watcher {
+    /// A watcher that doesn't pass anything along
+    pub fn detached() -> Self {
+        Self::disabled_inner(())
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Reload {
+    InPlace,
+    Replaced,
+}
+
+/// Tells apart the different kinds of reloads
+struct ReloadKinds(mpsc::Sender<Reload>);
+
+impl Callback for ReloadKinds {
+    fn file_reload(&self) {
+        self.0.send(Reload::InPlace).unwrap();
+    }
+
+    fn file_replaced(&self) {
+        self.0.send(Reload::Replaced).unwrap();
+    }
+
+    fn file_change(&self, _: String) {}
+}
+
 fn touch(file: &Path) {
     let now = filetime::FileTime::now();
     filetime::set_file_mtime(file, now).unwrap();
@@ -135,6 +169,7 @@ gen_watcher_test!(
     (sanity, sanity_fn),
     (update_moves_watcher, update_moves_watcher_fn),
     (slowly_swap_file, slowly_swap_file_fn),
+    (atomic_save_replaces_file, atomic_save_replaces_file_fn),
 );
 
 fn sanity_fn(
@@ -195,3 +230,78 @@ fn slowly_swap_file_fn(
     touch(&main_file);
     delays.assert_at_least_one_message(&callback_rx);
 }
+
+fn atomic_save_replaces_file_fn(
+    TestEnv {
+        base_dir,
+        main_file,
+        ..
+    }: TestEnv,
+    delays: Delays,
+) {
+    let (reload_tx, reload_rx) = mpsc::channel();
+    let _watcher = Watcher::spawn_inner(ReloadKinds(reload_tx), main_file.clone());
+    delays.delay();
+    while reload_rx.recv_timeout(delays.short_timeout).is_ok() {}
+
+    // Editors commonly save by writing to a temp file and renaming it over the original. That
+    // gets reported as a replace, so that the view can keep its position
+    let temp_file = base_dir.join(".main.md.swp");
+    fs::write(&temp_file, "# Main\n\nSaved").unwrap();
+    fs::rename(&temp_file, &main_file).unwrap();
+    assert_eq!(
+        reload_rx.recv_timeout(delays.long_timeout),
+        Ok(Reload::Replaced)
+    );
+    while reload_rx.recv_timeout(delays.short_timeout).is_ok() {}
+
+    // and regular edits afterwards are still in place
+    touch(&main_file);
+    assert_eq!(
+        reload_rx.recv_timeout(delays.long_timeout),
+        Ok(Reload::InPlace)
+    );
+}
+
+#[test]
+fn disabled_still_changes_files() {
+    crate::test_utils::log::init();
+
+    let (_, temp_dir) = init_test_env();
+    let main_file = temp_dir.path().join("main.md");
+    let rel_file = temp_dir.path().join("rel.md");
+    let delays = Delays::new();
+
+    let (callback_tx, callback_rx) = mpsc::channel();
+    let watcher = Watcher::disabled_inner(callback_tx);
+
+    // Navigating still goes through, but nothing gets watched
+    watcher.update_file(&rel_file, fs::read_to_string(&rel_file).unwrap());
+    delays.assert_at_least_one_message(&callback_rx);
+    touch(&rel_file);
+    delays.assert_no_message(&callback_rx);
+    touch(&main_file);
+    delays.assert_no_message(&callback_rx);
+}
+
+#[test]
+fn dropping_stops_watching() {
+    crate::test_utils::log::init();
+
+    let (test_env, _temp_dir) = init_test_env();
+    let TestEnv {
+        main_file,
+        watcher,
+        callback_rx,
+        ..
+    } = test_env;
+    let delays = Delays::new();
+
+    // The watcher's thread wraps up, taking the callback down with it
+    drop(watcher);
+    touch(&main_file);
+    assert_eq!(
+        callback_rx.recv_timeout(delays.long_timeout),
+        Err(mpsc::RecvTimeoutError::Disconnected)
+    );
+}
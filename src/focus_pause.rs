@@ -0,0 +1,75 @@
+//! Holding off on redraws while the window is unfocused (`pause-when-unfocused`)
+//!
+//! Things like reloads still get applied in the background so that the document is current, but
+//! the redraws that they would trigger get coalesced into a single one for when focus returns
+
+#[derive(Debug)]
+pub struct FocusPause {
+    enabled: bool,
+    focused: bool,
+    deferred: bool,
+}
+
+impl FocusPause {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            focused: true,
+            deferred: false,
+        }
+    }
+
+    /// Whether non-essential work (redraws, repositioning for images, etc.) should wait
+    pub fn is_paused(&self) -> bool {
+        self.enabled && !self.focused
+    }
+
+    /// Holds onto a redraw for later when paused
+    ///
+    /// Returns `true` when it was deferred, and `false` when it should happen now
+    pub fn defer_redraw(&mut self) -> bool {
+        if self.is_paused() {
+            self.deferred = true;
+        }
+        self.deferred
+    }
+
+    /// Returns `true` when regaining focus with a redraw that was deferred
+    pub fn set_focused(&mut self, focused: bool) -> bool {
+        self.focused = focused;
+        focused && std::mem::take(&mut self.deferred)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coalesces_redraws_until_focused() {
+        let mut pause = FocusPause::new(true);
+        assert!(!pause.defer_redraw());
+
+        assert!(!pause.set_focused(false));
+        assert!(pause.is_paused());
+        assert!(pause.defer_redraw());
+        assert!(pause.defer_redraw());
+        // All of the deferred redraws come back as one
+        assert!(pause.set_focused(true));
+        assert!(!pause.is_paused());
+        assert!(!pause.set_focused(true));
+
+        // Losing focus without anything happening has nothing to catch up on
+        pause.set_focused(false);
+        assert!(!pause.set_focused(true));
+    }
+
+    #[test]
+    fn disabled_never_pauses() {
+        let mut pause = FocusPause::new(false);
+        pause.set_focused(false);
+        assert!(!pause.is_paused());
+        assert!(!pause.defer_redraw());
+        assert!(!pause.set_focused(true));
+    }
+}
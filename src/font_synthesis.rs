@@ -0,0 +1,740 @@
+//! Faux bold and italic faces for fonts that don't have them (`synthetic-emphasis`)
+//!
+//! Our text stack only draws the glyphs that a face actually has, so missing faces get made up
+//! ahead of time instead. The outlines of the closest existing face get slanted for italics and
+//! drawn twice slightly apart for bold, and the result gets added to the font database as another
+//! face of the same family. Only TrueType outlines (`glyf`) can be reworked this way, so CFF based
+//! fonts are left alone
+
+use std::sync::Arc;
+
+use fontdb::{Database, FaceInfo, Source, Style, Weight, ID};
+use ttf_parser::{RawFace, Tag};
+
+/// How far italics lean, as the horizontal shift per unit of height (about 11 degrees)
+const SLANT: f32 = 0.2;
+/// The distance between the two strokes of faux bold, relative to the em size
+const EMBOLDEN: f32 = 0.03;
+
+// Composite glyph flags
+const ARG_1_AND_2_ARE_WORDS: u16 = 0x0001;
+const ARGS_ARE_XY_VALUES: u16 = 0x0002;
+const WE_HAVE_A_SCALE: u16 = 0x0008;
+const MORE_COMPONENTS: u16 = 0x0020;
+const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
+const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
+const WE_HAVE_INSTRUCTIONS: u16 = 0x0100;
+
+// Simple glyph flags
+const ON_CURVE_POINT: u8 = 0x01;
+const X_SHORT_VECTOR: u8 = 0x02;
+const Y_SHORT_VECTOR: u8 = 0x04;
+const REPEAT_FLAG: u8 = 0x08;
+const X_IS_SAME_OR_POSITIVE: u8 = 0x10;
+const Y_IS_SAME_OR_POSITIVE: u8 = 0x20;
+
+/// Tables that get dropped from synthesized faces. Hinting and variations both depend on the
+/// original outlines, and the device metrics would be off after emboldening
+const DROPPED_TABLES: &[&[u8; 4]] = &[
+    b"cvt ", b"fpgm", b"prep", b"hdmx", b"LTSH", b"VDMX", b"gvar", b"cvar", b"fvar", b"avar",
+    b"HVAR", b"MVAR", b"DSIG",
+];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Synthesis {
+    pub bold: bool,
+    pub italic: bool,
+}
+
+/// Adds faux bold, italic, and bold italic faces to any of `families` that are missing them
+pub fn add_missing_faces(db: &mut Database, families: &[&str]) {
+    for family in families {
+        let faces: Vec<FaceInfo> = db
+            .faces()
+            .filter(|face| is_family(face, family))
+            .cloned()
+            .collect();
+        for (weight, style) in [
+            (Weight::BOLD, Style::Normal),
+            (Weight::NORMAL, Style::Italic),
+            (Weight::BOLD, Style::Italic),
+        ] {
+            // Any real face that's close enough gets picked over a synthetic one. Adding one anyways
+            // would match the query exactly and win out over the real face
+            if faces.iter().any(|face| {
+                is_heavy(face.weight) == is_heavy(weight)
+                    && is_slanted(face.style) == is_slanted(style)
+            }) {
+                continue;
+            }
+            let Some((base, synthesis)) = closest_face(&faces, weight, style) else {
+                continue;
+            };
+            let Some(data) = db
+                .with_face_data(base.id, |data, index| synthesize(data, index, synthesis))
+                .flatten()
+            else {
+                tracing::warn!(
+                    "Couldn't synthesize {} text for font '{family}'. Only TrueType outlines are \
+                     supported",
+                    describe(weight, style)
+                );
+                continue;
+            };
+            tracing::debug!(
+                "Synthesized {} text for font '{family}'",
+                describe(weight, style)
+            );
+            db.push_face_info(FaceInfo {
+                id: ID::dummy(),
+                source: Source::Binary(Arc::new(data)),
+                index: 0,
+                post_script_name: format!("{}-Synthetic", base.post_script_name),
+                weight,
+                // Matching an oblique base keeps queries for regular italics on the real face
+                style: if is_slanted(base.style) {
+                    base.style
+                } else {
+                    style
+                },
+                ..base.clone()
+            });
+        }
+    }
+}
+
+/// Semibold and up all count as bold
+fn is_heavy(weight: Weight) -> bool {
+    weight >= Weight::SEMIBOLD
+}
+
+/// Oblique faces count as italic
+fn is_slanted(style: Style) -> bool {
+    style != Style::Normal
+}
+
+fn describe(weight: Weight, style: Style) -> &'static str {
+    match (weight == Weight::BOLD, style == Style::Normal) {
+        (true, true) => "bold",
+        (false, _) => "italic",
+        (true, false) => "bold italic",
+    }
+}
+
+fn is_family(face: &FaceInfo, family: &str) -> bool {
+    face.families
+        .iter()
+        .any(|(name, _)| name.eq_ignore_ascii_case(family))
+}
+
+/// The face to derive a `weight` and `style` face from, along with what it still needs
+///
+/// A real italic face makes for a better bold italic than a slanted bold, and the other way
+/// around. Anything else starts from the regular face
+fn closest_face(
+    faces: &[FaceInfo],
+    weight: Weight,
+    style: Style,
+) -> Option<(&FaceInfo, Synthesis)> {
+    let is_regular_weight = |face: &FaceInfo| face.weight == Weight::NORMAL;
+    let wants_italic = is_slanted(style);
+    let wants_bold = is_heavy(weight);
+
+    let candidates = [
+        faces
+            .iter()
+            .find(|face| is_slanted(face.style) == wants_italic && is_regular_weight(face)),
+        faces
+            .iter()
+            .find(|face| is_heavy(face.weight) == wants_bold && !is_slanted(face.style)),
+        faces
+            .iter()
+            .find(|face| is_regular_weight(face) && !is_slanted(face.style)),
+    ];
+    candidates.into_iter().flatten().next().map(|face| {
+        let synthesis = Synthesis {
+            bold: wants_bold && !is_heavy(face.weight),
+            italic: wants_italic && !is_slanted(face.style),
+        };
+        (face, synthesis)
+    })
+}
+
+/// A standalone copy of the font at `index` within `data` with its outlines reworked
+///
+/// Returns `None` for fonts without TrueType outlines
+pub fn synthesize(data: &[u8], index: u32, synthesis: Synthesis) -> Option<Vec<u8>> {
+    let raw = RawFace::parse(data, index).ok()?;
+    let table = |tag: &[u8; 4]| raw.table(Tag::from_bytes(tag));
+
+    let mut head = table(b"head")?.to_vec();
+    let mut hhea = table(b"hhea")?.to_vec();
+    let mut maxp = table(b"maxp")?.to_vec();
+    let glyf = table(b"glyf")?;
+    let loca = table(b"loca")?;
+    let hmtx = table(b"hmtx")?;
+
+    let units_per_em = read_u16(&head, 18)?;
+    let long_loca = read_u16(&head, 50)? != 0;
+    let num_glyphs = usize::from(read_u16(&maxp, 4)?);
+    let num_metrics = usize::from(read_u16(&hhea, 34)?).clamp(1, num_glyphs.max(1));
+    let strength = if synthesis.bold {
+        (f32::from(units_per_em) * EMBOLDEN).round() as i16
+    } else {
+        0
+    };
+    let transform = Transform {
+        slant: if synthesis.italic { SLANT } else { 0. },
+        strength,
+    };
+
+    let mut new_glyf = Vec::new();
+    let mut new_loca = Vec::with_capacity((num_glyphs + 1) * 4);
+    let mut new_hmtx = Vec::with_capacity(num_glyphs * 4);
+    for glyph_id in 0..num_glyphs {
+        let offset = |i: usize| {
+            if long_loca {
+                read_u32(loca, i * 4).map(|offset| offset as usize)
+            } else {
+                read_u16(loca, i * 2).map(|offset| usize::from(offset) * 2)
+            }
+        };
+        let glyph = glyf.get(offset(glyph_id)?..offset(glyph_id + 1)?)?;
+
+        new_loca.extend_from_slice(&(new_glyf.len() as u32).to_be_bytes());
+        let x_min = if glyph.is_empty() {
+            0
+        } else {
+            transform.glyph(glyph, &mut new_glyf)?
+        };
+        // Glyphs need to stay 2 byte aligned
+        if new_glyf.len() % 2 != 0 {
+            new_glyf.push(0);
+        }
+
+        let advance = read_u16(hmtx, glyph_id.min(num_metrics - 1) * 4)?;
+        let advance = advance.saturating_add_signed(strength);
+        new_hmtx.extend_from_slice(&advance.to_be_bytes());
+        new_hmtx.extend_from_slice(&x_min.to_be_bytes());
+    }
+    new_loca.extend_from_slice(&(new_glyf.len() as u32).to_be_bytes());
+
+    // Long offsets, and every glyph gets a full metric
+    write_u16(&mut head, 50, 1);
+    write_u16(&mut head, 8, 0);
+    write_u16(&mut head, 10, 0);
+    let advance_max = read_u16(&hhea, 10)?.saturating_add_signed(strength);
+    write_u16(&mut hhea, 10, advance_max);
+    write_u16(&mut hhea, 34, num_glyphs as u16);
+    if synthesis.bold && maxp.len() >= 14 {
+        // Every contour gets drawn twice
+        for offset in [6, 8, 10, 12] {
+            let max = read_u16(&maxp, offset)?;
+            write_u16(&mut maxp, offset, max.saturating_mul(2));
+        }
+    }
+
+    let mut tables: Vec<(Tag, Vec<u8>)> = Vec::new();
+    for record in raw.table_records {
+        let tag = record.tag;
+        let data = match &tag.to_bytes() {
+            b"head" => head.clone(),
+            b"hhea" => hhea.clone(),
+            b"maxp" => maxp.clone(),
+            b"glyf" => std::mem::take(&mut new_glyf),
+            b"loca" => std::mem::take(&mut new_loca),
+            b"hmtx" => std::mem::take(&mut new_hmtx),
+            bytes if DROPPED_TABLES.contains(&bytes) => continue,
+            _ => raw.table(tag)?.to_vec(),
+        };
+        tables.push((tag, data));
+    }
+    Some(write_font(tables))
+}
+
+/// How glyph outlines get reworked, in font units
+struct Transform {
+    slant: f32,
+    strength: i16,
+}
+
+impl Transform {
+    fn x(&self, x: i16, y: i16) -> i16 {
+        (f32::from(x) + f32::from(y) * self.slant).round() as i16
+    }
+
+    /// Writes out the reworked `glyph`, returning its new left side bearing
+    fn glyph(&self, glyph: &[u8], out: &mut Vec<u8>) -> Option<i16> {
+        let contours = read_i16(glyph, 0)?;
+        let (x_min, y_min) = (read_i16(glyph, 2)?, read_i16(glyph, 4)?);
+        let (x_max, y_max) = (read_i16(glyph, 6)?, read_i16(glyph, 8)?);
+        // The new bounds might be a little loose for slanted glyphs, which is fine
+        let new_x_min = self.x(x_min, y_min).min(self.x(x_min, y_max));
+        let new_x_max = self
+            .x(x_max, y_min)
+            .max(self.x(x_max, y_max))
+            .saturating_add(self.strength);
+
+        // Faux bold draws each of a simple glyph's contours twice
+        let new_contours = if contours > 0 && self.strength != 0 {
+            contours.saturating_mul(2)
+        } else {
+            contours
+        };
+        out.extend_from_slice(&new_contours.to_be_bytes());
+        for value in [new_x_min, y_min, new_x_max, y_max] {
+            out.extend_from_slice(&value.to_be_bytes());
+        }
+        if contours >= 0 {
+            self.simple_glyph(glyph, contours as usize, out)?;
+        } else {
+            self.composite_glyph(glyph, out)?;
+        }
+        Some(new_x_min)
+    }
+
+    fn simple_glyph(&self, glyph: &[u8], contours: usize, out: &mut Vec<u8>) -> Option<()> {
+        let mut offset = 10;
+        let mut end_points = Vec::with_capacity(contours);
+        for _ in 0..contours {
+            end_points.push(read_u16(glyph, offset)?);
+            offset += 2;
+        }
+        let num_points = end_points.last().map_or(0, |&end| usize::from(end) + 1);
+        let instructions = usize::from(read_u16(glyph, offset)?);
+        offset += 2 + instructions;
+
+        let mut flags = Vec::with_capacity(num_points);
+        while flags.len() < num_points {
+            let flag = *glyph.get(offset)?;
+            offset += 1;
+            flags.push(flag);
+            if flag & REPEAT_FLAG != 0 {
+                let repeats = *glyph.get(offset)?;
+                offset += 1;
+                flags.extend(std::iter::repeat(flag).take(usize::from(repeats)));
+            }
+        }
+        flags.truncate(num_points);
+
+        let mut read_coords = |short: u8, same_or_positive: u8| {
+            let mut coords = Vec::with_capacity(num_points);
+            let mut value = 0i16;
+            for flag in &flags {
+                let delta = if flag & short != 0 {
+                    let byte = i16::from(*glyph.get(offset)?);
+                    offset += 1;
+                    if flag & same_or_positive != 0 {
+                        byte
+                    } else {
+                        -byte
+                    }
+                } else if flag & same_or_positive != 0 {
+                    0
+                } else {
+                    let delta = read_i16(glyph, offset)?;
+                    offset += 2;
+                    delta
+                };
+                value = value.wrapping_add(delta);
+                coords.push(value);
+            }
+            Some(coords)
+        };
+        let xs = read_coords(X_SHORT_VECTOR, X_IS_SAME_OR_POSITIVE)?;
+        let ys = read_coords(Y_SHORT_VECTOR, Y_IS_SAME_OR_POSITIVE)?;
+
+        let mut points: Vec<(i16, i16, bool)> = xs
+            .iter()
+            .zip(&ys)
+            .zip(&flags)
+            .map(|((&x, &y), flag)| (self.x(x, y), y, flag & ON_CURVE_POINT != 0))
+            .collect();
+        if self.strength != 0 {
+            if num_points > usize::from(u16::MAX / 2) {
+                return None;
+            }
+            // Overlapping contours fill as their union, so drawing everything a second time a bit
+            // further right thickens the strokes
+            let shifted: Vec<_> = points
+                .iter()
+                .map(|&(x, y, on_curve)| (x.saturating_add(self.strength), y, on_curve))
+                .collect();
+            points.extend(shifted);
+            let copies: Vec<_> = end_points
+                .iter()
+                .map(|end| end + num_points as u16)
+                .collect();
+            end_points.extend(copies);
+        }
+
+        for end in &end_points {
+            out.extend_from_slice(&end.to_be_bytes());
+        }
+        // Hinting gets dropped along with the rest of the instructions
+        out.extend_from_slice(&0u16.to_be_bytes());
+        // Every coordinate gets written as a full 2 byte delta
+        out.extend(points.iter().map(|&(_, _, on_curve)| u8::from(on_curve)));
+        let mut prev = 0i16;
+        for &(x, _, _) in &points {
+            out.extend_from_slice(&x.wrapping_sub(prev).to_be_bytes());
+            prev = x;
+        }
+        let mut prev = 0i16;
+        for &(_, y, _) in &points {
+            out.extend_from_slice(&y.wrapping_sub(prev).to_be_bytes());
+            prev = y;
+        }
+        Some(())
+    }
+
+    /// Components get reworked on their own, so only their offsets need to be slanted
+    fn composite_glyph(&self, glyph: &[u8], out: &mut Vec<u8>) -> Option<()> {
+        let mut offset = 10;
+        loop {
+            let flags = read_u16(glyph, offset)?;
+            let glyph_index = read_u16(glyph, offset + 2)?;
+            offset += 4;
+            let (arg1, arg2) = if flags & ARG_1_AND_2_ARE_WORDS != 0 {
+                let args = (read_i16(glyph, offset)?, read_i16(glyph, offset + 2)?);
+                offset += 4;
+                args
+            } else if flags & ARGS_ARE_XY_VALUES != 0 {
+                let args = (
+                    i16::from(*glyph.get(offset)? as i8),
+                    i16::from(*glyph.get(offset + 1)? as i8),
+                );
+                offset += 2;
+                args
+            } else {
+                // Point numbers are unsigned
+                let args = (
+                    i16::from(*glyph.get(offset)?),
+                    i16::from(*glyph.get(offset + 1)?),
+                );
+                offset += 2;
+                args
+            };
+            let (arg1, arg2) = if flags & ARGS_ARE_XY_VALUES != 0 {
+                (self.x(arg1, arg2), arg2)
+            } else {
+                (arg1, arg2)
+            };
+            let scale_len = if flags & WE_HAVE_A_SCALE != 0 {
+                2
+            } else if flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+                4
+            } else if flags & WE_HAVE_A_TWO_BY_TWO != 0 {
+                8
+            } else {
+                0
+            };
+
+            let new_flags = (flags | ARG_1_AND_2_ARE_WORDS) & !WE_HAVE_INSTRUCTIONS;
+            for value in [new_flags, glyph_index, arg1 as u16, arg2 as u16] {
+                out.extend_from_slice(&value.to_be_bytes());
+            }
+            out.extend_from_slice(glyph.get(offset..offset + scale_len)?);
+            offset += scale_len;
+
+            if flags & MORE_COMPONENTS == 0 {
+                return Some(());
+            }
+        }
+    }
+}
+
+/// Lays out an sfnt font file containing `tables`
+fn write_font(mut tables: Vec<(Tag, Vec<u8>)>) -> Vec<u8> {
+    tables.sort_by_key(|(tag, _)| *tag);
+    let num_tables = tables.len() as u16;
+    let entry_selector = num_tables.max(1).ilog2() as u16;
+    let search_range = (1 << entry_selector) * 16;
+
+    let mut font = Vec::new();
+    font.extend_from_slice(&0x0001_0000u32.to_be_bytes());
+    for value in [
+        num_tables,
+        search_range,
+        entry_selector,
+        num_tables * 16 - search_range,
+    ] {
+        font.extend_from_slice(&value.to_be_bytes());
+    }
+
+    let mut offset = 12 + tables.len() * 16;
+    for (tag, data) in &tables {
+        font.extend_from_slice(&tag.to_bytes());
+        font.extend_from_slice(&checksum(data).to_be_bytes());
+        font.extend_from_slice(&(offset as u32).to_be_bytes());
+        font.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        offset += data.len().next_multiple_of(4);
+    }
+    let mut head_offset = None;
+    for (tag, data) in &tables {
+        if tag.to_bytes() == *b"head" {
+            head_offset = Some(font.len());
+        }
+        font.extend_from_slice(data);
+        font.resize(font.len().next_multiple_of(4), 0);
+    }
+
+    if let Some(head) = head_offset {
+        let adjustment = 0xB1B0_AFBAu32.wrapping_sub(checksum(&font));
+        font[head + 8..head + 12].copy_from_slice(&adjustment.to_be_bytes());
+    }
+    font
+}
+
+fn checksum(data: &[u8]) -> u32 {
+    data.chunks(4).fold(0u32, |sum, chunk| {
+        let mut word = [0; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(word))
+    })
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(
+        data.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn read_i16(data: &[u8], offset: usize) -> Option<i16> {
+    read_u16(data, offset).map(|value| value as i16)
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+fn write_u16(data: &mut [u8], offset: usize, value: u16) {
+    if let Some(bytes) = data.get_mut(offset..offset + 2) {
+        bytes.copy_from_slice(&value.to_be_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use fontdb::{Family, Language, Query, Stretch};
+    use ttf_parser::{Face, GlyphId, OutlineBuilder};
+
+    /// A font with a rectangle and a composite glyph that raises it, but no bold or italic faces
+    fn regular_font() -> Vec<u8> {
+        let mut head = vec![0; 54];
+        head[..4].copy_from_slice(&0x0001_0000u32.to_be_bytes());
+        head[12..16].copy_from_slice(&0x5F0F_3CF5u32.to_be_bytes());
+        write_u16(&mut head, 18, 1_000);
+        let mut hhea = vec![0; 36];
+        hhea[..4].copy_from_slice(&0x0001_0000u32.to_be_bytes());
+        write_u16(&mut hhea, 4, 800);
+        write_u16(&mut hhea, 10, 200);
+        write_u16(&mut hhea, 34, 1);
+        let mut maxp = vec![0; 6];
+        maxp[..4].copy_from_slice(&0x0000_5000u32.to_be_bytes());
+        write_u16(&mut maxp, 4, 2);
+
+        #[rustfmt::skip]
+        let rect: &[u8] = &[
+            0, 1, 0, 0, 0, 0, 0, 100, 1, 244,
+            0, 3, 0, 0,
+            // Flags using short and repeated coordinates
+            0x31, 0x33, 0x11, 0x23,
+            100, 100,
+            1, 244,
+        ];
+        #[rustfmt::skip]
+        let raised: &[u8] = &[
+            255, 255, 0, 0, 0, 100, 0, 100, 2, 88,
+            0, 2, 0, 0, 0, 100,
+        ];
+        let mut glyf = rect.to_vec();
+        glyf.extend_from_slice(raised);
+        let loca: Vec<u8> = [0u16, 11, 19]
+            .iter()
+            .flat_map(|offset| offset.to_be_bytes())
+            .collect();
+        let hmtx = vec![0, 200, 0, 0];
+
+        let tables = [
+            (b"head", head),
+            (b"hhea", hhea),
+            (b"maxp", maxp),
+            (b"glyf", glyf),
+            (b"loca", loca),
+            (b"hmtx", hmtx),
+        ];
+        write_font(
+            tables
+                .into_iter()
+                .map(|(tag, data)| (Tag::from_bytes(tag), data))
+                .collect(),
+        )
+    }
+
+    #[derive(Default)]
+    struct Points(Vec<Vec<(f32, f32)>>);
+
+    impl OutlineBuilder for Points {
+        fn move_to(&mut self, x: f32, y: f32) {
+            self.0.push(vec![(x, y)]);
+        }
+        fn line_to(&mut self, x: f32, y: f32) {
+            self.0.last_mut().unwrap().push((x, y));
+        }
+        fn quad_to(&mut self, _: f32, _: f32, x: f32, y: f32) {
+            self.line_to(x, y);
+        }
+        fn curve_to(&mut self, _: f32, _: f32, _: f32, _: f32, x: f32, y: f32) {
+            self.line_to(x, y);
+        }
+        fn close(&mut self) {
+            // Contours end with a line back to where they started
+            let contour = self.0.last_mut().unwrap();
+            if contour.len() > 1 && contour.first() == contour.last() {
+                contour.pop();
+            }
+        }
+    }
+
+    fn contours(font: &[u8], glyph: u16) -> Vec<Vec<(f32, f32)>> {
+        let face = Face::parse(font, 0).unwrap();
+        let mut points = Points::default();
+        face.outline_glyph(GlyphId(glyph), &mut points).unwrap();
+        points.0
+    }
+
+    #[test]
+    fn slants_and_thickens_outlines() {
+        let font = regular_font();
+        let rect = [(0., 0.), (100., 0.), (100., 500.), (0., 500.)];
+        assert_eq!(contours(&font, 0), [rect]);
+
+        let italic = Synthesis {
+            italic: true,
+            ..Default::default()
+        };
+        let italic = synthesize(&font, 0, italic).unwrap();
+        let slanted = [(0., 0.), (100., 0.), (200., 500.), (100., 500.)];
+        assert_eq!(contours(&italic, 0), [slanted]);
+        // Components get moved over along with the slant
+        let raised = slanted.map(|(x, y)| (x + 20., y + 100.));
+        assert_eq!(contours(&italic, 1), [raised]);
+
+        let bold = Synthesis {
+            bold: true,
+            ..Default::default()
+        };
+        let bold = synthesize(&font, 0, bold).unwrap();
+        let shifted = rect.map(|(x, y)| (x + 30., y));
+        assert_eq!(contours(&bold, 0), [rect, shifted]);
+        let face = Face::parse(&bold, 0).unwrap();
+        assert_eq!(face.glyph_hor_advance(GlyphId(0)), Some(230));
+        assert_eq!(face.glyph_hor_advance(GlyphId(1)), Some(230));
+    }
+
+    fn push_test_face(db: &mut Database, weight: Weight, style: Style) {
+        db.push_face_info(FaceInfo {
+            id: ID::dummy(),
+            source: Source::Binary(Arc::new(regular_font())),
+            index: 0,
+            families: vec![("Test Sans".into(), Language::English_UnitedStates)],
+            post_script_name: "TestSans".into(),
+            style,
+            weight,
+            stretch: Stretch::Normal,
+            monospaced: false,
+        });
+    }
+
+    #[test]
+    fn fills_in_missing_faces() {
+        let mut db = Database::new();
+        push_test_face(&mut db, Weight::NORMAL, Style::Normal);
+        add_missing_faces(&mut db, &["test sans", "Missing"]);
+
+        let face_for = |weight, style| {
+            let families = [Family::Name("Test Sans")];
+            let query = Query {
+                families: &families,
+                weight,
+                style,
+                stretch: Stretch::Normal,
+            };
+            let id = db.query(&query).unwrap();
+            let face = db.face(id).unwrap();
+            assert_eq!((face.weight, face.style), (weight, style));
+            db.with_face_data(id, |data, _| contours(data, 0)).unwrap()
+        };
+
+        let rect = [(0., 0.), (100., 0.), (100., 500.), (0., 500.)];
+        let slanted = [(0., 0.), (100., 0.), (200., 500.), (100., 500.)];
+        assert_eq!(face_for(Weight::NORMAL, Style::Normal), [rect]);
+        assert_eq!(face_for(Weight::NORMAL, Style::Italic), [slanted]);
+        assert_eq!(face_for(Weight::BOLD, Style::Normal).len(), 2);
+        let bold_italic = face_for(Weight::BOLD, Style::Italic);
+        assert_eq!(bold_italic[0], slanted);
+        assert_eq!(db.len(), 4);
+    }
+
+    #[test]
+    fn keeps_heavier_and_slanted_faces() {
+        let mut db = Database::new();
+        push_test_face(&mut db, Weight::NORMAL, Style::Normal);
+        push_test_face(&mut db, Weight::SEMIBOLD, Style::Normal);
+        push_test_face(&mut db, Weight::NORMAL, Style::Oblique);
+        add_missing_faces(&mut db, &["Test Sans"]);
+
+        // Only bold italic is missing, and it gets built on top of the real oblique face
+        assert_eq!(db.len(), 4);
+        let synthetic = db
+            .faces()
+            .find(|face| face.post_script_name.ends_with("-Synthetic"))
+            .unwrap();
+        assert_eq!(
+            (synthetic.weight, synthetic.style),
+            (Weight::BOLD, Style::Oblique)
+        );
+        let (contours, advance) = db
+            .with_face_data(synthetic.id, |data, _| {
+                let face = Face::parse(data, 0).unwrap();
+                (contours(data, 0), face.glyph_hor_advance(GlyphId(0)))
+            })
+            .unwrap();
+        let rect = [(0., 0.), (100., 0.), (100., 500.), (0., 500.)];
+        let shifted = rect.map(|(x, y)| (x + 30., y));
+        assert_eq!(contours, [rect, shifted]);
+        assert_eq!(advance, Some(230));
+
+        // The real faces still win their queries
+        let families = [Family::Name("Test Sans")];
+        let query = Query {
+            families: &families,
+            weight: Weight::BOLD,
+            style: Style::Italic,
+            stretch: Stretch::Normal,
+        };
+        assert_eq!(db.query(&query), Some(synthetic.id));
+        for (style, expected) in [
+            (Style::Normal, (Weight::SEMIBOLD, Style::Normal)),
+            (Style::Italic, (Weight::NORMAL, Style::Oblique)),
+        ] {
+            let weight = if style == Style::Normal {
+                Weight::BOLD
+            } else {
+                Weight::NORMAL
+            };
+            let query = Query {
+                style,
+                weight,
+                ..query
+            };
+            let face = db.face(db.query(&query).unwrap()).unwrap();
+            assert_eq!((face.weight, face.style), expected);
+        }
+    }
+}
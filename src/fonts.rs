@@ -1,17 +1,248 @@
-use glyphon::FontSystem;
+use fontdb::{Database, FaceInfo, Family, ID};
+use glyphon::{Attrs, FontSystem, Shaping, Style, Weight};
 
+use crate::font_synthesis;
 use crate::opts::FontOptions;
 
 pub fn get_fonts(font_opts: &FontOptions) -> FontSystem {
     let mut font_system = FontSystem::new();
 
+    let fallbacks: Vec<&str> = font_opts
+        .emoji_family
+        .iter()
+        .chain(&font_opts.fallback_families)
+        .map(String::as_str)
+        .filter(|&family| {
+            let found = has_family(&font_system, family);
+            if !found {
+                tracing::warn!("Couldn't find the fallback font '{family}'. It'll be skipped");
+            }
+            found
+        })
+        .collect();
+
     if let Some(regular_name) = &font_opts.regular_font {
-        font_system.db_mut().set_sans_serif_family(regular_name)
+        font_system.db_mut().set_sans_serif_family(regular_name);
+        if !font_opts.synthetic_emphasis {
+            warn_on_missing_emphasis(&font_system, regular_name);
+        }
     }
 
     if let Some(monospace_name) = &font_opts.monospace_font {
-        font_system.db_mut().set_monospace_family(monospace_name)
+        font_system.db_mut().set_monospace_family(monospace_name);
+        if !font_opts.synthetic_emphasis {
+            warn_on_missing_emphasis(&font_system, monospace_name);
+        }
+    }
+
+    if !fallbacks.is_empty() {
+        font_system = prioritize_families(font_system, &fallbacks);
+    }
+
+    if font_opts.synthetic_emphasis {
+        let db = font_system.db();
+        let families = [
+            db.family_name(&Family::SansSerif).to_owned(),
+            db.family_name(&Family::Monospace).to_owned(),
+        ];
+        let families: Vec<_> = families.iter().map(String::as_str).collect();
+        font_synthesis::add_missing_faces(font_system.db_mut(), &families);
     }
 
     font_system
 }
+
+fn is_family(face: &FaceInfo, family: &str) -> bool {
+    face.families
+        .iter()
+        .any(|(name, _)| name.eq_ignore_ascii_case(family))
+}
+
+fn has_family(font_system: &FontSystem, family: &str) -> bool {
+    font_system.db().faces().any(|face| is_family(face, family))
+}
+
+/// Makes `families` the fonts that get tried first for glyphs the regular or monospace font lacks
+///
+/// There's no way to hand our text stack a fallback list directly. It first tries the platform's
+/// preset fallbacks (e.g. "Noto Color Emoji" or "Noto Sans CJK SC"), looked up by family name,
+/// and then every other font in the order that they sit in the database. The presets would win
+/// over anything configured, so every other font is kept out of them, which leaves the configured
+/// families at the front of the database as the first fonts tried
+fn prioritize_families(mut font_system: FontSystem, families: &[&str]) -> FontSystem {
+    // The presets also stand in for generic families that aren't installed, so pin those down
+    // before they're gone
+    let resolved = [Family::Serif, Family::SansSerif, Family::Monospace]
+        .map(|family| resolved_family(&mut font_system, family));
+    let [serif, sans_serif, monospace] = resolved;
+    let db = font_system.db_mut();
+    if let Some(serif) = serif {
+        db.set_serif_family(serif);
+    }
+    if let Some(sans_serif) = sans_serif {
+        db.set_sans_serif_family(sans_serif);
+    }
+    if let Some(monospace) = monospace {
+        db.set_monospace_family(monospace);
+    }
+
+    let (locale, db) = font_system.into_locale_and_db();
+    let db = reorder_faces(db, families);
+    FontSystem::new_with_locale_and_db(locale, db)
+}
+
+/// Moves the faces of `families` to the front of `db`, in the order given, and strips the family
+/// names from every face outside of them and the generic families
+///
+/// Nameless faces are still used as fallbacks, but only after the named ones
+/// The family that text set in `family` actually gets drawn with
+fn resolved_family(font_system: &mut FontSystem, family: Family) -> Option<String> {
+    let metrics = glyphon::Metrics::new(16., 16.);
+    let mut buffer = glyphon::Buffer::new(font_system, metrics);
+    buffer.set_size(font_system, 100., 100.);
+    let attrs = Attrs::new().family(family);
+    buffer.set_text(font_system, "a", attrs, Shaping::Advanced);
+    let id = buffer.layout_runs().next()?.glyphs.first()?.font_id;
+    let face = font_system.db().face(id)?;
+    Some(face.families.first()?.0.clone())
+}
+
+fn reorder_faces(db: Database, families: &[&str]) -> Database {
+    let generic = [
+        &Family::Serif,
+        &Family::SansSerif,
+        &Family::Cursive,
+        &Family::Fantasy,
+        &Family::Monospace,
+    ]
+    .map(|family| db.family_name(family));
+    let keeps_name = |face: &FaceInfo| {
+        families
+            .iter()
+            .chain(&generic)
+            .any(|family| is_family(face, family))
+    };
+
+    let mut faces: Vec<FaceInfo> = db.faces().cloned().collect();
+    // A stable sort keeps everything else in its original order
+    faces.sort_by_key(|face| {
+        families
+            .iter()
+            .position(|family| is_family(face, family))
+            .unwrap_or(families.len())
+    });
+
+    let mut reordered = Database::new();
+    reordered.set_serif_family(db.family_name(&Family::Serif));
+    reordered.set_sans_serif_family(db.family_name(&Family::SansSerif));
+    reordered.set_cursive_family(db.family_name(&Family::Cursive));
+    reordered.set_fantasy_family(db.family_name(&Family::Fantasy));
+    reordered.set_monospace_family(db.family_name(&Family::Monospace));
+    for mut face in faces {
+        // Gets replaced with a fresh ID when pushed
+        face.id = ID::dummy();
+        if !keeps_name(&face) {
+            face.families.clear();
+        }
+        reordered.push_face_info(face);
+    }
+    reordered
+}
+
+/// Warns when a font lacks a real bold or italic face
+///
+/// Our text stack doesn't synthesize missing faces on its own, so emphasized text in these fonts
+/// renders identically to regular text unless `synthetic-emphasis` is on
+fn warn_on_missing_emphasis(font_system: &FontSystem, family: &str) {
+    let faces = font_system
+        .db()
+        .faces()
+        .filter(|face| is_family(face, family));
+
+    let (mut found, mut has_bold, mut has_italic) = (false, false, false);
+    for face in faces {
+        found = true;
+        has_bold |= face.weight >= Weight::SEMIBOLD;
+        has_italic |= face.style != Style::Normal;
+    }
+
+    if !found {
+        tracing::warn!("Couldn't find the configured font '{family}'");
+        return;
+    }
+    if !has_bold {
+        tracing::warn!(
+            "Font '{family}' has no bold face. Bold text will render as regular unless \
+             `synthetic-emphasis` is set"
+        );
+    }
+    if !has_italic {
+        tracing::warn!(
+            "Font '{family}' has no italic face. Italic text will render as regular unless \
+             `synthetic-emphasis` is set"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+
+    use fontdb::{Language, Source, Stretch};
+
+    fn face(family: &str) -> FaceInfo {
+        FaceInfo {
+            id: ID::dummy(),
+            source: Source::Binary(Arc::new(Vec::<u8>::new())),
+            index: 0,
+            families: vec![(family.to_owned(), Language::English_UnitedStates)],
+            post_script_name: family.replace(' ', ""),
+            style: Style::Normal,
+            weight: Weight::NORMAL,
+            stretch: Stretch::Normal,
+            monospaced: false,
+        }
+    }
+
+    #[test]
+    fn fallbacks_come_first_in_order() {
+        let mut db = Database::new();
+        for family in [
+            "DejaVu Sans",
+            "Noto Sans CJK JP",
+            "Twemoji",
+            "Noto Sans Arabic",
+            "Noto Color Emoji",
+        ] {
+            db.push_face_info(face(family));
+        }
+        db.set_sans_serif_family("DejaVu Sans");
+        db.set_monospace_family("DejaVu Sans Mono");
+
+        let db = reorder_faces(db, &["twemoji", "Noto Sans CJK JP"]);
+        let order: Vec<_> = db
+            .faces()
+            .map(|face| {
+                (
+                    face.families.first().map(|(name, _)| name.as_str()),
+                    face.post_script_name.as_str(),
+                )
+            })
+            .collect();
+        // The rest lose their names so that the platform's preset fallbacks can't pick them first
+        assert_eq!(
+            order,
+            [
+                (Some("Twemoji"), "Twemoji"),
+                (Some("Noto Sans CJK JP"), "NotoSansCJKJP"),
+                (Some("DejaVu Sans"), "DejaVuSans"),
+                (None, "NotoSansArabic"),
+                (None, "NotoColorEmoji"),
+            ]
+        );
+        assert_eq!(db.family_name(&Family::SansSerif), "DejaVu Sans");
+        assert_eq!(db.family_name(&Family::Monospace), "DejaVu Sans Mono");
+    }
+}
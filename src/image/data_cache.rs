@@ -0,0 +1,82 @@
+//! Keeping the data of loaded images around for re-interpreting documents
+//!
+//! Reloads and theme changes re-interpret the whole document, and remote images are looked up
+//! here instead of being downloaded again. Only the images from the latest document are kept, and
+//! even then only up to a limit
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use super::ImageData;
+
+/// How many images to hold on to by default
+pub const DEFAULT_MAX_CACHED_IMAGES: usize = 100;
+
+type SharedImageData = Arc<Mutex<Option<ImageData>>>;
+
+struct Entry {
+    src: String,
+    image_data: SharedImageData,
+    /// The last document that used this image
+    document: u64,
+}
+
+/// Loaded images by their `src`. The least recently used ones get evicted first
+pub struct LoadedImages {
+    capacity: usize,
+    entries: VecDeque<Entry>,
+    document: u64,
+}
+
+impl Default for LoadedImages {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_CACHED_IMAGES)
+    }
+}
+
+impl LoadedImages {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: VecDeque::new(),
+            document: 0,
+        }
+    }
+
+    pub fn get(&mut self, src: &str) -> Option<SharedImageData> {
+        let index = self.entries.iter().position(|entry| entry.src == src)?;
+        let mut entry = self.entries.remove(index)?;
+        entry.document = self.document;
+        let image_data = Arc::clone(&entry.image_data);
+        self.entries.push_back(entry);
+        Some(image_data)
+    }
+
+    pub fn insert(&mut self, src: String, image_data: SharedImageData) {
+        self.entries.retain(|entry| entry.src != src);
+        self.entries.push_back(Entry {
+            src,
+            image_data,
+            document: self.document,
+        });
+        while self.entries.len() > self.capacity {
+            self.entries.pop_front();
+        }
+    }
+
+    /// Forgets every image, so that they all get loaded again
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Marks the start of interpreting a new document
+    pub fn start_document(&mut self) {
+        self.document += 1;
+    }
+
+    /// Drops every image that the document since [`Self::start_document()`] didn't use
+    pub fn finish_document(&mut self) {
+        let document = self.document;
+        self.entries.retain(|entry| entry.document == document);
+    }
+}
@@ -1,10 +1,14 @@
+mod data_cache;
 mod decode;
 #[cfg(test)]
 mod tests;
+mod texture_cache;
 
 use std::borrow::Cow;
+use std::collections::HashSet;
 use std::path::PathBuf;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex, OnceLock};
 use std::time::Instant;
 use std::{
@@ -12,10 +16,15 @@ use std::{
     io::{self, Read},
 };
 
+pub use data_cache::{LoadedImages, DEFAULT_MAX_CACHED_IMAGES};
+pub use texture_cache::{TextureCache, TEXTURE_CACHE_CAPACITY};
+
 use crate::debug_impls::{DebugBytesPrefix, DebugInline};
 use crate::interpreter::ImageCallback;
 use crate::metrics::{histogram, HistTag};
+use crate::opts::OnImageError;
 use crate::positioner::DEFAULT_MARGIN;
+use crate::text::TextBox;
 use crate::utils::{usize_in_mib, Align, Point, Size};
 
 use anyhow::Context;
@@ -45,19 +54,93 @@ impl From<u32> for Px {
     }
 }
 
+/// An image's `width` or `height` attribute e.g. `200`, `200px`, or `50%`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Length {
+    Px(Px),
+    Percent(f32),
+}
+
+impl FromStr for Length {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        match s.strip_suffix('%') {
+            Some(percent) => {
+                let percent: f32 = percent.trim_end().parse()?;
+                anyhow::ensure!(
+                    percent.is_finite() && percent >= 0.,
+                    "Invalid percentage: {s}"
+                );
+                Ok(Self::Percent(percent))
+            }
+            None => Ok(Self::Px(s.parse()?)),
+        }
+    }
+}
+
+impl From<Px> for Length {
+    fn from(px: Px) -> Self {
+        Self::Px(px)
+    }
+}
+
+impl From<u32> for Length {
+    fn from(px: u32) -> Self {
+        Self::Px(px.into())
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ImageSize {
     PxWidth(Px),
     PxHeight(Px),
+    /// Percentage of the available content width
+    PercentWidth(f32),
+    /// Percentage of the window's height
+    PercentHeight(f32),
 }
 
 impl ImageSize {
-    pub fn width<P: Into<Px>>(px: P) -> Self {
-        Self::PxWidth(px.into())
+    pub fn width<L: Into<Length>>(length: L) -> Self {
+        match length.into() {
+            Length::Px(px) => Self::PxWidth(px),
+            Length::Percent(percent) => Self::PercentWidth(percent),
+        }
+    }
+
+    pub fn height<L: Into<Length>>(length: L) -> Self {
+        match length.into() {
+            Length::Px(px) => Self::PxHeight(px),
+            Length::Percent(percent) => Self::PercentHeight(percent),
+        }
     }
 
-    pub fn height<P: Into<Px>>(px: P) -> Self {
-        Self::PxHeight(px.into())
+    /// The size to display an image at given its `natural` size
+    ///
+    /// Pixel sizes get scaled, while percentages are taken of the `max_width` that's available for
+    /// content and the screen's height
+    pub fn resolve(self, natural: Size, max_width: f32, screen_height: f32, scale: f32) -> Size {
+        let aspect = natural.1 / natural.0;
+        match self {
+            Self::PxWidth(Px(width)) => {
+                let width = width as f32;
+                (width * scale, width * aspect * scale)
+            }
+            Self::PxHeight(Px(height)) => {
+                let height = height as f32;
+                (height / aspect * scale, height * scale)
+            }
+            Self::PercentWidth(percent) => {
+                let width = max_width * percent / 100.;
+                (width, width * aspect)
+            }
+            Self::PercentHeight(percent) => {
+                let height = screen_height * percent / 100.;
+                (height / aspect, height)
+            }
+        }
     }
 }
 
@@ -105,12 +188,43 @@ impl ImageData {
         }
     }
 
+    /// Renders an SVG at the display's scale
+    pub fn from_svg(svg: &[u8], hidpi_scale: f32) -> anyhow::Result<Self> {
+        let opt = usvg::Options::default();
+        let mut tree = usvg::Tree::from_data(svg, &opt)?;
+        tree.size = tree.size.scale_to(
+            tiny_skia::Size::from_wh(
+                tree.size.width() * hidpi_scale,
+                tree.size.height() * hidpi_scale,
+            )
+            .context("Svg has invalid dimensions")?,
+        );
+        tree.postprocess(Default::default(), svg_fontdb());
+        let mut pixmap =
+            tiny_skia::Pixmap::new(tree.size.width() as u32, tree.size.height() as u32)
+                .context("Couldn't create svg pixmap")?;
+        resvg::render(&tree, tiny_skia::Transform::default(), &mut pixmap.as_mut());
+        let buffer = ImageBuffer::from_raw(pixmap.width(), pixmap.height(), pixmap.data().into())
+            .context("Svg buffer has invalid dimensions")?;
+        Ok(Self::new(buffer, false))
+    }
+
     fn rgba_image_byte_size(&self) -> usize {
         let (x, y) = self.dimensions;
         x as usize * y as usize * 4
     }
 }
 
+/// The fonts that text within SVGs gets drawn with
+pub fn svg_fontdb() -> &'static fontdb::Database {
+    static FONTDB: OnceLock<fontdb::Database> = OnceLock::new();
+    FONTDB.get_or_init(|| {
+        let mut db = fontdb::Database::new();
+        db.load_system_fonts();
+        db
+    })
+}
+
 #[derive(SmartDebug, Default)]
 pub struct Image {
     // TODO: Instead of sharing a mutex with the image loading thread change this to hold a oneshot
@@ -121,12 +235,35 @@ pub struct Image {
     pub is_aligned: Option<Align>,
     #[debug(skip_fn = Option::is_none, wrapper = DebugInline)]
     pub size: Option<ImageSize>,
+    /// The `width` and `height` attributes, when either is set, which hold the image's space while
+    /// it loads
+    #[debug(skip_fn = Option::is_none, wrapper = DebugInline)]
+    pub reserved: Option<(Option<Length>, Option<Length>)>,
     #[debug(skip)]
     pub bind_group: Option<Arc<wgpu::BindGroup>>,
     #[debug(skip_fn = Option::is_none, wrapper = DebugInline)]
     pub is_link: Option<String>,
+    /// Alt text that's shown while the image loads, and replaces it if loading fails (depending on
+    /// `on_error`)
+    #[debug(skip_fn = Option::is_none)]
+    pub alt: Option<TextBox>,
+    #[debug(skip)]
+    pub on_error: OnImageError,
+    #[debug(skip)]
+    pub load_failed: Arc<AtomicBool>,
     #[debug(skip)]
     pub hidpi_scale: f32,
+    /// Everything needed to load the image when it's been deferred until it's scrolled near
+    #[debug(skip)]
+    pending: Option<PendingLoad>,
+}
+
+struct PendingLoad {
+    src: String,
+    file_path: PathBuf,
+    allow_remote: bool,
+    max_image_bytes: usize,
+    image_callback: Box<dyn ImageCallback + Send>,
 }
 
 // NOTE: Internally performs some expensive operations. Avoid calling often
@@ -137,17 +274,27 @@ impl PartialEq for Image {
             image_data,
             is_aligned,
             size,
+            reserved,
             bind_group,
             is_link,
+            alt,
+            on_error,
+            load_failed: _,
             hidpi_scale,
+            pending: _,
         } = self;
         let Self {
             image_data: other_image_data,
             is_aligned: other_is_aligned,
             size: other_size,
+            reserved: other_reserved,
             bind_group: other_bind_group,
             is_link: other_is_link,
+            alt: other_alt,
+            on_error: other_on_error,
+            load_failed: _,
             hidpi_scale: other_hidpi_scale,
+            pending: _,
         } = other;
 
         let clone_image_data = |shared_image: &Mutex<Option<_>>| {
@@ -166,7 +313,10 @@ impl PartialEq for Image {
         image_data == other_image_data
             && is_aligned == other_is_aligned
             && size == other_size
+            && reserved == other_reserved
             && is_link == other_is_link
+            && alt == other_alt
+            && on_error == other_on_error
             && hidpi_scale == other_hidpi_scale
             && bind_group_variant_matches
     }
@@ -186,6 +336,7 @@ impl Image {
         queue: &wgpu::Queue,
         sampler: &wgpu::Sampler,
         bindgroup_layout: &wgpu::BindGroupLayout,
+        texture_cache: &mut TextureCache<Arc<BindGroup>>,
     ) -> Option<Arc<BindGroup>> {
         let dimensions = self.buffer_dimensions()?;
         if dimensions.0 == 0 || dimensions.1 == 0 {
@@ -193,6 +344,12 @@ impl Image {
             return None;
         }
 
+        if let Some(bind_group) = texture_cache.get(&self.image_data, dimensions) {
+            tracing::debug!("Reusing cached image texture");
+            self.bind_group = Some(bind_group.clone());
+            return Some(bind_group);
+        }
+
         let start = Instant::now();
         let rgba_image = self
             .image_data
@@ -253,6 +410,8 @@ impl Image {
             label: Some("Image Bind Group"),
         });
         let bind_group = Arc::new(bind_group);
+        histogram!(HistTag::ImageUpload).record(start.elapsed());
+        texture_cache.insert(&self.image_data, dimensions, bind_group.clone());
         self.bind_group = Some(bind_group.clone());
         Some(bind_group)
     }
@@ -261,12 +420,85 @@ impl Image {
         src: String,
         file_path: PathBuf,
         hidpi_scale: f32,
+        allow_remote: bool,
+        max_image_bytes: usize,
+        on_error: OnImageError,
         image_callback: Box<dyn ImageCallback + Send>,
     ) -> anyhow::Result<Image> {
-        let image_data = Arc::new(Mutex::new(None));
-        let image_data_clone = image_data.clone();
+        let mut image = Self::deferred(
+            src,
+            file_path,
+            hidpi_scale,
+            allow_remote,
+            max_image_bytes,
+            on_error,
+            image_callback,
+        );
+        image.start_loading();
+        Ok(image)
+    }
+
+    /// Like [`Image::from_src()`], but the image doesn't load until [`Image::start_loading()`] gets
+    /// called
+    pub fn deferred(
+        src: String,
+        file_path: PathBuf,
+        hidpi_scale: f32,
+        allow_remote: bool,
+        max_image_bytes: usize,
+        on_error: OnImageError,
+        image_callback: Box<dyn ImageCallback + Send>,
+    ) -> Image {
+        let pending = PendingLoad {
+            src,
+            file_path,
+            allow_remote,
+            max_image_bytes,
+            image_callback,
+        };
+        Image {
+            on_error,
+            hidpi_scale,
+            pending: Some(pending),
+            ..Default::default()
+        }
+    }
+
+    /// Whether the image is still waiting on [`Image::start_loading()`]
+    pub fn is_deferred(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Kicks off loading a deferred image in the background. Does nothing for any other image
+    pub fn start_loading(&mut self) {
+        let Some(PendingLoad {
+            src,
+            file_path,
+            allow_remote,
+            max_image_bytes,
+            image_callback,
+        }) = self.pending.take()
+        else {
+            return;
+        };
+        let image_data_clone = self.image_data.clone();
+        let load_failed_clone = self.load_failed.clone();
+        let on_error = self.on_error;
+        let hidpi_scale = self.hidpi_scale;
 
         std::thread::spawn(move || {
+            let fail = |src| {
+                match on_error {
+                    OnImageError::Placeholder => {
+                        *image_data_clone.lock().unwrap() = Some(broken_image());
+                    }
+                    OnImageError::Hide | OnImageError::Alt => {
+                        load_failed_clone.store(true, Ordering::Relaxed);
+                    }
+                }
+                image_callback.loaded_image(src, image_data_clone.clone());
+            };
+
             let start = Instant::now();
 
             let mut src_path = PathBuf::from(&src);
@@ -278,70 +510,46 @@ impl Image {
 
             let image_data = if let Ok(img_file) = fs::read(&src_path) {
                 img_file
-            } else if let Ok(bytes) = http_get_image(&src) {
-                bytes
-            } else {
-                tracing::warn!("Request for image from {} failed", src_path.display());
+            } else if !allow_remote {
+                warn_blocked_once(&src);
+                fail(src);
                 return;
+            } else {
+                match http_get_image(&src, max_image_bytes) {
+                    Ok(bytes) => bytes,
+                    Err(err) if err.is::<ImageTooLarge>() => {
+                        tracing::warn!("Rejected image from {src}: {err}");
+                        fail(src);
+                        return;
+                    }
+                    Err(_) => {
+                        tracing::warn!("Request for image from {} failed", src_path.display());
+                        fail(src);
+                        return;
+                    }
+                }
             };
 
-            let image = if let Ok(image) = ImageData::load(&image_data, true) {
-                image
-            } else {
-                let opt = usvg::Options::default();
-                // TODO: yes all of this image loading is very messy and could use a refactor
-                let Ok(mut tree) = usvg::Tree::from_data(&image_data, &opt) else {
+            // TODO: yes all of this image loading is very messy and could use a refactor
+            let image = match ImageData::load(&image_data, true)
+                .or_else(|_| ImageData::from_svg(&image_data, hidpi_scale))
+            {
+                Ok(image) => image,
+                Err(_) => {
                     tracing::warn!(
                         "Failed loading image:\n- src: {}\n- src_path: {}",
                         src,
                         src_path.display()
                     );
-                    let image =
-                        ImageData::load(include_bytes!("../../assets/img/broken.png"), false)
-                            .unwrap();
-                    *image_data_clone.lock().unwrap() = Some(image);
-                    image_callback.loaded_image(src, image_data_clone);
+                    fail(src);
                     return;
-                };
-                tree.size = tree.size.scale_to(
-                    tiny_skia::Size::from_wh(
-                        tree.size.width() * hidpi_scale,
-                        tree.size.height() * hidpi_scale,
-                    )
-                    .unwrap(),
-                );
-                static FONTDB: OnceLock<fontdb::Database> = OnceLock::new();
-                let fontdb = FONTDB.get_or_init(|| {
-                    let mut db = fontdb::Database::new();
-                    db.load_system_fonts();
-                    db
-                });
-                tree.postprocess(Default::default(), fontdb);
-                let mut pixmap =
-                    tiny_skia::Pixmap::new(tree.size.width() as u32, tree.size.height() as u32)
-                        .context("Couldn't create svg pixmap")
-                        .unwrap();
-                resvg::render(&tree, tiny_skia::Transform::default(), &mut pixmap.as_mut());
-                ImageData::new(
-                    ImageBuffer::from_raw(pixmap.width(), pixmap.height(), pixmap.data().into())
-                        .context("Svg buffer has invalid dimensions")
-                        .unwrap(),
-                    false,
-                )
+                }
             };
 
             *image_data_clone.lock().unwrap() = Some(image);
             histogram!(HistTag::ImageLoad).record(start.elapsed());
             image_callback.loaded_image(src, image_data_clone);
         });
-
-        let image = Image {
-            image_data,
-            hidpi_scale,
-            ..Default::default()
-        };
-
-        Ok(image)
     }
 
     pub fn from_image_data(image_data: Arc<Mutex<Option<ImageData>>>, hidpi_scale: f32) -> Image {
@@ -366,19 +574,70 @@ impl Image {
         self
     }
 
-    pub fn dimensions_from_image_size(&mut self, size: &ImageSize) -> Option<(u32, u32)> {
-        let image_dimensions = self.buffer_dimensions()?;
-        match size {
-            ImageSize::PxWidth(px_width) => Some((
-                px_width.0,
-                ((px_width.0 as f32 / image_dimensions.0 as f32) * image_dimensions.1 as f32)
-                    as u32,
-            )),
-            ImageSize::PxHeight(px_height) => Some((
-                ((px_height.0 as f32 / image_dimensions.1 as f32) * image_dimensions.0 as f32)
-                    as u32,
-                px_height.0,
-            )),
+    pub fn with_reserved(mut self, width: Option<Length>, height: Option<Length>) -> Self {
+        self.reserved = (width.is_some() || height.is_some()).then_some((width, height));
+        self
+    }
+
+    pub fn with_alt(mut self, alt: TextBox) -> Self {
+        self.alt = Some(alt);
+        self
+    }
+
+    /// Takes the alt text to show instead, once the image has failed to load
+    pub fn take_failed_alt(&mut self) -> Option<TextBox> {
+        if self.on_error == OnImageError::Alt && self.load_failed.load(Ordering::Relaxed) {
+            self.alt.take()
+        } else {
+            None
+        }
+    }
+
+    /// The alt text to show in the image's place while it's still loading
+    pub fn loading_alt(&self) -> Option<&TextBox> {
+        let loading =
+            self.image_data.lock().unwrap().is_none() && !self.load_failed.load(Ordering::Relaxed);
+        self.alt.as_ref().filter(|_| loading)
+    }
+
+    /// The space to hold for the image while it's still loading, if it has a `width` and `height`
+    ///
+    /// `page_size` is the page's width along with the window's height
+    pub fn placeholder_size(&self, page_size: Size, zoom: f32) -> Option<Size> {
+        match self.placeholder_sides(page_size, zoom) {
+            (Some(width), Some(height)) => Some((width, height)),
+            _ => None,
+        }
+    }
+
+    /// Like [`Self::placeholder_size()`], but for each of the `width` and `height` on its own
+    pub fn placeholder_sides(&self, page_size: Size, zoom: f32) -> (Option<f32>, Option<f32>) {
+        let Some((width, height)) = self.reserved else {
+            return (None, None);
+        };
+        let loading =
+            self.image_data.lock().unwrap().is_none() && !self.load_failed.load(Ordering::Relaxed);
+        if !loading {
+            return (None, None);
+        }
+
+        let max_width = (page_size.0 - 2. * DEFAULT_MARGIN).max(0.);
+        let scale = self.hidpi_scale * zoom;
+        let width = width.map(|width| match width {
+            Length::Px(Px(px)) => px as f32 * scale,
+            Length::Percent(percent) => max_width * percent / 100.,
+        });
+        let height = height.map(|height| match height {
+            Length::Px(Px(px)) => px as f32 * scale,
+            Length::Percent(percent) => page_size.1 * percent / 100.,
+        });
+        // Shrunk to fit the page just like the loaded image would be
+        match width {
+            Some(width) if width > max_width => (
+                Some(max_width),
+                height.map(|height| height * max_width / width),
+            ),
+            _ => (width, height),
         }
     }
 
@@ -386,7 +645,8 @@ impl Image {
         Some(self.image_data.lock().unwrap().as_ref()?.dimensions)
     }
 
-    fn dimensions(&mut self, screen_size: Size, zoom: f32) -> Option<(u32, u32)> {
+    /// `page_size` is the page's width along with the window's height
+    fn dimensions(&mut self, page_size: Size, zoom: f32) -> Option<(u32, u32)> {
         let buffer_size = self.buffer_dimensions()?;
         let mut buffer_size = (buffer_size.0 as f32 * zoom, buffer_size.1 as f32 * zoom);
         if let Some(image) = self.image_data.lock().as_deref().unwrap() {
@@ -395,13 +655,13 @@ impl Image {
                 buffer_size.1 *= self.hidpi_scale;
             }
         }
-        let max_width = screen_size.0 - 2. * DEFAULT_MARGIN;
+        let max_width = page_size.0 - 2. * DEFAULT_MARGIN;
         let dimensions = if let Some(size) = self.size {
-            let dimensions = self.dimensions_from_image_size(&size)?;
-            let target_dimensions = (
-                (dimensions.0 as f32 * self.hidpi_scale * zoom) as u32,
-                (dimensions.1 as f32 * self.hidpi_scale * zoom) as u32,
-            );
+            let natural = self.buffer_dimensions()?;
+            let natural = (natural.0 as f32, natural.1 as f32);
+            let target_dimensions =
+                size.resolve(natural, max_width, page_size.1, self.hidpi_scale * zoom);
+            let target_dimensions = (target_dimensions.0 as u32, target_dimensions.1 as u32);
             if target_dimensions.0 > max_width as u32 {
                 (
                     max_width as u32,
@@ -421,32 +681,83 @@ impl Image {
         Some(dimensions)
     }
 
-    pub fn size(&mut self, screen_size: Size, zoom: f32) -> Option<Size> {
-        self.dimensions(screen_size, zoom)
+    pub fn size(&mut self, page_size: Size, zoom: f32) -> Option<Size> {
+        self.dimensions(page_size, zoom)
             .map(|d| (d.0 as f32, d.1 as f32))
     }
 }
 
-pub fn http_get_image(url: &str) -> anyhow::Result<Vec<u8>> {
+/// The zoom that fits an image of `size` (at the default zoom) within `available`
+///
+/// Returns `None` when there's no sensible zoom, like when either is empty
+pub fn zoom_to_fit(size: Size, available: Size) -> Option<f32> {
+    let zoom = (available.0 / size.0).min(available.1 / size.1);
+    (zoom.is_finite() && zoom > 0.).then_some(zoom)
+}
+
+/// Default for the largest remote image that will be downloaded
+pub const DEFAULT_MAX_IMAGE_BYTES: usize = 20 * 1_024 * 1_024;
+
+/// A remote image was rejected for being larger than the configured limit
+#[derive(Debug)]
+pub struct ImageTooLarge {
+    pub limit: usize,
+}
+
+impl std::fmt::Display for ImageTooLarge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Image exceeds the maximum size of {:.2} MiB",
+            usize_in_mib(self.limit)
+        )
+    }
+}
+
+impl std::error::Error for ImageTooLarge {}
+
+/// Logs that the image at `src` wasn't downloaded, but only the first time for each `src` since
+/// reloading a document would repeat it otherwise
+fn warn_blocked_once(src: &str) {
+    static BLOCKED: OnceLock<Mutex<HashSet<String>>> = OnceLock::new();
+    let mut blocked = BLOCKED.get_or_init(Default::default).lock().unwrap();
+    if blocked.insert(src.to_owned()) {
+        tracing::warn!("Skipped downloading image from {src} since remote images are disabled");
+    }
+}
+
+/// Downloads an image, failing with [`ImageTooLarge`] instead of reading more than `max_bytes`
+pub fn http_get_image(url: &str, max_bytes: usize) -> anyhow::Result<Vec<u8>> {
     const USER_AGENT: &str = concat!(
         "inlyne ",
         env!("CARGO_PKG_VERSION"),
         " https://github.com/Inlyne-Project/inlyne"
     );
 
-    const LIMIT: usize = 20 * 1_024 * 1_024;
-
+    let too_large = ImageTooLarge { limit: max_bytes };
     let resp = ureq::get(url).set("User-Agent", USER_AGENT).call()?;
     let len = resp
         .header("Content-Length")
         .and_then(|len| len.parse::<usize>().ok());
-    let mut body = Vec::with_capacity(len.unwrap_or(0).clamp(0, LIMIT));
+    // Bail before reading anything when we already know that it's too big
+    if len.is_some_and(|len| len > max_bytes) {
+        return Err(too_large.into());
+    }
+    let mut body = Vec::with_capacity(len.unwrap_or(0));
+    // Read one past the limit to tell a body that's exactly the limit from one that's over
     resp.into_reader()
-        .take(u64::try_from(LIMIT).unwrap())
+        .take(u64::try_from(max_bytes).unwrap().saturating_add(1))
         .read_to_end(&mut body)?;
+    if body.len() > max_bytes {
+        return Err(too_large.into());
+    }
     Ok(body)
 }
 
+fn broken_image() -> ImageData {
+    ImageData::load(include_bytes!("../../assets/img/broken.png"), false).unwrap()
+}
+
 #[repr(C)]
 #[derive(Clone, Copy, Pod, Zeroable, Debug)]
 pub struct ImageVertex {
@@ -459,6 +770,7 @@ pub struct ImageRenderer {
     pub index_buf: wgpu::Buffer,
     pub bindgroup_layout: wgpu::BindGroupLayout,
     pub sampler: wgpu::Sampler,
+    pub texture_cache: TextureCache<Arc<BindGroup>>,
 }
 
 pub fn point(x: f32, y: f32, position: Point, size: Size, screen: Size) -> [f32; 3] {
@@ -561,6 +873,7 @@ impl ImageRenderer {
             index_buf,
             bindgroup_layout: texture_bind_group_layout,
             sampler,
+            texture_cache: TextureCache::new(TEXTURE_CACHE_CAPACITY),
         }
     }
 
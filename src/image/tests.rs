@@ -1,8 +1,21 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::{fmt, fs};
 
-use super::{ImageData, Px};
-use crate::test_utils::log;
+use std::sync::atomic::Ordering;
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
+
+use super::{
+    http_get_image, zoom_to_fit, Image, ImageData, ImageSize, ImageTooLarge, Length, LoadedImages,
+    Px, TextureCache,
+};
+use crate::interpreter::ImageCallback;
+use crate::opts::OnImageError;
+use crate::positioner::DEFAULT_MARGIN;
+use crate::test_utils::{log, server};
+use crate::text::{Text, TextBox};
+
+use image::RgbaImage;
 
 #[test]
 fn px_parsing() {
@@ -10,6 +23,203 @@ fn px_parsing() {
     assert_eq!("500px".parse::<Px>().unwrap(), Px(500));
 }
 
+#[test]
+fn length_parsing() {
+    assert_eq!("200".parse::<Length>().unwrap(), Length::Px(Px(200)));
+    assert_eq!("200px".parse::<Length>().unwrap(), Length::Px(Px(200)));
+    assert_eq!(" 50% ".parse::<Length>().unwrap(), Length::Percent(50.));
+    assert_eq!("12.5%".parse::<Length>().unwrap(), Length::Percent(12.5));
+    for malformed in ["", "%", "-10%", "wide", "20em", "1.5px"] {
+        assert!(malformed.parse::<Length>().is_err(), "{malformed:?}");
+    }
+}
+
+#[test]
+fn resolve_image_size() {
+    let natural = (400., 200.);
+    let (max_width, screen_height) = (1000., 600.);
+    let resolve = |size: ImageSize| size.resolve(natural, max_width, screen_height, 2.);
+
+    // Pixel sizes keep the aspect ratio and get scaled
+    assert_eq!(resolve(ImageSize::width(100)), (200., 100.));
+    assert_eq!(resolve(ImageSize::height(100)), (400., 200.));
+    // while percentages are of the space that's available
+    assert_eq!(
+        resolve(ImageSize::width(Length::Percent(50.))),
+        (500., 250.)
+    );
+    assert_eq!(
+        resolve(ImageSize::height(Length::Percent(50.))),
+        (600., 300.)
+    );
+}
+
+#[test]
+fn fit_single_image() {
+    // Large images shrink to fit whichever dimension is tighter
+    assert_eq!(zoom_to_fit((2000., 1000.), (1000., 800.)), Some(0.5));
+    assert_eq!(zoom_to_fit((1000., 2000.), (1000., 800.)), Some(0.4));
+    // Small ones grow
+    assert_eq!(zoom_to_fit((250., 100.), (1000., 800.)), Some(4.));
+    assert_eq!(zoom_to_fit((0., 0.), (1000., 800.)), None);
+    assert_eq!(zoom_to_fit((250., 100.), (0., 800.)), None);
+}
+
+#[test]
+fn texture_cache() {
+    let image_a = Arc::new(Mutex::new(None));
+    let image_b = Arc::new(Mutex::new(None));
+    let mut cache = TextureCache::new(2);
+    assert_eq!(cache.get(&image_a, (10, 10)), None);
+
+    // Entries are for the exact same image data and dimensions
+    cache.insert(&image_a, (10, 10), 1);
+    assert_eq!(cache.get(&image_a, (10, 10)), Some(1));
+    assert_eq!(cache.get(&image_a, (20, 20)), None);
+    assert_eq!(cache.get(&Arc::new(Mutex::new(None)), (10, 10)), None);
+
+    // Using an entry keeps it from being the next one evicted
+    cache.insert(&image_b, (10, 10), 2);
+    assert_eq!(cache.get(&image_a, (10, 10)), Some(1));
+    cache.insert(&image_b, (20, 20), 3);
+    assert_eq!(cache.get(&image_b, (10, 10)), None);
+    assert_eq!(cache.get(&image_a, (10, 10)), Some(1));
+    assert_eq!(cache.get(&image_b, (20, 20)), Some(3));
+
+    // Dropped image data gets cleared out to make room
+    drop(image_a);
+    let image_c = Arc::new(Mutex::new(None));
+    cache.insert(&image_c, (10, 10), 4);
+    cache.insert(&image_c, (20, 20), 5);
+    assert_eq!(cache.get(&image_b, (20, 20)), None);
+    assert_eq!(cache.get(&image_c, (10, 10)), Some(4));
+}
+
+#[test]
+fn alt_text_while_loading() {
+    let alt = TextBox::new(vec![Text::new("Logo".into(), 1., [0.; 4])], 1.);
+    let image = |on_error| Image {
+        on_error,
+        ..Image::default().with_alt(alt.clone())
+    };
+
+    let mut loading = image(OnImageError::Alt);
+    assert_eq!(loading.loading_alt(), Some(&alt));
+    assert_eq!(loading.take_failed_alt(), None);
+
+    // A lone `width` or `height` still holds its side of the space over the alt text's
+    let page_size = (1000. + 2. * DEFAULT_MARGIN, 600.);
+    let wide = Image {
+        hidpi_scale: 1.,
+        ..image(OnImageError::Alt).with_reserved(Some(Length::Px(Px(120))), None)
+    };
+    assert_eq!(wide.placeholder_sides(page_size, 2.), (Some(240.), None));
+    assert_eq!(wide.placeholder_size(page_size, 2.), None);
+    let tall = image(OnImageError::Alt).with_reserved(None, Some(Length::Percent(10.)));
+    assert_eq!(tall.placeholder_sides(page_size, 1.), (None, Some(60.)));
+
+    let loaded = image(OnImageError::Alt);
+    *loaded.image_data.lock().unwrap() = Some(ImageData::new(RgbaImage::new(1, 1), false));
+    assert_eq!(loaded.loading_alt(), None);
+
+    // Failing either swaps in the alt text for good or leaves nothing behind
+    let mut failed = image(OnImageError::Alt);
+    failed.load_failed.store(true, Ordering::Relaxed);
+    assert_eq!(failed.loading_alt(), None);
+    assert_eq!(failed.take_failed_alt(), Some(alt.clone()));
+    let mut hidden = image(OnImageError::Hide);
+    hidden.load_failed.store(true, Ordering::Relaxed);
+    assert_eq!(hidden.loading_alt(), None);
+    assert_eq!(hidden.take_failed_alt(), None);
+}
+
+#[test]
+fn deferred_image_placeholder() {
+    struct Sender(mpsc::Sender<String>);
+
+    impl ImageCallback for Sender {
+        fn loaded_image(&self, src: String, _: Arc<Mutex<Option<ImageData>>>) {
+            self.0.send(src).unwrap();
+        }
+    }
+
+    let (tx, rx) = mpsc::channel();
+    let mut image = Image::deferred(
+        "missing.png".into(),
+        PathBuf::from("does_not_exist.md"),
+        2.,
+        false,
+        100,
+        OnImageError::Hide,
+        Box::new(Sender(tx)),
+    )
+    .with_reserved(Some(Length::Px(Px(300))), Some(Length::Percent(50.)));
+
+    // Holds the attributes' space, scaled and shrunk to fit the page
+    let page_size = (1000. + 2. * DEFAULT_MARGIN, 600.);
+    assert_eq!(image.placeholder_size(page_size, 1.), Some((600., 300.)));
+    assert_eq!(image.placeholder_size(page_size, 2.), Some((1000., 250.)));
+    assert!(image.is_deferred());
+    assert!(rx.recv_timeout(Duration::from_millis(100)).is_err());
+
+    image.start_loading();
+    assert!(!image.is_deferred());
+    assert_eq!(rx.recv().unwrap(), "missing.png");
+    // There's nothing left to hold space for once it's failed
+    assert_eq!(image.placeholder_size(page_size, 1.), None);
+}
+
+#[test]
+fn loaded_images() {
+    let image = || Arc::new(Mutex::new(None));
+    let mut cache = LoadedImages::new(2);
+    let (a, b, c) = (image(), image(), image());
+
+    // The least recently used image gets evicted first
+    cache.insert("a".to_owned(), Arc::clone(&a));
+    cache.insert("b".to_owned(), Arc::clone(&b));
+    assert!(Arc::ptr_eq(&cache.get("a").unwrap(), &a));
+    cache.insert("c".to_owned(), Arc::clone(&c));
+    assert!(cache.get("b").is_none());
+    assert!(cache.get("a").is_some());
+    assert!(cache.get("c").is_some());
+
+    // Hard reloads start over with nothing
+    cache.clear();
+    assert!(cache.get("a").is_none());
+    assert!(cache.get("c").is_none());
+}
+
+#[test]
+fn loaded_images_after_reload() {
+    let mut cache = LoadedImages::default();
+
+    // A document with three images
+    cache.start_document();
+    for src in ["a", "b", "c"] {
+        assert!(cache.get(src).is_none());
+        cache.insert(src.to_owned(), Arc::new(Mutex::new(None)));
+    }
+    cache.finish_document();
+    for src in ["a", "b", "c"] {
+        assert!(cache.get(src).is_some());
+    }
+
+    // Gets reloaded with only one of them left
+    cache.start_document();
+    assert!(cache.get("b").is_some());
+    cache.finish_document();
+    assert!(cache.get("a").is_none());
+    assert!(cache.get("b").is_some());
+    assert!(cache.get("c").is_none());
+
+    // Images that finish loading after the document was interpreted still stick around
+    cache.start_document();
+    cache.finish_document();
+    cache.insert("d".to_owned(), Arc::new(Mutex::new(None)));
+    assert!(cache.get("d").is_some());
+}
+
 // Checks that the image crate converting to RGBA8 is the same as our technique
 fn check(input_path: &Path) {
     let bytes = fs::read(input_path).unwrap();
@@ -78,3 +288,17 @@ impl fmt::Debug for Rgba8Data {
             .finish()
     }
 }
+
+#[test]
+fn max_image_bytes() {
+    log::init();
+
+    let bytes = vec![0; 100];
+    let files = vec![server::File::new("/image.png", "image/png", &bytes)];
+    let (_server, server_url) = server::mock_file_server(files);
+    let url = server_url + "/image.png";
+
+    assert_eq!(http_get_image(&url, 100).unwrap(), bytes);
+    let err = http_get_image(&url, 99).unwrap_err();
+    assert!(err.is::<ImageTooLarge>(), "{err}");
+}
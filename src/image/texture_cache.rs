@@ -0,0 +1,66 @@
+//! Reusing the GPU textures of images between documents
+//!
+//! Re-interpreting a document (reloads, theme changes, going back in history) creates new image
+//! elements, but remote images share their data through the image cache. Keeping their textures
+//! around means skipping a full decompress and re-upload for each one
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, Weak};
+
+use super::ImageData;
+
+/// How many textures to hold on to
+pub const TEXTURE_CACHE_CAPACITY: usize = 32;
+
+type SharedImageData = Mutex<Option<ImageData>>;
+
+struct Entry<T> {
+    image_data: Weak<SharedImageData>,
+    dimensions: (u32, u32),
+    texture: T,
+}
+
+/// The least recently used textures get evicted first
+pub struct TextureCache<T> {
+    capacity: usize,
+    entries: VecDeque<Entry<T>>,
+}
+
+impl<T: Clone> TextureCache<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: VecDeque::new(),
+        }
+    }
+
+    pub fn get(&mut self, image_data: &Arc<SharedImageData>, dimensions: (u32, u32)) -> Option<T> {
+        let index = self.entries.iter().position(|entry| {
+            entry.dimensions == dimensions
+                && Weak::as_ptr(&entry.image_data) == Arc::as_ptr(image_data)
+        })?;
+        let entry = self.entries.remove(index)?;
+        let texture = entry.texture.clone();
+        self.entries.push_back(entry);
+        Some(texture)
+    }
+
+    pub fn insert(
+        &mut self,
+        image_data: &Arc<SharedImageData>,
+        dimensions: (u32, u32),
+        texture: T,
+    ) {
+        // Textures for data that's gone can never be hit again
+        self.entries
+            .retain(|entry| entry.image_data.strong_count() > 0);
+        self.entries.push_back(Entry {
+            image_data: Arc::downgrade(image_data),
+            dimensions,
+            texture,
+        });
+        while self.entries.len() > self.capacity {
+            self.entries.pop_front();
+        }
+    }
+}
@@ -0,0 +1,81 @@
+//! Coalescing repositions while images stream in
+//!
+//! Every loaded image changes the layout, but repositioning for each one thrashes on image-heavy
+//! pages. The first image in a while gets repositioned for right away, while any others that load
+//! soon after get batched together into a single reposition once the window elapses
+
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_IMAGE_BATCH: Duration = Duration::from_millis(50);
+
+#[derive(Debug)]
+pub struct ImageBatch {
+    window: Duration,
+    last_flush: Option<Instant>,
+    pending: usize,
+}
+
+impl ImageBatch {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            last_flush: None,
+            pending: 0,
+        }
+    }
+
+    pub fn loaded(&mut self) {
+        self.pending += 1;
+    }
+
+    /// Takes the size of the pending batch if it's time to reposition for it
+    pub fn flush(&mut self, now: Instant) -> Option<usize> {
+        if self.pending == 0 || self.deadline().map_or(false, |deadline| now < deadline) {
+            return None;
+        }
+
+        self.last_flush = Some(now);
+        Some(std::mem::take(&mut self.pending))
+    }
+
+    /// When the pending batch is due, if there is one
+    pub fn deadline(&self) -> Option<Instant> {
+        if self.pending == 0 {
+            return None;
+        }
+        self.last_flush.map(|last_flush| last_flush + self.window)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coalesces_bursts() {
+        let window = Duration::from_millis(50);
+        let start = Instant::now();
+        let mut batch = ImageBatch::new(window);
+        assert_eq!(batch.flush(start), None);
+
+        // A lone image repositions right away
+        batch.loaded();
+        assert_eq!(batch.deadline(), None);
+        assert_eq!(batch.flush(start), Some(1));
+
+        // while a burst right after it waits for the window to elapse
+        let soon = start + Duration::from_millis(10);
+        batch.loaded();
+        batch.loaded();
+        batch.loaded();
+        assert_eq!(batch.flush(soon), None);
+        assert_eq!(batch.deadline(), Some(start + window));
+        assert_eq!(batch.flush(start + window), Some(3));
+        assert_eq!(batch.deadline(), None);
+
+        // Things are back to prompt after a quiet period
+        let later = start + window * 4;
+        batch.loaded();
+        assert_eq!(batch.flush(later), Some(1));
+    }
+}
@@ -1,6 +1,7 @@
 use std::slice;
 
-use crate::{image::Px, opts::ResolvedTheme, utils::Align};
+use super::OrderedMarker;
+use crate::{image::Length, opts::ResolvedTheme, utils::Align};
 
 use html5ever::{local_name, Attribute};
 
@@ -22,17 +23,27 @@ impl Iterator for Iter<'_> {
                 local_name!("align") => Align::new(value).map(Attr::Align),
                 local_name!("href") => Some(Attr::Href(value.to_string())),
                 local_name!("id") => Some(Attr::Anchor(format!("#{value}"))),
-                local_name!("width") => value.parse().ok().map(Attr::Width),
-                local_name!("height") => value.parse().ok().map(Attr::Height),
+                local_name!("width") => parse_length("width", value).map(Attr::Width),
+                local_name!("height") => parse_length("height", value).map(Attr::Height),
                 local_name!("src") => Some(Attr::Src(value.to_string())),
+                local_name!("alt") => Some(Attr::Alt(value.to_string())),
                 local_name!("start") => value.parse().ok().map(Attr::Start),
+                local_name!("reversed") => Some(Attr::Reversed),
+                local_name!("value") => value.parse().ok().map(Attr::Value),
                 local_name!("style") => Some(Attr::Style(value.to_string())),
-                local_name!("type") => {
-                    (value.to_string() == "checkbox").then_some(Attr::IsCheckbox)
-                }
+                local_name!("type") => match &**value {
+                    "checkbox" => Some(Attr::IsCheckbox),
+                    other => OrderedMarker::new(other).map(Attr::OrderedMarker),
+                },
                 local_name!("checked") => Some(Attr::IsChecked),
                 local_name!("media") => PrefersColorScheme::new(value).map(Attr::Media),
                 local_name!("srcset") => Some(Attr::SrcSet(value.to_string())),
+                ref other if &**other == "data-math-style" => {
+                    MathStyle::new(value).map(Attr::MathStyle)
+                }
+                ref other if &**other == "data-sourcepos" => {
+                    parse_sourcepos_start(value).map(Attr::SourceStart)
+                }
                 _ => continue,
             };
 
@@ -43,19 +54,47 @@ impl Iterator for Iter<'_> {
     }
 }
 
+/// Malformed lengths get ignored instead of taking the whole element down with them
+fn parse_length(attr: &str, value: &str) -> Option<Length> {
+    match value.parse() {
+        Ok(length) => Some(length),
+        Err(err) => {
+            tracing::warn!("Ignoring invalid {attr}=\"{value}\": {err}");
+            None
+        }
+    }
+}
+
+/// The `(line, column)` that a `data-sourcepos="1:1-2:10"` starts at
+fn parse_sourcepos_start(value: &str) -> Option<(usize, usize)> {
+    let (start, _end) = value.split_once('-')?;
+    let (line, column) = start.split_once(':')?;
+    Some((line.parse().ok()?, column.parse().ok()?))
+}
+
 pub enum Attr {
     Align(Align),
     Href(String),
     Anchor(String),
-    Width(Px),
-    Height(Px),
+    Width(Length),
+    Height(Length),
     Src(String),
+    Alt(String),
     Start(usize),
+    /// An ordered list that counts down
+    Reversed,
+    /// Sets a list item's number, which the following items continue on from
+    Value(usize),
+    OrderedMarker(OrderedMarker),
     Style(String),
     IsCheckbox,
     IsChecked,
     Media(PrefersColorScheme),
     SrcSet(String),
+    /// Marks a span of `$...$` or `$$...$$` math
+    MathStyle(MathStyle),
+    /// Where the markdown for an element starts as a `(line, column)`
+    SourceStart((usize, usize)),
 }
 
 impl Attr {
@@ -68,6 +107,22 @@ impl Attr {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MathStyle {
+    Inline,
+    Display,
+}
+
+impl MathStyle {
+    pub fn new(s: &str) -> Option<Self> {
+        match s {
+            "inline" => Some(Self::Inline),
+            "display" => Some(Self::Display),
+            _ => None,
+        }
+    }
+}
+
 pub struct PrefersColorScheme(pub ResolvedTheme);
 
 impl PrefersColorScheme {
@@ -1,4 +1,5 @@
-use super::{picture, Header, List, ListType};
+use super::{picture, Header, List, ListType, OrderedMarker};
+use crate::table::TableLimits;
 use crate::utils::Align;
 use crate::{Section, Table, TextBox};
 
@@ -22,8 +23,12 @@ impl From<picture::Builder> for Element {
 }
 
 impl Element {
-    pub fn table() -> Self {
-        Self::Table(Table::new())
+    pub fn table(align: Align, limits: TableLimits) -> Self {
+        Self::Table(Table {
+            align,
+            limits,
+            ..Table::new()
+        })
     }
 
     pub fn table_row() -> Self {
@@ -36,9 +41,13 @@ impl Element {
         })
     }
 
-    pub fn ordered_list(start_index: usize) -> Self {
+    pub fn ordered_list(start_index: usize, marker: OrderedMarker, reversed: bool) -> Self {
         Self::List(List {
-            ty: ListType::Ordered(start_index),
+            ty: ListType::Ordered {
+                index: start_index,
+                marker,
+                reversed,
+            },
         })
     }
 
@@ -0,0 +1,83 @@
+//! Counting the items of reversed lists ahead of time
+//!
+//! A reversed `<ol>` without a `start` counts down from its number of items, but the interpreter
+//! only sees the list one token at a time. A quick first pass over the document finds the counts
+
+use std::collections::VecDeque;
+use std::str::FromStr;
+
+use super::{attr, Attr, TagName};
+
+use html5ever::tendril::*;
+use html5ever::tokenizer::{
+    BufferQueue, TagKind, Token, TokenSink, TokenSinkResult, Tokenizer, TokenizerOpts,
+};
+
+#[derive(Default)]
+struct Counter {
+    /// For each open list: the slot to fill in for reversed lists along with its item count
+    open: Vec<(Option<usize>, usize)>,
+    lengths: Vec<usize>,
+}
+
+impl TokenSink for Counter {
+    type Handle = ();
+
+    fn process_token(&mut self, token: Token, _line_number: u64) -> TokenSinkResult<()> {
+        let Token::TagToken(tag) = token else {
+            return TokenSinkResult::Continue;
+        };
+        let Ok(tag_name) = TagName::try_from(&tag.name) else {
+            return TokenSinkResult::Continue;
+        };
+
+        match (tag.kind, tag_name) {
+            (TagKind::StartTag, TagName::OrderedList) => {
+                let reversed =
+                    attr::Iter::new(&tag.attrs).any(|attr| matches!(attr, Attr::Reversed));
+                let slot = reversed.then(|| {
+                    self.lengths.push(0);
+                    self.lengths.len() - 1
+                });
+                self.open.push((slot, 0));
+            }
+            (TagKind::StartTag, TagName::UnorderedList) => self.open.push((None, 0)),
+            (TagKind::StartTag, TagName::ListItem) => {
+                if let Some((_, count)) = self.open.last_mut() {
+                    *count += 1;
+                }
+            }
+            (TagKind::EndTag, TagName::OrderedList | TagName::UnorderedList) => {
+                if let Some((Some(slot), count)) = self.open.pop() {
+                    self.lengths[slot] = count;
+                }
+            }
+            _ => {}
+        }
+
+        TokenSinkResult::Continue
+    }
+}
+
+/// The number of items in each reversed ordered list within `html`, in the order they're opened
+pub fn reversed_list_lengths(html: &str) -> VecDeque<usize> {
+    let mut input = BufferQueue::default();
+    input.push_back(
+        Tendril::from_str(html)
+            .unwrap()
+            .try_reinterpret::<fmt::UTF8>()
+            .unwrap(),
+    );
+    let mut tok = Tokenizer::new(Counter::default(), TokenizerOpts::default());
+    let _ = tok.feed(&mut input);
+    tok.end();
+
+    // Lists that never got closed still count what they had
+    let Counter { open, mut lengths } = tok.sink;
+    for (slot, count) in open {
+        if let Some(slot) = slot {
+            lengths[slot] = count;
+        }
+    }
+    lengths.into()
+}
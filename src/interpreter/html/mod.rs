@@ -1,14 +1,17 @@
 pub mod attr;
 mod element;
+mod list_lengths;
 pub mod picture;
 pub mod style;
 mod tag_name;
 
 pub use attr::Attr;
 pub use element::Element;
+pub use list_lengths::reversed_list_lengths;
 pub use picture::Picture;
 pub use tag_name::TagName;
 
+use crate::text::Script;
 use crate::utils::Align;
 
 use html5ever::Attribute;
@@ -43,6 +46,17 @@ pub enum HeaderType {
 }
 
 impl HeaderType {
+    pub fn level(&self) -> u8 {
+        match self {
+            HeaderType::H1 => 1,
+            HeaderType::H2 => 2,
+            HeaderType::H3 => 3,
+            HeaderType::H4 => 4,
+            HeaderType::H5 => 5,
+            HeaderType::H6 => 6,
+        }
+    }
+
     // https://html.spec.whatwg.org/multipage/rendering.html#sections-and-headings
     pub fn size_multiplier(&self) -> f32 {
         match self {
@@ -69,10 +83,101 @@ impl Header {
 
 #[derive(Debug)]
 pub enum ListType {
-    Ordered(usize),
+    Ordered {
+        index: usize,
+        marker: OrderedMarker,
+        reversed: bool,
+    },
     Unordered,
 }
 
+/// The numbering style of an ordered list, set through its `type` attribute
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OrderedMarker {
+    #[default]
+    Decimal,
+    LowerAlpha,
+    UpperAlpha,
+    LowerRoman,
+    UpperRoman,
+}
+
+impl OrderedMarker {
+    pub fn new(s: &str) -> Option<Self> {
+        match s {
+            "1" => Some(Self::Decimal),
+            "a" => Some(Self::LowerAlpha),
+            "A" => Some(Self::UpperAlpha),
+            "i" => Some(Self::LowerRoman),
+            "I" => Some(Self::UpperRoman),
+            _ => None,
+        }
+    }
+
+    /// The prefix for the list item at `index`
+    ///
+    /// Indices that can't be represented in the marker's style (like `0` as a letter) fall back to
+    /// decimal
+    pub fn prefix(self, index: usize) -> String {
+        let marker = match self {
+            Self::Decimal => None,
+            Self::LowerAlpha => alpha(index),
+            Self::UpperAlpha => alpha(index).map(|s| s.to_ascii_uppercase()),
+            Self::LowerRoman => roman(index),
+            Self::UpperRoman => roman(index).map(|s| s.to_ascii_uppercase()),
+        };
+        let marker = marker.unwrap_or_else(|| index.to_string());
+        format!("{marker}. ")
+    }
+}
+
+/// `a`, `b`, ..., `z`, `aa`, `ab`, ...
+fn alpha(mut index: usize) -> Option<String> {
+    if index == 0 {
+        return None;
+    }
+
+    let mut letters = Vec::new();
+    while index > 0 {
+        index -= 1;
+        letters.push(b'a' + (index % 26) as u8);
+        index /= 26;
+    }
+    letters.reverse();
+    String::from_utf8(letters).ok()
+}
+
+fn roman(mut index: usize) -> Option<String> {
+    const NUMERALS: [(usize, &str); 13] = [
+        (1000, "m"),
+        (900, "cm"),
+        (500, "d"),
+        (400, "cd"),
+        (100, "c"),
+        (90, "xc"),
+        (50, "l"),
+        (40, "xl"),
+        (10, "x"),
+        (9, "ix"),
+        (5, "v"),
+        (4, "iv"),
+        (1, "i"),
+    ];
+
+    if !(1..4000).contains(&index) {
+        return None;
+    }
+
+    let mut numeral = String::new();
+    for (value, symbol) in NUMERALS {
+        while index >= value {
+            numeral.push_str(symbol);
+            index -= value;
+        }
+    }
+    Some(numeral)
+}
+
 pub struct List {
     pub ty: ListType,
 }
@@ -86,7 +191,10 @@ pub struct TextOptions {
     pub strike_through: usize,
     pub small: usize,
     pub code: usize,
+    pub kbd: usize,
     pub pre_formatted: usize,
     pub block_quote: usize,
     pub link: Vec<String>,
+    /// Nested `<sup>`s and `<sub>`s where the innermost one wins
+    pub script: Vec<Script>,
 }
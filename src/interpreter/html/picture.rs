@@ -1,4 +1,4 @@
-use crate::image::ImageSize;
+use crate::image::{ImageSize, Length};
 use crate::opts::ResolvedTheme;
 use crate::utils::Align;
 
@@ -10,6 +10,9 @@ pub struct Inner {
     pub dark_variant: Option<String>,
     pub light_variant: Option<String>,
     pub size: Option<ImageSize>,
+    pub width: Option<Length>,
+    pub height: Option<Length>,
+    pub alt: Option<String>,
 }
 
 #[derive(Debug, Default)]
@@ -31,8 +34,18 @@ impl Builder {
         self.inner.light_variant = Some(light);
     }
 
-    pub fn set_size(&mut self, size: ImageSize) {
-        self.inner.size = Some(size);
+    pub fn set_width(&mut self, width: Length) {
+        self.inner.size = Some(ImageSize::width(width));
+        self.inner.width = Some(width);
+    }
+
+    pub fn set_height(&mut self, height: Length) {
+        self.inner.size = Some(ImageSize::height(height));
+        self.inner.height = Some(height);
+    }
+
+    pub fn set_alt(&mut self, alt: String) {
+        self.inner.alt = Some(alt);
     }
 
     pub fn set_src(&mut self, src: String) {
@@ -61,7 +74,8 @@ impl Picture {
         scheme
             .and_then(|scheme| match scheme {
                 ResolvedTheme::Dark => self.inner.dark_variant.as_ref(),
-                ResolvedTheme::Light => self.inner.light_variant.as_ref(),
+                // Mono is black on white
+                ResolvedTheme::Light | ResolvedTheme::Mono => self.inner.light_variant.as_ref(),
             })
             .unwrap_or(&self.src)
     }
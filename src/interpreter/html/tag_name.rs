@@ -19,6 +19,7 @@ pub enum TagName {
     Source,
     Image,
     Input,
+    Kbd,
     ListItem,
     OrderedList,
     Paragraph,
@@ -27,13 +28,16 @@ pub enum TagName {
     Small,
     Span,
     Strikethrough,
+    Subscript,
     Summary,
+    Superscript,
     Table,
     TableBody,
     TableDataCell,
     TableHead,
     TableHeader,
     TableRow,
+    Time,
     Underline,
     UnorderedList,
 }
@@ -47,10 +51,14 @@ impl TryFrom<&Atom<LocalNameStaticSet>> for TagName {
             &local_name!("blockquote") => Self::BlockQuote,
             &local_name!("b") | &local_name!("strong") => Self::BoldOrStrong,
             &local_name!("br") => Self::Break,
-            &local_name!("code") | &local_name!("kbd") => Self::Code,
+            &local_name!("code") | &local_name!("samp") => Self::Code,
             &local_name!("details") => Self::Details,
             &local_name!("div") => Self::Div,
-            &local_name!("em") | &local_name!("i") => Self::EmphasisOrItalic,
+            &local_name!("em")
+            | &local_name!("i")
+            | &local_name!("cite")
+            | &local_name!("var")
+            | &local_name!("dfn") => Self::EmphasisOrItalic,
             &local_name!("h1") => Self::Header(HeaderType::H1),
             &local_name!("h2") => Self::Header(HeaderType::H2),
             &local_name!("h3") => Self::Header(HeaderType::H3),
@@ -62,6 +70,7 @@ impl TryFrom<&Atom<LocalNameStaticSet>> for TagName {
             &local_name!("source") => Self::Source,
             &local_name!("img") => Self::Image,
             &local_name!("input") => Self::Input,
+            &local_name!("kbd") => Self::Kbd,
             &local_name!("li") => Self::ListItem,
             &local_name!("ol") => Self::OrderedList,
             &local_name!("p") => Self::Paragraph,
@@ -70,13 +79,16 @@ impl TryFrom<&Atom<LocalNameStaticSet>> for TagName {
             &local_name!("small") => Self::Small,
             &local_name!("span") => Self::Span,
             &local_name!("s") | &local_name!("del") => Self::Strikethrough,
+            &local_name!("sub") => Self::Subscript,
             &local_name!("summary") => Self::Summary,
+            &local_name!("sup") => Self::Superscript,
             &local_name!("table") => Self::Table,
             &local_name!("tbody") => Self::TableBody,
             &local_name!("td") => Self::TableDataCell,
             &local_name!("th") => Self::TableHeader,
             &local_name!("thead") => Self::TableHead,
             &local_name!("tr") => Self::TableRow,
+            &local_name!("time") => Self::Time,
             &local_name!("u") | &local_name!("ins") => Self::Underline,
             &local_name!("ul") => Self::UnorderedList,
             _ => return Err(atom.to_owned()),
@@ -2,22 +2,28 @@ mod html;
 #[cfg(test)]
 mod tests;
 
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use std::path::PathBuf;
 use std::slice;
 use std::str::FromStr;
 use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
-use std::sync::{mpsc, Arc, Mutex};
+use std::sync::{mpsc, Arc, Condvar, Mutex};
+use std::time::Duration;
 
+use crate::alert::Alert;
 use crate::color::{native_color, Theme};
-use crate::image::{Image, ImageData, ImageSize};
-use crate::opts::ResolvedTheme;
+use crate::image::{Image, ImageData};
+use crate::math;
+use crate::opts::{OnImageError, Opts, ResolvedTheme};
 use crate::positioner::{Positioned, Row, Section, Spacer, DEFAULT_MARGIN};
-use crate::text::{Text, TextBox};
-use crate::utils::{markdown_to_html, Align};
+use crate::table::TableLimits;
+use crate::task_list;
+use crate::text::{Script, Text, TextBox};
+use crate::toc::Heading;
+use crate::utils::{markdown_to_html_with_sourcepos, normalize_anchor, Align};
 use crate::{Element, ImageCache, InlyneEvent};
 use html::{
-    attr::{self, PrefersColorScheme},
+    attr::{self, MathStyle, PrefersColorScheme},
     style::{self, FontStyle, FontWeight, Style, TextDecoration},
     Attr, Element as InterpreterElement, TagName,
 };
@@ -34,29 +40,62 @@ use winit::window::Window;
 
 use self::html::{picture, HeaderType, Picture};
 
+/// Default for how deeply lists and blockquotes can nest before they stop being indented further
+pub const DEFAULT_MAX_NESTING_DEPTH: usize = 16;
+
 struct State {
     global_indent: f32,
+    /// Current nesting of lists and blockquotes
+    nesting_depth: usize,
     element_stack: Vec<InterpreterElement>,
     text_options: html::TextOptions,
     span: Span,
     // Stores the row and a counter of newlines after each image
     inline_images: Option<(Row, usize)>,
+    /// Text and images that share a line when inline images are aligned to the text's baseline
+    baseline_row: Option<Row>,
     pending_anchor: Option<String>,
     pending_list_prefix: Option<String>,
+    /// The alert (if any) for each level of blockquote nesting
+    alerts: Vec<Option<Alert>>,
+    /// Set at the start of a blockquote until its first text is seen, which may be an alert marker
+    pending_alert: bool,
+    /// Set when a paragraph was just opened and nothing has been put in it yet
+    blank_paragraph: bool,
     anchorizer: Anchorizer,
+    /// The URLs of all links so far when listing them as references
+    link_references: Vec<String>,
+    /// Item counts for the document's reversed lists that haven't been reached yet
+    reversed_list_lengths: VecDeque<usize>,
+    /// Source offsets of the document's task list checkboxes keyed by where their items start
+    task_markers: HashMap<(usize, usize), usize>,
+    /// The source offset of the checkbox for the list item that was just started, if it has one
+    pending_task_marker: Option<usize>,
+    /// The source of the math that's being read, if any
+    math: Option<(MathStyle, String)>,
 }
 
 impl State {
     fn with_span_color(span_color: [f32; 4]) -> Self {
         Self {
             global_indent: 0.0,
+            nesting_depth: 0,
             element_stack: Vec::new(),
             text_options: Default::default(),
             span: Span::with_color(span_color),
             inline_images: None,
+            baseline_row: None,
             pending_anchor: None,
             pending_list_prefix: None,
+            alerts: Vec::new(),
+            pending_alert: false,
+            blank_paragraph: false,
             anchorizer: Default::default(),
+            link_references: Vec::new(),
+            reversed_list_lengths: VecDeque::new(),
+            task_markers: HashMap::new(),
+            pending_task_marker: None,
+            math: None,
         }
     }
 
@@ -126,6 +165,128 @@ impl WindowInteractor for LiveWindow {
     }
 }
 
+// No window at all, for when the document is only wanted as elements
+struct Headless;
+
+impl ImageCallback for Headless {
+    fn loaded_image(&self, _: String, _: Arc<Mutex<Option<ImageData>>>) {}
+}
+
+impl WindowInteractor for Headless {
+    fn finished_single_doc(&self) {}
+
+    fn request_redraw(&self) {}
+
+    fn image_callback(&self) -> Box<dyn ImageCallback + Send> {
+        Box::new(Headless)
+    }
+}
+
+/// No window, but keeps count of the images that are still loading, so that they can be waited on
+#[derive(Clone, Default)]
+struct ImageWaiter(Arc<(Mutex<usize>, Condvar)>);
+
+impl ImageWaiter {
+    /// Returns `false` if some images were still loading when it gave up
+    fn wait(&self, timeout: Duration) -> bool {
+        let (loading, loaded) = &*self.0;
+        let loading = loading.lock().unwrap();
+        let (_loading, res) = loaded
+            .wait_timeout_while(loading, timeout, |loading| *loading > 0)
+            .unwrap();
+        !res.timed_out()
+    }
+}
+
+impl ImageCallback for ImageWaiter {
+    fn loaded_image(&self, _: String, _: Arc<Mutex<Option<ImageData>>>) {
+        let (loading, loaded) = &*self.0;
+        *loading.lock().unwrap() -= 1;
+        loaded.notify_all();
+    }
+}
+
+impl WindowInteractor for ImageWaiter {
+    fn finished_single_doc(&self) {}
+
+    fn request_redraw(&self) {}
+
+    // Every image that starts loading gets exactly one callback once it's done
+    fn image_callback(&self) -> Box<dyn ImageCallback + Send> {
+        *self.0 .0.lock().unwrap() += 1;
+        Box::new(self.clone())
+    }
+}
+
+/// Interprets the markdown without a window, returning all of the resulting elements
+///
+/// Images are left to load in the background, so they won't have any data
+pub fn interpret_headless(md: String, opts: &Opts) -> Vec<Element> {
+    interpret_without_window(
+        md,
+        opts,
+        TextureFormat::Bgra8UnormSrgb,
+        1.0,
+        Box::new(Headless),
+    )
+}
+
+/// Like [`interpret_headless()`], but waits for the images to load, so that they can be drawn
+pub fn interpret_for_render(
+    md: String,
+    opts: &Opts,
+    surface_format: TextureFormat,
+    hidpi_scale: f32,
+) -> Vec<Element> {
+    const IMAGE_TIMEOUT: Duration = Duration::from_secs(30);
+
+    let waiter = ImageWaiter::default();
+    let elements = interpret_without_window(
+        md,
+        opts,
+        surface_format,
+        hidpi_scale,
+        Box::new(waiter.clone()),
+    );
+    if !waiter.wait(IMAGE_TIMEOUT) {
+        tracing::warn!("Gave up waiting on images that were still loading");
+    }
+    elements
+}
+
+fn interpret_without_window(
+    md: String,
+    opts: &Opts,
+    surface_format: TextureFormat,
+    hidpi_scale: f32,
+    window: Box<dyn WindowInteractor + Send>,
+) -> Vec<Element> {
+    let element_queue = Arc::default();
+    let interpreter = HtmlInterpreter::new_with_interactor(
+        Arc::clone(&element_queue),
+        opts.theme.clone(),
+        surface_format,
+        hidpi_scale,
+        opts.history.get_path().to_owned(),
+        ImageCache::default(),
+        window,
+        InterpreterSettings {
+            // Nothing scrolls without a window, so every image loads up front
+            lazy_images: false,
+            ..opts.into()
+        },
+    );
+
+    // Interpreting wraps up once the sender is gone
+    let (md_tx, md_rx) = mpsc::channel();
+    md_tx.send(md).unwrap();
+    drop(md_tx);
+    interpreter.interpret_md(md_rx);
+
+    let mut element_queue = element_queue.lock().unwrap();
+    element_queue.drain(..).collect()
+}
+
 pub struct HtmlInterpreter {
     element_queue: Arc<Mutex<VecDeque<Element>>>,
     current_textbox: TextBox,
@@ -141,7 +302,51 @@ pub struct HtmlInterpreter {
     first_pass: bool,
     image_cache: ImageCache,
     window: Box<dyn WindowInteractor + Send>,
-    color_scheme: Option<ResolvedTheme>,
+    settings: InterpreterSettings,
+}
+
+/// The options that change how documents get interpreted
+#[derive(Clone, Debug)]
+pub struct InterpreterSettings {
+    pub color_scheme: Option<ResolvedTheme>,
+    pub compact_kv_tables: bool,
+    pub allow_remote_images: bool,
+    pub max_image_bytes: usize,
+    pub max_nesting_depth: usize,
+    pub table_limits: TableLimits,
+    pub inline_image_baseline: bool,
+    pub preserve_blank_lines: bool,
+    pub on_image_error: OnImageError,
+    pub link_references: bool,
+    pub preserve_spaces: bool,
+    pub code_block_wrap: bool,
+    pub code_line_numbers: bool,
+    /// Whether images wait to load until they're scrolled near
+    pub lazy_images: bool,
+    /// Whether `$...$` and `$$...$$` get rendered as math
+    pub math: bool,
+}
+
+impl From<&Opts> for InterpreterSettings {
+    fn from(opts: &Opts) -> Self {
+        Self {
+            color_scheme: opts.color_scheme,
+            compact_kv_tables: opts.compact_kv_tables,
+            allow_remote_images: opts.allow_remote_images,
+            max_image_bytes: opts.max_image_bytes,
+            max_nesting_depth: opts.max_nesting_depth,
+            table_limits: opts.table_limits,
+            inline_image_baseline: opts.inline_image_baseline,
+            preserve_blank_lines: opts.preserve_blank_lines,
+            on_image_error: opts.on_image_error,
+            link_references: opts.link_references,
+            preserve_spaces: opts.preserve_spaces,
+            code_block_wrap: opts.code_block_wrap,
+            code_line_numbers: opts.code_line_numbers,
+            lazy_images: opts.lazy_image_distance.is_some(),
+            math: opts.math,
+        }
+    }
 }
 
 impl HtmlInterpreter {
@@ -157,7 +362,7 @@ impl HtmlInterpreter {
         file_path: PathBuf,
         image_cache: ImageCache,
         event_proxy: EventLoopProxy<InlyneEvent>,
-        color_scheme: Option<ResolvedTheme>,
+        settings: InterpreterSettings,
     ) -> Self {
         let live_window = LiveWindow {
             window,
@@ -171,7 +376,7 @@ impl HtmlInterpreter {
             file_path,
             image_cache,
             Box::new(live_window),
-            color_scheme,
+            settings,
         )
     }
 
@@ -185,7 +390,7 @@ impl HtmlInterpreter {
         file_path: PathBuf,
         image_cache: ImageCache,
         window: Box<dyn WindowInteractor + Send>,
-        color_scheme: Option<ResolvedTheme>,
+        settings: InterpreterSettings,
     ) -> Self {
         Self {
             window,
@@ -200,7 +405,7 @@ impl HtmlInterpreter {
             stopped: false,
             first_pass: true,
             image_cache,
-            color_scheme,
+            settings,
         }
     }
 
@@ -209,6 +414,8 @@ impl HtmlInterpreter {
 
         let span_color = self.native_color(self.theme.text_color);
         let code_highlighter = self.theme.code_highlighter.clone();
+        let preserve_blank_lines = self.settings.preserve_blank_lines;
+        let math = self.settings.math;
         let mut tok = Tokenizer::new(self, TokenizerOpts::default());
 
         for md_string in receiver {
@@ -221,7 +428,14 @@ impl HtmlInterpreter {
                 tok.sink.state = State::with_span_color(span_color);
                 tok.sink.current_textbox = TextBox::new(Vec::new(), tok.sink.hidpi_scale);
                 tok.sink.stopped = false;
-                let htmlified = markdown_to_html(&md_string, code_highlighter.clone());
+                let htmlified = markdown_to_html_with_sourcepos(
+                    &md_string,
+                    code_highlighter.clone(),
+                    preserve_blank_lines,
+                    math,
+                );
+                tok.sink.state.reversed_list_lengths = html::reversed_list_lengths(&htmlified);
+                tok.sink.state.task_markers = task_list::markers(&md_string);
 
                 input.push_back(
                     Tendril::from_str(&htmlified)
@@ -230,9 +444,14 @@ impl HtmlInterpreter {
                         .unwrap(),
                 );
 
+                tok.sink.image_cache.lock().unwrap().start_document();
                 let _ = tok.feed(&mut input);
                 assert!(input.is_empty());
                 tok.end();
+                // Images from before a reload can go, unless this got cut short by another one
+                if !tok.sink.stopped {
+                    tok.sink.image_cache.lock().unwrap().finish_document();
+                }
             }
         }
     }
@@ -262,6 +481,19 @@ impl HtmlInterpreter {
         native_color(color, &self.surface_format)
     }
 
+    /// The mono theme ignores any colors coming from the document (e.g. syntax highlighting)
+    fn is_mono(&self) -> bool {
+        self.settings.color_scheme == Some(ResolvedTheme::Mono)
+    }
+
+    /// Pushes any pending inline images regardless of how many newlines followed them
+    fn flush_inline_images(&mut self) {
+        if let Some((row, _)) = self.state.inline_images.take() {
+            self.push_element(row);
+            self.push_spacer();
+        }
+    }
+
     fn push_current_textbox(&mut self) {
         // Push any inline images
         if let Some((row, count)) = self.state.inline_images.take() {
@@ -273,35 +505,148 @@ impl HtmlInterpreter {
             }
         }
 
-        if !self.current_textbox.texts.is_empty() {
-            let mut empty = true;
-            for text in &self.current_textbox.texts {
-                if !text.text.trim().is_empty() {
-                    empty = false;
-                    break;
-                }
+        let text_box = self.take_current_textbox();
+        if let Some(mut row) = self.state.baseline_row.take() {
+            row.elements.extend(text_box.map(Positioned::new));
+            self.push_block(row);
+        } else if let Some(text_box) = text_box {
+            self.push_block(text_box);
+        }
+    }
+
+    /// Takes the current text box if it has any visible text, leaving a fresh one in its place
+    fn take_current_textbox(&mut self) -> Option<TextBox> {
+        let has_text = self
+            .current_textbox
+            .texts
+            .iter()
+            .any(|text| !text.text.trim().is_empty());
+        let mut fresh = TextBox::new(Vec::new(), self.hidpi_scale);
+        fresh.indent = self.state.global_indent;
+        let mut text_box = std::mem::replace(&mut self.current_textbox, fresh);
+        text_box.indent = self.state.global_indent;
+        has_text.then_some(text_box)
+    }
+
+    /// Pushes an element to the innermost `<details>` if there is one, or the queue otherwise
+    fn push_block<I: Into<Element>>(&mut self, element: I) {
+        let section = self.state.element_iter_mut().rev().find_map(|e| {
+            if let InterpreterElement::Details(section) = e {
+                Some(section)
+            } else {
+                None
             }
-            if !empty {
-                self.current_textbox.indent = self.state.global_indent;
-                let section = self.state.element_iter_mut().rev().find_map(|e| {
-                    if let InterpreterElement::Details(section) = e {
-                        Some(section)
-                    } else {
-                        None
-                    }
-                });
-                if let Some(section) = section {
-                    section
-                        .elements
-                        .push(Positioned::new(self.current_textbox.clone()));
-                } else {
-                    self.push_element(self.current_textbox.clone());
-                }
+        });
+        if let Some(section) = section {
+            section.elements.push(Positioned::new(element));
+        } else {
+            self.push_element(element);
+        }
+    }
+
+    /// Indents for another level of nesting, unless we're already past the max nesting depth
+    fn indent(&mut self) {
+        self.state.nesting_depth += 1;
+        if self.state.nesting_depth <= self.settings.max_nesting_depth {
+            self.state.global_indent += DEFAULT_MARGIN / 2.;
+        } else if self.state.nesting_depth == self.settings.max_nesting_depth + 1 {
+            tracing::warn!(
+                "Exceeded the max nesting depth of {}. Deeper levels won't be indented",
+                self.settings.max_nesting_depth
+            );
+        }
+    }
+
+    fn dedent(&mut self) {
+        if self.state.nesting_depth <= self.settings.max_nesting_depth {
+            self.state.global_indent -= DEFAULT_MARGIN / 2.;
+        }
+        self.state.nesting_depth = self.state.nesting_depth.saturating_sub(1);
+    }
+
+    fn quote_nest(&self) -> usize {
+        self.state
+            .text_options
+            .block_quote
+            .min(self.settings.max_nesting_depth)
+    }
+
+    fn set_quote_block(&mut self) {
+        self.current_textbox.set_quote_block(self.quote_nest());
+        self.current_textbox.quote_bar_color = self
+            .state
+            .alerts
+            .last()
+            .copied()
+            .flatten()
+            .map(|alert| self.native_color(alert.color(&self.theme)));
+    }
+
+    /// Swaps an alert's marker out for its title on its own line
+    fn push_alert_title(&mut self, alert: Alert) {
+        if let Some(last) = self.state.alerts.last_mut() {
+            *last = Some(alert);
+        }
+        let color = self.native_color(alert.color(&self.theme));
+        self.set_quote_block();
+        self.current_textbox
+            .texts
+            .push(Text::new(alert.title().to_owned(), self.hidpi_scale, color).make_bold(true));
+        self.push_current_textbox();
+    }
+
+    /// The alignment of the column for the table cell that's being started
+    fn column_align(&mut self) -> Option<Align> {
+        let mut iter = self.state.element_iter_mut().rev();
+        let Some(InterpreterElement::TableRow(row)) = iter.next() else {
+            return None;
+        };
+        let column = row.len();
+        iter.find_map(|elem| elem.as_mut_table())?
+            .column_align(column)
+    }
+
+    /// Follows a link's text with its number in the references listed at the end
+    fn push_link_reference(&mut self, link: String) {
+        let references = &mut self.state.link_references;
+        let number = match references.iter().position(|existing| *existing == link) {
+            Some(index) => index + 1,
+            None => {
+                references.push(link);
+                references.len()
             }
+        };
+        let text = Text::new(
+            format!("[{number}]"),
+            self.hidpi_scale,
+            self.state.span.color,
+        );
+        self.current_textbox.texts.push(text);
+    }
+
+    /// Lists out the URLs for all of the numbered link references
+    fn push_link_references(&mut self) {
+        let references = std::mem::take(&mut self.state.link_references);
+        if references.is_empty() {
+            return;
+        }
+
+        self.push_element(Spacer::visible());
+        let title =
+            Text::new("References".into(), self.hidpi_scale, self.state.span.color).make_bold(true);
+        self.push_element(TextBox::new(vec![title], self.hidpi_scale));
+        let link_color = self.native_color(self.theme.link_color);
+        for (i, url) in references.into_iter().enumerate() {
+            let number = Text::new(
+                format!("[{}] ", i + 1),
+                self.hidpi_scale,
+                self.state.span.color,
+            );
+            let url = Text::new(url.clone(), self.hidpi_scale, link_color).with_link(url);
+            self.push_element(TextBox::new(vec![number, url], self.hidpi_scale));
         }
-        self.current_textbox = TextBox::new(Vec::new(), self.hidpi_scale);
-        self.current_textbox.indent = self.state.global_indent;
     }
+
     fn push_spacer(&mut self) {
         self.push_element(Spacer::invisible());
     }
@@ -314,31 +659,61 @@ impl HtmlInterpreter {
 
     fn push_image_from_picture(&mut self, pic: Picture) {
         let align = pic.inner.align;
-        let src = pic.resolve_src(self.color_scheme).to_owned();
+        let src = pic.resolve_src(self.settings.color_scheme).to_owned();
         let align = align.unwrap_or_default();
         let is_url = src.starts_with("http://") || src.starts_with("https://");
         let mut image = match self.image_cache.lock().unwrap().get(&src) {
             Some(image_data) if is_url => {
                 Image::from_image_data(image_data.clone(), self.hidpi_scale)
             }
+            _ if self.settings.lazy_images => Image::deferred(
+                src.clone(),
+                self.file_path.clone(),
+                self.hidpi_scale,
+                self.settings.allow_remote_images,
+                self.settings.max_image_bytes,
+                self.settings.on_image_error,
+                self.window.image_callback(),
+            ),
             _ => Image::from_src(
                 src.clone(),
                 self.file_path.clone(),
                 self.hidpi_scale,
+                self.settings.allow_remote_images,
+                self.settings.max_image_bytes,
+                self.settings.on_image_error,
                 self.window.image_callback(),
             )
             .unwrap(),
         }
         .with_align(align);
 
+        if let Some(alt) = pic.inner.alt.filter(|alt| !alt.trim().is_empty()) {
+            let text = Text::new(alt, self.hidpi_scale, self.state.span.color).make_italic(true);
+            let mut text_box = TextBox::new(vec![text], self.hidpi_scale);
+            text_box.indent = self.state.global_indent;
+            image = image.with_alt(text_box);
+        }
+
         if let Some(link) = self.state.text_options.link.last() {
             image.set_link(link.clone())
         }
         if let Some(size) = pic.inner.size {
             image = image.with_size(size);
         }
+        image = image.with_reserved(pic.inner.width, pic.inner.height);
 
-        if align == Align::Left {
+        if align == Align::Left && self.settings.inline_image_baseline {
+            // Keep the image in line with the surrounding text
+            let text_box = self.take_current_textbox();
+            let hidpi_scale = self.hidpi_scale;
+            let row = self
+                .state
+                .baseline_row
+                .get_or_insert_with(|| Row::baseline(hidpi_scale));
+            row.elements.extend(text_box.map(Positioned::new));
+            row.elements.push(Positioned::new(image));
+        } else if align == Align::Left {
             if let Some((row, count)) = &mut self.state.inline_images {
                 row.elements.push(Positioned::new(image));
                 // Restart newline count
@@ -352,6 +727,58 @@ impl HtmlInterpreter {
         }
     }
 
+    /// Whether the current text box ends up as part of a table cell, header, or `<summary>`
+    fn in_text_only_block(&self) -> bool {
+        self.state.element_stack.iter().any(|elem| {
+            matches!(
+                elem,
+                InterpreterElement::TableRow(_)
+                    | InterpreterElement::Header(_)
+                    | InterpreterElement::Summary
+            )
+        })
+    }
+
+    /// Renders math as an image, falling back to its source when it can't be rendered
+    fn push_math(&mut self, tex: String, math_style: MathStyle) {
+        let display = math_style == MathStyle::Display;
+        let delimiter = if display { "$$" } else { "$" };
+        // Table cells, headers, and summaries take their text straight from the current text box,
+        // so there's nowhere for an image to go
+        if self.in_text_only_block() {
+            self.process_character_tokens(format!("{delimiter}{tex}{delimiter}"));
+            return;
+        }
+        let rendered = math::to_svg(&tex, display, self.theme.text_color)
+            .and_then(|svg| ImageData::from_svg(svg.as_bytes(), self.hidpi_scale));
+        let image_data = match rendered {
+            Ok(image_data) => image_data,
+            Err(err) => {
+                tracing::warn!("Couldn't render math `{tex}`: {err}");
+                self.process_character_tokens(format!("{delimiter}{tex}{delimiter}"));
+                return;
+            }
+        };
+        let image =
+            Image::from_image_data(Arc::new(Mutex::new(Some(image_data))), self.hidpi_scale);
+
+        if display {
+            self.push_current_textbox();
+            self.push_block(image.with_align(Align::Center));
+            self.push_block(Spacer::invisible());
+        } else {
+            // Kept in line with the surrounding text
+            let text_box = self.take_current_textbox();
+            let hidpi_scale = self.hidpi_scale;
+            let row = self
+                .state
+                .baseline_row
+                .get_or_insert_with(|| Row::baseline(hidpi_scale));
+            row.elements.extend(text_box.map(Positioned::new));
+            row.elements.push(Positioned::new(image));
+        }
+    }
+
     fn process_start_tag(&mut self, tag: Tag) {
         let tag_name = match TagName::try_from(&tag.name) {
             Ok(name) => name,
@@ -365,38 +792,49 @@ impl HtmlInterpreter {
                 // FIXME blockquotes in list have no marker
                 self.push_current_textbox();
                 self.state.text_options.block_quote += 1;
-                self.state.global_indent += DEFAULT_MARGIN / 2.;
-                self.current_textbox
-                    .set_quote_block(self.state.text_options.block_quote);
+                self.indent();
+                self.current_textbox.set_quote_block(self.quote_nest());
+                self.state.alerts.push(None);
+                self.state.pending_alert = true;
             }
             TagName::TableHead | TagName::TableBody => {}
             TagName::Table => {
                 self.push_spacer();
-                self.state.element_stack.push(InterpreterElement::table());
+                let align = self.align_or_inherit(html::find_align(&tag.attrs));
+                self.state.element_stack.push(InterpreterElement::table(
+                    align.unwrap_or_default(),
+                    self.settings.table_limits,
+                ));
             }
             TagName::TableHeader => {
                 self.state.text_options.bold += 1;
                 let align = html::find_align(&tag.attrs);
                 self.current_textbox.set_align_or_default(align);
+                let iter = self.state.element_iter_mut();
+                if let Some(table) = iter.rev().find_map(|elem| elem.as_mut_table()) {
+                    table.column_aligns.push(align);
+                }
             }
             TagName::TableRow => self
                 .state
                 .element_stack
                 .push(InterpreterElement::table_row()),
             TagName::TableDataCell => {
-                let align = html::find_align(&tag.attrs);
+                let align = html::find_align(&tag.attrs).or_else(|| self.column_align());
                 self.current_textbox.set_align_or_default(align);
             }
             TagName::Anchor => {
                 for attr in attr::Iter::new(&tag.attrs) {
                     match attr {
                         Attr::Href(link) => self.state.text_options.link.push(link),
-                        Attr::Anchor(a) => self.current_textbox.set_anchor(a),
+                        Attr::Anchor(a) => self.current_textbox.set_anchor(normalize_anchor(&a)),
                         _ => {}
                     }
                 }
             }
             TagName::Small => self.state.text_options.small += 1,
+            TagName::Superscript => self.state.text_options.script.push(Script::Super),
+            TagName::Subscript => self.state.text_options.script.push(Script::Sub),
             TagName::Break => self.push_current_textbox(),
             TagName::Underline => self.state.text_options.underline += 1,
             TagName::Strikethrough => self.state.text_options.strike_through += 1,
@@ -431,7 +869,9 @@ impl HtmlInterpreter {
 
                 match media {
                     PrefersColorScheme(ResolvedTheme::Dark) => builder.set_dark_variant(src_set),
-                    PrefersColorScheme(ResolvedTheme::Light) => builder.set_light_variant(src_set),
+                    PrefersColorScheme(ResolvedTheme::Light | ResolvedTheme::Mono) => {
+                        builder.set_light_variant(src_set)
+                    }
                 }
             }
             TagName::Image => {
@@ -439,9 +879,10 @@ impl HtmlInterpreter {
                     for attr in attr_iter {
                         match attr {
                             Attr::Align(a) => builder.set_align(a),
-                            Attr::Width(w) => builder.set_size(ImageSize::width(w)),
-                            Attr::Height(h) => builder.set_size(ImageSize::height(h)),
+                            Attr::Width(w) => builder.set_width(w),
+                            Attr::Height(h) => builder.set_height(h),
                             Attr::Src(s) => builder.set_src(s),
+                            Attr::Alt(a) => builder.set_alt(a),
                             _ => {}
                         }
                     }
@@ -478,6 +919,7 @@ impl HtmlInterpreter {
                 if let Some(align) = self.align_or_inherit(align) {
                     self.current_textbox.set_align(align);
                 }
+                self.state.blank_paragraph = tag_name == TagName::Paragraph;
                 self.state.element_stack.push(match tag_name {
                     TagName::Div => InterpreterElement::Div(align),
                     TagName::Paragraph => InterpreterElement::Paragraph(align),
@@ -487,9 +929,19 @@ impl HtmlInterpreter {
             TagName::EmphasisOrItalic => self.state.text_options.italic += 1,
             TagName::BoldOrStrong => self.state.text_options.bold += 1,
             TagName::Code => self.state.text_options.code += 1,
+            TagName::Kbd => self.state.text_options.kbd += 1,
             TagName::ListItem => {
+                let mut value = None;
+                self.state.pending_task_marker = None;
                 for attr in attr::Iter::new(&tag.attrs) {
-                    self.state.pending_anchor = attr.to_anchor();
+                    match attr {
+                        Attr::Value(v) => value = Some(v),
+                        Attr::SourceStart(start) => {
+                            self.state.pending_task_marker =
+                                self.state.task_markers.get(&start).copied();
+                        }
+                        other => self.state.pending_anchor = other.to_anchor(),
+                    }
                 }
 
                 // Push a pending list prefix based on the list type
@@ -497,9 +949,21 @@ impl HtmlInterpreter {
                 let list = iter.rev().find_map(|elem| elem.as_mut_list()).unwrap();
                 if self.current_textbox.texts.is_empty() {
                     let prefix = match &mut list.ty {
-                        html::ListType::Ordered(index) => {
-                            *index += 1;
-                            format!("{}. ", *index - 1)
+                        html::ListType::Ordered {
+                            index,
+                            marker,
+                            reversed,
+                        } => {
+                            if let Some(value) = value {
+                                *index = value;
+                            }
+                            let prefix = marker.prefix(*index);
+                            *index = if *reversed {
+                                index.saturating_sub(1)
+                            } else {
+                                *index + 1
+                            };
+                            prefix
                         }
                         html::ListType::Unordered => "· ".to_owned(),
                     };
@@ -509,23 +973,39 @@ impl HtmlInterpreter {
             }
             TagName::UnorderedList => {
                 self.push_current_textbox();
-                self.state.global_indent += DEFAULT_MARGIN / 2.;
+                self.indent();
                 self.state
                     .element_stack
                     .push(InterpreterElement::unordered_list());
             }
             TagName::OrderedList => {
-                let mut start_index = 1;
+                let mut start_index = None;
+                let mut marker = html::OrderedMarker::default();
+                let mut reversed = false;
                 for attr in attr::Iter::new(&tag.attrs) {
-                    if let Attr::Start(start) = attr {
-                        start_index = start;
+                    match attr {
+                        Attr::Start(start) => start_index = Some(start),
+                        Attr::OrderedMarker(ty) => marker = ty,
+                        Attr::Reversed => reversed = true,
+                        _ => {}
                     }
                 }
+                let start_index = if reversed {
+                    // Counts down from the number of items by default
+                    let len = self.state.reversed_list_lengths.pop_front();
+                    start_index.or(len).unwrap_or(1)
+                } else {
+                    start_index.unwrap_or(1)
+                };
                 self.push_current_textbox();
-                self.state.global_indent += DEFAULT_MARGIN / 2.;
+                self.indent();
                 self.state
                     .element_stack
-                    .push(InterpreterElement::ordered_list(start_index));
+                    .push(InterpreterElement::ordered_list(
+                        start_index,
+                        marker,
+                        reversed,
+                    ));
             }
             TagName::Header(header_type) => {
                 let mut align = html::find_align(&tag.attrs);
@@ -548,20 +1028,34 @@ impl HtmlInterpreter {
                 let style_str = html::find_style(&tag.attrs).unwrap_or_default();
                 for style in style::Iter::new(&style_str) {
                     if let Style::BackgroundColor(color) = style {
-                        let native_color = self.native_color(color);
-                        self.current_textbox.set_background_color(native_color);
+                        if !self.is_mono() {
+                            let native_color = self.native_color(color);
+                            self.current_textbox.set_background_color(native_color);
+                        }
                     }
                 }
                 self.state.text_options.pre_formatted += 1;
                 self.current_textbox.set_code_block(true);
+                self.current_textbox.no_wrap = !self.settings.code_block_wrap;
+                self.current_textbox.line_numbers = self.settings.code_line_numbers;
             }
             // HACK: spans are only supported enough to get syntax highlighting in code
             // blocks working
             TagName::Span => {
+                if self.settings.math {
+                    let math_style = attr::Iter::new(&tag.attrs).find_map(|attr| match attr {
+                        Attr::MathStyle(style) => Some(style),
+                        _ => None,
+                    });
+                    if let Some(math_style) = math_style {
+                        self.state.math = Some((math_style, String::new()));
+                        return;
+                    }
+                }
                 let style_str = html::find_style(&tag.attrs).unwrap_or_default();
                 for style in style::Iter::new(&style_str) {
                     match style {
-                        Style::Color(color) => {
+                        Style::Color(color) if !self.is_mono() => {
                             self.state.span.color = native_color(color, &self.surface_format)
                         }
                         Style::FontWeight(weight) => self.state.span.weight = weight,
@@ -585,6 +1079,14 @@ impl HtmlInterpreter {
                     // Checkbox uses a custom prefix, so remove pending text prefix
                     let _ = self.state.pending_list_prefix.take();
                     self.current_textbox.set_checkbox(is_checked);
+                    // Task list items are the only checkboxes with a marker in the source
+                    if matches!(
+                        self.state.element_stack.last(),
+                        Some(InterpreterElement::List(_))
+                    ) {
+                        self.current_textbox.checkbox_source =
+                            self.state.pending_task_marker.take();
+                    }
                     self.state.element_stack.push(InterpreterElement::Input);
                 }
             }
@@ -604,7 +1106,8 @@ impl HtmlInterpreter {
             TagName::HorizontalRuler => {
                 self.push_element(Spacer::visible());
             }
-            TagName::Section => {}
+            // Only the contents get rendered as regular text
+            TagName::Section | TagName::Time => {}
         }
     }
 
@@ -620,6 +1123,9 @@ impl HtmlInterpreter {
             TagName::Underline => self.state.text_options.underline -= 1,
             TagName::Strikethrough => self.state.text_options.strike_through -= 1,
             TagName::Small => self.state.text_options.small -= 1,
+            TagName::Superscript | TagName::Subscript => {
+                let _ = self.state.text_options.script.pop();
+            }
             TagName::TableHead | TagName::TableBody => {}
             TagName::TableHeader => {
                 let iter = self.state.element_iter_mut();
@@ -650,19 +1156,43 @@ impl HtmlInterpreter {
                 self.current_textbox.texts.clear();
             }
             TagName::Table => {
-                if let Some(InterpreterElement::Table(table)) = self.state.element_stack.pop() {
+                if let Some(InterpreterElement::Table(mut table)) = self.state.element_stack.pop() {
+                    if self.settings.compact_kv_tables && table.is_key_value() {
+                        table.make_compact();
+                    }
+                    let note = table.truncation_note();
                     self.push_element(table);
+                    if let Some(note) = note {
+                        let text = Text::new(note, self.hidpi_scale, self.state.span.color)
+                            .make_italic(true);
+                        self.push_element(TextBox::new(vec![text], self.hidpi_scale));
+                    }
                     self.push_spacer();
                 }
             }
             TagName::Anchor => {
-                self.state.text_options.link.pop();
+                let link = self.state.text_options.link.pop();
+                if self.settings.link_references {
+                    // Links within the document can already be followed as is
+                    if let Some(link) = link.filter(|link| !link.starts_with('#')) {
+                        self.push_link_reference(link);
+                    }
+                }
             }
             TagName::Code => self.state.text_options.code -= 1,
+            TagName::Kbd => self.state.text_options.kbd -= 1,
             TagName::Div | TagName::Paragraph => {
                 self.push_current_textbox();
+                // Images can't continue on past the end of their block
+                self.flush_inline_images();
                 if tag_name == TagName::Paragraph {
                     self.push_spacer();
+                    // Blank paragraphs stand in for blank lines that would otherwise be collapsed
+                    let blank = std::mem::take(&mut self.state.blank_paragraph);
+                    if self.settings.preserve_blank_lines && blank {
+                        let line_height = TextBox::default().line_height(1.);
+                        self.push_element(Spacer::new(line_height, false));
+                    }
                 }
                 self.state.element_stack.pop();
             }
@@ -672,12 +1202,16 @@ impl HtmlInterpreter {
                 if header_type == HeaderType::H1 {
                     self.state.text_options.underline -= 1;
                 }
-                let anchor_name = self
+                let anchor_name: String = self
                     .current_textbox
                     .texts
                     .iter()
                     .flat_map(|t| t.text.chars())
                     .collect();
+                self.current_textbox.heading = Some(Heading {
+                    level: header_type.level(),
+                    title: anchor_name.trim().to_owned(),
+                });
                 let anchorized = self.state.anchorizer.anchorize(anchor_name);
                 self.current_textbox.set_anchor(format!("#{anchorized}"));
                 self.push_current_textbox();
@@ -697,7 +1231,7 @@ impl HtmlInterpreter {
             }
             TagName::UnorderedList | TagName::OrderedList => {
                 self.push_current_textbox();
-                self.state.global_indent -= DEFAULT_MARGIN / 2.;
+                self.dedent();
                 self.state.element_stack.pop();
                 if self.state.global_indent == 0. {
                     self.push_spacer();
@@ -708,17 +1242,24 @@ impl HtmlInterpreter {
                 self.push_spacer();
                 self.state.text_options.pre_formatted -= 1;
                 self.current_textbox.set_code_block(false);
+                self.current_textbox.line_numbers = false;
             }
             TagName::BlockQuote => {
                 self.push_current_textbox();
                 self.state.text_options.block_quote -= 1;
-                self.state.global_indent -= DEFAULT_MARGIN / 2.;
+                self.dedent();
                 self.current_textbox.clear_quote_block();
+                self.state.alerts.pop();
+                self.state.pending_alert = false;
                 if self.state.global_indent == 0. {
                     self.push_spacer();
                 }
             }
             TagName::Span => {
+                if let Some((math_style, tex)) = self.state.math.take() {
+                    self.push_math(tex, math_style);
+                    return;
+                }
                 let color = self.native_color(self.theme.code_color);
                 self.state.span = Span::with_color(color);
             }
@@ -764,11 +1305,16 @@ impl HtmlInterpreter {
             | TagName::Break
             | TagName::Image
             | TagName::Section
-            | TagName::Source => {}
+            | TagName::Source
+            | TagName::Time => {}
         }
     }
 
     fn process_character_tokens(&mut self, mut str: String) {
+        if let Some((_, tex)) = &mut self.state.math {
+            tex.push_str(&str);
+            return;
+        }
         let text_native_color = self.native_color(self.theme.text_color);
         if str == "\n" {
             if self.state.text_options.pre_formatted >= 1 {
@@ -820,6 +1366,23 @@ impl HtmlInterpreter {
                 }
             }
 
+            if self.settings.preserve_spaces
+                && self.state.text_options.pre_formatted == 0
+                && self.state.text_options.code == 0
+            {
+                str = preserve_space_runs(&str);
+            }
+
+            if std::mem::take(&mut self.state.pending_alert) {
+                if let Some((alert, rest)) = Alert::from_marker(&str) {
+                    self.push_alert_title(alert);
+                    if rest.trim().is_empty() {
+                        return;
+                    }
+                    str = rest.trim_start().to_owned();
+                }
+            }
+
             let mut text = Text::new(str, self.hidpi_scale, text_native_color);
             if let Some(prefix) = self.state.pending_list_prefix.take() {
                 if self.current_textbox.texts.is_empty() {
@@ -829,8 +1392,10 @@ impl HtmlInterpreter {
                 }
             }
             if self.state.text_options.block_quote >= 1 {
-                self.current_textbox
-                    .set_quote_block(self.state.text_options.block_quote);
+                self.set_quote_block();
+            }
+            if self.state.text_options.kbd >= 1 {
+                text = text.with_family(FamilyOwned::Monospace).make_kbd(true);
             }
             if self.state.text_options.code >= 1 {
                 text = text
@@ -856,6 +1421,10 @@ impl HtmlInterpreter {
             if let Some(link) = self.state.text_options.link.last() {
                 text = text.with_link((*link).clone());
                 text = text.with_color(self.native_color(self.theme.link_color));
+                // Links can't stand out by color alone in black and white
+                if self.is_mono() {
+                    text = text.make_underlined(true);
+                }
             }
             if self.state.text_options.bold >= 1 {
                 text = text.make_bold(true);
@@ -872,11 +1441,28 @@ impl HtmlInterpreter {
             if self.state.text_options.small >= 1 {
                 self.current_textbox.font_size = 12.;
             }
+            text = text.with_script(self.state.text_options.script.last().copied());
             self.current_textbox.texts.push(text);
         }
     }
 }
 
+/// Keeps runs of spaces by making all but the last space in each run non-breaking
+///
+/// The last space is left as a regular space, so that lines can still wrap after a run
+fn preserve_space_runs(text: &str) -> String {
+    let mut preserved = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == ' ' && chars.peek() == Some(&' ') {
+            preserved.push('\u{a0}');
+        } else {
+            preserved.push(c);
+        }
+    }
+    preserved
+}
+
 impl TokenSink for HtmlInterpreter {
     type Handle = ();
 
@@ -887,6 +1473,10 @@ impl TokenSink for HtmlInterpreter {
         if self.stopped {
             return TokenSinkResult::Continue;
         }
+        // Only the closing tag can directly follow the opening tag of a blank paragraph
+        if !matches!(&token, Token::TagToken(tag) if tag.kind == TagKind::EndTag) {
+            self.state.blank_paragraph = false;
+        }
         match token {
             Token::TagToken(tag) => match tag.kind {
                 TagKind::StartTag => self.process_start_tag(tag),
@@ -895,6 +1485,7 @@ impl TokenSink for HtmlInterpreter {
             Token::CharacterTokens(str) => self.process_character_tokens(str.to_string()),
             Token::EOFToken => {
                 self.push_current_textbox();
+                self.push_link_references();
                 self.should_queue.store(false, AtomicOrdering::Relaxed);
                 self.first_pass = false;
                 self.window.finished_single_doc();
@@ -7,15 +7,16 @@ use std::sync::{
 use std::time::{Duration, Instant};
 use std::{env, thread};
 
-use super::{HtmlInterpreter, ImageCallback, WindowInteractor};
-use crate::color::{Theme, ThemeDefaults};
-use crate::image::{Image, ImageData};
-use crate::opts::ResolvedTheme;
-use crate::positioner::Spacer;
+use super::{HtmlInterpreter, ImageCallback, InterpreterSettings, WindowInteractor};
+use crate::color::{native_color, Theme, ThemeDefaults};
+use crate::image::{self, Image, ImageData};
+use crate::opts::{OnImageError, ResolvedTheme};
+use crate::positioner::{Positioned, Spacer, DEFAULT_MARGIN};
+use crate::table::{Table, TableLimits, TABLE_ROW_GAP};
 use crate::test_utils::image::{Sample, SamplePng};
 use crate::test_utils::{log, server};
 use crate::text::{Text, TextBox};
-use crate::utils::Align;
+use crate::utils::{classify_link, Align, LinkTarget};
 use crate::{Element, ImageCache};
 
 use base64::prelude::*;
@@ -77,7 +78,7 @@ impl ImageCallback for DummyCallback {
 struct InterpreterOpts {
     theme: Theme,
     fail_after: Duration,
-    color_scheme: Option<ResolvedTheme>,
+    settings: InterpreterSettings,
 }
 
 impl Default for InterpreterOpts {
@@ -85,7 +86,23 @@ impl Default for InterpreterOpts {
         Self {
             theme: Theme::light_default(),
             fail_after: Duration::from_secs(8),
-            color_scheme: None,
+            settings: InterpreterSettings {
+                color_scheme: None,
+                compact_kv_tables: false,
+                allow_remote_images: true,
+                max_image_bytes: image::DEFAULT_MAX_IMAGE_BYTES,
+                max_nesting_depth: super::DEFAULT_MAX_NESTING_DEPTH,
+                table_limits: TableLimits::default(),
+                inline_image_baseline: false,
+                preserve_blank_lines: false,
+                on_image_error: OnImageError::default(),
+                link_references: false,
+                preserve_spaces: false,
+                code_block_wrap: true,
+                code_line_numbers: false,
+                lazy_images: false,
+                math: false,
+            },
         }
     }
 }
@@ -107,14 +124,69 @@ impl InterpreterOpts {
     }
 
     fn set_color_scheme(&mut self, color_scheme: ResolvedTheme) {
-        self.color_scheme = Some(color_scheme);
+        self.settings.color_scheme = Some(color_scheme);
+    }
+
+    fn compact_kv_tables(mut self, compact: bool) -> Self {
+        self.settings.compact_kv_tables = compact;
+        self
+    }
+
+    fn inline_image_baseline(mut self, baseline: bool) -> Self {
+        self.settings.inline_image_baseline = baseline;
+        self
+    }
+
+    fn preserve_blank_lines(mut self, preserve: bool) -> Self {
+        self.settings.preserve_blank_lines = preserve;
+        self
+    }
+
+    fn on_image_error(mut self, on_error: OnImageError) -> Self {
+        self.settings.on_image_error = on_error;
+        self
+    }
+
+    fn allow_remote_images(mut self, allow: bool) -> Self {
+        self.settings.allow_remote_images = allow;
+        self
+    }
+
+    fn link_references(mut self, references: bool) -> Self {
+        self.settings.link_references = references;
+        self
+    }
+
+    fn preserve_spaces(mut self, preserve: bool) -> Self {
+        self.settings.preserve_spaces = preserve;
+        self
+    }
+
+    fn code_block_wrap(mut self, wrap: bool) -> Self {
+        self.settings.code_block_wrap = wrap;
+        self
+    }
+
+    fn code_line_numbers(mut self, line_numbers: bool) -> Self {
+        self.settings.code_line_numbers = line_numbers;
+        self
+    }
+
+    fn table_limits(mut self, limits: TableLimits) -> Self {
+        self.settings.table_limits = limits;
+        self
+    }
+
+    fn math(mut self, math: bool) -> Self {
+        self.settings.math = math;
+        self
     }
 
     fn finish(self, counter: AtomicCounter) -> (HtmlInterpreter, Arc<Mutex<VecDeque<Element>>>) {
         let Self {
             theme,
             fail_after: _,
-            color_scheme,
+            settings,
         } = self;
         let element_queue = Arc::default();
         let surface_format = TextureFormat::Bgra8UnormSrgb;
@@ -130,7 +202,7 @@ impl InterpreterOpts {
             file_path,
             image_cache,
             window,
-            color_scheme,
+            settings,
         );
 
         (interpreter, element_queue)
@@ -218,6 +290,8 @@ macro_rules! snapshot_interpreted_elements {
                 let htmlified = $crate::utils::markdown_to_html(
                     text,
                     opts.theme.code_highlighter.clone(),
+                    opts.settings.preserve_blank_lines,
+                    opts.settings.math,
                 );
                 let description = format!(" --- md\n\n{text}\n\n --- html\n\n{htmlified}");
 
@@ -231,7 +305,6 @@ macro_rules! snapshot_interpreted_elements {
     }
 }
 
-#[allow(unused)]
 const FOOTNOTES_LIST_PREFIX: &str = "\
 This sentence[^1] has two footnotes[^2]
 
@@ -341,6 +414,223 @@ fn identical_anchors_are_unique() {
     "###);
 }
 
+#[test]
+fn header_headings() {
+    log::init();
+
+    let elems = interpret_md("# Hello *World*\n\nNot a header\n\n## Foo\n");
+    let titles: Vec<_> = elems
+        .iter()
+        .filter_map(elem_as_text_box)
+        .map(|text_box| {
+            (
+                text_box.is_anchor.as_deref(),
+                text_box
+                    .heading
+                    .as_ref()
+                    .map(|h| (h.level, h.title.as_str())),
+            )
+        })
+        .collect();
+    assert_eq!(
+        titles,
+        [
+            (Some("#hello-world"), Some((1, "Hello World"))),
+            (None, None),
+            (Some("#foo"), Some((2, "Foo"))),
+        ]
+    );
+}
+
+const ANCHOR_CASING: &str = "\
+# Getting Started
+# Überschrift
+# What's New? (v2.0)
+Text with a <a id=\"Custom_ID\"></a>custom anchor
+";
+
+#[test]
+fn anchor_lookup_matches_anchorizer() {
+    log::init();
+
+    let elems = interpret_md(ANCHOR_CASING);
+    let anchors = elems
+        .iter()
+        .filter_map(elem_as_text_box)
+        .flat_map(|text_box| text_box.anchors())
+        .enumerate()
+        .map(|(i, anchor)| (anchor.to_owned(), i as f32))
+        .collect();
+    let classify = |link| classify_link(link, &anchors, &[]);
+
+    assert_eq!(classify("#getting-started"), LinkTarget::Anchor(0.));
+    assert_eq!(classify("#Getting-Started"), LinkTarget::Anchor(0.));
+    assert_eq!(classify("#überschrift"), LinkTarget::Anchor(1.));
+    assert_eq!(classify("#Überschrift"), LinkTarget::Anchor(1.));
+    assert_eq!(classify("#whats-new-v20"), LinkTarget::Anchor(2.));
+    assert_eq!(classify("#What's-New?-(v2.0)"), LinkTarget::Anchor(2.));
+    assert_eq!(classify("#custom_id"), LinkTarget::Anchor(3.));
+    assert_eq!(classify("#Custom_ID"), LinkTarget::Anchor(3.));
+    assert_eq!(classify("#whats-new"), LinkTarget::MissingAnchor);
+}
+
+#[test]
+fn footnote_anchors() {
+    log::init();
+
+    let elems = interpret_md(FOOTNOTES_LIST_PREFIX);
+    let text_boxes: Vec<_> = elems.iter().filter_map(elem_as_text_box).collect();
+    let anchors: Vec<_> = text_boxes
+        .iter()
+        .flat_map(|text_box| text_box.anchors())
+        .collect();
+    // Both references share a paragraph, so neither can clobber the other
+    for anchor in ["#fnref-1", "#fnref-2", "#fn-1", "#fn-2"] {
+        assert!(
+            anchors.contains(&&anchor.to_owned()),
+            "{anchor} in {anchors:?}"
+        );
+    }
+
+    // Every footnote reference and back-reference leads somewhere
+    let links: Vec<_> = text_boxes
+        .iter()
+        .flat_map(|text_box| &text_box.texts)
+        .filter_map(|text| text.link.as_ref())
+        .collect();
+    assert_eq!(links.len(), 4);
+    assert!(links.iter().all(|link| anchors.contains(link)));
+}
+
+#[test]
+fn non_utf8_percent_escapes_in_links() {
+    log::init();
+
+    // `%E9` is `é` in Latin-1, which doesn't decode to valid UTF-8
+    let elems = interpret_md("[Café](caf%E9.md) and [anchor](#caf%E9)");
+    let links: Vec<_> = elems
+        .iter()
+        .filter_map(elem_as_text_box)
+        .flat_map(|text_box| &text_box.texts)
+        .filter_map(|text| text.link.as_deref())
+        .collect();
+    assert_eq!(links, ["caf%E9.md", "#caf%E9"]);
+
+    // The raw link is kept around for the click handler to try
+    let anchors = Default::default();
+    assert_eq!(
+        classify_link(links[0], &anchors, &[]),
+        LinkTarget::Markdown(PathBuf::from("caf%E9.md"))
+    );
+    assert_eq!(
+        classify_link(links[1], &anchors, &[]),
+        LinkTarget::MissingAnchor
+    );
+}
+
+const ORDERED_LIST_TYPES: &str = r#"
+<ol type="I">
+<li>one
+<ul>
+<li>bullet
+<ol type="a" start="26">
+<li>z</li>
+<li>aa</li>
+</ol>
+</li>
+</ul>
+</li>
+<li>two
+<ol type="i" start="4">
+<li>four</li>
+</ol>
+</li>
+<li>three</li>
+</ol>
+"#;
+
+#[test]
+fn ordered_list_types() {
+    log::init();
+
+    let elems = interpret_md(ORDERED_LIST_TYPES);
+    let prefixes: Vec<_> = elems
+        .iter()
+        .filter_map(elem_as_text_box)
+        .filter_map(|text_box| text_box.texts.first())
+        .map(|text| text.text.as_str())
+        .collect();
+    insta::assert_debug_snapshot!(prefixes, @r###"
+    [
+        "I. ",
+        "· ",
+        "z. ",
+        "aa. ",
+        "II. ",
+        "iv. ",
+        "III. ",
+    ]
+    "###);
+}
+
+const ORDERED_LIST_COUNTERS: &str = r#"
+<ol>
+<li>one</li>
+<li value="5">five</li>
+<li>six</li>
+<li value="2">two</li>
+<li>three</li>
+</ol>
+<ol reversed>
+<li>three</li>
+<li>two
+<ol reversed start="10">
+<li>ten</li>
+<li value="20">twenty</li>
+<li>nineteen</li>
+</ol>
+</li>
+<li>one</li>
+</ol>
+<ol reversed>
+<li>two</li>
+<li>one</li>
+</ol>
+"#;
+
+#[test]
+fn ordered_list_counters() {
+    log::init();
+
+    let elems = interpret_md(ORDERED_LIST_COUNTERS);
+    let prefixes: Vec<_> = elems
+        .iter()
+        .filter_map(elem_as_text_box)
+        .filter_map(|text_box| text_box.texts.first())
+        .map(|text| text.text.as_str())
+        .collect();
+    // `value`s reset the count, and reversed lists count down from their length unless they have
+    // a `start`
+    assert_eq!(
+        prefixes,
+        [
+            "1. ", "5. ", "6. ", "2. ", "3. ", "3. ", "2. ", "10. ", "20. ", "19. ", "1. ", "2. ",
+            "1. ",
+        ]
+    );
+}
+
+#[test]
+fn ordered_marker_prefixes() {
+    use super::html::OrderedMarker;
+
+    assert_eq!(OrderedMarker::UpperAlpha.prefix(28), "AB. ");
+    assert_eq!(OrderedMarker::LowerRoman.prefix(1994), "mcmxciv. ");
+    // Unrepresentable indices fall back to decimal
+    assert_eq!(OrderedMarker::LowerAlpha.prefix(0), "0. ");
+    assert_eq!(OrderedMarker::UpperRoman.prefix(4000), "4000. ");
+}
+
 const BARE_LINK_GETS_AUTOLINKED: &str = "\
 In a paragraph https://example.org/in/para
 
@@ -406,6 +696,81 @@ fn blockquote() {
     "###);
 }
 
+#[test]
+fn nesting_depth_is_capped() {
+    log::init();
+
+    let max_indent = super::DEFAULT_MAX_NESTING_DEPTH as f32 * DEFAULT_MARGIN / 2.;
+    let quotes = format!("{} Deep quote", ">".repeat(50));
+    let list = (0..50)
+        .map(|depth| format!("{}- Level {depth}", "  ".repeat(depth)))
+        .collect::<Vec<_>>()
+        .join("\n");
+    for md in [quotes, list] {
+        let elems = interpret_md(&md);
+        let text_boxes: Vec<_> = elems.iter().filter_map(elem_as_text_box).collect();
+        assert!(!text_boxes.is_empty());
+        for text_box in text_boxes {
+            assert!(!text_box.is_code_block);
+            assert!(text_box.indent <= max_indent, "{}", text_box.indent);
+            let nest = text_box.is_quote_block.unwrap_or_default();
+            assert!(nest <= super::DEFAULT_MAX_NESTING_DEPTH);
+        }
+    }
+}
+
+const ALERTS: &str = r#"
+> [!NOTE]
+> Useful information
+
+> [!warning] Not on its own line
+
+> [!FOO]
+> Unknown type
+
+> [!TIP]
+"#;
+
+#[test]
+fn alerts() {
+    log::init();
+
+    let elems = interpret_md(ALERTS);
+    let quotes: Vec<_> = elems
+        .iter()
+        .filter_map(|elem| {
+            let text_box = elem_as_text_box(elem)?;
+            text_box.is_quote_block?;
+            let text: String = text_box.texts.iter().map(|t| t.text.as_str()).collect();
+            Some((text, text_box.quote_bar_color.is_some()))
+        })
+        .collect();
+    insta::assert_debug_snapshot!(quotes, @r###"
+    [
+        (
+            "Note",
+            true,
+        ),
+        (
+            "Useful information",
+            true,
+        ),
+        (
+            "[!warning] Not on its own line",
+            false,
+        ),
+        (
+            "[!FOO] Unknown type",
+            false,
+        ),
+        (
+            "Tip",
+            true,
+        ),
+    ]
+    "###);
+}
+
 #[test]
 fn horizontal_ruler_is_visible_spacer() {
     log::init();
@@ -635,6 +1000,40 @@ fn kbd_tag_monospace() {
     insta::assert_snapshot!(&mono_text, @"Alt-<num>");
 }
 
+const SEMANTIC_INLINE_TAGS: &str = "\
+<cite>The Book</cite> at <time>12:00</time>
+
+Output: <samp>ok</samp> for <var>x</var>";
+
+#[test]
+fn semantic_inline_tags() {
+    log::init();
+
+    let elems = interpret_md(SEMANTIC_INLINE_TAGS);
+    let texts: Vec<_> = elems
+        .iter()
+        .filter_map(elem_as_text_box)
+        .flat_map(|text_box| text_box.texts.iter())
+        .filter(|text| !text.text.trim().is_empty())
+        .map(|text| {
+            let mono = matches!(text.font_family, FamilyOwned::Monospace);
+            (text.text.trim(), Styles::from(text), mono)
+        })
+        .collect();
+    assert_eq!(
+        texts,
+        [
+            ("The Book", Styles::new().italic(), false),
+            ("at", Styles::new(), false),
+            ("12:00", Styles::new(), false),
+            ("Output:", Styles::new(), false),
+            ("ok", Styles::new(), true),
+            ("for", Styles::new(), false),
+            ("x", Styles::new().italic(), false),
+        ]
+    );
+}
+
 const UNDERLINE_IN_CODEBLOCK: &str = "\
 ```rust
 use std::io;
@@ -786,6 +1185,31 @@ fn code_block_bg_color() {
     }
 }
 
+#[test]
+fn mono_ignores_document_colors() {
+    log::init();
+
+    let text = "[link](https://example.org)\n\n```rust\nfn main() {}\n```";
+    let mut opts = InterpreterOpts::new().theme(Theme::mono_default());
+    opts.set_color_scheme(ResolvedTheme::Mono);
+    let elems = interpret_md_with_opts(text, opts);
+    let text_boxes: Vec<_> = elems.iter().filter_map(elem_as_text_box).collect();
+
+    let link = &text_boxes[0].texts[0];
+    assert!(link.is_underlined);
+    let code = text_boxes.last().unwrap();
+    assert_eq!(code.background_color, None);
+    // Everything is in the one code color instead of being highlighted
+    let code_color = native_color(
+        Theme::mono_default().code_color,
+        &TextureFormat::Bgra8UnormSrgb,
+    );
+    assert!(code
+        .texts
+        .iter()
+        .all(|text| text.color.unwrap_or(text.default_color) == code_color));
+}
+
 const TOML_GETS_HIGHLIGHTED: &str = "\
 ```toml
 key = 123
@@ -803,6 +1227,131 @@ fn toml_gets_highlighted() {
     assert_ne!(highlighted_elems, plain_elems, "Highlighting should differ");
 }
 
+const KEY_VALUE_TABLE: &str = "\
+| Key | Value |
+| --- | ----- |
+| version | 1.2.3 |
+| license | MIT |
+";
+
+const THREE_COLUMN_TABLE: &str = "\
+| Key | Value | Notes |
+| --- | ----- | ----- |
+| version | 1.2.3 | latest |
+";
+
+fn find_table(elements: &VecDeque<Element>) -> Option<&Table> {
+    elements.iter().find_map(|element| match element {
+        crate::Element::Table(table) => Some(table),
+        _ => None,
+    })
+}
+
+#[test]
+fn cells_inherit_column_align() {
+    log::init();
+
+    let html = r#"<table>
+<tr><th>Name</th><th align="right">Count</th></tr>
+<tr><td>apples</td><td>1</td></tr>
+<tr><td>pears</td><td align="center">1000</td></tr>
+</table>"#;
+    let elems = interpret_md(html);
+    let table = find_table(&elems).unwrap();
+    let aligns: Vec<Vec<_>> = table
+        .rows
+        .iter()
+        .map(|row| row.iter().map(|cell| cell.align).collect())
+        .collect();
+    // A cell's own alignment still wins
+    assert_eq!(
+        aligns,
+        [[Align::Left, Align::Right], [Align::Left, Align::Center]]
+    );
+}
+
+#[test]
+fn compact_kv_tables() {
+    log::init();
+
+    let opts = InterpreterOpts::new().compact_kv_tables(true);
+    let elems = interpret_md_with_opts(KEY_VALUE_TABLE, opts);
+    let table = find_table(&elems).unwrap();
+    assert!(table.compact);
+    assert!(table.row_gap() < TABLE_ROW_GAP);
+    let keys_are_bold = table
+        .rows
+        .iter()
+        .all(|row| row[0].texts.iter().all(|text| text.is_bold));
+    assert!(keys_are_bold);
+    assert!(!table.rows[0][1].texts[0].is_bold);
+
+    let opts = InterpreterOpts::new().compact_kv_tables(true);
+    let elems = interpret_md_with_opts(THREE_COLUMN_TABLE, opts);
+    assert!(!find_table(&elems).unwrap().compact);
+
+    // Disabled by default
+    let elems = interpret_md(KEY_VALUE_TABLE);
+    assert!(!find_table(&elems).unwrap().compact);
+}
+
+#[test]
+fn truncated_tables() {
+    log::init();
+
+    let mut md = String::from("| a | b | c |\n| - | - | - |\n");
+    for i in 0..5 {
+        md.push_str(&format!("| {i} | {i} | {i} |\n"));
+    }
+    let limits = TableLimits {
+        rows: 2,
+        columns: 2,
+    };
+    let elems = interpret_md_with_opts(&md, InterpreterOpts::new().table_limits(limits));
+    let table = find_table(&elems).unwrap();
+    assert_eq!(table.headers.len(), 2);
+    assert_eq!(table.rows, [&table.rows[0][..2], &table.rows[1][..2]]);
+    assert_eq!(table.max_columns(), 2);
+
+    let note = elems
+        .iter()
+        .skip_while(|elem| !matches!(elem, Element::Table(_)))
+        .nth(1);
+    let Some(Element::TextBox(note)) = note else {
+        panic!("Missing the truncation note: {note:?}");
+    };
+    assert_eq!(note.texts[0].text, "... 3 more rows and 1 more column");
+
+    // Tables within the limits are left alone
+    let elems = interpret_md(&md);
+    let table = find_table(&elems).unwrap();
+    assert_eq!((table.rows.len(), table.max_columns()), (5, 3));
+    assert_eq!(table.truncation_note(), None);
+
+    // Huge tables get capped by the default limits
+    let mut md = String::from("| a | b |\n| - | - |\n");
+    for i in 0..10_000 {
+        md.push_str(&format!("| {i} | {i} |\n"));
+    }
+    let elems = interpret_md(&md);
+    let table = find_table(&elems).unwrap();
+    assert_eq!(table.rows.len(), crate::table::DEFAULT_MAX_ROWS);
+    let last_row = table.rows.last().unwrap();
+    assert_eq!(last_row[0].texts[0].text, "999");
+    assert_eq!(
+        table.truncation_note().as_deref(),
+        Some("... 9000 more rows")
+    );
+    let note = elems
+        .iter()
+        .skip_while(|elem| !matches!(elem, Element::Table(_)))
+        .nth(1);
+    let Some(Element::TextBox(note)) = note else {
+        panic!("Missing the truncation note: {note:?}");
+    };
+    assert_eq!(note.texts[0].text, "... 9000 more rows");
+}
+
 fn find_image(elements: &VecDeque<Element>) -> Option<&Image> {
     elements.iter().find_map(|element| match element {
         crate::Element::Image(image) => Some(image),
@@ -848,12 +1397,480 @@ fn centered_image_with_size_align_and_link() {
         },
         is_aligned: Some(Center),
         size: Some(PxHeight(Px(170))),
+        reserved: Some((None, Some(Px(Px(170))))),
         is_link: Some("https://bun.sh"),
+        alt: Some(
+            TextBox {
+                texts: [
+                    Text {
+                        text: "Logo",
+                        default_color: Color(BLACK),
+                        style: ITALIC ,
+                        ..
+                    },
+                ],
+                ..
+            },
+        ),
         ..
     }
     "###);
 }
 
+#[test]
+fn inline_image_shares_baseline_with_text() {
+    log::init();
+
+    let badge: Sample = SamplePng::Bun.into();
+    let badge_path = "/badge.png";
+    let files = vec![server::File::new(
+        badge_path,
+        badge.content_type(),
+        &badge.pre_decode(),
+    )];
+    let (_server, server_url) = server::mock_file_server(files);
+    let badge_url = server_url + badge_path;
+    let text = format!("Build ![badge]({badge_url}) passing");
+
+    let opts = InterpreterOpts::new().inline_image_baseline(true);
+    let elems = interpret_md_with_opts(&text, opts);
+    let [crate::Element::Row(row), crate::Element::Spacer(_)] =
+        elems.iter().collect::<Vec<_>>()[..]
+    else {
+        panic!("Expected a single row: {elems:#?}");
+    };
+    assert!(row.baseline);
+    let inner: Vec<_> = row
+        .elements
+        .iter()
+        .map(|element| match &element.inner {
+            crate::Element::TextBox(text_box) => text_box.texts[0].text.trim().to_owned(),
+            crate::Element::Image(_) => "<image>".to_owned(),
+            other => panic!("Unexpected element: {other:?}"),
+        })
+        .collect();
+    assert_eq!(inner, ["Build", "<image>", "passing"]);
+
+    // The image gets broken out onto its own row by default
+    let elems = interpret_md(&text);
+    assert!(elems
+        .iter()
+        .any(|element| matches!(element, crate::Element::TextBox(_))));
+}
+
+#[test]
+fn trailing_image_flushed_at_paragraph_end() {
+    log::init();
+
+    let badge: Sample = SamplePng::Bun.into();
+    let badge_path = "/badge.png";
+    let files = vec![server::File::new(
+        badge_path,
+        badge.content_type(),
+        &badge.pre_decode(),
+    )];
+    let (_server, server_url) = server::mock_file_server(files);
+    let badge_url = server_url + badge_path;
+
+    let markdown = format!("Ends with ![badge]({badge_url})\n\n# Heading");
+    // Without the newline between the blocks that would normally flush the image
+    let html = format!("<p>Ends with <img src=\"{badge_url}\"></p><h1>Heading</h1>");
+    for text in [markdown, html] {
+        let elems = interpret_md(&text);
+        let kinds: Vec<_> = elems
+            .iter()
+            .filter_map(|element| match element {
+                Element::TextBox(text_box) => Some(text_box.texts[0].text.trim().to_owned()),
+                Element::Row(_) => Some("<image>".to_owned()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(kinds, ["Ends with", "<image>", "Heading"], "{text}");
+    }
+}
+
+#[test]
+fn kbd() {
+    log::init();
+
+    let elements = interpret_md("Press <kbd>Ctrl</kbd> + <kbd>**C**</kbd>");
+    let Some(Element::TextBox(text_box)) = elements.front() else {
+        panic!("Expected a text box: {elements:#?}");
+    };
+    let keys: Vec<_> = text_box
+        .texts
+        .iter()
+        .filter(|text| text.is_kbd)
+        .map(|text| {
+            assert_eq!(text.font_family, FamilyOwned::Monospace);
+            (text.text.as_str(), text.is_bold)
+        })
+        .collect();
+    // Formatting within the key still applies
+    assert_eq!(keys, [("Ctrl", false), ("C", true)]);
+    assert!(!text_box.texts[0].is_kbd);
+}
+
+#[test]
+fn checkbox_sources() {
+    log::init();
+
+    let md = "\
+- [ ] Task
+
+<ul><li><input type=\"checkbox\"> Raw</li></ul>
+
+- [x] Done
+";
+    let elements = interpret_md(md);
+    let checkboxes: Vec<_> = elements
+        .iter()
+        .filter_map(|element| match element {
+            Element::TextBox(text_box) if text_box.is_checkbox.is_some() => {
+                Some((text_box.texts[0].text.trim(), text_box.checkbox_source))
+            }
+            _ => None,
+        })
+        .collect();
+    // Only checkboxes from the markdown's task list items can be toggled
+    assert_eq!(
+        checkboxes,
+        [
+            ("Task", md.find("[ ]")),
+            ("Raw", None),
+            ("Done", md.find("[x]")),
+        ]
+    );
+}
+
+#[test]
+fn link_references() {
+    log::init();
+
+    let text = "\
+See [the docs](https://example.com/docs) and [the repo](https://example.com/repo), or \
+[the docs](https://example.com/docs) again.
+
+Internal [links](#see-also) are left alone.";
+    let texts = |elements: &VecDeque<Element>| -> Vec<String> {
+        elements
+            .iter()
+            .filter_map(|element| match element {
+                Element::TextBox(text_box) => Some(
+                    text_box
+                        .texts
+                        .iter()
+                        .map(|text| text.text.as_str())
+                        .collect(),
+                ),
+                _ => None,
+            })
+            .collect()
+    };
+
+    let elements = interpret_md_with_opts(text, InterpreterOpts::new().link_references(true));
+    assert_eq!(
+        texts(&elements),
+        [
+            "See the docs[1] and the repo[2], or the docs[1] again.",
+            "Internal links are left alone.",
+            "References",
+            "[1] https://example.com/docs",
+            "[2] https://example.com/repo",
+        ]
+    );
+    // The listed URLs are links themselves
+    let Some(Element::TextBox(last)) = elements.back() else {
+        panic!("Expected the references to come last: {elements:#?}");
+    };
+    assert_eq!(
+        last.texts[1].link.as_deref(),
+        Some("https://example.com/repo")
+    );
+
+    // Nothing changes by default
+    let elements = interpret_md(text);
+    assert_eq!(
+        texts(&elements),
+        [
+            "See the docs and the repo, or the docs again.",
+            "Internal links are left alone.",
+        ]
+    );
+}
+
+fn total_spacing(elements: &VecDeque<Element>) -> f32 {
+    elements
+        .iter()
+        .filter_map(|element| match element {
+            Element::Spacer(spacer) => Some(spacer.space),
+            _ => None,
+        })
+        .sum()
+}
+
+#[test]
+fn preserve_blank_lines() {
+    log::init();
+
+    let one_blank = "First\n\nSecond";
+    let three_blank = "First\n\n\n\nSecond";
+    let spacing = |text, preserve| {
+        let opts = InterpreterOpts::new().preserve_blank_lines(preserve);
+        total_spacing(&interpret_md_with_opts(text, opts))
+    };
+
+    // Collapsed like usual by default
+    assert_eq!(spacing(three_blank, false), spacing(one_blank, false));
+    // A single blank line is just a regular paragraph break either way
+    assert_eq!(spacing(one_blank, true), spacing(one_blank, false));
+    // Each blank line past the first adds a line's worth of space
+    let line_height = TextBox::default().line_height(1.);
+    let extra = spacing(three_blank, true) - spacing(one_blank, true);
+    assert!(extra >= 2. * line_height, "{extra}");
+
+    // Blank lines inside of code blocks are already kept
+    let code = "```\na\n\n\n\nb\n```";
+    assert_eq!(spacing(code, true), spacing(code, false));
+}
+
+#[test]
+fn preserve_spaces() {
+    log::init();
+
+    let text = "Name:    Value\n\n`a    b`";
+    let texts = |preserve| {
+        let opts = InterpreterOpts::new().preserve_spaces(preserve);
+        interpret_md_with_opts(text, opts)
+            .iter()
+            .filter_map(|element| match element {
+                Element::TextBox(text_box) => Some(
+                    text_box
+                        .texts
+                        .iter()
+                        .map(|text| text.text.as_str())
+                        .collect::<String>(),
+                ),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+
+    // Runs of spaces are left as regular spaces by default
+    assert_eq!(texts(false), ["Name:    Value", "a    b"]);
+    // The run is kept with the last space still able to wrap
+    assert_eq!(texts(true), ["Name:\u{a0}\u{a0}\u{a0} Value", "a    b"]);
+}
+
+#[test]
+fn code_block_wrap() {
+    log::init();
+
+    let text = "Some text\n\n```\nlet x = 1;\n```";
+    let no_wraps = |wrap| {
+        let opts = InterpreterOpts::new().code_block_wrap(wrap);
+        interpret_md_with_opts(text, opts)
+            .iter()
+            .filter_map(|element| match element {
+                Element::TextBox(text_box) => Some(text_box.no_wrap),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(no_wraps(true), [false, false]);
+    // Only the code block is affected
+    assert_eq!(no_wraps(false), [false, true]);
+}
+
+#[test]
+fn sub_and_sup() {
+    log::init();
+
+    let text = "H<sub>2</sub>O and x<sup>**n** *i*</sup> plus <sup>a<sub>b</sub></sup>";
+    let texts: Vec<_> = interpret_md_with_opts(text, InterpreterOpts::new())
+        .iter()
+        .filter_map(elem_as_text_box)
+        .flat_map(|text_box| text_box.texts.clone())
+        .filter(|text| text.script.is_some())
+        .collect();
+    insta::assert_debug_snapshot!(texts, @r###"
+    [
+        Text {
+            text: "2",
+            default_color: Color(BLACK),
+            script: Some(Sub),
+            ..
+        },
+        Text {
+            text: "n",
+            default_color: Color(BLACK),
+            style: BOLD ,
+            script: Some(Super),
+            ..
+        },
+        Text {
+            text: "i",
+            default_color: Color(BLACK),
+            style: ITALIC ,
+            script: Some(Super),
+            ..
+        },
+        Text {
+            text: "a",
+            default_color: Color(BLACK),
+            script: Some(Super),
+            ..
+        },
+        Text {
+            text: "b",
+            default_color: Color(BLACK),
+            script: Some(Sub),
+            ..
+        },
+    ]
+    "###);
+}
+
+#[test]
+fn code_line_numbers() {
+    log::init();
+
+    let text = "```\nlet x = 1;\n```\n\nSome text\n\n    indented\n    code";
+    let line_numbers = |enabled| {
+        let opts = InterpreterOpts::new().code_line_numbers(enabled);
+        interpret_md_with_opts(text, opts)
+            .iter()
+            .filter_map(|element| match element {
+                Element::TextBox(text_box) => Some(text_box.line_numbers),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(line_numbers(false), [false, false, false]);
+    // Only the code blocks get numbered
+    assert_eq!(line_numbers(true), [true, false, true]);
+}
+
+#[test]
+fn math() {
+    log::init();
+
+    let text = "\
+Inline $x^2$ math
+
+$$\\frac{1}{2}$$
+
+Broken $\\frac{1$ math
+
+# Energy $E=mc^2$ law
+
+| left $x^2$ right | z |
+| --- | --- |
+| 1 | 2 |
+
+<details>
+<summary>More</summary>
+
+$$y$$
+
+</details>
+";
+    fn kinds(elements: &[Positioned<Element>]) -> String {
+        let kinds: Vec<_> = elements
+            .iter()
+            .map(|element| match &element.inner {
+                Element::TextBox(_) => "text",
+                Element::Image(_) => "image",
+                Element::Spacer(_) => "spacer",
+                _ => "other",
+            })
+            .collect();
+        kinds.join(" ")
+    }
+    let text_of =
+        |text_box: &TextBox| -> String { text_box.texts.iter().map(|t| &*t.text).collect() };
+    let describe = |math| {
+        let opts = InterpreterOpts::new().math(math);
+        interpret_md_with_opts(text, opts)
+            .iter()
+            .filter_map(|element| match element {
+                Element::TextBox(text_box) => match &text_box.is_anchor {
+                    Some(anchor) => Some(format!("{} {anchor}", text_of(text_box))),
+                    None => Some(text_of(text_box)),
+                },
+                Element::Image(image) => Some(format!("image {:?}", image.is_aligned)),
+                Element::Row(row) => Some(kinds(&row.elements)),
+                Element::Table(table) => Some(
+                    table
+                        .headers
+                        .iter()
+                        .map(text_of)
+                        .collect::<Vec<_>>()
+                        .join(" | "),
+                ),
+                Element::Section(section) => Some(format!("details: {}", kinds(&section.elements))),
+                _ => None,
+            })
+            .collect::<Vec<String>>()
+    };
+
+    assert_eq!(
+        describe(false),
+        [
+            "Inline $x^2$ math",
+            "$$\\frac{1}{2}$$",
+            "Broken $\\frac{1$ math",
+            "Energy $E=mc^2$ law #energy-emc2-law",
+            "left $x^2$ right | z",
+            "details: text",
+        ]
+    );
+    // Inline math shares a row with its text, display math is centered on its own, and malformed
+    // math is left as written. Headers and table cells keep their text together instead
+    assert_eq!(
+        describe(true),
+        [
+            "text image text",
+            "image Some(Center)",
+            "Broken $\\frac{1$ math",
+            "Energy $E=mc^2$ law #energy-emc2-law",
+            "left $x^2$ right | z",
+            "details: image spacer",
+        ]
+    );
+}
+
+#[test]
+fn table_align() {
+    log::init();
+
+    let table_align = |html: &str| {
+        interpret_md_with_opts(html, InterpreterOpts::new())
+            .into_iter()
+            .find_map(|element| match element {
+                Element::Table(table) => Some(table.align),
+                _ => None,
+            })
+            .unwrap()
+    };
+
+    let cells = "<tr><td>a</td><td>b</td></tr>";
+    assert_eq!(table_align(&format!("<table>{cells}</table>")), Align::Left);
+    assert_eq!(
+        table_align(&format!("<table align=\"center\">{cells}</table>")),
+        Align::Center
+    );
+    // Inherited from a surrounding element
+    assert_eq!(
+        table_align(&format!(
+            "<div align=\"right\"><table>{cells}</table></div>"
+        )),
+        Align::Right
+    );
+}
+
 // TODO: change this to test against the image cache so that we can inspect the error?
 #[test]
 fn image_loading_fails_gracefully() {
@@ -889,6 +1906,81 @@ fn image_loading_fails_gracefully() {
     });
 }
 
+#[test]
+fn on_image_error_modes() {
+    log::init();
+
+    let text = "![Build status](does_not_exist.png)";
+    let failed_image = |on_error| {
+        let opts = InterpreterOpts::new().on_image_error(on_error);
+        let mut elems = interpret_md_with_opts(text, opts);
+        let image = elems.iter_mut().find_map(|element| match element {
+            crate::Element::Row(row) => match &mut row.elements[0].inner {
+                crate::Element::Image(image) => Some(std::mem::take(image)),
+                _ => None,
+            },
+            _ => None,
+        });
+        image.unwrap()
+    };
+
+    // The broken image icon takes its place
+    let mut image = failed_image(OnImageError::Placeholder);
+    assert!(image.image_data.lock().unwrap().is_some());
+    assert!(image.take_failed_alt().is_none());
+
+    // Hidden images don't take up any space
+    let mut image = failed_image(OnImageError::Hide);
+    assert!(image.image_data.lock().unwrap().is_none());
+    assert_eq!(image.size((800., 600.), 1.), None);
+    assert!(image.take_failed_alt().is_none());
+
+    // The alt text takes its place
+    let mut image = failed_image(OnImageError::Alt);
+    assert!(image.image_data.lock().unwrap().is_none());
+    let alt = image.take_failed_alt().unwrap();
+    assert_eq!(alt.texts[0].text, "Build status");
+    assert!(alt.texts[0].is_italic);
+}
+
+#[test]
+fn blocked_remote_images() {
+    log::init();
+
+    let (send_ua, recv_ua) = mpsc::channel();
+    let state = server::State::new().send(send_ua);
+    let server = server::spawn(state, |state, req, _req_url| {
+        let maybe_ua = req.headers().iter().find_map(|Header { field, value }| {
+            field.equiv("user-agent").then(|| value.as_str().to_owned())
+        });
+        let _ = state
+            .send
+            .as_ref()
+            .unwrap()
+            .send(server::FromServer::UserAgent(maybe_ua));
+        let sample_body = Sample::Png(SamplePng::Bun).pre_decode();
+        Response::from_data(sample_body).boxed()
+    });
+
+    let text = format!("![Tracking pixel]({}/pixel.png)", server.url());
+    let opts = InterpreterOpts::new()
+        .allow_remote_images(false)
+        .on_image_error(OnImageError::Alt);
+    let mut elems = interpret_md_with_opts(&text, opts);
+    let image = elems.iter_mut().find_map(|element| match element {
+        crate::Element::Row(row) => match &mut row.elements[0].inner {
+            crate::Element::Image(image) => Some(image),
+            _ => None,
+        },
+        _ => None,
+    });
+
+    // Falls back to the alt text without ever making a request
+    let alt = image.unwrap().take_failed_alt().unwrap();
+    assert_eq!(alt.texts[0].text, "Tracking pixel");
+    assert!(recv_ua.try_recv().is_err());
+}
+
 // Check to see that each paths are used for their respective color-schemes
 #[test]
 fn picture_dark_light() {
@@ -930,7 +2022,13 @@ fn picture_dark_light() {
 "#,
     );
 
-    for color_scheme in [None, Some(ResolvedTheme::Dark), Some(ResolvedTheme::Light)] {
+    let color_schemes = [
+        None,
+        Some(ResolvedTheme::Dark),
+        Some(ResolvedTheme::Light),
+        Some(ResolvedTheme::Mono),
+    ];
+    for color_scheme in color_schemes {
         let mut opts = InterpreterOpts::new();
         if let Some(color_scheme) = color_scheme {
             opts.set_color_scheme(color_scheme);
@@ -950,7 +2048,8 @@ fn picture_dark_light() {
             .to_bytes();
         let byte = match color_scheme {
             Some(ResolvedTheme::Dark) => 0xff,
-            Some(ResolvedTheme::Light) => 0x00,
+            // Mono is black on white, so it goes with the light variant
+            Some(ResolvedTheme::Light | ResolvedTheme::Mono) => 0x00,
             None => 0x99,
         };
         // Image data stores pixel data in RGBA format
@@ -4,8 +4,44 @@ pub enum Action {
     ToEdge(VertDirection),
     Scroll(VertDirection),
     Page(VertDirection),
+    /// Scrolls by half of the window's height
+    ScrollHalfPage(VertDirection),
     Zoom(Zoom),
     Copy,
+    /// Copies the selection as markdown, keeping its inline formatting
+    CopyMarkdown,
+    /// Selects the whole document
+    SelectAll,
+    /// Copies the current file's path
+    CopyPath,
+    Search,
+    /// Opens a list of the document's headers to jump to
+    GoToAnchor,
+    SearchMatch(HistDirection),
+    ToggleWhitespace,
+    /// Shows or hides the status bar along the bottom of the window
+    ToggleStatusBar,
+    /// Shows or hides the table of contents
+    ToggleToc,
+    /// Opens the current file in the user's editor
+    Edit,
+    /// Expands or collapses `<details>` sections
+    ToggleDetails(DetailsScope),
+    /// Marks the current reading position
+    SetMark,
+    /// Jumps back to the reading position mark
+    GotoMark,
+    /// Switches to the next tab
+    NextTab,
+    /// Closes the active tab
+    CloseTab,
+    /// Re-reads the config file and applies what can change without a restart
+    ReloadConfig,
+    /// Re-reads the current file from a clean slate, resetting the zoom, scroll position,
+    /// selection, and loaded images
+    HardReload,
+    /// Dismisses the active mode (if any)
+    Dismiss,
     Quit,
 }
 
@@ -21,9 +57,18 @@ pub enum VertDirection {
     Down,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DetailsScope {
+    All,
+    /// The section closest to the top of the window
+    Nearest,
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Zoom {
     In,
     Out,
     Reset,
+    /// Fits the widest element to the window's width
+    FitWidth,
 }
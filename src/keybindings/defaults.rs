@@ -1,6 +1,6 @@
 use crate::keybindings::action::HistDirection;
 
-use super::action::{Action, VertDirection, Zoom};
+use super::action::{Action, DetailsScope, VertDirection, Zoom};
 use super::{Key, KeyCombo, ModifiedKey};
 
 use winit::event::{ModifiersState, VirtualKeyCode as VirtKey};
@@ -20,6 +20,11 @@ pub fn defaults() -> Vec<(Action, KeyCombo)> {
             Action::Copy,
             KeyCombo(vec![ModifiedKey(Key::from(VirtKey::C), ctrl_or_command)]),
         ),
+        // Select all: Ctrl+A / Command+A
+        (
+            Action::SelectAll,
+            KeyCombo(vec![ModifiedKey(Key::from(VirtKey::A), ctrl_or_command)]),
+        ),
         // Zoom in: Ctrl+= / Command+=
         (
             Action::Zoom(Zoom::In),
@@ -52,6 +57,19 @@ pub fn defaults() -> Vec<(Action, KeyCombo)> {
                 ModifiersState::ALT,
             )]),
         ),
+        // Switch to the next tab: Ctrl+Tab
+        (
+            Action::NextTab,
+            KeyCombo(vec![ModifiedKey(
+                Key::from(VirtKey::Tab),
+                ModifiersState::CTRL,
+            )]),
+        ),
+        // Close the tab: Ctrl+W / Command+W
+        (
+            Action::CloseTab,
+            KeyCombo(vec![ModifiedKey(Key::from(VirtKey::W), ctrl_or_command)]),
+        ),
         // Scroll up: Up-arrow
         (
             Action::Scroll(VertDirection::Up),
@@ -82,11 +100,19 @@ pub fn defaults() -> Vec<(Action, KeyCombo)> {
             Action::ToEdge(VertDirection::Down),
             KeyCombo::from(VirtKey::End),
         ),
-        // Quit: Esc
-        (Action::Quit, KeyCombo::from(VirtKey::Escape)),
+        // Dismiss: Esc
+        (Action::Dismiss, KeyCombo::from(VirtKey::Escape)),
         // vim-like bindings
         // Copy: y
         (Action::Copy, KeyCombo::from(VirtKey::Y)),
+        // Copy as markdown: Y
+        (
+            Action::CopyMarkdown,
+            KeyCombo(vec![ModifiedKey(
+                Key::from(VirtKey::Y),
+                ModifiersState::SHIFT,
+            )]),
+        ),
         // Scroll up: k
         (
             Action::Scroll(VertDirection::Up),
@@ -97,6 +123,22 @@ pub fn defaults() -> Vec<(Action, KeyCombo)> {
             Action::Scroll(VertDirection::Down),
             KeyCombo::from(VirtKey::J),
         ),
+        // Half page up: Ctrl+u
+        (
+            Action::ScrollHalfPage(VertDirection::Up),
+            KeyCombo(vec![ModifiedKey(
+                Key::from(VirtKey::U),
+                ModifiersState::CTRL,
+            )]),
+        ),
+        // Half page down: Ctrl+d
+        (
+            Action::ScrollHalfPage(VertDirection::Down),
+            KeyCombo(vec![ModifiedKey(
+                Key::from(VirtKey::D),
+                ModifiersState::CTRL,
+            )]),
+        ),
         // Go to top of doc: gg
         (
             Action::ToEdge(VertDirection::Up),
@@ -129,6 +171,29 @@ pub fn defaults() -> Vec<(Action, KeyCombo)> {
                 ModifiedKey(Key::from(VirtKey::Q), ModifiersState::SHIFT),
             ]),
         ),
+        // Search: /
+        (Action::Search, KeyCombo::from(VirtKey::Slash)),
+        // Next search match: n
+        (
+            Action::SearchMatch(HistDirection::Next),
+            KeyCombo::from(VirtKey::N),
+        ),
+        // Previous search match: N
+        (
+            Action::SearchMatch(HistDirection::Prev),
+            KeyCombo(vec![ModifiedKey(
+                Key::from(VirtKey::N),
+                ModifiersState::SHIFT,
+            )]),
+        ),
+        // Jump to a header: gh
+        (
+            Action::GoToAnchor,
+            KeyCombo(vec![
+                ModifiedKey::from(VirtKey::G),
+                ModifiedKey::from(VirtKey::H),
+            ]),
+        ),
         // Navigate to next file: bn
         (
             Action::History(HistDirection::Next),
@@ -145,5 +210,38 @@ pub fn defaults() -> Vec<(Action, KeyCombo)> {
                 ModifiedKey::from(VirtKey::P),
             ]),
         ),
+        // Open in editor: e
+        (Action::Edit, KeyCombo::from(VirtKey::E)),
+        // Toggle the nearest details section: za
+        (
+            Action::ToggleDetails(DetailsScope::Nearest),
+            KeyCombo(vec![
+                ModifiedKey::from(VirtKey::Z),
+                ModifiedKey::from(VirtKey::A),
+            ]),
+        ),
+        // Toggle all details sections: zA
+        (
+            Action::ToggleDetails(DetailsScope::All),
+            KeyCombo(vec![
+                ModifiedKey::from(VirtKey::Z),
+                ModifiedKey(Key::from(VirtKey::A), ModifiersState::SHIFT),
+            ]),
+        ),
+        // Set and jump back to the reading mark: m and '
+        (Action::SetMark, KeyCombo::from(VirtKey::M)),
+        (Action::GotoMark, KeyCombo::from(VirtKey::Apostrophe)),
+        // Toggle the status bar: s
+        (Action::ToggleStatusBar, KeyCombo::from(VirtKey::S)),
+        // Toggle the table of contents: t
+        (Action::ToggleToc, KeyCombo::from(VirtKey::T)),
+        // Reload from a clean slate: R
+        (
+            Action::HardReload,
+            KeyCombo(vec![ModifiedKey(
+                Key::from(VirtKey::R),
+                ModifiersState::SHIFT,
+            )]),
+        ),
     ]
 }
@@ -71,7 +71,7 @@ impl From<KeybindingsSection> for Keybindings {
 mod tests {
     use winit::event::ModifiersState;
 
-    use crate::keybindings::{action::VertDirection, Key, ModifiedKey};
+    use crate::keybindings::{action::VertDirection, Key, ModifiedKey, MouseBindings};
 
     use super::*;
 
@@ -80,7 +80,8 @@ mod tests {
         assert_eq!(
             Keybindings::from(KeybindingsSection {
                 base: Keybindings::default(),
-                extra: None
+                extra: None,
+                mouse: MouseBindings::default(),
             }),
             Keybindings::default()
         );
@@ -99,7 +100,8 @@ mod tests {
         assert_eq!(
             Keybindings::from(KeybindingsSection {
                 base: Keybindings::default(),
-                extra: Some(Keybindings(vec![(Action::Quit, combo)]))
+                extra: Some(Keybindings(vec![(Action::Quit, combo)])),
+                mouse: MouseBindings::default(),
             }),
             expected
         );
@@ -120,7 +122,8 @@ mod tests {
         assert_eq!(
             Keybindings::from(KeybindingsSection {
                 base,
-                extra: Some(extra)
+                extra: Some(extra),
+                mouse: MouseBindings::default(),
             }),
             expected
         );
@@ -3,6 +3,7 @@ mod defaults;
 #[allow(clippy::module_inception)]
 mod keybindings;
 mod mappings;
+mod mouse;
 mod serialization;
 #[cfg(test)]
 mod tests;
@@ -17,6 +18,7 @@ use winit::event::{ModifiersState, ScanCode, VirtualKeyCode as VirtKey};
 
 use action::Action;
 pub use keybindings::Keybindings;
+pub use mouse::MouseBindings;
 
 use crate::opts::KeybindingsSection;
 
@@ -24,6 +26,9 @@ use crate::opts::KeybindingsSection;
 pub enum Key {
     Resolved(VirtKey),
     ScanCode(ScanCode),
+    /// A key matched by its physical position regardless of what it resolves to with the current
+    /// keyboard layout
+    Physical(ScanCode),
 }
 
 impl Key {
@@ -54,6 +59,7 @@ impl fmt::Display for Key {
                 }
             }
             Key::ScanCode(scan_code) => write!(f, "<scan code: {scan_code}>"),
+            Key::Physical(scan_code) => write!(f, "physical:{scan_code}"),
         }
     }
 }
@@ -62,6 +68,13 @@ impl FromStr for Key {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(scan_code) = s.strip_prefix("physical:") {
+            return scan_code
+                .parse()
+                .map(Key::Physical)
+                .map_err(|_| anyhow::anyhow!("Invalid physical scan code: {scan_code}"));
+        }
+
         mappings::STR_TO_VIRT_KEY
             .iter()
             .find_map(|&(key_str, key)| (s == key_str).then_some(Key::Resolved(key)))
@@ -276,7 +289,13 @@ impl KeyCombos {
     }
 
     /// Processes a modified key and emits the corresponding action if this completes a keycombo
-    pub fn munch(&mut self, modified_key: ModifiedKey) -> Option<Action> {
+    ///
+    /// Physical keybindings matching `scan_code` take priority over the resolved key
+    pub fn munch(
+        &mut self,
+        modified_key: ModifiedKey,
+        scan_code: Option<ScanCode>,
+    ) -> Option<Action> {
         // We ignore modifier keys since they aren't considered part of combos
         if let Key::Resolved(key) = &modified_key.0 {
             if [
@@ -295,9 +314,14 @@ impl KeyCombos {
             }
         }
 
-        tracing::debug!("Received key: {modified_key}");
+        match scan_code {
+            Some(scan_code) => {
+                tracing::debug!("Received key: {modified_key} (physical:{scan_code})")
+            }
+            None => tracing::debug!("Received key: {modified_key}"),
+        }
 
-        let maybe_action = self.munch_(modified_key);
+        let maybe_action = self.munch_(modified_key, scan_code);
 
         if let Some(action) = maybe_action {
             tracing::debug!("Emitting action: {:?}", action);
@@ -306,10 +330,14 @@ impl KeyCombos {
         maybe_action
     }
 
-    fn munch_(&mut self, modified_key: ModifiedKey) -> Option<Action> {
+    fn munch_(&mut self, modified_key: ModifiedKey, scan_code: Option<ScanCode>) -> Option<Action> {
         let node = self.storage.get(self.position)?;
 
-        match node.get(&modified_key) {
+        let physical_connection = scan_code.and_then(|scan_code| {
+            let physical_key = ModifiedKey(Key::Physical(scan_code), modified_key.1);
+            node.get(&physical_key)
+        });
+        match physical_connection.or_else(|| node.get(&modified_key)) {
             Some(&Connection::Leaf(action)) => {
                 self.reset();
                 Some(action)
@@ -325,7 +353,7 @@ impl KeyCombos {
                 if in_multikey_combo {
                     // If we were broken out of a multi-key combo the key that broke us out could be
                     // part of a new keycombo
-                    self.munch_(modified_key)
+                    self.munch_(modified_key, scan_code)
                 } else {
                     None
                 }
@@ -0,0 +1,161 @@
+//! Binding actions to the extra buttons that a lot of mice have (e.g. back and forward)
+//!
+//! The left, right, and middle buttons already do their own thing, so only the others can be
+//! bound. Each platform numbers the extra buttons differently, so `Back` and `Forward` stand in for
+//! whatever the current platform uses
+
+use std::fmt;
+
+use super::action::{Action, HistDirection};
+
+use serde::{de, Deserialize, Deserializer};
+use winit::event::MouseButton as WinitButton;
+
+#[cfg(windows)]
+const BACK: &[u16] = &[1];
+#[cfg(windows)]
+const FORWARD: &[u16] = &[2];
+#[cfg(target_os = "macos")]
+const BACK: &[u16] = &[3];
+#[cfg(target_os = "macos")]
+const FORWARD: &[u16] = &[4];
+// X11 and Wayland (`BTN_SIDE`/`BTN_EXTRA`) respectively
+#[cfg(not(any(windows, target_os = "macos")))]
+const BACK: &[u16] = &[8, 0x113];
+#[cfg(not(any(windows, target_os = "macos")))]
+const FORWARD: &[u16] = &[9, 0x114];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MouseButton {
+    Back,
+    Forward,
+    /// Any other button by the number that the platform gives it
+    Other(u16),
+}
+
+impl MouseButton {
+    fn matches(self, button: WinitButton) -> bool {
+        let WinitButton::Other(num) = button else {
+            return false;
+        };
+        match self {
+            Self::Back => BACK.contains(&num),
+            Self::Forward => FORWARD.contains(&num),
+            Self::Other(other) => other == num,
+        }
+    }
+}
+
+impl fmt::Display for MouseButton {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Back => f.write_str("Back"),
+            Self::Forward => f.write_str("Forward"),
+            Self::Other(num) => write!(f, "{num}"),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for MouseButton {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum StringOrNum {
+            Str(String),
+            Num(u16),
+        }
+
+        match StringOrNum::deserialize(deserializer)? {
+            StringOrNum::Str(s) => match s.as_str() {
+                "Back" => Ok(Self::Back),
+                "Forward" => Ok(Self::Forward),
+                _ => Err(de::Error::custom(format!(
+                    "Unsupported mouse button: {s}. Expected \"Back\", \"Forward\", or a button \
+                    number"
+                ))),
+            },
+            StringOrNum::Num(num) => Ok(Self::Other(num)),
+        }
+    }
+}
+
+/// A list of mouse buttons each associated with an [`Action`]
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+pub struct MouseBindings(pub Vec<(Action, MouseButton)>);
+
+impl Default for MouseBindings {
+    fn default() -> Self {
+        Self(vec![
+            (Action::History(HistDirection::Prev), MouseButton::Back),
+            (Action::History(HistDirection::Next), MouseButton::Forward),
+        ])
+    }
+}
+
+impl MouseBindings {
+    pub fn iter(&self) -> std::slice::Iter<'_, (Action, MouseButton)> {
+        self.0.iter()
+    }
+
+    /// The action bound to `button`, if there is one
+    pub fn action(&self, button: WinitButton) -> Option<Action> {
+        let action = self
+            .iter()
+            .find_map(|&(action, bound)| bound.matches(button).then_some(action));
+        tracing::debug!("Mouse button: {button:?}, Action: {action:?}");
+        action
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::opts::Config;
+
+    #[test]
+    fn defaults() {
+        let bindings = MouseBindings::default();
+        let back = WinitButton::Other(BACK[0]);
+        let forward = WinitButton::Other(FORWARD[0]);
+        assert_eq!(
+            bindings.action(back),
+            Some(Action::History(HistDirection::Prev))
+        );
+        assert_eq!(
+            bindings.action(forward),
+            Some(Action::History(HistDirection::Next))
+        );
+        // The regular buttons are never bound
+        assert_eq!(bindings.action(WinitButton::Left), None);
+        assert_eq!(bindings.action(WinitButton::Other(100)), None);
+    }
+
+    #[test]
+    fn from_config() {
+        let config = r#"
+[keybindings]
+mouse = [
+    ["HistoryNext", "Back"],
+    ["Quit", 12],
+]
+"#;
+        let Config { keybindings, .. } = Config::load_from_str(config).unwrap();
+        let bindings = keybindings.mouse;
+        assert_eq!(
+            bindings.action(WinitButton::Other(BACK[0])),
+            Some(Action::History(HistDirection::Next))
+        );
+        assert_eq!(bindings.action(WinitButton::Other(12)), Some(Action::Quit));
+        // Setting any replaces the defaults
+        assert_eq!(bindings.action(WinitButton::Other(FORWARD[0])), None);
+
+        let err = Config::load_from_str("[keybindings]\nmouse = [[\"Quit\", \"Side\"]]")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("Unsupported mouse button: Side"), "{err}");
+    }
+}
@@ -2,7 +2,7 @@ use std::str::FromStr;
 
 use crate::keybindings::action::HistDirection;
 
-use super::action::{Action, VertDirection, Zoom};
+use super::action::{Action, DetailsScope, VertDirection, Zoom};
 use super::{Key, KeyCombo, ModifiedKey};
 
 use serde::{de, Deserialize, Deserializer};
@@ -23,10 +23,33 @@ impl<'de> Deserialize<'de> for Action {
             ScrollDown,
             PageUp,
             PageDown,
+            HalfPageUp,
+            HalfPageDown,
             ZoomIn,
             ZoomOut,
             ZoomReset,
+            ZoomFitWidth,
             Copy,
+            CopyMarkdown,
+            SelectAll,
+            CopyPath,
+            Search,
+            SearchNext,
+            SearchPrevious,
+            GoToAnchor,
+            ToggleWhitespace,
+            ToggleStatusBar,
+            ToggleToc,
+            Edit,
+            ToggleAllDetails,
+            ToggleNearestDetails,
+            SetMark,
+            GotoMark,
+            NextTab,
+            CloseTab,
+            ReloadConfig,
+            HardReload,
+            Dismiss,
             Quit,
         }
 
@@ -39,10 +62,33 @@ impl<'de> Deserialize<'de> for Action {
             FlatAction::ScrollDown => Action::Scroll(VertDirection::Down),
             FlatAction::PageUp => Action::Page(VertDirection::Up),
             FlatAction::PageDown => Action::Page(VertDirection::Down),
+            FlatAction::HalfPageUp => Action::ScrollHalfPage(VertDirection::Up),
+            FlatAction::HalfPageDown => Action::ScrollHalfPage(VertDirection::Down),
             FlatAction::ZoomIn => Action::Zoom(Zoom::In),
             FlatAction::ZoomOut => Action::Zoom(Zoom::Out),
             FlatAction::ZoomReset => Action::Zoom(Zoom::Reset),
+            FlatAction::ZoomFitWidth => Action::Zoom(Zoom::FitWidth),
             FlatAction::Copy => Action::Copy,
+            FlatAction::CopyMarkdown => Action::CopyMarkdown,
+            FlatAction::SelectAll => Action::SelectAll,
+            FlatAction::CopyPath => Action::CopyPath,
+            FlatAction::Search => Action::Search,
+            FlatAction::SearchNext => Action::SearchMatch(HistDirection::Next),
+            FlatAction::SearchPrevious => Action::SearchMatch(HistDirection::Prev),
+            FlatAction::GoToAnchor => Action::GoToAnchor,
+            FlatAction::ToggleWhitespace => Action::ToggleWhitespace,
+            FlatAction::ToggleStatusBar => Action::ToggleStatusBar,
+            FlatAction::ToggleToc => Action::ToggleToc,
+            FlatAction::Edit => Action::Edit,
+            FlatAction::ToggleAllDetails => Action::ToggleDetails(DetailsScope::All),
+            FlatAction::ToggleNearestDetails => Action::ToggleDetails(DetailsScope::Nearest),
+            FlatAction::SetMark => Action::SetMark,
+            FlatAction::GotoMark => Action::GotoMark,
+            FlatAction::NextTab => Action::NextTab,
+            FlatAction::CloseTab => Action::CloseTab,
+            FlatAction::ReloadConfig => Action::ReloadConfig,
+            FlatAction::HardReload => Action::HardReload,
+            FlatAction::Dismiss => Action::Dismiss,
             FlatAction::Quit => Action::Quit,
         };
 
@@ -1,8 +1,10 @@
 use super::action::{Action, VertDirection};
-use super::{KeyCombos, Keybindings, ModifiedKey};
+use super::{Key, KeyCombos, Keybindings, ModifiedKey};
 use crate::opts::Config;
 use crate::test_utils::log;
 
+use std::str::FromStr;
+
 use winit::event::{ModifiersState, VirtualKeyCode as VirtKey};
 
 #[test]
@@ -41,7 +43,85 @@ base = [
     ];
 
     for (key, maybe_action) in test_vectors {
-        assert_eq!(key_combos.munch(key), maybe_action);
+        assert_eq!(key_combos.munch(key, None), maybe_action);
+    }
+}
+
+#[test]
+fn physical_keys() {
+    log::init();
+
+    let config = r#"
+[keybindings]
+base = [
+    ["ToTop", "physical:53"],
+    ["ToBottom", { key = "physical:53", mod = "Shift" }],
+    ["ScrollDown", "/"],
+    ["ScrollUp", "k"],
+]
+"#;
+
+    let Config { keybindings, .. } = Config::load_from_str(config).unwrap();
+    let mut key_combos = KeyCombos::new(keybindings).unwrap();
+
+    let slash: ModifiedKey = VirtKey::Slash.into();
+    let minus: ModifiedKey = VirtKey::Minus.into();
+    let shift_minus = ModifiedKey(minus.0, ModifiersState::SHIFT);
+    let k: ModifiedKey = VirtKey::K.into();
+
+    let test_vectors = [
+        // The physical binding matches no matter what the key resolves to with the layout
+        (slash, Some(53), Some(Action::ToEdge(VertDirection::Up))),
+        (minus, Some(53), Some(Action::ToEdge(VertDirection::Up))),
+        (
+            shift_minus,
+            Some(53),
+            Some(Action::ToEdge(VertDirection::Down)),
+        ),
+        // Falls back to the resolved key when there's no matching physical binding
+        (slash, Some(61), Some(Action::Scroll(VertDirection::Down))),
+        (slash, None, Some(Action::Scroll(VertDirection::Down))),
+        (k, Some(37), Some(Action::Scroll(VertDirection::Up))),
+        (minus, Some(12), None),
+    ];
+
+    for (key, scan_code, maybe_action) in test_vectors {
+        assert_eq!(key_combos.munch(key, scan_code), maybe_action);
+    }
+
+    let invalid = Key::from_str("physical:slash").unwrap_err();
+    assert_eq!(invalid.to_string(), "Invalid physical scan code: slash");
+}
+
+#[test]
+fn half_page_scroll() {
+    log::init();
+
+    let config = r#"
+[keybindings]
+base = [
+    ["HalfPageUp", { key = "u", mod = "Ctrl" }],
+    ["HalfPageDown", { key = "d", mod = "Ctrl" }],
+    ["PageDown", "d"],
+]
+"#;
+
+    let Config { keybindings, .. } = Config::load_from_str(config).unwrap();
+    let mut key_combos = KeyCombos::new(keybindings).unwrap();
+
+    let d: ModifiedKey = VirtKey::D.into();
+    let ctrl_d = ModifiedKey(d.0, ModifiersState::CTRL);
+    let ctrl_u = ModifiedKey(Key::from(VirtKey::U), ModifiersState::CTRL);
+
+    let test_vectors = [
+        (ctrl_u, Some(Action::ScrollHalfPage(VertDirection::Up))),
+        (ctrl_d, Some(Action::ScrollHalfPage(VertDirection::Down))),
+        // The modifier is what sets it apart from a full page
+        (d, Some(Action::Page(VertDirection::Down))),
+    ];
+
+    for (key, maybe_action) in test_vectors {
+        assert_eq!(key_combos.munch(key, None), maybe_action);
     }
 }
 
@@ -54,28 +134,46 @@ const DEFAULTS_TEMPLATE: &str = r#"
 base = [
     # Regular
     ["Copy", { key = "c", mod = "CTRL_OR_CMD" }],
+    ["SelectAll", { key = "a", mod = "CTRL_OR_CMD" }],
     ["ZoomIn", { key = "=", mod = "CTRL_OR_CMD" }],
     ["ZoomOut", { key = "-", mod = "CTRL_OR_CMD" }],
     ["HistoryNext", { key = "Right", mod = "Alt" }],
     ["HistoryPrevious", { key = "Left", mod = "Alt" }],
+    ["NextTab", { key = "Tab", mod = "Ctrl" }],
+    ["CloseTab", { key = "w", mod = "CTRL_OR_CMD" }],
     ["ScrollUp", "Up"],
     ["ScrollDown", "Down"],
     ["PageUp", "PageUp"],
     ["PageDown", "PageDown"],
     ["ToTop", "Home"],
     ["ToBottom", "End"],
-    ["Quit", "Escape"],
+    ["Dismiss", "Escape"],
     # Vim-like
     ["Copy", "y"],
+    ["CopyMarkdown", "Y"],
     ["ScrollUp", "k"],
     ["ScrollDown", "j"],
+    ["HalfPageUp", { key = "u", mod = "Ctrl" }],
+    ["HalfPageDown", { key = "d", mod = "Ctrl" }],
     ["ToTop", ["g", "g"]],
     ["ToBottom", "G"],
     ["Quit", "q"],
     ["Quit", ["Z", "Z"]],
     ["Quit", ["Z", "Q"]],
+    ["Search", "/"],
+    ["SearchNext", "n"],
+    ["SearchPrevious", "N"],
+    ["GoToAnchor", ["g", "h"]],
     ["HistoryNext", ["b", "n"]],
     ["HistoryPrevious", ["b", "p"]],
+    ["Edit", "e"],
+    ["ToggleNearestDetails", ["z", "a"]],
+    ["ToggleAllDetails", ["z", "A"]],
+    ["SetMark", "m"],
+    ["GotoMark", "'"],
+    ["ToggleStatusBar", "s"],
+    ["ToggleToc", "t"],
+    ["HardReload", "R"],
 ]
 "#;
 
@@ -0,0 +1,102 @@
+//! Opening external links with a specific command instead of the system's default (`link-command`)
+//!
+//! The command gets split up like a shell would, and the link is passed as one extra argument at
+//! the end. No shell is involved, so the link never needs escaping
+
+use std::fmt;
+use std::process::Command;
+
+use serde::Deserialize;
+
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(try_from = "String")]
+pub struct LinkCommand {
+    program: String,
+    args: Vec<String>,
+}
+
+impl TryFrom<String> for LinkCommand {
+    type Error = String;
+
+    fn try_from(command: String) -> Result<Self, Self::Error> {
+        let mut words = shlex::split(&command)
+            .ok_or_else(|| format!("Failed splitting link-command `{command}` into arguments"))?
+            .into_iter();
+        let program = words
+            .next()
+            .ok_or_else(|| "link-command can't be empty".to_owned())?;
+        Ok(Self {
+            program,
+            args: words.collect(),
+        })
+    }
+}
+
+impl fmt::Display for LinkCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let words = std::iter::once(&self.program).chain(&self.args);
+        f.write_str(&shlex::try_join(words.map(String::as_str)).map_err(|_| fmt::Error)?)
+    }
+}
+
+impl LinkCommand {
+    fn command(&self, link: &str) -> Command {
+        let mut command = Command::new(&self.program);
+        command.args(&self.args).arg(link);
+        command
+    }
+}
+
+/// Opens `link` with `command` when there is one, falling back to the system's default handler
+pub fn open(command: Option<&LinkCommand>, link: &str) {
+    if let Some(command) = command {
+        match command.command(link).spawn() {
+            Ok(mut child) => {
+                // Reap it once it's done instead of leaving a zombie behind
+                std::thread::spawn(move || child.wait());
+                return;
+            }
+            Err(err) => tracing::warn!(
+                "Failed running link-command `{command}` for {link}: {err}. Falling back to the \
+                system's default"
+            ),
+        }
+    }
+
+    if let Err(err) = open::that(link) {
+        tracing::error!(
+            "Could not open link: {err} from {:?}",
+            std::env::current_dir()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(command: &str) -> Result<LinkCommand, String> {
+        LinkCommand::try_from(command.to_owned())
+    }
+
+    #[test]
+    fn splitting() {
+        let command = parse(r#"firefox --new-window "Some Profile""#).unwrap();
+        let built = command.command("https://example.org/?a=1&b='2'");
+        assert_eq!(built.get_program(), "firefox");
+        let args: Vec<_> = built.get_args().collect();
+        assert_eq!(
+            args,
+            [
+                "--new-window",
+                "Some Profile",
+                "https://example.org/?a=1&b='2'"
+            ]
+        );
+        // Quoting survives round tripping through the printed config
+        assert_eq!(parse(&command.to_string()).unwrap(), command);
+
+        assert!(parse("").unwrap_err().contains("empty"));
+        assert!(parse("firefox \"unclosed").is_err());
+    }
+}
@@ -9,71 +9,115 @@
     clippy::print_stdout, clippy::print_stderr,
 )]
 
+mod alert;
+mod anchor_jump;
 mod clipboard;
 pub mod color;
 mod debug_impls;
+mod details;
 mod file_watcher;
+mod focus_pause;
+mod font_synthesis;
 pub mod fonts;
 pub mod history;
 pub mod image;
+mod image_batch;
 pub mod interpreter;
 mod keybindings;
+mod link_command;
+mod math;
 mod metrics;
+mod minimap;
+mod mode;
 pub mod opts;
 mod panic_hook;
+mod placement;
+mod plaintext;
 pub mod positioner;
+mod reading_mark;
+mod reload_flash;
 pub mod renderer;
+mod scroll_memory;
+pub mod search;
 pub mod selection;
+mod status_bar;
 pub mod table;
+mod tabs;
+mod task_list;
 #[cfg(test)]
 pub mod test_utils;
 pub mod text;
+mod toc;
 pub mod utils;
+mod whitespace;
+mod window_state;
 
-use std::collections::{HashMap, VecDeque};
+use std::collections::VecDeque;
 use std::fmt::Debug;
 use std::fs::read_to_string;
+use std::io::{self, Write};
 use std::path::{Path, PathBuf};
 use std::process::Command;
-use std::str::FromStr;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::{self, channel};
 use std::sync::{Arc, Mutex};
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
+use anchor_jump::AnchorJump;
 use file_watcher::Watcher;
-use image::{Image, ImageData};
+use focus_pause::FocusPause;
+use image::{zoom_to_fit, Image, ImageData, LoadedImages};
+use image_batch::ImageBatch;
 use interpreter::HtmlInterpreter;
-use keybindings::action::{Action, HistDirection, VertDirection, Zoom};
+use keybindings::action::{Action, DetailsScope, HistDirection, VertDirection, Zoom};
 use keybindings::{Key, KeyCombos, ModifiedKey};
 use metrics::{histogram, HistTag};
-use opts::{Cli, Config, Opts};
+use mode::{ActiveMode, Mode};
+use opts::{Cli, Config, OnFileReplaced, Opts, Placement, ResolvedTheme, WindowPosition};
 use positioner::{Positioned, Row, Section, Spacer, DEFAULT_MARGIN, DEFAULT_PADDING};
 use raw_window_handle::HasRawDisplayHandle;
-use renderer::Renderer;
+use reload_flash::ReloadFlash;
+use renderer::{RenderTarget, Renderer};
+use scroll_memory::ScrollMemory;
+use search::Search;
+use status_bar::StatusBar;
 use table::Table;
+use tabs::{CloseOutcome, TabBar, TabDoc, TabId, Tabs, EMPTY_PLACEHOLDER};
 use text::{Text, TextBox, TextSystem};
 use tracing_subscriber::prelude::*;
 use tracing_subscriber::util::SubscriberInitExt;
-use utils::{ImageCache, Point, Rect, Size};
+use utils::{ImageCache, LinkTarget, Point, Size};
+use window_state::WindowState;
 
-use crate::opts::{Commands, ConfigCmd, MetricsExporter};
+use crate::history::History;
+use crate::opts::{Commands, ConfigCmd, MetricsExporter, Render, View};
 use crate::selection::Selection;
 use anyhow::Context;
 use clap::Parser;
 use taffy::Taffy;
+use winit::dpi::PhysicalSize;
 use winit::event::{
-    ElementState, Event, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta, WindowEvent,
+    ElementState, Event, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta,
+    VirtualKeyCode, WindowEvent,
 };
-use winit::event_loop::{ControlFlow, EventLoop, EventLoopBuilder};
+use winit::event_loop::{ControlFlow, EventLoop, EventLoopBuilder, EventLoopProxy};
 use winit::window::{CursorIcon, Window, WindowBuilder};
 
 pub enum InlyneEvent {
     LoadedImage(String, Arc<Mutex<Option<ImageData>>>),
-    FileReload,
-    FileChange { contents: String },
+    FileReload {
+        tab: TabId,
+        /// Whether the file was replaced rather than edited in place
+        replaced: bool,
+    },
+    FileChange {
+        tab: TabId,
+        contents: String,
+    },
     Reposition,
     PositionQueue,
+    /// The system switched between light and dark mode
+    ColorSchemeChange(Option<ResolvedTheme>),
 }
 
 impl Debug for InlyneEvent {
@@ -86,6 +130,7 @@ pub enum Hoverable<'a> {
     Image(&'a Image),
     Text(&'a Text),
     Summary(&'a Section),
+    Checkbox(&'a TextBox),
 }
 
 #[derive(Debug, PartialEq)]
@@ -134,8 +179,28 @@ impl From<Table> for Element {
     }
 }
 
+/// Progress on fitting a document that's just a single image to the window at startup
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ImageFit {
+    Idle,
+    /// The document is still being interpreted
+    WaitingForDoc,
+    /// The document is loaded, but the image might not be
+    WaitingForImage,
+}
+
+/// How a link got clicked, which decides whether it opens in a new window
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum LinkClick {
+    Plain,
+    Shift,
+    Middle,
+}
+
 pub struct Inlyne {
     opts: Opts,
+    /// The command line args, which get merged with the config again when it's reloaded
+    view: View,
     window: Arc<Window>,
     // HACK: `Option<_>` is used here to keep `Inlyne` valid while running the event loop. Consider
     // splitting this out from the rest of the state
@@ -147,14 +212,25 @@ pub struct Inlyne {
     image_cache: ImageCache,
     interpreter_sender: mpsc::Sender<String>,
     interpreter_should_queue: Arc<AtomicBool>,
+    event_proxy: EventLoopProxy<InlyneEvent>,
     keycombos: KeyCombos,
     need_repositioning: bool,
-    watcher: Watcher,
+    image_batch: ImageBatch,
+    focus_pause: FocusPause,
     selection: Selection,
+    mode: ActiveMode,
+    image_fit: ImageFit,
+    scroll_memory: ScrollMemory,
+    /// An anchor to scroll to once the document it's in gets positioned (e.g. from `--anchor` or
+    /// following a link to `other.md#section`)
+    pending_anchor: Option<String>,
+    tabs: Tabs,
+    /// The document is still being interpreted, so `elements` only has part of it
+    doc_loading: bool,
 }
 
 impl Inlyne {
-    pub fn new(opts: Opts) -> anyhow::Result<Self> {
+    pub fn new(mut opts: Opts, view: View) -> anyhow::Result<Self> {
         let keycombos = KeyCombos::new(opts.keybindings.clone())?;
 
         let file_path = opts.history.get_path().to_owned();
@@ -164,11 +240,49 @@ impl Inlyne {
         let window = {
             let mut wb = WindowBuilder::new().with_title(utils::format_title(&file_path));
 
-            if let Some(ref pos) = opts.position {
-                wb = wb.with_position(winit::dpi::PhysicalPosition::new(pos.x, pos.y));
+            let remembered = if opts.remember_window {
+                WindowState::load().unwrap_or_default()
+            } else {
+                WindowState::default()
+            };
+            let size = opts.size.clone().or(remembered.size).or_else(|| {
+                let percent = opts.default_size?;
+                let monitor = event_loop.primary_monitor()?.size();
+                Some(percent.of(opts::Size {
+                    width: monitor.width,
+                    height: monitor.height,
+                }))
+            });
+            let size = size.map(|size| winit::dpi::PhysicalSize::new(size.width, size.height));
+            if let Some(size) = size {
+                wb = wb.with_inner_size(size);
             }
-            if let Some(ref size) = opts.size {
-                wb = wb.with_inner_size(winit::dpi::PhysicalSize::new(size.width, size.height));
+            let position = opts
+                .position
+                .clone()
+                .or(remembered.position.map(WindowPosition::At));
+            match position {
+                Some(WindowPosition::At(pos)) => {
+                    wb = wb.with_position(winit::dpi::PhysicalPosition::new(pos.x, pos.y));
+                }
+                Some(WindowPosition::Placed(placed)) => {
+                    let monitors: Vec<_> = event_loop
+                        .available_monitors()
+                        .map(|monitor| placement::Monitor::from(&monitor))
+                        .collect();
+                    let primary = event_loop
+                        .primary_monitor()
+                        .map(|monitor| placement::Monitor::from(&monitor));
+                    let cursor = (placed == Placement::Cursor)
+                        .then(placement::cursor_position)
+                        .flatten();
+                    if let Some(pos) =
+                        placement::window_position(placed, &monitors, primary, cursor, size)
+                    {
+                        wb = wb.with_position(pos);
+                    }
+                }
+                None => {}
             }
             #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
             {
@@ -179,45 +293,82 @@ impl Inlyne {
             Arc::new(wb.build(&event_loop).unwrap())
         };
 
-        let renderer = pollster::block_on(Renderer::new(
-            &window,
+        let mut renderer = pollster::block_on(Renderer::new(
+            RenderTarget::Window(&window),
             opts.theme.clone(),
             opts.scale.unwrap_or(window.scale_factor() as f32),
-            opts.page_width.unwrap_or(f32::MAX),
+            opts.page_width,
             opts.font_opts.clone(),
+            opts.quote_block_background,
+            opts.minimap,
         ))?;
+        renderer.reload_flash = ReloadFlash::new(opts.reload_flash);
+        renderer.show_whitespace = opts.show_whitespace;
+        renderer.image_background = opts.image_background;
+        renderer.image_layer = opts.image_layer;
+        renderer.lazy_image_distance = opts.lazy_image_distance;
+        renderer.scrollbar = opts.scrollbar.clone();
+        renderer.code_block_border = opts.code_block_border;
+        renderer.smooth_scroll = opts.smooth_scroll;
+        if opts.status_bar {
+            let theme = opts.color_scheme.unwrap_or_default();
+            renderer.status_bar = Some(StatusBar::new(opts.history.get_path(), theme));
+        }
+        renderer.max_line_chars = opts.max_line_chars;
+        renderer.set_toc(opts.toc);
+        let watch = opts.watch;
+        let tabs = Tabs::new(
+            &opts.history,
+            std::mem::take(&mut opts.tabs),
+            |tab, path| {
+                let event_proxy = event_loop.create_proxy();
+                if watch {
+                    Watcher::spawn(event_proxy, tab, path.to_owned())
+                } else {
+                    Watcher::disabled(event_proxy, tab)
+                }
+            },
+        );
+        Self::update_tab_bar(&mut renderer, &tabs, &opts.history);
+        renderer.positioner.reserved_height += renderer.positioner.top_inset;
 
         let element_queue = Arc::new(Mutex::new(VecDeque::new()));
-        let image_cache = Arc::new(Mutex::new(HashMap::new()));
+        let image_cache = Arc::new(Mutex::new(LoadedImages::new(opts.max_cached_images)));
         let md_string = read_to_string(&file_path)
             .with_context(|| format!("Could not read file at '{}'", file_path.display()))?;
 
-        let interpreter = HtmlInterpreter::new(
-            window.clone(),
-            element_queue.clone(),
-            renderer.theme.clone(),
-            renderer.surface_format,
-            renderer.hidpi_scale,
-            file_path.clone(),
-            image_cache.clone(),
-            event_loop.create_proxy(),
-            opts.color_scheme,
+        let event_proxy = event_loop.create_proxy();
+        let (interpreter_sender, interpreter_should_queue) = Self::spawn_interpreter(
+            &opts,
+            &window,
+            &renderer,
+            &element_queue,
+            &image_cache,
+            &event_proxy,
         );
 
-        let (interpreter_sender, interpreter_receiver) = channel();
-        let interpreter_should_queue = interpreter.should_queue.clone();
-        std::thread::spawn(move || interpreter.interpret_md(interpreter_receiver));
-
         interpreter_sender.send(md_string)?;
 
-        let lines_to_scroll = opts.lines_to_scroll;
+        if opts.system_themes.is_some() {
+            watch_color_scheme(event_proxy.clone(), opts.color_scheme);
+        }
 
-        let watcher = Watcher::spawn(event_loop.create_proxy(), file_path.clone());
+        let lines_to_scroll = opts.lines_to_scroll;
+        let image_batch = ImageBatch::new(opts.image_batch);
+        let focus_pause = FocusPause::new(opts.pause_when_unfocused);
 
         let _ = file_path.parent().map(std::env::set_current_dir);
 
+        let image_fit = if opts.fit_single_image {
+            ImageFit::WaitingForDoc
+        } else {
+            ImageFit::Idle
+        };
+
+        let pending_anchor = opts.anchor.clone();
         Ok(Self {
             opts,
+            view,
             window,
             event_loop: Some(event_loop),
             renderer,
@@ -226,14 +377,179 @@ impl Inlyne {
             lines_to_scroll,
             interpreter_sender,
             interpreter_should_queue,
+            event_proxy,
             image_cache,
             keycombos,
             need_repositioning: false,
-            watcher,
+            image_batch,
+            focus_pause,
             selection: Selection::new(),
+            mode: ActiveMode::default(),
+            image_fit,
+            scroll_memory: ScrollMemory::default(),
+            pending_anchor,
+            tabs,
+            doc_loading: true,
         })
     }
 
+    /// Starts up an interpreter on its own thread that interprets any markdown sent over the
+    /// returned channel
+    fn spawn_interpreter(
+        opts: &Opts,
+        window: &Arc<Window>,
+        renderer: &Renderer,
+        element_queue: &Arc<Mutex<VecDeque<Element>>>,
+        image_cache: &ImageCache,
+        event_proxy: &EventLoopProxy<InlyneEvent>,
+    ) -> (mpsc::Sender<String>, Arc<AtomicBool>) {
+        let interpreter = HtmlInterpreter::new(
+            window.clone(),
+            element_queue.clone(),
+            renderer.theme.clone(),
+            renderer.surface_format,
+            renderer.hidpi_scale,
+            opts.history.get_path().to_owned(),
+            image_cache.clone(),
+            event_proxy.clone(),
+            opts.into(),
+        );
+
+        let (interpreter_sender, interpreter_receiver) = channel();
+        let interpreter_should_queue = interpreter.should_queue.clone();
+        std::thread::spawn(move || interpreter.interpret_md(interpreter_receiver));
+
+        (interpreter_sender, interpreter_should_queue)
+    }
+
+    /// Swaps over to the theme for the new system color scheme
+    ///
+    /// The interpreter bakes the theme into the elements it emits (and picks `<picture>` sources
+    /// based on the color scheme), so it gets replaced and the document is re-interpreted
+    fn set_color_scheme(&mut self, color_scheme: Option<ResolvedTheme>) {
+        let Some(system_themes) = &self.opts.system_themes else {
+            return;
+        };
+        if color_scheme == self.opts.color_scheme {
+            return;
+        }
+        tracing::info!("System color scheme changed to {color_scheme:?}");
+        self.opts.color_scheme = color_scheme;
+        self.renderer.theme = system_themes.get(color_scheme).clone();
+        if let Some(status_bar) = &mut self.renderer.status_bar {
+            status_bar.theme = color_scheme.unwrap_or_default();
+        }
+        self.reinterpret();
+    }
+
+    /// Re-reads the config file and applies the settings that can change while running
+    ///
+    /// That covers the theme, scale, page width, max line chars, lines to scroll, and
+    /// keybindings. Everything else (fonts, window settings, watching, etc.) needs a restart
+    fn reload_config(&mut self) {
+        let reloaded = load_config(&self.view)
+            .and_then(|config| Opts::parse_and_load_from(self.view.clone(), config));
+        let opts = match reloaded {
+            Ok(opts) => opts,
+            Err(err) => {
+                tracing::warn!("Failed reloading the config. Keeping the current one: {err}");
+                return;
+            }
+        };
+        tracing::info!("Reloading the config");
+
+        match KeyCombos::new(opts.keybindings.clone()) {
+            Ok(keycombos) => {
+                self.keycombos = keycombos;
+                self.opts.keybindings = opts.keybindings;
+            }
+            Err(err) => tracing::warn!("Keeping the current keybindings: {err}"),
+        }
+        self.lines_to_scroll = opts.lines_to_scroll;
+        self.opts.lines_to_scroll = opts.lines_to_scroll;
+        self.renderer.smooth_scroll = opts.smooth_scroll;
+        self.opts.smooth_scroll = opts.smooth_scroll;
+
+        let scale = opts.scale.unwrap_or(self.window.scale_factor() as f32);
+        self.renderer.hidpi_scale = scale;
+        self.renderer.positioner.hidpi_scale = scale;
+        Self::update_tab_bar(&mut self.renderer, &self.tabs, &self.opts.history);
+        self.renderer.max_page_width = opts.page_width;
+        self.renderer.max_line_chars = opts.max_line_chars;
+        // The table of contents' width follows the scale
+        self.renderer.set_toc(self.renderer.toc);
+        self.opts.scale = opts.scale;
+        self.opts.page_width = opts.page_width;
+        self.opts.max_line_chars = opts.max_line_chars;
+
+        self.renderer.theme = opts.theme.clone();
+        if let Some(status_bar) = &mut self.renderer.status_bar {
+            status_bar.theme = opts.color_scheme.unwrap_or_default();
+        }
+        self.opts.theme = opts.theme;
+        self.opts.color_scheme = opts.color_scheme;
+        self.opts.system_themes = opts.system_themes;
+
+        // The theme and scale are both baked into the elements
+        self.reinterpret();
+    }
+
+    /// Replaces the interpreter with one using the current settings and re-interprets the file
+    fn reinterpret(&mut self) {
+        // The other tabs' documents were interpreted with the old settings too
+        self.tabs.forget_docs();
+        // Stop the old interpreter. Dropping its sender shuts down its thread
+        self.interpreter_should_queue
+            .store(false, Ordering::Relaxed);
+        (self.interpreter_sender, self.interpreter_should_queue) = Self::spawn_interpreter(
+            &self.opts,
+            &self.window,
+            &self.renderer,
+            &self.element_queue,
+            &self.image_cache,
+            &self.event_proxy,
+        );
+
+        match read_to_string(self.opts.history.get_path()) {
+            Ok(contents) => self.load_file(contents),
+            Err(err) => tracing::warn!(
+                "Failed reloading file at {}\nError: {}",
+                self.opts.history.get_path().display(),
+                err
+            ),
+        }
+        self.window.request_redraw();
+    }
+
+    /// Re-reads the file and lays it out from scratch, dropping the zoom, scroll position,
+    /// selection, and loaded images along the way
+    fn hard_reload(&mut self) {
+        if self.tabs.is_emptied() {
+            return;
+        }
+        let contents = match read_to_string(self.opts.history.get_path()) {
+            Ok(contents) => contents,
+            Err(err) => {
+                tracing::warn!(
+                    "Failed reloading file at {}\nError: {}",
+                    self.opts.history.get_path().display(),
+                    err
+                );
+                return;
+            }
+        };
+
+        tracing::info!("Hard reloading {}", self.opts.history.get_path().display());
+        self.renderer.zoom = 1.0;
+        self.renderer.update_page_width();
+        self.renderer.set_scroll_y(0.);
+        self.selection = Selection::new();
+        self.image_cache.lock().unwrap().clear();
+        self.renderer.reload_flash.trigger(Instant::now());
+        self.load_file(contents);
+        self.window.request_redraw();
+    }
+
     pub fn position_queued_elements(
         element_queue: &Arc<Mutex<VecDeque<Element>>>,
         renderer: &mut Renderer,
@@ -273,16 +589,582 @@ impl Inlyne {
             .store(false, Ordering::Relaxed);
         self.element_queue.lock().unwrap().clear();
         self.elements.clear();
-        self.renderer.positioner.reserved_height = DEFAULT_PADDING * self.renderer.hidpi_scale;
+        self.doc_loading = true;
+        // The new contents get positioned at the current zoom, so it carries over on reloads
+        self.renderer.positioner.reserved_height = self.renderer.positioner.top_inset
+            + DEFAULT_PADDING * self.renderer.hidpi_scale * self.renderer.zoom;
         self.renderer.positioner.anchors.clear();
+        self.renderer.positioner.headings.clear();
         self.interpreter_should_queue.store(true, Ordering::Relaxed);
         self.interpreter_sender.send(contents).unwrap();
     }
 
+    /// Re-runs the active search (if any) and syncs the renderer's highlighting with it
+    fn update_search(&mut self, jump: bool) {
+        let query = match self.mode.get_mut() {
+            Some(Mode::Search(search)) => {
+                search.update(&self.elements, self.renderer.positioner.reserved_height);
+                if jump {
+                    if let Some(y) = search.jump_from_origin() {
+                        self.renderer.set_scroll_y(y);
+                    }
+                }
+                (!search.query.is_empty()).then(|| search.query.clone())
+            }
+            Some(Mode::GoToAnchor(_)) | None => None,
+        };
+        self.renderer.search_query = query;
+        self.window.request_redraw();
+    }
+
+    /// Scrolls to the header that's selected in the anchor jump list and closes the list
+    fn go_to_selected_anchor(&mut self) {
+        if let Some(Mode::GoToAnchor(jump)) = self.mode.get_mut() {
+            let positioner = &self.renderer.positioner;
+            let entries = jump.entries(&positioner.anchors, &positioner.headings);
+            if let Some(selected) = jump.selected(entries.len()) {
+                self.renderer.set_scroll_y(entries[selected].y);
+            }
+        }
+        let _ = self.dismiss_mode();
+    }
+
+    fn dismiss_mode(&mut self) -> Option<Action> {
+        let fallback = self.mode.dismiss(self.opts.escape_fallback);
+        self.update_search(false);
+        fallback
+    }
+
+    fn set_zoom(&mut self, zoom: f32) {
+        self.renderer.zoom = zoom;
+        // The page's width follows the zoom when it's capped to a number of characters
+        self.renderer.update_page_width();
+        let old_reserved = self.renderer.positioner.reserved_height;
+        self.renderer.reposition(&mut self.elements).unwrap();
+        let new_reserved = self.renderer.positioner.reserved_height;
+        self.renderer
+            .set_scroll_y(self.renderer.scroll_y * (new_reserved / old_reserved));
+        self.window.request_redraw();
+    }
+
+    /// The zoom that fits the widest element within the page's width
+    fn fit_width_zoom(&self) -> Option<f32> {
+        let widest = self
+            .elements
+            .iter()
+            .filter_map(|element| element.bounds.as_ref())
+            .map(|bounds| bounds.size.0)
+            .fold(0., f32::max);
+        let screen_width = self.renderer.positioner.content_width();
+        let available = screen_width.min(self.renderer.page_width) - 2. * DEFAULT_MARGIN;
+        // Widths scale with the zoom, so fit what the width would be without it
+        let natural_width = widest / self.renderer.zoom;
+        zoom_to_fit((natural_width, 1.), (available, f32::INFINITY))
+    }
+
+    /// Zooms a document that's only a single image to fit the window once the image is loaded
+    fn try_fit_single_image(&mut self) {
+        if self.image_fit != ImageFit::WaitingForImage {
+            return;
+        }
+
+        let mut visible = self
+            .elements
+            .iter_mut()
+            .filter(|element| !matches!(element.inner, Element::Spacer(_)));
+        let (Some(element), None) = (visible.next(), visible.next()) else {
+            self.image_fit = ImageFit::Idle;
+            return;
+        };
+        let Element::Image(image) = &mut element.inner else {
+            self.image_fit = ImageFit::Idle;
+            return;
+        };
+        // Unconstrained size at the default zoom
+        let Some(natural_size) = image.size((f32::INFINITY, f32::INFINITY), 1.) else {
+            // Still loading
+            return;
+        };
+        self.image_fit = ImageFit::Idle;
+
+        let image_height = element.bounds.as_ref().map_or(0., |bounds| bounds.size.1);
+        let surrounding_height = self.renderer.positioner.reserved_height - image_height;
+        let screen_width = self.renderer.positioner.content_width();
+        let screen_height = self.renderer.screen_height();
+        let available = (
+            screen_width.min(self.renderer.page_width) - 2. * DEFAULT_MARGIN,
+            screen_height - surrounding_height,
+        );
+        if let Some(zoom) = zoom_to_fit(natural_size, available) {
+            tracing::info!("Zooming to fit the document's image: {zoom}");
+            self.set_zoom(zoom);
+        }
+    }
+
+    /// Saves the scroll position within the current file, so that it can be restored later
+    fn remember_scroll(&mut self) {
+        self.scroll_memory.save(
+            self.opts.history.get_path(),
+            self.renderer.scroll_y,
+            self.renderer.positioner.reserved_height,
+        );
+    }
+
+    /// Scrolls to the pending anchor once it's been positioned
+    fn scroll_to_pending_anchor(&mut self) {
+        let Some(anchor) = &self.pending_anchor else {
+            return;
+        };
+        let anchors = &self.renderer.positioner.anchors;
+        if let LinkTarget::Anchor(anchor_pos) =
+            utils::classify_link(anchor, anchors, &self.opts.index_files)
+        {
+            self.renderer.set_scroll_y(anchor_pos);
+            self.pending_anchor = None;
+        }
+    }
+
+    /// Where the table of contents' entry at `(x, y)` (in screen coordinates) scrolls to, if
+    /// there's one there
+    fn toc_entry_at(&self, (x, y): Point) -> Option<f32> {
+        let renderer = &self.renderer;
+        let panel = renderer.positioner.content_width()..renderer.toc_right();
+        if !renderer.toc || !panel.contains(&x) || y >= renderer.content_height() {
+            return None;
+        }
+        let (entries, visible) = renderer.toc_entries();
+        let top = renderer.positioner.top_inset;
+        let index = toc::entry_at(y, top, visible, renderer.hidpi_scale)?;
+        Some(entries[index].y)
+    }
+
+    /// The link of the text or image at `loc`, if there is one
+    fn link_at(&mut self, loc: Point) -> Option<String> {
+        let screen_size = self.renderer.layout_size();
+        match Self::find_hoverable(
+            &mut self.renderer.text_system,
+            &mut self.renderer.positioner.taffy,
+            &self.elements,
+            loc,
+            screen_size,
+            self.renderer.zoom,
+        )? {
+            Hoverable::Image(Image {
+                is_link: Some(link),
+                ..
+            })
+            | Hoverable::Text(Text {
+                link: Some(link), ..
+            }) => Some(link.clone()),
+            _ => None,
+        }
+    }
+
+    /// Opens whatever `link` points to
+    fn follow_link(&mut self, link: &str, click: LinkClick) {
+        let link = utils::trim_link_delimiters(link);
+        match utils::classify_link(
+            link,
+            &self.renderer.positioner.anchors,
+            &self.opts.index_files,
+        ) {
+            LinkTarget::Markdown(path) => {
+                let (_, anchor) = utils::split_anchor(link);
+                // Open them in a new window, akin to what a browser does
+                if click != LinkClick::Plain {
+                    spawn_inlyne(Opts::program_args(&path, anchor));
+                } else if anchor.is_some() && self.is_current_file(&path) {
+                    self.pending_anchor = anchor.map(ToOwned::to_owned);
+                    self.scroll_to_pending_anchor();
+                    self.window.request_redraw();
+                } else {
+                    match read_to_string(&path) {
+                        Ok(contents) => {
+                            self.remember_scroll();
+                            self.opts.history.make_next(path.clone());
+                            self.update_file(&path, contents);
+                            self.pending_anchor = anchor.map(ToOwned::to_owned);
+                        }
+                        Err(err) => {
+                            tracing::warn!(
+                                "Failed loading markdown file at {}\nError: {}",
+                                path.display(),
+                                err,
+                            );
+                        }
+                    }
+                }
+            }
+            LinkTarget::Anchor(_)
+                if click == LinkClick::Middle
+                    || (click == LinkClick::Shift && self.opts.anchors_in_new_window) =>
+            {
+                let path = self.opts.history.get_path();
+                spawn_inlyne(Opts::program_args(path, Some(link)));
+            }
+            LinkTarget::Anchor(anchor_pos) => {
+                self.renderer.set_scroll_y(anchor_pos);
+                self.window.request_redraw();
+                self.window.set_cursor_icon(CursorIcon::Default);
+            }
+            LinkTarget::MissingAnchor => {
+                tracing::warn!("Link to missing anchor: {link}");
+            }
+            LinkTarget::File(path) => {
+                link_command::open(self.opts.link_command.as_ref(), &path.to_string_lossy())
+            }
+            LinkTarget::External => link_command::open(self.opts.link_command.as_ref(), link),
+        }
+    }
+
+    fn is_current_file(&self, path: &Path) -> bool {
+        let current = self.opts.history.get_path();
+        match (path.canonicalize(), current.canonicalize()) {
+            (Ok(path), Ok(current)) => path == current,
+            _ => path == current,
+        }
+    }
+
     fn update_file(&mut self, path: &Path, contents: String) {
         self.window.set_title(&utils::format_title(path));
-        self.watcher.update_file(path, contents);
+        if let Some(status_bar) = &mut self.renderer.status_bar {
+            status_bar.file_path = path.to_owned();
+        }
+        Self::update_tab_bar(&mut self.renderer, &self.tabs, &self.opts.history);
+        self.tabs.watcher().update_file(path, contents);
         self.renderer.set_scroll_y(0.0);
+        // The mark and any anchor were for the previous file
+        self.renderer.reading_mark.clear();
+        self.pending_anchor = None;
+    }
+
+    /// Shows a strip with the open tabs when there's more than one of them
+    fn update_tab_bar(renderer: &mut Renderer, tabs: &Tabs, current: &History) {
+        let titles = tabs.titles(current);
+        if titles.len() > 1 {
+            renderer.tab_bar = Some(TabBar {
+                titles,
+                active: tabs.active(),
+            });
+            renderer.positioner.top_inset = TabBar::height(renderer.hidpi_scale);
+        } else {
+            renderer.tab_bar = None;
+            renderer.positioner.top_inset = 0.;
+        }
+    }
+
+    /// Swaps the document out for a placeholder once the last tab is closed
+    /// (`close-last-tab = "empty"`)
+    fn show_empty(&mut self) {
+        self.remember_scroll();
+        self.tabs.set_emptied(true);
+        self.window.set_title("Inlyne");
+        self.renderer.set_scroll_y(0.);
+        self.renderer.reading_mark.clear();
+        self.pending_anchor = None;
+        self.selection = Selection::new();
+        self.load_file(EMPTY_PLACEHOLDER.to_owned());
+        self.window.request_redraw();
+    }
+
+    /// Opens a file that was dragged onto the window in the active tab
+    fn open_dropped_file(&mut self, path: PathBuf) {
+        match read_to_string(&path) {
+            Ok(contents) => {
+                if !self.tabs.is_emptied() {
+                    self.remember_scroll();
+                }
+                self.tabs.set_emptied(false);
+                self.opts.history.make_next(path.clone());
+                self.update_file(&path, contents);
+                if let Some(parent) = path.parent() {
+                    let _ = std::env::set_current_dir(parent);
+                }
+            }
+            Err(err) => {
+                tracing::warn!(
+                    "Failed loading markdown file at {}\nError: {}",
+                    path.display(),
+                    err,
+                );
+            }
+        }
+    }
+
+    /// Shows the document of whichever tab `switch` made active. Tabs that were shown before pick
+    /// up where they left off, and the rest load their file
+    ///
+    /// Returns `false` when `switch` didn't change the active tab
+    fn switch_tab(&mut self, switch: impl FnOnce(&mut Tabs, &mut History) -> bool) -> bool {
+        self.remember_scroll();
+        let leaving = self.tabs.active_id();
+        if !switch(&mut self.tabs, &mut self.opts.history) {
+            return false;
+        }
+        // Stop interpreting the document that's being left
+        self.interpreter_should_queue
+            .store(false, Ordering::Relaxed);
+        self.element_queue.lock().unwrap().clear();
+        let elements = std::mem::take(&mut self.elements);
+        if !self.doc_loading {
+            let scroll_y = self.renderer.scroll_y;
+            self.tabs.save_doc(leaving, TabDoc { elements, scroll_y });
+        }
+        self.selection = Selection::new();
+
+        let file_path = self.opts.history.get_path().to_owned();
+        if let Some(TabDoc { elements, scroll_y }) = self.tabs.take_doc() {
+            self.show_doc(&file_path, elements, scroll_y);
+            return true;
+        }
+        match read_to_string(&file_path) {
+            Ok(contents) => {
+                self.update_file(&file_path, contents);
+                self.scroll_memory.restore(&file_path);
+                if let Some(parent) = file_path.parent() {
+                    let _ = std::env::set_current_dir(parent);
+                }
+            }
+            Err(err) => {
+                tracing::warn!(
+                    "Failed loading markdown file at {}\nError: {}",
+                    file_path.display(),
+                    err,
+                );
+            }
+        }
+        true
+    }
+
+    /// Puts a tab's document from when it was last shown back in place
+    fn show_doc(&mut self, file_path: &Path, elements: Vec<Positioned<Element>>, scroll_y: f32) {
+        self.window.set_title(&utils::format_title(file_path));
+        if let Some(status_bar) = &mut self.renderer.status_bar {
+            status_bar.file_path = file_path.to_owned();
+        }
+        Self::update_tab_bar(&mut self.renderer, &self.tabs, &self.opts.history);
+        self.renderer.reading_mark.clear();
+        self.pending_anchor = None;
+        if let Some(parent) = file_path.parent() {
+            let _ = std::env::set_current_dir(parent);
+        }
+
+        // The window may have been resized or zoomed since, and the anchors are the old document's
+        self.elements = elements;
+        self.doc_loading = false;
+        self.renderer.positioner.anchors.clear();
+        self.renderer.positioner.headings.clear();
+        self.renderer.reposition(&mut self.elements).unwrap();
+        self.renderer.set_scroll_y(scroll_y);
+        self.update_search(false);
+        self.window.request_redraw();
+    }
+
+    /// Carries out an action triggered by a key combo or mouse button
+    ///
+    /// Returns `true` when the window should close
+    fn perform_action(&mut self, action: Action, clipboard: &mut clipboard::Clipboard) -> bool {
+        match action {
+            Action::ToEdge(direction) => {
+                let scroll = match direction {
+                    VertDirection::Up => 0.0,
+                    VertDirection::Down => f32::INFINITY,
+                };
+                self.renderer.scroll_to(scroll);
+                self.window.request_redraw();
+            }
+            Action::Scroll(direction) => {
+                let lines = match direction {
+                    VertDirection::Up => 1.0,
+                    VertDirection::Down => -1.0,
+                };
+
+                Self::scroll_lines(
+                    &mut self.renderer,
+                    &self.window,
+                    self.lines_to_scroll,
+                    lines,
+                )
+            }
+            Action::Page(direction) => {
+                // Move 90% of current page height
+                let scroll_amount = self.renderer.config.height as f32 * 0.9;
+                let scroll_with_direction = match direction {
+                    VertDirection::Up => scroll_amount,
+                    VertDirection::Down => -scroll_amount,
+                };
+
+                Self::scroll_pixels(&mut self.renderer, &self.window, scroll_with_direction);
+            }
+            Action::ScrollHalfPage(direction) => {
+                let scroll_amount = self.renderer.config.height as f32 * 0.5;
+                let scroll_with_direction = match direction {
+                    VertDirection::Up => scroll_amount,
+                    VertDirection::Down => -scroll_amount,
+                };
+
+                Self::scroll_pixels(&mut self.renderer, &self.window, scroll_with_direction);
+            }
+            Action::Zoom(zoom_action) => {
+                let zoom = match zoom_action {
+                    Zoom::In => self.renderer.zoom * 1.1,
+                    Zoom::Out => self.renderer.zoom * 0.9,
+                    Zoom::Reset => 1.0,
+                    Zoom::FitWidth => match self.fit_width_zoom() {
+                        Some(zoom) => zoom,
+                        None => return false,
+                    },
+                };
+                self.set_zoom(zoom);
+            }
+            Action::ToggleWhitespace => {
+                self.renderer.show_whitespace = !self.renderer.show_whitespace;
+                self.window.request_redraw();
+            }
+            Action::ToggleStatusBar => {
+                StatusBar::toggle(
+                    &mut self.renderer.status_bar,
+                    self.opts.history.get_path(),
+                    self.opts.color_scheme.unwrap_or_default(),
+                );
+                // The document's viewport changes size, so keep the scroll position in bounds
+                self.renderer.set_scroll_y(self.renderer.scroll_y);
+                self.window.request_redraw();
+            }
+            Action::ToggleToc => {
+                self.renderer.set_toc(!self.renderer.toc);
+                let old_reserved = self.renderer.positioner.reserved_height;
+                self.renderer.reposition(&mut self.elements).unwrap();
+                let new_reserved = self.renderer.positioner.reserved_height;
+                self.renderer
+                    .set_scroll_y(self.renderer.scroll_y * (new_reserved / old_reserved));
+                self.window.request_redraw();
+            }
+            Action::Edit => open_in_editor(self.opts.history.get_path().to_owned()),
+            Action::ToggleDetails(scope) => {
+                let toggled = match scope {
+                    DetailsScope::All => details::toggle_all(&self.elements),
+                    DetailsScope::Nearest => {
+                        details::toggle_nearest(&self.elements, self.renderer.scroll_y)
+                    }
+                };
+                if toggled {
+                    self.event_proxy
+                        .send_event(InlyneEvent::Reposition)
+                        .unwrap();
+                }
+            }
+            Action::SetMark => {
+                self.renderer.reading_mark.set(self.renderer.scroll_y);
+                self.window.request_redraw();
+            }
+            Action::GotoMark => {
+                if let Some(mark) = self.renderer.reading_mark.get() {
+                    self.renderer.set_scroll_y(mark);
+                    self.window.request_redraw();
+                }
+            }
+            Action::Copy => {
+                // Only what's on screen gets its selected text collected while
+                // drawing, so grab the whole document directly instead
+                let text = if self.selection.is_all() {
+                    plaintext::extract(&self.elements, false)
+                } else {
+                    self.selection.text.clone()
+                };
+                clipboard.set_contents(text.trim().to_owned())
+            }
+            Action::CopyMarkdown => {
+                // Nothing beats the original when it's the whole document
+                let markdown = if self.selection.is_all() {
+                    let path = self.opts.history.get_path();
+                    match read_to_string(path) {
+                        Ok(contents) => contents,
+                        Err(err) => {
+                            tracing::warn!(
+                                "Failed reading file at {}\nError: {}",
+                                path.display(),
+                                err
+                            );
+                            return false;
+                        }
+                    }
+                } else {
+                    self.selection.markdown.clone()
+                };
+                clipboard.set_contents(markdown.trim().to_owned())
+            }
+            Action::CopyPath => {
+                clipboard.set_contents(utils::absolute_display(self.opts.history.get_path()))
+            }
+            Action::SelectAll => {
+                self.selection.select_all();
+                self.window.request_redraw();
+            }
+            Action::Search => {
+                self.mode
+                    .set(Mode::Search(Search::new(self.renderer.scroll_y)));
+                self.update_search(false);
+            }
+            Action::GoToAnchor => {
+                self.mode.set(Mode::GoToAnchor(AnchorJump::default()));
+                // Also clears the highlighting from any search that this replaced
+                self.update_search(false);
+            }
+            Action::SearchMatch(direction) => {
+                if let Some(Mode::Search(search)) = self.mode.get_mut() {
+                    if let Some(y) = search.cycle(direction) {
+                        self.renderer.set_scroll_y(y);
+                        self.window.request_redraw();
+                    }
+                }
+            }
+            Action::NextTab => {
+                self.switch_tab(Tabs::switch_next);
+            }
+            Action::CloseTab => match self.tabs.close_outcome(self.opts.close_last_tab) {
+                CloseOutcome::Switch => {
+                    self.switch_tab(Tabs::close);
+                }
+                CloseOutcome::Empty => self.show_empty(),
+                CloseOutcome::Quit => return true,
+            },
+            Action::ReloadConfig => self.reload_config(),
+            Action::HardReload => self.hard_reload(),
+            Action::Quit => return true,
+            // Already resolved to its fallback above
+            Action::Dismiss => {}
+            Action::History(hist_dir) => {
+                self.remember_scroll();
+                let changed_path = match hist_dir {
+                    HistDirection::Next => self.opts.history.next(),
+                    HistDirection::Prev => self.opts.history.previous(),
+                }
+                .map(ToOwned::to_owned);
+                let Some(file_path) = changed_path else {
+                    return false;
+                };
+                match read_to_string(&file_path) {
+                    Ok(contents) => {
+                        self.update_file(&file_path, contents);
+                        self.scroll_memory.restore(&file_path);
+                        let parent = file_path
+                            .parent()
+                            .expect("File should have parent directory");
+                        std::env::set_current_dir(parent)
+                            .expect("Could not set current directory.");
+                    }
+                    Err(err) => {
+                        tracing::warn!(
+                            "Failed loading markdown file at {}\nError: {}",
+                            file_path.display(),
+                            err,
+                        );
+                    }
+                }
+            }
+        }
+        false
     }
 
     pub fn run(mut self) {
@@ -291,6 +1173,9 @@ impl Inlyne {
         let mut mouse_down = false;
         let mut modifiers = ModifiersState::empty();
         let mut mouse_position: Point = Point::default();
+        // The key that started a search (or anchor jump). The one character that it types shouldn't
+        // end up in the query
+        let mut input_trigger = None;
 
         let event_loop = self.event_loop.take().unwrap();
         let event_loop_proxy = event_loop.create_proxy();
@@ -298,25 +1183,58 @@ impl Inlyne {
         // which is done by `move` into event loop.
         let mut clipboard = unsafe { clipboard::Clipboard::new(event_loop.raw_display_handle()) };
         event_loop.run(move |event, _, control_flow| {
-            *control_flow = ControlFlow::Wait;
+            // Wake back up for any images waiting on their batch. Those wait for focus when paused
+            let batch_deadline = self
+                .image_batch
+                .deadline()
+                .filter(|_| !self.focus_pause.is_paused());
+            *control_flow = match batch_deadline {
+                Some(deadline) => ControlFlow::WaitUntil(deadline),
+                None => ControlFlow::Wait,
+            };
 
             match event {
                 Event::UserEvent(inlyne_event) => match inlyne_event {
                     InlyneEvent::LoadedImage(src, image_data) => {
-                        self.image_cache.lock().unwrap().insert(src, image_data);
-                        self.need_repositioning = true;
+                        // Failed images are left empty and shouldn't stick around in the cache
+                        if image_data.lock().unwrap().is_some() {
+                            self.image_cache.lock().unwrap().insert(src, image_data);
+                        }
+                        self.image_batch.loaded();
                     }
-                    InlyneEvent::FileReload => match read_to_string(self.opts.history.get_path()) {
-                        Ok(contents) => self.load_file(contents),
-                        Err(err) => {
-                            tracing::warn!(
-                                "Failed reloading file at {}\nError: {}",
-                                self.opts.history.get_path().display(),
-                                err
-                            );
+                    // The last tab's file was closed, so there's nothing to reload
+                    InlyneEvent::FileReload { .. } if self.tabs.is_emptied() => {}
+                    // Background tabs load their file again once they're switched back to
+                    InlyneEvent::FileReload { tab, .. } | InlyneEvent::FileChange { tab, .. }
+                        if tab != self.tabs.active_id() =>
+                    {
+                        self.tabs.forget_doc(tab);
+                    }
+                    InlyneEvent::FileReload { replaced, .. } => {
+                        match read_to_string(self.opts.history.get_path()) {
+                            Ok(contents) => {
+                                self.renderer.reload_flash.trigger(Instant::now());
+                                if replaced && self.opts.on_file_replaced == OnFileReplaced::ToTop {
+                                    self.renderer.set_scroll_y(0.);
+                                } else {
+                                    self.remember_scroll();
+                                    self.scroll_memory.restore(self.opts.history.get_path());
+                                }
+                                self.load_file(contents);
+                            }
+                            Err(err) => {
+                                tracing::warn!(
+                                    "Failed reloading file at {}\nError: {}",
+                                    self.opts.history.get_path().display(),
+                                    err
+                                );
+                            }
                         }
-                    },
-                    InlyneEvent::FileChange { contents } => self.load_file(contents),
+                    }
+                    InlyneEvent::FileChange { contents, .. } => self.load_file(contents),
+                    InlyneEvent::ColorSchemeChange(color_scheme) => {
+                        self.set_color_scheme(color_scheme)
+                    }
                     InlyneEvent::Reposition => {
                         self.need_repositioning = true;
                     }
@@ -326,7 +1244,19 @@ impl Inlyne {
                             &mut self.renderer,
                             &mut self.elements,
                         );
-                        self.window.request_redraw()
+                        self.doc_loading = false;
+                        let doc_height = self.renderer.positioner.reserved_height;
+                        if let Some(scroll_y) = self.scroll_memory.take_pending(doc_height) {
+                            self.renderer.set_scroll_y(scroll_y);
+                        }
+                        self.scroll_to_pending_anchor();
+                        if self.image_fit == ImageFit::WaitingForDoc {
+                            self.image_fit = ImageFit::WaitingForImage;
+                        }
+                        self.try_fit_single_image();
+                        if !self.focus_pause.defer_redraw() {
+                            self.window.request_redraw();
+                        }
                     }
                 },
                 Event::RedrawRequested(_) => {
@@ -336,20 +1266,44 @@ impl Inlyne {
                         &mut self.renderer,
                         &mut self.elements,
                     );
-                    self.renderer.set_scroll_y(self.renderer.scroll_y);
+                    let still_scrolling = self.renderer.step_scroll();
+                    // Keeps the search's matches up to date with reloads, resizes, etc.
+                    if let Some(Mode::Search(search)) = self.mode.get_mut() {
+                        search.refresh(&self.elements, self.renderer.positioner.reserved_height);
+                    }
+                    let positioner = &self.renderer.positioner;
+                    self.renderer.anchor_list = match self.mode.get_mut() {
+                        Some(Mode::GoToAnchor(jump)) => {
+                            Some(jump.list(&positioner.anchors, &positioner.headings))
+                        }
+                        _ => None,
+                    };
                     self.renderer
                         .redraw(&mut self.elements, &mut self.selection)
                         .context("Renderer failed to redraw the screen")
                         .unwrap();
+                    // Keep drawing frames until the flash and any smooth scroll finish
+                    if still_scrolling || self.renderer.reload_flash.is_active() {
+                        self.window.request_redraw();
+                    }
 
                     histogram!(HistTag::Redraw).record(redraw_start.elapsed());
                 }
                 Event::WindowEvent { event, .. } => match event {
                     WindowEvent::Resized(size) => pending_resize = Some(size),
                     WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+                    WindowEvent::DroppedFile(path) => self.open_dropped_file(path),
+                    WindowEvent::Focused(focused) => {
+                        if self.focus_pause.set_focused(focused) {
+                            self.window.request_redraw();
+                        }
+                    }
                     WindowEvent::MouseWheel { delta, .. } => match delta {
+                        // Touchpads already scroll smoothly on their own
                         MouseScrollDelta::PixelDelta(pos) => {
-                            Self::scroll_pixels(&mut self.renderer, &self.window, pos.y as f32)
+                            self.renderer
+                                .set_scroll_y(self.renderer.scroll_y - pos.y as f32);
+                            self.window.request_redraw();
                         }
                         MouseScrollDelta::LineDelta(_, y_delta) => Self::scroll_lines(
                             &mut self.renderer,
@@ -359,7 +1313,7 @@ impl Inlyne {
                         ),
                     },
                     WindowEvent::CursorMoved { position, .. } => {
-                        let screen_size = self.renderer.screen_size();
+                        let screen_size = self.renderer.layout_size();
                         let loc = (
                             position.x as f32,
                             position.y as f32 + self.renderer.scroll_y,
@@ -378,6 +1332,11 @@ impl Inlyne {
                                     CursorIcon::Default
                                 }
                                 Hoverable::Text(Text { link: None, .. }) => CursorIcon::Text,
+                                Hoverable::Checkbox(TextBox {
+                                    checkbox_source, ..
+                                }) if !self.opts.toggle_checkboxes || checkbox_source.is_none() => {
+                                    CursorIcon::Default
+                                }
                                 _some_link => CursorIcon::Hand,
                             }
                         } else {
@@ -386,34 +1345,17 @@ impl Inlyne {
                         self.window.set_cursor_icon(cursor_icon);
 
                         if scrollbar_held.is_some()
-                            || (Rect::new(
-                                (screen_size.0 - DEFAULT_MARGIN / 4., 0.),
-                                (DEFAULT_MARGIN / 4., screen_size.1),
-                            )
-                            .contains(position.into())
+                            || (self.renderer.scrollbar_track().contains(position.into())
                                 && mouse_down)
                         {
                             let scrollbar_height = self.renderer.scrollbar_height();
                             if scrollbar_held.is_none() {
-                                if Rect::new(
-                                    (
-                                        screen_size.0 - DEFAULT_MARGIN / 4.,
-                                        ((self.renderer.scroll_y
-                                            / self.renderer.positioner.reserved_height)
-                                            * screen_size.1),
-                                    ),
-                                    (DEFAULT_MARGIN / 4., scrollbar_height),
-                                )
-                                .contains(position.into())
-                                {
+                                let thumb = self.renderer.scrollbar_thumb();
+                                if thumb.contains(position.into()) {
                                     // If we click in the bounds of the scrollbar, maintain the difference between the
                                     // center of the scrollbar and the mouse
                                     scrollbar_held = Some(
-                                        position.y as f32
-                                            - (((self.renderer.scroll_y
-                                                / self.renderer.positioner.reserved_height)
-                                                * screen_size.1)
-                                                + scrollbar_height / 2.),
+                                        position.y as f32 - (thumb.pos.1 + scrollbar_height / 2.),
                                     );
                                 } else {
                                     scrollbar_held = Some(0.);
@@ -425,7 +1367,8 @@ impl Inlyne {
                             } else {
                                 position.y as f32
                             };
-                            let target_scroll = ((pos_y - scrollbar_height / 2.) / screen_size.1)
+                            let target_scroll = ((pos_y - scrollbar_height / 2.)
+                                / self.renderer.content_height())
                                 * self.renderer.positioner.reserved_height;
                             self.renderer.set_scroll_y(target_scroll);
                             self.window.request_redraw();
@@ -440,76 +1383,97 @@ impl Inlyne {
                         ..
                     } => match state {
                         ElementState::Pressed => {
-                            // Try to click a link
                             let screen_size = self.renderer.screen_size();
+                            let clicked_tab = self.renderer.tab_bar.as_ref().and_then(|tab_bar| {
+                                tab_bar.tab_at(
+                                    mouse_position,
+                                    screen_size.0,
+                                    self.renderer.hidpi_scale,
+                                )
+                            });
+                            if let Some(index) = clicked_tab {
+                                self.switch_tab(|tabs, current| tabs.switch(index, current));
+                                return;
+                            }
+                            let screen_pos =
+                                (mouse_position.0, mouse_position.1 - self.renderer.scroll_y);
+                            if let Some(target_scroll) = self.toc_entry_at(screen_pos) {
+                                self.renderer.set_scroll_y(target_scroll);
+                                self.window.request_redraw();
+                                return;
+                            }
+
+                            // Try to click a link
 
                             let y = mouse_position.1 - self.renderer.scroll_y;
-                            if Rect::new(
-                                (screen_size.0 - DEFAULT_MARGIN / 4., 0.),
-                                (DEFAULT_MARGIN / 4., screen_size.1),
-                            ).contains((mouse_position.0, y)) {
+                            if self
+                                .renderer
+                                .scrollbar_track()
+                                .contains((mouse_position.0, y))
+                            {
                                 let scrollbar_height = self.renderer.scrollbar_height();
 
-                                let target_scroll = ((y - scrollbar_height / 2.) / screen_size.1)
+                                let target_scroll = ((y - scrollbar_height / 2.)
+                                    / self.renderer.content_height())
                                     * self.renderer.positioner.reserved_height;
 
                                 self.renderer.set_scroll_y(target_scroll);
                                 self.window.request_redraw();
+                            } else if let Some(strip) = self
+                                .renderer
+                                .minimap_bounds()
+                                .filter(|strip| strip.contains((mouse_position.0, y)))
+                            {
+                                let target_scroll = minimap::click_to_scroll(
+                                    y,
+                                    &self.renderer.positioner.anchors,
+                                    self.renderer.positioner.reserved_height,
+                                    &strip,
+                                );
+
+                                self.renderer.set_scroll_y(target_scroll);
+                                self.window.request_redraw();
+                                return;
                             }
 
+                            let layout_size = self.renderer.layout_size();
                             if let Some(hoverable) = Self::find_hoverable(
                                 &mut self.renderer.text_system,
                                 &mut self.renderer.positioner.taffy,
                                 &self.elements,
                                 mouse_position,
-                                screen_size,
+                                layout_size,
                                 self.renderer.zoom,
                             ) {
                                 match hoverable {
-                                    Hoverable::Image(Image { is_link: Some(link), .. }) |
-                                    Hoverable::Text(Text { link: Some(link), .. }) => {
-                                        let path = PathBuf::from_str(link).unwrap(); // Can't fail
-
-                                        if  path.extension().map_or(false, |ext| ext == "md")
-                                            && !path.to_str().map_or(false, |s| s.starts_with("http")) {
-                                            // Open them in a new window, akin to what a browser does
-                                            if modifiers.shift() {
-                                                std::thread::spawn(move || {
-                                                    Command::new(
-                                                        std::env::current_exe()
-                                                            .unwrap_or_else(|_| "inlyne".into()),
-                                                    )
-                                                        .args(Opts::program_args(&path))
-                                                        .spawn()
-                                                        .expect("Couldn't spawn inlyne instance")
-                                                        .wait()
-                                                        .expect("Failed waiting on child");
-                                                });
-                                            } else {
-                                                match read_to_string(&path) {
-                                                    Ok(contents) => {
-                                                        self.update_file(&path, contents);
-                                                        self.opts.history.make_next(path);
-                                                    }
-                                                    Err(err) => {
-                                                        tracing::warn!(
-                                                        "Failed loading markdown file at {}\nError: {}",
-                                                        path.display(),
-                                                        err,
-                                                    );
-                                                    }
-                                                }
-                                            }
-                                        } else if let Some(anchor_pos) =
-                                            self.renderer.positioner.anchors.get(&link.to_lowercase())
-                                        {
-                                            self.renderer.set_scroll_y(*anchor_pos);
-                                            self.window.request_redraw();
-                                            self.window.set_cursor_icon(CursorIcon::Default);
-                                        } else if let Err(e) = open::that(link) {
-                                            tracing::error!("Could not open link: {e} from {:?}", std::env::current_dir())
+                                    Hoverable::Image(Image {
+                                        is_link: Some(link),
+                                        ..
+                                    })
+                                    | Hoverable::Text(Text {
+                                        link: Some(link), ..
+                                    }) => {
+                                        let click = if modifiers.shift() {
+                                            LinkClick::Shift
+                                        } else {
+                                            LinkClick::Plain
+                                        };
+                                        let link = link.clone();
+                                        self.follow_link(&link, click);
+                                    }
+                                    Hoverable::Checkbox(TextBox {
+                                        is_checkbox: Some(checked),
+                                        checkbox_source: Some(offset),
+                                        ..
+                                    }) if self.opts.toggle_checkboxes => {
+                                        if let Err(err) = task_list::toggle(
+                                            self.opts.history.get_path(),
+                                            *offset,
+                                            *checked,
+                                        ) {
+                                            tracing::warn!("Failed toggling checkbox: {err}");
                                         }
-                                    },
+                                    }
                                     Hoverable::Summary(summary) => {
                                         let mut hidden = summary.hidden.borrow_mut();
                                         *hidden = !*hidden;
@@ -517,7 +1481,7 @@ impl Inlyne {
                                             .send_event(InlyneEvent::Reposition)
                                             .unwrap();
                                         self.selection.add_position(mouse_position);
-                                    },
+                                    }
                                     _ => {
                                         self.selection.add_position(mouse_position);
                                         self.window.request_redraw();
@@ -534,7 +1498,73 @@ impl Inlyne {
                             mouse_down = false;
                         }
                     },
+                    // Middle clicks only ever follow links, so they never start a selection
+                    WindowEvent::MouseInput {
+                        state: ElementState::Pressed,
+                        button: MouseButton::Middle,
+                        ..
+                    } => {
+                        if let Some(link) = self.link_at(mouse_position) {
+                            self.follow_link(&link, LinkClick::Middle);
+                        }
+                    }
+                    WindowEvent::MouseInput {
+                        state: ElementState::Pressed,
+                        button: MouseButton::Right,
+                        ..
+                    } => {
+                        if let Some(link) = self.link_at(mouse_position) {
+                            clipboard.set_contents(link);
+                        }
+                    }
+                    // The extra buttons (e.g. back and forward) can be bound to actions. They never
+                    // start a selection or grab the scrollbar
+                    WindowEvent::MouseInput {
+                        state: ElementState::Pressed,
+                        button: button @ MouseButton::Other(_),
+                        ..
+                    } => {
+                        let maybe_action = match self.opts.keybindings.mouse.action(button) {
+                            Some(Action::Dismiss) => self.dismiss_mode(),
+                            maybe_action => maybe_action,
+                        };
+                        if let Some(action) = maybe_action {
+                            if self.perform_action(action, &mut clipboard) {
+                                *control_flow = ControlFlow::Exit;
+                            }
+                        }
+                    }
                     WindowEvent::ModifiersChanged(new_state) => modifiers = new_state,
+                    WindowEvent::ReceivedCharacter(c) => {
+                        if input_trigger.take().is_some() || c.is_control() {
+                            return;
+                        }
+                        match self.mode.get_mut() {
+                            Some(Mode::Search(search)) if search.editing => {
+                                search.query.push(c);
+                                self.update_search(true);
+                            }
+                            Some(Mode::GoToAnchor(jump)) => {
+                                jump.push(c);
+                                self.window.request_redraw();
+                            }
+                            _ => {}
+                        }
+                    }
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Released,
+                                scancode,
+                                ..
+                            },
+                        ..
+                    } => {
+                        // Keys that don't type anything never clear it through `ReceivedCharacter`
+                        if input_trigger == Some(scancode) {
+                            input_trigger = None;
+                        }
+                    }
                     WindowEvent::KeyboardInput {
                         input:
                             KeyboardInput {
@@ -545,87 +1575,68 @@ impl Inlyne {
                             },
                         ..
                     } => {
-                        let key = Key::new(virtual_keycode, scancode);
-                        let modified_key = ModifiedKey(key, modifiers);
-                        if let Some(action) = self.keycombos.munch(modified_key) {
-                            match action {
-                                Action::ToEdge(direction) => {
-                                    let scroll = match direction {
-                                        VertDirection::Up => 0.0,
-                                        VertDirection::Down => f32::INFINITY,
-                                    };
-                                    self.renderer.set_scroll_y(scroll);
-                                    self.window.request_redraw();
-                                }
-                                Action::Scroll(direction) => {
-                                    let lines = match direction {
-                                        VertDirection::Up => 1.0,
-                                        VertDirection::Down => -1.0,
-                                    };
-
-                                    Self::scroll_lines(
-                                        &mut self.renderer,
-                                        &self.window,
-                                        self.lines_to_scroll,
-                                        lines,
-                                    )
+                        if let Some(Mode::GoToAnchor(jump)) = self.mode.get_mut() {
+                            // Picking out a header takes over the keyboard the same way that
+                            // typing out a search query does
+                            let positioner = &self.renderer.positioner;
+                            let len = jump
+                                .entries(&positioner.anchors, &positioner.headings)
+                                .len();
+                            match virtual_keycode {
+                                Some(VirtualKeyCode::Escape) => {
+                                    let _ = self.dismiss_mode();
                                 }
-                                Action::Page(direction) => {
-                                    // Move 90% of current page height
-                                    let scroll_amount = self.renderer.config.height as f32 * 0.9;
-                                    let scroll_with_direction = match direction {
-                                        VertDirection::Up => scroll_amount,
-                                        VertDirection::Down => -scroll_amount,
-                                    };
-
-                                    Self::scroll_pixels(
-                                        &mut self.renderer,
-                                        &self.window,
-                                        scroll_with_direction,
-                                    );
+                                Some(VirtualKeyCode::Return) => self.go_to_selected_anchor(),
+                                Some(VirtualKeyCode::Back) => jump.pop(),
+                                Some(VirtualKeyCode::Up) => {
+                                    jump.move_selection(VertDirection::Up, len)
                                 }
-                                Action::Zoom(zoom_action) => {
-                                    let zoom = match zoom_action {
-                                        Zoom::In => self.renderer.zoom * 1.1,
-                                        Zoom::Out => self.renderer.zoom * 0.9,
-                                        Zoom::Reset => 1.0,
-                                    };
-
-                                    self.renderer.zoom = zoom;
-                                    let old_reserved = self.renderer.positioner.reserved_height;
-                                    self.renderer.reposition(&mut self.elements).unwrap();
-                                    let new_reserved = self.renderer.positioner.reserved_height;
-                                    self.renderer.set_scroll_y(
-                                        self.renderer.scroll_y * (new_reserved / old_reserved),
-                                    );
-                                    self.window.request_redraw();
+                                Some(VirtualKeyCode::Down) | Some(VirtualKeyCode::Tab) => {
+                                    jump.move_selection(VertDirection::Down, len)
                                 }
-                                Action::Copy => clipboard
-                                    .set_contents(self.selection.text.trim().to_owned()),
-                                Action::Quit => *control_flow = ControlFlow::Exit,
-                                Action::History(hist_dir) => {
-                                    let changed_path = match hist_dir {
-                                        HistDirection::Next => self.opts.history.next(),
-                                        HistDirection::Prev => self.opts.history.previous(),
-                                    }.map(ToOwned::to_owned);
-                                    let Some(file_path) = changed_path else {
-                                        return;
-                                    };
-                                    match read_to_string(&file_path) {
-                                        Ok(contents) => {
-                                            self.update_file(&file_path, contents);
-                                            let parent = file_path.parent().expect("File should have parent directory");
-                                            std::env::set_current_dir(parent).expect("Could not set current directory.");
-                                        }
-                                        Err(err) => {
-                                            tracing::warn!(
-                                                "Failed loading markdown file at {}\nError: {}",
-                                                file_path.display(),
-                                                err,
-                                            );
+                                _ => {}
+                            }
+                            self.window.request_redraw();
+                            return;
+                        }
+                        if let Some(Mode::Search(search)) = self.mode.get_mut() {
+                            if search.editing {
+                                // Typing out the query takes over the keyboard. The query's text
+                                // comes in through `ReceivedCharacter`
+                                match virtual_keycode {
+                                    Some(VirtualKeyCode::Escape) => {
+                                        let _ = self.dismiss_mode();
+                                    }
+                                    Some(VirtualKeyCode::Return) => {
+                                        if search.query.is_empty() {
+                                            let _ = self.dismiss_mode();
+                                        } else {
+                                            search.editing = false;
                                         }
                                     }
+                                    Some(VirtualKeyCode::Back) => {
+                                        search.query.pop();
+                                        self.update_search(true);
+                                    }
+                                    _ => {}
                                 }
+                                return;
+                            }
+                        }
+
+                        let key = Key::new(virtual_keycode, scancode);
+                        let modified_key = ModifiedKey(key, modifiers);
+                        let maybe_action = match self.keycombos.munch(modified_key, Some(scancode))
+                        {
+                            Some(Action::Dismiss) => self.dismiss_mode(),
+                            maybe_action => maybe_action,
+                        };
+                        if let Some(action) = maybe_action {
+                            if matches!(action, Action::Search | Action::GoToAnchor) {
+                                input_trigger = Some(scancode);
+                            }
+                            if self.perform_action(action, &mut clipboard) {
+                                *control_flow = ControlFlow::Exit;
                             }
                         }
                     }
@@ -637,12 +1648,7 @@ impl Inlyne {
                     // with a bunch of costly resizes. (https://github.com/Inlyne-Project/inlyne/issues/25)
                     if let Some(size) = pending_resize.take() {
                         if size.width > 0 && size.height > 0 {
-                            self.renderer.config.width = size.width;
-                            self.renderer.config.height = size.height;
-                            self.renderer.positioner.screen_size = size.into();
-                            self.renderer
-                                .surface
-                                .configure(&self.renderer.device, &self.renderer.config);
+                            self.renderer.resize(size);
                             let old_reserved = self.renderer.positioner.reserved_height;
                             self.renderer.reposition(&mut self.elements).unwrap();
                             let new_reserved = self.renderer.positioner.reserved_height;
@@ -653,10 +1659,23 @@ impl Inlyne {
                         }
                     }
 
-                    if self.need_repositioning {
-                        self.renderer.reposition(&mut self.elements).unwrap();
-                        self.window.request_redraw();
-                        self.need_repositioning = false;
+                    // Loaded images and repositioning for them pile up until focus returns
+                    if !self.focus_pause.is_paused() {
+                        if let Some(batch_size) = self.image_batch.flush(Instant::now()) {
+                            histogram!(HistTag::ImageBatch).record(batch_size as f64);
+                            self.need_repositioning = true;
+                        }
+                        if self.need_repositioning {
+                            self.renderer.reposition(&mut self.elements).unwrap();
+                            self.window.request_redraw();
+                            self.need_repositioning = false;
+                            self.try_fit_single_image();
+                        }
+                    }
+                }
+                Event::LoopDestroyed => {
+                    if self.opts.remember_window {
+                        self.save_window_state();
                     }
                 }
                 _ => {}
@@ -664,6 +1683,24 @@ impl Inlyne {
         });
     }
 
+    fn save_window_state(&self) {
+        let size = self.window.inner_size();
+        let state = WindowState {
+            // Not every platform lets us know where the window is (e.g. Wayland)
+            position: self
+                .window
+                .outer_position()
+                .ok()
+                .map(|pos| opts::Position { x: pos.x, y: pos.y }),
+            // Minimized windows have no size to speak of
+            size: (size.width > 0 && size.height > 0).then_some(opts::Size {
+                width: size.width,
+                height: size.height,
+            }),
+        };
+        state.save();
+    }
+
     fn scroll_lines(
         renderer: &mut Renderer,
         window: &Window,
@@ -675,7 +1712,7 @@ impl Inlyne {
     }
 
     fn scroll_pixels(renderer: &mut Renderer, window: &Window, num_pixels: f32) {
-        renderer.set_scroll_y(renderer.scroll_y - num_pixels);
+        renderer.scroll_to(renderer.scroll_destination() - num_pixels);
         window.request_redraw();
     }
 
@@ -700,6 +1737,11 @@ impl Inlyne {
             .and_then(|element| match &element.inner {
                 Element::TextBox(text_box) => {
                     let bounds = element.bounds.as_ref().unwrap();
+                    if let Some(checkbox) = text_box.checkbox_bounds(bounds.pos, zoom) {
+                        if checkbox.contains(loc) {
+                            return Some(Hoverable::Checkbox(text_box));
+                        }
+                    }
                     text_box
                         .find_hoverable(
                             text_system,
@@ -753,6 +1795,154 @@ impl Inlyne {
     }
 }
 
+/// Polls the system's color scheme in the background, notifying the event loop whenever it changes
+fn watch_color_scheme(
+    event_proxy: EventLoopProxy<InlyneEvent>,
+    mut color_scheme: Option<ResolvedTheme>,
+) {
+    const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+    std::thread::spawn(move || loop {
+        std::thread::sleep(POLL_INTERVAL);
+        let detected = ResolvedTheme::try_detect();
+        if detected != color_scheme {
+            color_scheme = detected;
+            // The event loop is gone, so there's no one left to notify
+            if event_proxy
+                .send_event(InlyneEvent::ColorSchemeChange(detected))
+                .is_err()
+            {
+                break;
+            }
+        }
+    });
+}
+
+/// Opens the file in the user's editor without blocking. Saves get picked up by the file watcher
+fn open_in_editor(file_path: PathBuf) {
+    std::thread::spawn(move || {
+        // Fails when there's no editor configured or it can't be launched
+        if let Err(err) = edit::edit_file(&file_path) {
+            tracing::warn!("Failed opening {} in an editor: {err}", file_path.display());
+        }
+    });
+}
+
+/// Opens another instance of inlyne with `args`
+fn spawn_inlyne(args: Vec<String>) {
+    std::thread::spawn(move || {
+        Command::new(std::env::current_exe().unwrap_or_else(|_| "inlyne".into()))
+            .args(args)
+            .spawn()
+            .expect("Couldn't spawn inlyne instance")
+            .wait()
+            .expect("Failed waiting on child");
+    });
+}
+
+/// Loads the config file passed with `--config` or else the system's one (if any)
+fn load_config(view: &View) -> anyhow::Result<Config> {
+    Ok(match &view.config {
+        Some(config_path) => Config::load_from_file(config_path)?,
+        None => Config::load_from_system().unwrap_or_else(|err| {
+            tracing::warn!(
+                "Failed reading config file. Falling back to defaults. Error: {}",
+                err
+            );
+            Config::default()
+        }),
+    })
+}
+
+/// Writes the HTML that the file gets converted to (before being interpreted) to stdout
+fn dump_html(opts: &Opts) -> anyhow::Result<()> {
+    let file_path = opts.history.get_path();
+    let md_string = read_to_string(file_path)
+        .with_context(|| format!("Could not read file at '{}'", file_path.display()))?;
+    let html = utils::markdown_to_html(
+        &md_string,
+        opts.theme.code_highlighter.clone(),
+        opts.preserve_blank_lines,
+        opts.math,
+    );
+    io::stdout().lock().write_all(html.as_bytes())?;
+    Ok(())
+}
+
+/// Writes the text of the rendered document to stdout
+fn dump_text(opts: &Opts) -> anyhow::Result<()> {
+    let file_path = opts.history.get_path();
+    let md_string = read_to_string(file_path)
+        .with_context(|| format!("Could not read file at '{}'", file_path.display()))?;
+    let elements: Vec<_> = interpreter::interpret_headless(md_string, opts)
+        .into_iter()
+        .map(Positioned::new)
+        .collect();
+    let text = plaintext::extract(&elements, false);
+    io::stdout().lock().write_all(text.as_bytes())?;
+    Ok(())
+}
+
+/// Renders the file to a PNG without ever opening a window
+fn render_png(render: Render) -> anyhow::Result<()> {
+    let view = render.to_view();
+    let config = load_config(&view)?;
+    let opts = Opts::parse_and_load_from(view, config)?;
+
+    let size = PhysicalSize::new(render.width.max(1), render.height.max(1));
+    let mut renderer = pollster::block_on(Renderer::new(
+        RenderTarget::Offscreen(size),
+        opts.theme.clone(),
+        opts.scale.unwrap_or(1.),
+        opts.page_width,
+        opts.font_opts.clone(),
+        opts.quote_block_background,
+        opts.minimap,
+    ))?;
+    renderer.show_whitespace = opts.show_whitespace;
+    renderer.image_background = opts.image_background;
+    renderer.image_layer = opts.image_layer;
+    renderer.scrollbar = opts.scrollbar.clone();
+    renderer.code_block_border = opts.code_block_border;
+    renderer.max_line_chars = opts.max_line_chars;
+    renderer.update_page_width();
+
+    let file_path = opts.history.get_path();
+    let md_string = read_to_string(file_path)
+        .with_context(|| format!("Could not read file at '{}'", file_path.display()))?;
+    let mut elements: Vec<_> = interpreter::interpret_for_render(
+        md_string,
+        &opts,
+        renderer.surface_format,
+        renderer.hidpi_scale,
+    )
+    .into_iter()
+    .map(Positioned::new)
+    .collect();
+    renderer.reposition(&mut elements)?;
+
+    if render.full_height {
+        let max_height = renderer.device.limits().max_texture_dimension_2d;
+        let doc_height = renderer.positioner.reserved_height.ceil() as u32;
+        if doc_height > max_height {
+            tracing::warn!(
+                "The document is {doc_height}px tall, but the GPU can only render up to \
+                {max_height}px at once. Cutting it off there"
+            );
+        }
+        renderer.resize(PhysicalSize::new(
+            size.width,
+            doc_height.clamp(1, max_height),
+        ));
+        renderer.reposition(&mut elements)?;
+    }
+
+    let image = renderer.render_to_image(&mut elements, &mut Selection::new())?;
+    image
+        .save_with_format(&render.output, ::image::ImageFormat::Png)
+        .with_context(|| format!("Failed writing the image to '{}'", render.output.display()))
+}
+
 fn main() -> anyhow::Result<()> {
     setup_panic!();
 
@@ -769,17 +1959,22 @@ fn main() -> anyhow::Result<()> {
 
     match command {
         Commands::View(view) => {
-            let config = match &view.config {
-                Some(config_path) => Config::load_from_file(config_path)?,
-                None => Config::load_from_system().unwrap_or_else(|err| {
-                    tracing::warn!(
-                        "Failed reading config file. Falling back to defaults. Error: {}",
-                        err
-                    );
-                    Config::default()
-                }),
-            };
-            let opts = Opts::parse_and_load_from(view, config)?;
+            for file_path in std::iter::once(&view.file_path).chain(&view.extra_files) {
+                if let Err(err) = utils::check_readable(file_path) {
+                    tracing::error!("{err}");
+                    std::process::exit(1);
+                }
+            }
+
+            let config = load_config(&view)?;
+            let opts = Opts::parse_and_load_from(view.clone(), config)?;
+            if opts.dump_html {
+                return dump_html(&opts);
+            }
+            if opts.dump_text {
+                return dump_text(&opts);
+            }
+            panic_hook::set_auto_open(opts.open_crash_report);
 
             if let Some(exporter) = &opts.metrics {
                 match exporter {
@@ -788,6 +1983,19 @@ fn main() -> anyhow::Result<()> {
                         metrics::set_global_recorder(recorder)
                             .expect("Failed setting metrics recorder");
                     }
+                    #[cfg(feature = "prometheus")]
+                    MetricsExporter::Prometheus => {
+                        if let Err(err) =
+                            metrics::PrometheusRecorder::default().install(opts.metrics_address)
+                        {
+                            tracing::warn!("Running without metrics: {err:#}");
+                        }
+                    }
+                    #[cfg(not(feature = "prometheus"))]
+                    MetricsExporter::Prometheus => tracing::warn!(
+                        "Running without metrics: inlyne was built without the `prometheus` \
+                         feature"
+                    ),
                     #[cfg(inlyne_tcp_metrics)]
                     MetricsExporter::Tcp => metrics_exporter_tcp::TcpBuilder::new()
                         .install()
@@ -799,9 +2007,21 @@ fn main() -> anyhow::Result<()> {
                 tag.set_global_description();
             }
 
-            let inlyne = Inlyne::new(opts)?;
+            let inlyne = Inlyne::new(opts, view)?;
             inlyne.run();
         }
+        Commands::Render(render) => {
+            if let Err(err) = utils::check_readable(&render.input) {
+                tracing::error!("{err}");
+                std::process::exit(1);
+            }
+            render_png(render)?;
+        }
+        Commands::Config(ConfigCmd::Print(view)) => {
+            let config = load_config(&view)?;
+            let opts = Opts::parse_and_load_from(view, config)?;
+            io::stdout().lock().write_all(opts.to_toml().as_bytes())?;
+        }
         Commands::Config(ConfigCmd::Open) => {
             let config_path = dirs::config_dir()
                 .context("Failed to find the configuration directory")?
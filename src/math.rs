@@ -0,0 +1,1057 @@
+//! Typesetting the `$...$` and `$$...$$` math from markdown
+//!
+//! Only a subset of TeX is understood: scripts, fractions, square roots, `\text{}` and friends,
+//! accents, the Greek letters, and a decent spread of symbols. The math gets laid out here and
+//! drawn as an SVG, so that it can go down the same path as any other SVG image. Anything else is
+//! an error, and it's left to the caller to show the source instead
+
+use std::fmt::Write;
+use std::iter::Peekable;
+use std::str::Chars;
+
+use anyhow::{bail, Context};
+use resvg::usvg::fontdb;
+
+/// Size of inline math, which matches the document's text
+const FONT_SIZE: f32 = 16.;
+/// Size of display math
+const DISPLAY_SIZE: f32 = 19.;
+/// Room around the math, so that glyphs that overhang their advance don't get cut off
+const PADDING: f32 = 2.;
+
+// Everything below is in ems of the current font size
+const ASCENT: f32 = 0.78;
+const DESCENT: f32 = 0.24;
+/// Height of the math axis, which fractions and big operators are centered on
+const AXIS: f32 = 0.25;
+const RULE: f32 = 0.06;
+const THIN: f32 = 3. / 18.;
+const MEDIUM: f32 = 4. / 18.;
+const THICK: f32 = 5. / 18.;
+/// How deeply groups, commands, and scripts can nest before the math is given up on. Parsing and
+/// laying out recurse for each level, so this keeps malicious input from overflowing the stack
+const MAX_DEPTH: usize = 64;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Font {
+    Italic,
+    Upright,
+    Bold,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    Glyphs(String, Font),
+    /// Binary operators, which get some room on either side unless they're acting as a sign
+    Bin(String),
+    /// Symbols that get some room on either side, like relations
+    Op {
+        text: String,
+        left: f32,
+        right: f32,
+    },
+    /// Sums, integrals, etc. that are drawn larger than everything around them
+    BigOp(String),
+    Space(f32),
+    Group(Vec<Node>),
+    Scripts {
+        base: Box<Node>,
+        sup: Option<Box<Node>>,
+        sub: Option<Box<Node>>,
+    },
+    Frac(Box<Node>, Box<Node>),
+    Sqrt(Box<Node>),
+}
+
+impl Node {
+    fn upright(text: &str) -> Self {
+        Self::Glyphs(text.to_owned(), Font::Upright)
+    }
+
+    fn bin(text: &str) -> Self {
+        Self::Bin(text.to_owned())
+    }
+
+    fn rel(text: &str) -> Self {
+        Self::Op {
+            text: text.to_owned(),
+            left: THICK,
+            right: THICK,
+        }
+    }
+
+    fn punct(text: &str) -> Self {
+        Self::Op {
+            text: text.to_owned(),
+            left: 0.,
+            right: THIN,
+        }
+    }
+
+    /// Switches the letters over to `font`, like `\mathrm{}` and `\mathbf{}` do
+    fn restyle(self, font: Font) -> Self {
+        let restyle = |node: Box<Node>| Box::new(node.restyle(font));
+        match self {
+            Self::Glyphs(text, Font::Italic) => Self::Glyphs(text, font),
+            Self::Glyphs(text, _) if font == Font::Bold => Self::Glyphs(text, font),
+            Self::Group(nodes) => {
+                Self::Group(nodes.into_iter().map(|node| node.restyle(font)).collect())
+            }
+            Self::Scripts { base, sup, sub } => Self::Scripts {
+                base: restyle(base),
+                sup: sup.map(restyle),
+                sub: sub.map(restyle),
+            },
+            Self::Frac(num, den) => Self::Frac(restyle(num), restyle(den)),
+            Self::Sqrt(inner) => Self::Sqrt(restyle(inner)),
+            other => other,
+        }
+    }
+}
+
+/// Lays out `tex` as an SVG drawn in `color`. Display math comes out a bit larger
+pub fn to_svg(tex: &str, display: bool, color: u32) -> anyhow::Result<String> {
+    let node = parse(tex)?;
+    let fonts = Fonts::new(crate::image::svg_fontdb());
+    let size = if display { DISPLAY_SIZE } else { FONT_SIZE };
+    let layout = layout(&node, Style::new(size, display), &fonts);
+    Ok(render(&layout, color, fonts.family()))
+}
+
+fn parse(tex: &str) -> anyhow::Result<Node> {
+    let mut parser = Parser {
+        chars: tex.chars().peekable(),
+        depth: 0,
+    };
+    let nodes = parser.row(false)?;
+    anyhow::ensure!(!nodes.is_empty(), "Empty math");
+    Ok(Node::Group(nodes))
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+    /// How many atoms are being parsed within each other
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+    }
+
+    /// Parses nodes until the end of the input, or the closing brace of a group
+    fn row(&mut self, in_group: bool) -> anyhow::Result<Vec<Node>> {
+        let mut nodes = Vec::new();
+        loop {
+            self.skip_whitespace();
+            match self.chars.peek() {
+                None if in_group => bail!("Missing a closing `}}`"),
+                None => return Ok(nodes),
+                Some('}') if in_group => {
+                    self.chars.next();
+                    return Ok(nodes);
+                }
+                Some('}') => bail!("Unexpected `}}`"),
+                Some(&c @ ('^' | '_')) => {
+                    self.chars.next();
+                    let script = Box::new(self.argument()?);
+                    let base = nodes.pop().unwrap_or(Node::Group(Vec::new()));
+                    let (base, mut sup, mut sub) = match base {
+                        Node::Scripts { base, sup, sub } => (base, sup, sub),
+                        base => (Box::new(base), None, None),
+                    };
+                    let slot = if c == '^' { &mut sup } else { &mut sub };
+                    anyhow::ensure!(slot.is_none(), "Double `{c}`");
+                    *slot = Some(script);
+                    nodes.push(Node::Scripts { base, sup, sub });
+                }
+                Some(_) => nodes.push(self.atom()?),
+            }
+        }
+    }
+
+    /// A single atom that a command or script applies to
+    fn argument(&mut self) -> anyhow::Result<Node> {
+        self.skip_whitespace();
+        match self.chars.peek() {
+            None | Some('}' | '^' | '_') => bail!("Missing an argument"),
+            Some(_) => self.atom(),
+        }
+    }
+
+    fn atom(&mut self) -> anyhow::Result<Node> {
+        anyhow::ensure!(self.depth < MAX_DEPTH, "Nested too deeply");
+        self.depth += 1;
+        let node = self.inner_atom();
+        self.depth -= 1;
+        node
+    }
+
+    fn inner_atom(&mut self) -> anyhow::Result<Node> {
+        let c = self.chars.next().context("Unexpected end of math")?;
+        let node = match c {
+            '{' => Node::Group(self.row(true)?),
+            '\\' => self.command()?,
+            '+' => Node::bin("+"),
+            '-' => Node::bin("\u{2212}"),
+            '*' => Node::bin("\u{2217}"),
+            '=' | '<' | '>' | ':' => Node::rel(c.encode_utf8(&mut [0; 4])),
+            ',' | ';' => Node::punct(c.encode_utf8(&mut [0; 4])),
+            '\'' => Node::upright("\u{2032}"),
+            '~' => Node::Space(THIN * 2.),
+            '&' | '#' | '%' | '$' => bail!("Unsupported `{c}`"),
+            c if c.is_alphabetic() => Node::Glyphs(c.into(), Font::Italic),
+            c => Node::Glyphs(c.into(), Font::Upright),
+        };
+        Ok(node)
+    }
+
+    fn command(&mut self) -> anyhow::Result<Node> {
+        let mut name = String::new();
+        while let Some(c) = self.chars.next_if(char::is_ascii_alphabetic) {
+            name.push(c);
+        }
+        if name.is_empty() {
+            let c = self.chars.next().context("Unexpected `\\` at the end")?;
+            return match c {
+                ',' => Ok(Node::Space(THIN)),
+                ':' | '>' => Ok(Node::Space(MEDIUM)),
+                ';' => Ok(Node::Space(THICK)),
+                '!' => Ok(Node::Space(-THIN)),
+                ' ' => Ok(Node::Space(THIN * 2.)),
+                '{' | '}' | '%' | '$' | '#' | '&' | '_' => Ok(Node::upright(&c.to_string())),
+                '|' => Ok(Node::upright("\u{2016}")),
+                _ => bail!("Unknown command `\\{c}`"),
+            };
+        }
+
+        let node = match name.as_str() {
+            "frac" | "dfrac" | "tfrac" => {
+                let num = self.argument()?;
+                let den = self.argument()?;
+                Node::Frac(Box::new(num), Box::new(den))
+            }
+            "sqrt" => {
+                self.skip_whitespace();
+                anyhow::ensure!(
+                    self.chars.peek() != Some(&'['),
+                    "Roots with an index aren't supported"
+                );
+                Node::Sqrt(Box::new(self.argument()?))
+            }
+            "text" | "textrm" | "mbox" => Node::Glyphs(self.raw_group()?, Font::Upright),
+            "textit" => Node::Glyphs(self.raw_group()?, Font::Italic),
+            "textbf" => Node::Glyphs(self.raw_group()?, Font::Bold),
+            "mathrm" | "operatorname" => self.argument()?.restyle(Font::Upright),
+            "mathbf" | "boldsymbol" => self.argument()?.restyle(Font::Bold),
+            "mathit" => self.argument()?.restyle(Font::Italic),
+            // Delimiters are always drawn at their regular size
+            "left" | "right" | "big" | "Big" | "bigg" | "Bigg" | "bigl" | "bigr" | "Bigl"
+            | "Bigr" => {
+                self.skip_whitespace();
+                if self.chars.next_if_eq(&'.').is_some() {
+                    Node::Group(Vec::new())
+                } else {
+                    self.argument()?
+                }
+            }
+            "hat" | "bar" | "vec" | "dot" | "ddot" | "tilde" => {
+                let accent = match name.as_str() {
+                    "hat" => '\u{302}',
+                    "bar" => '\u{304}',
+                    "vec" => '\u{20d7}',
+                    "dot" => '\u{307}',
+                    "ddot" => '\u{308}',
+                    _ => '\u{303}',
+                };
+                let base = match self.argument()? {
+                    Node::Group(mut nodes) if nodes.len() == 1 => nodes.remove(0),
+                    node => node,
+                };
+                match base {
+                    Node::Glyphs(mut text, font) if text.chars().count() == 1 => {
+                        text.push(accent);
+                        Node::Glyphs(text, font)
+                    }
+                    _ => bail!("`\\{name}` only works on a single character"),
+                }
+            }
+            "quad" => Node::Space(1.),
+            "qquad" => Node::Space(2.),
+            name => {
+                if let Some(text) = function(name) {
+                    Node::punct(text)
+                } else if let Some(letter) = greek(name) {
+                    let font = if letter.chars().all(char::is_lowercase) {
+                        Font::Italic
+                    } else {
+                        Font::Upright
+                    };
+                    Node::Glyphs(letter.to_owned(), font)
+                } else if let Some(op) = big_operator(name) {
+                    Node::BigOp(op.to_owned())
+                } else if let Some(op) = binary_operator(name) {
+                    Node::bin(op)
+                } else if let Some(rel) = relation(name) {
+                    Node::rel(rel)
+                } else if let Some(symbol) = symbol(name) {
+                    Node::upright(symbol)
+                } else {
+                    bail!("Unknown command `\\{name}`");
+                }
+            }
+        };
+        Ok(node)
+    }
+
+    /// The contents of a `{}` group as is, for text within math
+    fn raw_group(&mut self) -> anyhow::Result<String> {
+        self.skip_whitespace();
+        anyhow::ensure!(self.chars.next() == Some('{'), "Expected a `{{`");
+        let mut text = String::new();
+        let mut depth = 0;
+        loop {
+            match self.chars.next().context("Missing a closing `}`")? {
+                '{' => depth += 1,
+                '}' if depth == 0 => return Ok(text),
+                '}' => depth -= 1,
+                c => text.push(c),
+            }
+        }
+    }
+}
+
+fn function(name: &str) -> Option<&str> {
+    const FUNCTIONS: &[&str] = &[
+        "arccos", "arcsin", "arctan", "arg", "cos", "cosh", "cot", "coth", "csc", "deg", "det",
+        "dim", "exp", "gcd", "inf", "ker", "lg", "lim", "liminf", "limsup", "ln", "log", "max",
+        "min", "Pr", "sec", "sin", "sinh", "sup", "tan", "tanh",
+    ];
+    FUNCTIONS
+        .iter()
+        .find(|&&function| function == name)
+        .copied()
+}
+
+fn greek(name: &str) -> Option<&'static str> {
+    let letter = match name {
+        "alpha" => "α",
+        "beta" => "β",
+        "gamma" => "γ",
+        "delta" => "δ",
+        "epsilon" => "ϵ",
+        "varepsilon" => "ε",
+        "zeta" => "ζ",
+        "eta" => "η",
+        "theta" => "θ",
+        "vartheta" => "ϑ",
+        "iota" => "ι",
+        "kappa" => "κ",
+        "lambda" => "λ",
+        "mu" => "μ",
+        "nu" => "ν",
+        "xi" => "ξ",
+        "pi" => "π",
+        "varpi" => "ϖ",
+        "rho" => "ρ",
+        "varrho" => "ϱ",
+        "sigma" => "σ",
+        "varsigma" => "ς",
+        "tau" => "τ",
+        "upsilon" => "υ",
+        "phi" => "ϕ",
+        "varphi" => "φ",
+        "chi" => "χ",
+        "psi" => "ψ",
+        "omega" => "ω",
+        "Gamma" => "Γ",
+        "Delta" => "Δ",
+        "Theta" => "Θ",
+        "Lambda" => "Λ",
+        "Xi" => "Ξ",
+        "Pi" => "Π",
+        "Sigma" => "Σ",
+        "Upsilon" => "Υ",
+        "Phi" => "Φ",
+        "Psi" => "Ψ",
+        "Omega" => "Ω",
+        _ => return None,
+    };
+    Some(letter)
+}
+
+fn big_operator(name: &str) -> Option<&'static str> {
+    let op = match name {
+        "sum" => "∑",
+        "prod" => "∏",
+        "coprod" => "∐",
+        "int" => "∫",
+        "iint" => "∬",
+        "iiint" => "∭",
+        "oint" => "∮",
+        "bigcup" => "⋃",
+        "bigcap" => "⋂",
+        "bigvee" => "⋁",
+        "bigwedge" => "⋀",
+        "bigoplus" => "⨁",
+        "bigotimes" => "⨂",
+        _ => return None,
+    };
+    Some(op)
+}
+
+fn binary_operator(name: &str) -> Option<&'static str> {
+    let op = match name {
+        "pm" => "±",
+        "mp" => "∓",
+        "times" => "×",
+        "div" => "÷",
+        "cdot" => "⋅",
+        "ast" => "∗",
+        "star" => "⋆",
+        "circ" => "∘",
+        "bullet" => "∙",
+        "cup" => "∪",
+        "cap" => "∩",
+        "wedge" | "land" => "∧",
+        "vee" | "lor" => "∨",
+        "setminus" => "∖",
+        "oplus" => "⊕",
+        "ominus" => "⊖",
+        "otimes" => "⊗",
+        _ => return None,
+    };
+    Some(op)
+}
+
+fn relation(name: &str) -> Option<&'static str> {
+    let rel = match name {
+        "leq" | "le" => "≤",
+        "geq" | "ge" => "≥",
+        "neq" | "ne" => "≠",
+        "approx" => "≈",
+        "equiv" => "≡",
+        "sim" => "∼",
+        "simeq" => "≃",
+        "cong" => "≅",
+        "propto" => "∝",
+        "ll" => "≪",
+        "gg" => "≫",
+        "in" => "∈",
+        "notin" => "∉",
+        "ni" => "∋",
+        "subset" => "⊂",
+        "subseteq" => "⊆",
+        "supset" => "⊃",
+        "supseteq" => "⊇",
+        "to" | "rightarrow" => "→",
+        "gets" | "leftarrow" => "←",
+        "leftrightarrow" => "↔",
+        "Rightarrow" => "⇒",
+        "Leftarrow" => "⇐",
+        "Leftrightarrow" => "⇔",
+        "implies" => "⟹",
+        "iff" => "⟺",
+        "mapsto" => "↦",
+        "mid" => "∣",
+        "parallel" => "∥",
+        "perp" => "⊥",
+        _ => return None,
+    };
+    Some(rel)
+}
+
+fn symbol(name: &str) -> Option<&'static str> {
+    let symbol = match name {
+        "infty" => "∞",
+        "partial" => "∂",
+        "nabla" => "∇",
+        "forall" => "∀",
+        "exists" => "∃",
+        "neg" | "lnot" => "¬",
+        "emptyset" | "varnothing" => "∅",
+        "ell" => "ℓ",
+        "hbar" => "ℏ",
+        "Re" => "ℜ",
+        "Im" => "ℑ",
+        "aleph" => "ℵ",
+        "angle" => "∠",
+        "triangle" => "△",
+        "prime" => "′",
+        "degree" => "°",
+        "ldots" | "dots" => "…",
+        "cdots" => "⋯",
+        "vdots" => "⋮",
+        "ddots" => "⋱",
+        "langle" => "⟨",
+        "rangle" => "⟩",
+        "lfloor" => "⌊",
+        "rfloor" => "⌋",
+        "lceil" => "⌈",
+        "rceil" => "⌉",
+        "lbrace" => "{",
+        "rbrace" => "}",
+        "vert" => "|",
+        "Vert" => "‖",
+        _ => return None,
+    };
+    Some(symbol)
+}
+
+trait Measure {
+    /// How far `text` advances when drawn in `font` at `size`
+    fn advance(&self, text: &str, font: Font, size: f32) -> f32;
+}
+
+/// Measures with the same faces that the SVG ends up getting drawn with
+struct Fonts<'db> {
+    db: &'db fontdb::Database,
+    family: Option<String>,
+    faces: [Option<fontdb::ID>; 3],
+}
+
+impl<'db> Fonts<'db> {
+    fn new(db: &'db fontdb::Database) -> Self {
+        let has_family = |name: &str| {
+            db.faces()
+                .any(|face| face.families.iter().any(|(family, _)| family == name))
+        };
+        // Math is traditionally set in a serif font, but any font beats none at all
+        let serif = db.family_name(&fontdb::Family::Serif).to_owned();
+        let family = [
+            serif.as_str(),
+            "DejaVu Serif",
+            "Liberation Serif",
+            "Noto Serif",
+        ]
+        .into_iter()
+        .find(|&name| has_family(name))
+        .map(str::to_owned)
+        .or_else(|| Some(db.faces().next()?.families.first()?.0.clone()));
+
+        let faces = [Font::Italic, Font::Upright, Font::Bold].map(|font| {
+            let name = family.as_deref()?;
+            let (weight, style) = match font {
+                Font::Italic => (fontdb::Weight::NORMAL, fontdb::Style::Italic),
+                Font::Upright => (fontdb::Weight::NORMAL, fontdb::Style::Normal),
+                Font::Bold => (fontdb::Weight::BOLD, fontdb::Style::Normal),
+            };
+            db.query(&fontdb::Query {
+                families: &[fontdb::Family::Name(name)],
+                weight,
+                style,
+                ..Default::default()
+            })
+        });
+
+        Self { db, family, faces }
+    }
+
+    fn family(&self) -> &str {
+        self.family.as_deref().unwrap_or("serif")
+    }
+}
+
+impl Measure for Fonts<'_> {
+    fn advance(&self, text: &str, font: Font, size: f32) -> f32 {
+        // Glyphs that the face lacks get drawn from some fallback font, so they're guessed at
+        let guess = 0.6 * size;
+        let id = match font {
+            Font::Italic => self.faces[0],
+            Font::Upright => self.faces[1],
+            Font::Bold => self.faces[2],
+        };
+        let measured = id.and_then(|id| {
+            self.db.with_face_data(id, |data, index| {
+                let face = ttf_parser::Face::parse(data, index).ok()?;
+                let scale = size / f32::from(face.units_per_em());
+                let advance = text
+                    .chars()
+                    .map(|c| {
+                        face.glyph_index(c)
+                            .and_then(|glyph| face.glyph_hor_advance(glyph))
+                            .map_or(guess, |advance| f32::from(advance) * scale)
+                    })
+                    .sum();
+                Some(advance)
+            })
+        });
+        measured
+            .flatten()
+            .unwrap_or_else(|| text.chars().count() as f32 * guess)
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Style {
+    size: f32,
+    display: bool,
+    /// Scripts leave out the room around operators
+    script: bool,
+}
+
+impl Style {
+    fn new(size: f32, display: bool) -> Self {
+        Self {
+            size,
+            display,
+            script: false,
+        }
+    }
+
+    fn script(self) -> Self {
+        Self {
+            size: self.size * 0.7,
+            display: false,
+            script: true,
+        }
+    }
+
+    /// Fractions within display math stay full size, but shrink everywhere else
+    fn fraction(self) -> Self {
+        let size = if self.display {
+            self.size
+        } else {
+            self.size * 0.8
+        };
+        Self {
+            size,
+            display: false,
+            script: self.script,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Item {
+    Text {
+        x: f32,
+        y: f32,
+        size: f32,
+        text: String,
+        font: Font,
+    },
+    Rule {
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+    },
+    Line {
+        points: Vec<(f32, f32)>,
+        width: f32,
+    },
+}
+
+impl Item {
+    fn moved(self, dx: f32, dy: f32) -> Self {
+        match self {
+            Self::Text {
+                x,
+                y,
+                size,
+                text,
+                font,
+            } => Self::Text {
+                x: x + dx,
+                y: y + dy,
+                size,
+                text,
+                font,
+            },
+            Self::Rule {
+                x,
+                y,
+                width,
+                height,
+            } => Self::Rule {
+                x: x + dx,
+                y: y + dy,
+                width,
+                height,
+            },
+            Self::Line { points, width } => Self::Line {
+                points: points.into_iter().map(|(x, y)| (x + dx, y + dy)).collect(),
+                width,
+            },
+        }
+    }
+}
+
+/// A laid out box of math. Its origin is at the left end of its baseline, with `y` growing
+/// downwards
+#[derive(Debug, Default)]
+struct Layout {
+    width: f32,
+    ascent: f32,
+    descent: f32,
+    items: Vec<Item>,
+}
+
+impl Layout {
+    fn glyphs(text: &str, font: Font, size: f32, measure: &impl Measure) -> Self {
+        Self {
+            width: measure.advance(text, font, size),
+            ascent: ASCENT * size,
+            descent: DESCENT * size,
+            items: vec![Item::Text {
+                x: 0.,
+                y: 0.,
+                size,
+                text: text.to_owned(),
+                font,
+            }],
+        }
+    }
+
+    /// Places `other` with its origin at `(x, y)`
+    fn add(&mut self, other: Layout, x: f32, y: f32) {
+        self.width = self.width.max(x + other.width);
+        self.ascent = self.ascent.max(other.ascent - y);
+        self.descent = self.descent.max(other.descent + y);
+        self.items
+            .extend(other.items.into_iter().map(|item| item.moved(x, y)));
+    }
+}
+
+fn layout(node: &Node, style: Style, measure: &impl Measure) -> Layout {
+    let size = style.size;
+    match node {
+        Node::Glyphs(text, font) => Layout::glyphs(text, *font, size, measure),
+        Node::Bin(text) => layout(
+            &Node::Op {
+                text: text.clone(),
+                left: MEDIUM,
+                right: MEDIUM,
+            },
+            style,
+            measure,
+        ),
+        Node::Op { text, left, right } => {
+            let (left, right) = if style.script {
+                (0., 0.)
+            } else {
+                (*left, *right)
+            };
+            let mut layout = Layout::default();
+            let glyphs = Layout::glyphs(text, Font::Upright, size, measure);
+            let width = glyphs.width;
+            layout.add(glyphs, left * size, 0.);
+            layout.width = (left + right) * size + width;
+            layout
+        }
+        Node::BigOp(text) => {
+            let scale = if style.display { 1.5 } else { 1.15 };
+            let glyphs = Layout::glyphs(text, Font::Upright, size * scale, measure);
+            // Centered on the axis rather than sitting on the baseline
+            let height = glyphs.ascent + glyphs.descent;
+            let shift = glyphs.ascent - height / 2. - AXIS * size;
+            let mut layout = Layout::default();
+            layout.add(glyphs, 0., shift);
+            layout.width += THIN * size;
+            layout
+        }
+        Node::Space(em) => Layout {
+            width: em.max(0.) * size,
+            ..Default::default()
+        },
+        Node::Group(nodes) => {
+            let mut row = Layout::default();
+            let mut after_operand = false;
+            for node in nodes {
+                let x = row.width;
+                match node {
+                    // Negative spaces pull things closer together
+                    Node::Space(em) => row.width += em * size,
+                    // Signs like the one in `-1` or `= -x` stay snug against what they're on
+                    Node::Bin(text) if !after_operand => {
+                        row.add(Layout::glyphs(text, Font::Upright, size, measure), x, 0.)
+                    }
+                    node => row.add(layout(node, style, measure), x, 0.),
+                }
+                after_operand = match node {
+                    Node::Space(_) => after_operand,
+                    Node::Bin(_) | Node::Op { .. } | Node::BigOp(_) => false,
+                    _ => true,
+                };
+            }
+            row.width = row.width.max(0.);
+            row
+        }
+        Node::Scripts { base, sup, sub } => {
+            let mut scripted = layout(base, style, measure);
+            let x = scripted.width;
+            let sup = sup.as_ref().map(|sup| layout(sup, style.script(), measure));
+            let sub = sub.as_ref().map(|sub| layout(sub, style.script(), measure));
+            let mut up = (0.42 * size).max(scripted.ascent - 0.3 * size);
+            let mut down = (0.18 * size).max(scripted.descent - 0.1 * size);
+            if let (Some(sup), Some(sub)) = (&sup, &sub) {
+                down = down.max(0.26 * size);
+                // Keep the scripts from running into each other
+                let gap = (down - sub.ascent) - (sup.descent - up);
+                let min_gap = 4. * RULE * size;
+                if gap < min_gap {
+                    up += (min_gap - gap) / 2.;
+                    down += (min_gap - gap) / 2.;
+                }
+            }
+            let mut width = 0f32;
+            if let Some(sup) = sup {
+                width = width.max(sup.width);
+                scripted.add(sup, x, -up);
+            }
+            if let Some(sub) = sub {
+                width = width.max(sub.width);
+                scripted.add(sub, x, down);
+            }
+            scripted.width = x + width + 0.05 * size;
+            scripted
+        }
+        Node::Frac(num, den) => {
+            let num = layout(num, style.fraction(), measure);
+            let den = layout(den, style.fraction(), measure);
+            let (axis, rule, gap, pad) = (AXIS * size, RULE * size, 0.15 * size, 0.12 * size);
+            let width = num.width.max(den.width) + 2. * pad;
+            let mut layout = Layout {
+                width,
+                ascent: axis + rule / 2.,
+                descent: 0.,
+                items: vec![Item::Rule {
+                    x: pad / 2.,
+                    y: -axis - rule / 2.,
+                    width: width - pad,
+                    height: rule,
+                }],
+            };
+            let num_y = -axis - rule / 2. - gap - num.descent;
+            let den_y = -axis + rule / 2. + gap + den.ascent;
+            let (num_x, den_x) = ((width - num.width) / 2., (width - den.width) / 2.);
+            layout.add(num, num_x, num_y);
+            layout.add(den, den_x, den_y);
+            layout
+        }
+        Node::Sqrt(inner) => {
+            let inner = layout(inner, style, measure);
+            let (rule, gap, sign) = (RULE * size, 0.15 * size, 0.6 * size);
+            let top = -(inner.ascent + gap + rule / 2.);
+            let bottom = inner.descent;
+            let height = bottom - top;
+            let points = vec![
+                (0.05 * size, bottom - 0.4 * height),
+                (0.2 * size, bottom - 0.48 * height),
+                (0.32 * size, bottom),
+                (0.55 * size, top),
+                (sign + inner.width + 0.05 * size, top),
+            ];
+            let mut layout = Layout {
+                width: sign + inner.width + 0.1 * size,
+                ascent: -top + rule,
+                descent: bottom + rule,
+                items: vec![Item::Line {
+                    points,
+                    width: rule,
+                }],
+            };
+            layout.add(inner, sign, 0.);
+            layout
+        }
+    }
+}
+
+fn render(layout: &Layout, color: u32, family: &str) -> String {
+    let width = (layout.width + 2. * PADDING).max(1.);
+    let height = layout.ascent + layout.descent + 2. * PADDING;
+    let (origin_x, origin_y) = (PADDING, PADDING + layout.ascent);
+    let family = html_escape::encode_double_quoted_attribute(family);
+    let color = format!("#{color:06x}");
+
+    let mut svg = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+    );
+    for item in &layout.items {
+        match item {
+            Item::Text {
+                x,
+                y,
+                size,
+                text,
+                font,
+            } => {
+                let font = match font {
+                    Font::Italic => r#" font-style="italic""#,
+                    Font::Upright => "",
+                    Font::Bold => r#" font-weight="bold""#,
+                };
+                let _ = write!(
+                    svg,
+                    r#"<text x="{}" y="{}" font-family="'{family}'" font-size="{size}"{font} fill="{color}" xml:space="preserve">{}</text>"#,
+                    origin_x + x,
+                    origin_y + y,
+                    html_escape::encode_text(text),
+                );
+            }
+            Item::Rule {
+                x,
+                y,
+                width,
+                height,
+            } => {
+                let _ = write!(
+                    svg,
+                    r#"<rect x="{}" y="{}" width="{width}" height="{height}" fill="{color}"/>"#,
+                    origin_x + x,
+                    origin_y + y,
+                );
+            }
+            Item::Line { points, width } => {
+                let points: Vec<_> = points
+                    .iter()
+                    .map(|(x, y)| format!("{},{}", origin_x + x, origin_y + y))
+                    .collect();
+                let _ = write!(
+                    svg,
+                    r#"<polyline points="{}" fill="none" stroke="{color}" stroke-width="{width}" stroke-linejoin="round"/>"#,
+                    points.join(" "),
+                );
+            }
+        }
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every character is half an em wide
+    struct Monospace;
+
+    impl Measure for Monospace {
+        fn advance(&self, text: &str, _: Font, size: f32) -> f32 {
+            text.chars().count() as f32 * size / 2.
+        }
+    }
+
+    fn italic(text: &str) -> Node {
+        Node::Glyphs(text.into(), Font::Italic)
+    }
+
+    #[test]
+    fn parsing() {
+        let Node::Group(nodes) = parse(r"x^2_{i} \leq \frac{\alpha}{\mathrm{d}t}").unwrap() else {
+            unreachable!()
+        };
+        assert_eq!(
+            nodes,
+            [
+                Node::Scripts {
+                    base: Box::new(italic("x")),
+                    sup: Some(Box::new(Node::upright("2"))),
+                    sub: Some(Box::new(Node::Group(vec![italic("i")]))),
+                },
+                Node::rel("≤"),
+                Node::Frac(
+                    Box::new(Node::Group(vec![italic("α")])),
+                    Box::new(Node::Group(vec![
+                        Node::Group(vec![Node::upright("d")]),
+                        italic("t"),
+                    ])),
+                ),
+            ]
+        );
+
+        let Node::Group(nodes) = parse(r"\hat{x} \text{ if } \sin").unwrap() else {
+            unreachable!()
+        };
+        assert_eq!(
+            nodes,
+            [
+                italic("x\u{302}"),
+                Node::upright(" if "),
+                Node::punct("sin"),
+            ]
+        );
+    }
+
+    #[test]
+    fn malformed() {
+        for tex in [
+            "",
+            r"\frac{1}{2",
+            "x}",
+            "x^",
+            "x^1^2",
+            r"\unknown",
+            r"\begin{matrix} a & b \end{matrix}",
+            r"a \\ b",
+            r"\sqrt[3]{x}",
+            r"\hat{xy}",
+        ] {
+            assert!(parse(tex).is_err(), "{tex:?}");
+        }
+
+        // Deep nesting gets rejected instead of overflowing the stack
+        let nested = |depth| format!("{}x{}", "{".repeat(depth), "}".repeat(depth));
+        assert!(parse(&nested(MAX_DEPTH - 1)).is_ok());
+        assert!(parse(&nested(100_000)).is_err());
+        assert!(parse(&format!("{}x", r"\sqrt".repeat(100_000))).is_err());
+        assert!(to_svg(&nested(100_000), false, 0).is_err());
+    }
+
+    #[test]
+    fn operator_spacing() {
+        let width = |tex| layout(&parse(tex).unwrap(), Style::new(10., false), &Monospace).width;
+        let medium = MEDIUM * 10.;
+
+        assert_eq!(width("a-b"), 15. + 2. * medium);
+        // Signs don't get any room
+        assert_eq!(width("-b"), 10.);
+        assert_eq!(width("a=-b"), 20. + 2. * THICK * 10.);
+        // and neither does anything in a script
+        let scripted = width("a^{b-c}");
+        assert!(
+            (scripted - (5. + 3. * 3.5 + 0.5)).abs() < 1e-4,
+            "{scripted}"
+        );
+    }
+
+    #[test]
+    fn layout_stacks_fractions() {
+        let node = parse(r"\frac{a}{\text{bb}}").unwrap();
+        let layout = layout(&node, Style::new(10., true), &Monospace);
+        let close = |a: f32, b: f32| (a - b).abs() < 1e-4;
+
+        // As wide as the denominator plus some padding
+        assert!(close(layout.width, 10. + 2. * 1.2), "{layout:#?}");
+        let [Item::Rule { y: rule_y, .. }, Item::Text {
+            x: num_x, y: num_y, ..
+        }, Item::Text {
+            x: den_x, y: den_y, ..
+        }] = layout.items.as_slice()
+        else {
+            panic!("{layout:#?}");
+        };
+        // with the bar centered on the axis, and the numerator centered above it
+        assert!(close(*rule_y, -2.5 - 0.3), "{layout:#?}");
+        assert!(*num_y < *rule_y && *den_y > *rule_y, "{layout:#?}");
+        assert!(
+            close(*num_x, 1.2 + 2.5) && close(*den_x, 1.2),
+            "{layout:#?}"
+        );
+    }
+
+    #[test]
+    fn svg_output() {
+        let layout = layout(&parse("x+1").unwrap(), Style::new(10., false), &Monospace);
+        let svg = render(&layout, 0x123abc, "Serif & Co");
+        assert!(svg.starts_with("<svg "), "{svg}");
+        assert!(svg.contains(r#"font-family="'Serif &amp; Co'""#), "{svg}");
+        assert!(
+            svg.contains(r##"font-style="italic" fill="#123abc""##),
+            "{svg}"
+        );
+        assert_eq!(svg.matches("<text ").count(), 3, "{svg}");
+        assert!(svg.ends_with("</svg>"));
+    }
+}
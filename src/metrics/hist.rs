@@ -11,9 +11,11 @@ use tracing::{debug, info, span, trace};
 pub enum Tag {
     ImageDecompress,
     ImageLoad,
+    ImageUpload,
     Positioner,
     Redraw,
     Reposition,
+    ImageBatch,
 }
 
 impl Tag {
@@ -29,9 +31,11 @@ impl Tag {
         match self {
             Tag::ImageDecompress => "image.decompress",
             Tag::ImageLoad => "image.load",
+            Tag::ImageUpload => "image.upload",
             Tag::Positioner => "positioner",
             Tag::Redraw => "redraw",
             Tag::Reposition => "reposition",
+            Tag::ImageBatch => "image.batch",
         }
     }
 
@@ -39,9 +43,11 @@ impl Tag {
         match self {
             Self::ImageDecompress => "Decompressing image data to render",
             Self::ImageLoad => "Reading, decoding, and compressing the raw image data",
+            Self::ImageUpload => "Decompressing and uploading an image's texture to the GPU",
             Self::Positioner => "Positioning all of the elements",
             Self::Redraw => "A full redraw",
             Self::Reposition => "Repositioning all of the elements in the queue",
+            Self::ImageBatch => "Loaded images coalesced into a single reposition",
         }
     }
 
@@ -49,9 +55,11 @@ impl Tag {
         match self {
             Self::ImageDecompress
             | Self::ImageLoad
+            | Self::ImageUpload
             | Self::Positioner
             | Self::Redraw
             | Self::Reposition => Unit::Seconds,
+            Self::ImageBatch => Unit::Count,
         }
     }
 }
@@ -72,10 +80,12 @@ impl Iterator for TagIter {
         let next = std::mem::take(&mut self.0)?;
         self.0 = match next {
             Tag::ImageDecompress => Some(Tag::ImageLoad),
-            Tag::ImageLoad => Some(Tag::Positioner),
+            Tag::ImageLoad => Some(Tag::ImageUpload),
+            Tag::ImageUpload => Some(Tag::Positioner),
             Tag::Positioner => Some(Tag::Redraw),
             Tag::Redraw => Some(Tag::Reposition),
-            Tag::Reposition => None,
+            Tag::Reposition => Some(Tag::ImageBatch),
+            Tag::ImageBatch => None,
         };
         Some(next)
     }
@@ -11,9 +11,18 @@ mod counter;
 mod gauge;
 mod hist;
 mod log_recorder;
+#[cfg(feature = "prometheus")]
+mod prometheus;
+
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
 
 pub use hist::Tag as HistTag;
 pub use log_recorder::LogRecorder;
+#[cfg(feature = "prometheus")]
+pub use prometheus::PrometheusRecorder;
+
+pub const DEFAULT_PROMETHEUS_ADDRESS: SocketAddr =
+    SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 9184));
 
 const SPAN_LEVEL: Level = Level::INFO;
 
@@ -0,0 +1,256 @@
+//! Serves our metrics over HTTP in Prometheus' text format (`metrics = "prometheus"`)
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use super::Unit;
+
+use anyhow::Context;
+use metrics::{
+    Counter, Gauge, Histogram, HistogramFn, Key, KeyName, Metadata, Recorder, SharedString,
+};
+use metrics_util::registry::{Registry, Storage};
+use metrics_util::Summary;
+use parking_lot::Mutex;
+use tiny_http::{Header, Response, Server};
+
+const QUANTILES: [f64; 4] = [0.5, 0.9, 0.99, 0.999];
+
+#[derive(Default)]
+struct Summarized {
+    summary: Option<Summary>,
+    sum: f64,
+}
+
+struct SummaryHandle(Mutex<Summarized>);
+
+impl HistogramFn for SummaryHandle {
+    fn record(&self, value: f64) {
+        let mut summarized = self.0.lock();
+        summarized
+            .summary
+            .get_or_insert_with(Summary::with_defaults)
+            .add(value);
+        summarized.sum += value;
+    }
+}
+
+struct MetricStore;
+
+impl Storage<Key> for MetricStore {
+    type Counter = Arc<AtomicU64>;
+    type Gauge = Arc<AtomicU64>;
+    type Histogram = Arc<SummaryHandle>;
+
+    fn counter(&self, _: &Key) -> Self::Counter {
+        Arc::default()
+    }
+
+    fn gauge(&self, _: &Key) -> Self::Gauge {
+        Arc::default()
+    }
+
+    fn histogram(&self, _: &Key) -> Self::Histogram {
+        Arc::new(SummaryHandle(Mutex::default()))
+    }
+}
+
+struct Description {
+    unit: Option<Unit>,
+    help: SharedString,
+}
+
+#[derive(Clone)]
+pub struct PrometheusRecorder {
+    registry: Arc<Registry<Key, MetricStore>>,
+    descriptions: Arc<Mutex<HashMap<String, Description>>>,
+}
+
+impl Default for PrometheusRecorder {
+    fn default() -> Self {
+        Self {
+            registry: Arc::new(Registry::new(MetricStore)),
+            descriptions: Arc::default(),
+        }
+    }
+}
+
+impl PrometheusRecorder {
+    fn describe(&self, key: KeyName, unit: Option<Unit>, help: SharedString) {
+        let description = Description { unit, help };
+        self.descriptions
+            .lock()
+            .insert(key.as_str().to_owned(), description);
+    }
+
+    /// Installs the recorder globally and starts serving its metrics at `/metrics`
+    pub fn install(self, address: SocketAddr) -> anyhow::Result<()> {
+        let server = Server::http(address)
+            .map_err(|err| anyhow::anyhow!(err))
+            .with_context(|| format!("Failed binding the metrics server to {address}"))?;
+        let recorder = self.clone();
+        thread::spawn(move || {
+            let content_type =
+                Header::from_bytes(b"Content-Type", b"text/plain; version=0.0.4").unwrap();
+            for request in server.incoming_requests() {
+                let response = if request.url() == "/metrics" {
+                    Response::from_string(recorder.render()).with_header(content_type.clone())
+                } else {
+                    Response::from_string("Not found").with_status_code(404)
+                };
+                if let Err(err) = request.respond(response) {
+                    tracing::warn!("Failed responding to a metrics request: {err}");
+                }
+            }
+        });
+        tracing::info!("Serving metrics at http://{address}/metrics");
+        metrics::set_global_recorder(self).context("Failed setting metrics recorder")
+    }
+
+    /// All of the metrics in Prometheus' text exposition format
+    pub fn render(&self) -> String {
+        let descriptions = self.descriptions.lock();
+        let mut out = String::new();
+
+        let header = |out: &mut String, key: &Key, kind: &str| {
+            let description = descriptions.get(key.name());
+            let name = metric_name(key.name(), description.and_then(|desc| desc.unit));
+            if let Some(description) = description {
+                let _ = writeln!(out, "# HELP {name} {}", description.help);
+            }
+            let _ = writeln!(out, "# TYPE {name} {kind}");
+            name
+        };
+
+        let mut counters: Vec<_> = self.registry.get_counter_handles().into_iter().collect();
+        counters.sort_by(|(a, _), (b, _)| a.name().cmp(b.name()));
+        for (key, counter) in counters {
+            let name = header(&mut out, &key, "counter");
+            let value = counter.load(Ordering::Relaxed);
+            let _ = writeln!(out, "{name}{} {value}", labels(&key, None));
+        }
+
+        let mut gauges: Vec<_> = self.registry.get_gauge_handles().into_iter().collect();
+        gauges.sort_by(|(a, _), (b, _)| a.name().cmp(b.name()));
+        for (key, gauge) in gauges {
+            let name = header(&mut out, &key, "gauge");
+            let value = f64::from_bits(gauge.load(Ordering::Relaxed));
+            let _ = writeln!(out, "{name}{} {value}", labels(&key, None));
+        }
+
+        let mut histograms: Vec<_> = self.registry.get_histogram_handles().into_iter().collect();
+        histograms.sort_by(|(a, _), (b, _)| a.name().cmp(b.name()));
+        for (key, histogram) in histograms {
+            let name = header(&mut out, &key, "summary");
+            let summarized = histogram.0.lock();
+            let Some(summary) = &summarized.summary else {
+                let _ = writeln!(out, "{name}_sum{} 0", labels(&key, None));
+                let _ = writeln!(out, "{name}_count{} 0", labels(&key, None));
+                continue;
+            };
+            for quantile in QUANTILES {
+                if let Some(value) = summary.quantile(quantile) {
+                    let labels = labels(&key, Some(quantile));
+                    let _ = writeln!(out, "{name}{labels} {value}");
+                }
+            }
+            let _ = writeln!(out, "{name}_sum{} {}", labels(&key, None), summarized.sum);
+            let _ = writeln!(
+                out,
+                "{name}_count{} {}",
+                labels(&key, None),
+                summary.count()
+            );
+        }
+
+        out
+    }
+}
+
+impl Recorder for PrometheusRecorder {
+    fn describe_counter(&self, key: KeyName, unit: Option<Unit>, description: SharedString) {
+        self.describe(key, unit, description);
+    }
+
+    fn describe_gauge(&self, key: KeyName, unit: Option<Unit>, description: SharedString) {
+        self.describe(key, unit, description);
+    }
+
+    fn describe_histogram(&self, key: KeyName, unit: Option<Unit>, description: SharedString) {
+        self.describe(key, unit, description);
+    }
+
+    fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+        Counter::from_arc(self.registry.get_or_create_counter(key, Arc::clone))
+    }
+
+    fn register_gauge(&self, key: &Key, _: &Metadata<'_>) -> Gauge {
+        Gauge::from_arc(self.registry.get_or_create_gauge(key, Arc::clone))
+    }
+
+    fn register_histogram(&self, key: &Key, _: &Metadata<'_>) -> Histogram {
+        Histogram::from_arc(self.registry.get_or_create_histogram(key, Arc::clone))
+    }
+}
+
+/// Prometheus only allows `[a-zA-Z0-9_:]` in names, and it's customary to include the base unit
+fn metric_name(key: &str, unit: Option<Unit>) -> String {
+    let mut name: String = key
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    name.insert_str(0, "inlyne_");
+    if unit == Some(Unit::Seconds) {
+        name.push_str("_seconds");
+    }
+    name
+}
+
+fn labels(key: &Key, quantile: Option<f64>) -> String {
+    let mut labels: Vec<_> = key
+        .labels()
+        .map(|label| format!("{}={:?}", label.key(), label.value()))
+        .collect();
+    if let Some(quantile) = quantile {
+        labels.push(format!("quantile=\"{quantile}\""));
+    }
+    if labels.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", labels.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::{describe_histogram, histogram};
+
+    #[test]
+    fn exposition_format() {
+        let recorder = PrometheusRecorder::default();
+        metrics::with_local_recorder(&recorder, || {
+            describe_histogram!("image.load", Unit::Seconds, "Loading images");
+            histogram!("image.load").record(2.0);
+            metrics::counter!("reloads", "file" => "README.md").increment(2);
+        });
+
+        let rendered = recorder.render();
+        insta::assert_snapshot!(rendered, @r###"
+        # TYPE inlyne_reloads counter
+        inlyne_reloads{file="README.md"} 2
+        # HELP inlyne_image_load_seconds Loading images
+        # TYPE inlyne_image_load_seconds summary
+        inlyne_image_load_seconds{quantile="0.5"} 1.9999056357269998
+        inlyne_image_load_seconds{quantile="0.9"} 1.9999056357269998
+        inlyne_image_load_seconds{quantile="0.99"} 1.9999056357269998
+        inlyne_image_load_seconds{quantile="0.999"} 1.9999056357269998
+        inlyne_image_load_seconds_sum 2
+        inlyne_image_load_seconds_count 1
+        "###);
+    }
+}
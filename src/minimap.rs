@@ -0,0 +1,147 @@
+//! A lightweight overview of the document drawn along the right edge of the window
+//!
+//! Rendering a true scaled down copy of the document would be expensive, so instead we just mark
+//! where each anchor (typically a header) sits relative to the full document's height. Clicking a
+//! marker jumps to it while clicking anywhere else in the minimap jumps proportionally
+
+use std::collections::HashMap;
+
+use crate::positioner::DEFAULT_MARGIN;
+use crate::utils::{Rect, Size};
+
+pub const MINIMAP_WIDTH: f32 = DEFAULT_MARGIN / 4.;
+const MARKER_HEIGHT: f32 = 2.;
+/// How close (in pixels) a click has to be to a marker to snap to it
+const SNAP_DISTANCE: f32 = 5.;
+
+/// The strip of the window reserved for the minimap which sits just left of the scrollbar
+///
+/// `content_size` is the part of the window that the document scrolls within while `top_inset`
+/// is the space taken up by anything drawn over its top (e.g. the tab bar)
+pub fn bounds(content_size: Size, top_inset: f32, scrollbar_width: f32) -> Rect {
+    Rect::from_min_max(
+        (content_size.0 - scrollbar_width - MINIMAP_WIDTH, top_inset),
+        (
+            content_size.0 - scrollbar_width,
+            content_size.1.max(top_inset),
+        ),
+    )
+}
+
+fn marker_y(anchor_y: f32, reserved_height: f32, strip: &Rect) -> f32 {
+    strip.pos.1 + (anchor_y / reserved_height) * strip.size.1
+}
+
+/// Rects for all of the markers in screen coordinates
+pub fn markers(anchors: &HashMap<String, f32>, reserved_height: f32, strip: &Rect) -> Vec<Rect> {
+    anchors
+        .values()
+        .map(|&anchor_y| {
+            let y = marker_y(anchor_y, reserved_height, strip);
+            Rect::new(
+                (strip.pos.0, y - MARKER_HEIGHT / 2.),
+                (strip.size.0, MARKER_HEIGHT),
+            )
+        })
+        .collect()
+}
+
+/// Maps a click at `click_y` (in screen coordinates) to the position that should be scrolled to
+pub fn click_to_scroll(
+    click_y: f32,
+    anchors: &HashMap<String, f32>,
+    reserved_height: f32,
+    strip: &Rect,
+) -> f32 {
+    let nearest_marker = anchors
+        .values()
+        .map(|&anchor_y| {
+            let dist = (marker_y(anchor_y, reserved_height, strip) - click_y).abs();
+            (anchor_y, dist)
+        })
+        .filter(|&(_, dist)| dist <= SNAP_DISTANCE)
+        .min_by(|(_, a), (_, b)| a.total_cmp(b));
+
+    match nearest_marker {
+        Some((anchor_y, _)) => anchor_y,
+        None => ((click_y - strip.pos.1) / strip.size.1) * reserved_height,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn anchors() -> HashMap<String, f32> {
+        [("#intro", 0.), ("#usage", 1_000.), ("#faq", 3_600.)]
+            .into_iter()
+            .map(|(name, y)| (name.to_owned(), y))
+            .collect()
+    }
+
+    #[test]
+    fn click_to_scroll_position() {
+        let anchors = anchors();
+        let reserved_height = 4_000.;
+        let strip = bounds((800., 400.), 0., 25.);
+
+        // Markers are scaled down by 10x, so `#usage` sits at 100px
+        assert_eq!(
+            click_to_scroll(100., &anchors, reserved_height, &strip),
+            1_000.
+        );
+        // Close enough to snap to the marker
+        assert_eq!(
+            click_to_scroll(103., &anchors, reserved_height, &strip),
+            1_000.
+        );
+        assert_eq!(
+            click_to_scroll(357., &anchors, reserved_height, &strip),
+            3_600.
+        );
+        // Anywhere else is proportional to the whole document
+        assert_eq!(
+            click_to_scroll(200., &anchors, reserved_height, &strip),
+            2_000.
+        );
+        assert_eq!(
+            click_to_scroll(200., &HashMap::new(), reserved_height, &strip),
+            2_000.
+        );
+    }
+
+    #[test]
+    fn top_inset_pushes_markers_down() {
+        let anchors = anchors();
+        let reserved_height = 4_000.;
+        // A 40px tab bar leaves a 360px tall strip
+        let strip = bounds((800., 400.), 40., 25.);
+        assert_eq!(strip.pos.1, 40.);
+
+        let mut marker_ys: Vec<_> = markers(&anchors, reserved_height, &strip)
+            .iter()
+            .map(|marker| marker.pos.1 + MARKER_HEIGHT / 2.)
+            .collect();
+        marker_ys.sort_by(f32::total_cmp);
+        assert_eq!(marker_ys, [40., 130., 364.]);
+        assert_eq!(
+            click_to_scroll(130., &anchors, reserved_height, &strip),
+            1_000.
+        );
+        assert_eq!(
+            click_to_scroll(220., &anchors, reserved_height, &strip),
+            2_000.
+        );
+    }
+
+    #[test]
+    fn markers_in_bounds() {
+        let strip = bounds((800., 400.), 0., 25.);
+        assert_eq!(strip.max().0, 775.);
+        for marker in markers(&anchors(), 4_000., &strip) {
+            assert_eq!(marker.pos.0, strip.pos.0);
+            assert_eq!(marker.size.0, strip.size.0);
+            assert!(marker.pos.1 + MARKER_HEIGHT / 2. <= strip.max().1);
+        }
+    }
+}
@@ -0,0 +1,56 @@
+//! Modes that temporarily take over how input is handled (e.g. typing out a search query)
+//!
+//! At most one mode is active at a time and dismissing (`Esc` by default) always clears the active
+//! mode before falling back to any other behavior
+
+use crate::anchor_jump::AnchorJump;
+use crate::keybindings::action::Action;
+use crate::opts::EscapeFallback;
+use crate::search::Search;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Mode {
+    Search(Search),
+    GoToAnchor(AnchorJump),
+}
+
+#[derive(Debug, Default)]
+pub struct ActiveMode(Option<Mode>);
+
+impl ActiveMode {
+    pub fn get_mut(&mut self) -> Option<&mut Mode> {
+        self.0.as_mut()
+    }
+
+    pub fn set(&mut self, mode: Mode) {
+        self.0 = Some(mode);
+    }
+
+    /// Clears the active mode, returning the fallback action if there was no mode to clear
+    pub fn dismiss(&mut self, fallback: EscapeFallback) -> Option<Action> {
+        match self.0.take() {
+            Some(mode) => {
+                tracing::debug!("Dismissed mode: {mode:?}");
+                None
+            }
+            None => fallback.action(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dismiss_clears_mode_first() {
+        let mut mode = ActiveMode::default();
+        mode.set(Mode::Search(Search::new(0.)));
+
+        assert_eq!(mode.dismiss(EscapeFallback::Quit), None);
+        assert_eq!(mode.get_mut(), None);
+        // Nothing left to dismiss, so now we fall back
+        assert_eq!(mode.dismiss(EscapeFallback::Quit), Some(Action::Quit));
+        assert_eq!(mode.dismiss(EscapeFallback::Nothing), None);
+    }
+}
@@ -3,6 +3,8 @@ use clap::{
 };
 use serde::Deserialize;
 use std::array;
+use std::fmt;
+use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::str::FromStr;
 
@@ -12,6 +14,8 @@ pub enum ThemeType {
     Auto,
     Dark,
     Light,
+    /// High contrast black and white
+    Mono,
 }
 
 impl ThemeType {
@@ -20,13 +24,14 @@ impl ThemeType {
             Self::Auto => "auto",
             Self::Dark => "dark",
             Self::Light => "light",
+            Self::Mono => "mono",
         }
     }
 }
 
 impl ValueEnum for ThemeType {
     fn value_variants<'a>() -> &'a [Self] {
-        &[Self::Auto, Self::Dark, Self::Light]
+        &[Self::Auto, Self::Dark, Self::Light, Self::Mono]
     }
 
     fn to_possible_value<'a>(&self) -> Option<PossibleValue> {
@@ -58,6 +63,40 @@ impl FromStr for Position {
     }
 }
 
+/// Where to open the window, either at explicit coordinates e.g. `100,200` or placed on a monitor
+/// e.g. `center`
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum WindowPosition {
+    At(Position),
+    Placed(Placement),
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Placement {
+    /// Centered on the primary monitor
+    Center,
+    /// Centered on the mouse cursor (as far as it fits on the cursor's monitor). Only X11 lets us
+    /// find the cursor, so everywhere else falls back to `Center`
+    Cursor,
+}
+
+impl FromStr for WindowPosition {
+    type Err = &'static str;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "center" => Ok(Self::Placed(Placement::Center)),
+            "cursor" => Ok(Self::Placed(Placement::Cursor)),
+            _ if !input.contains(',') => {
+                Err("Invalid window position: expected <x>,<y>, center, or cursor")
+            }
+            _ => input.parse().map(Self::At),
+        }
+    }
+}
+
 #[derive(Deserialize, Clone, Debug, Default, PartialEq)]
 pub struct Size {
     pub width: u32,
@@ -81,6 +120,100 @@ impl FromStr for Size {
     }
 }
 
+/// A percentage of some size e.g. `70%`
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(try_from = "String")]
+pub struct Percent(f32);
+
+impl Percent {
+    /// Scales both dimensions of `size` by the percentage
+    pub fn of(self, size: Size) -> Size {
+        let scale = |dim| self.of_length(dim as f32).round() as u32;
+        Size {
+            width: scale(size.width),
+            height: scale(size.height),
+        }
+    }
+
+    pub fn of_length(self, length: f32) -> f32 {
+        length * self.0 / 100.
+    }
+}
+
+impl fmt::Display for Percent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}%", self.0)
+    }
+}
+
+impl FromStr for Percent {
+    type Err = &'static str;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let percent: f32 = input
+            .strip_suffix('%')
+            .ok_or("Invalid format for Percent: expected format <percent>%")?
+            .parse()
+            .map_err(|_| "Invalid percent: not a valid number")?;
+        if percent > 0. && percent <= 100. {
+            Ok(Self(percent))
+        } else {
+            Err("Invalid percent: must be within (0%, 100%]")
+        }
+    }
+}
+
+impl TryFrom<String> for Percent {
+    type Error = &'static str;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+/// The maximum width of the page, either in pixels e.g. `800` or relative to the window's width
+/// e.g. `60%`
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum PageWidth {
+    Pixels(f32),
+    Relative(Percent),
+}
+
+impl PageWidth {
+    /// The page's width in pixels for a window that's `window_width` wide
+    pub fn resolve(self, window_width: f32) -> f32 {
+        match self {
+            Self::Pixels(width) => width,
+            Self::Relative(percent) => percent.of_length(window_width),
+        }
+    }
+}
+
+impl fmt::Display for PageWidth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Pixels(width) => write!(f, "{width}"),
+            Self::Relative(percent) => write!(f, "{percent}"),
+        }
+    }
+}
+
+impl FromStr for PageWidth {
+    type Err = &'static str;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        if input.ends_with('%') {
+            input.parse().map(Self::Relative)
+        } else {
+            input
+                .parse()
+                .map(Self::Pixels)
+                .map_err(|_| "Invalid page width: expected <pixels> or <percent>%")
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Clone, Parser)]
 #[command(version, about, arg_required_else_help(true))]
 #[clap(args_conflicts_with_subcommands = true)]
@@ -113,6 +246,7 @@ impl Cli {
 #[derive(Subcommand, Debug, PartialEq, Clone)]
 pub enum Commands {
     View(View),
+    Render(Render),
     #[command(subcommand)]
     Config(ConfigCmd),
 }
@@ -125,10 +259,18 @@ pub struct View {
     #[arg(value_name = "FILE", required = true)]
     pub file_path: PathBuf,
 
+    /// More markdown files to open in tabs alongside the first one
+    #[arg(value_name = "FILES")]
+    pub extra_files: Vec<PathBuf>,
+
     /// Theme to use when rendering
     #[arg(short = 't', long = "theme", value_parser = value_parser!(ThemeType))]
     pub theme: Option<ThemeType>,
 
+    /// TOML file with theme colors to use for both the light and dark themes
+    #[arg(long = "theme-file", value_name = "PATH")]
+    pub theme_file: Option<PathBuf>,
+
     /// Factor to scale rendered file by [default: OS defined window scale factor]
     #[arg(short = 's', long = "scale")]
     pub scale: Option<f32>,
@@ -137,17 +279,88 @@ pub struct View {
     #[arg(short = 'c', long = "config")]
     pub config: Option<PathBuf>,
 
-    /// Maximum width of page in pixels
-    #[arg(short = 'w', long = "page-width")]
-    pub page_width: Option<f32>,
+    /// Maximum width of page in pixels or as a percentage of the window's width
+    #[arg(short = 'w', long = "page-width", value_parser = value_parser!(PageWidth))]
+    pub page_width: Option<PageWidth>,
 
-    /// Position of the opened window <x>,<y>
-    #[arg(short = 'p', long = "win-pos", value_parser = value_parser!(Position))]
-    pub position: Option<Position>,
+    /// Position of the opened window <x>,<y>, or `center` or `cursor` to place it on the monitor
+    /// (`cursor` only works on X11 and centers the window elsewhere)
+    #[arg(short = 'p', long = "win-pos", value_parser = value_parser!(WindowPosition))]
+    pub position: Option<WindowPosition>,
 
     /// Size of the opened window <width>x<height>
     #[arg(long = "win-size", value_parser = value_parser!(Size))]
     pub size: Option<Size>,
+
+    /// Don't watch the file for changes
+    #[arg(long = "no-watch")]
+    pub no_watch: bool,
+
+    /// Print the HTML that the file gets converted to and exit
+    #[arg(long = "dump-html")]
+    pub dump_html: bool,
+
+    /// Print the text of the rendered document and exit
+    #[arg(long = "dump-text")]
+    pub dump_text: bool,
+
+    /// Anchor to scroll to once the file is loaded e.g. `#installation`
+    #[arg(long = "anchor")]
+    pub anchor: Option<String>,
+
+    /// Serve metrics for Prometheus to scrape at http://<ADDRESS>/metrics (needs inlyne to be
+    /// built with the `prometheus` feature)
+    #[arg(long = "metrics-address", value_name = "ADDRESS")]
+    pub metrics_address: Option<SocketAddr>,
+}
+
+/// Render a markdown file to a PNG image without opening a window
+#[derive(ClapArgs, PartialEq, Debug, Clone)]
+pub struct Render {
+    /// Path to the markdown file
+    #[arg(value_name = "FILE")]
+    pub input: PathBuf,
+
+    /// Path to write the PNG to
+    #[arg(short = 'o', long = "output", value_name = "PATH")]
+    pub output: PathBuf,
+
+    /// Width of the image in pixels
+    #[arg(long = "width", default_value_t = 800)]
+    pub width: u32,
+
+    /// Height of the image in pixels
+    #[arg(long = "height", default_value_t = 600)]
+    pub height: u32,
+
+    /// Make the image as tall as the whole document instead of a single viewport
+    #[arg(long = "full-height")]
+    pub full_height: bool,
+
+    /// Theme to use when rendering
+    #[arg(short = 't', long = "theme", value_parser = value_parser!(ThemeType))]
+    pub theme: Option<ThemeType>,
+
+    /// Factor to scale rendered file by [default: 1]
+    #[arg(short = 's', long = "scale")]
+    pub scale: Option<f32>,
+
+    /// Configuration file to use
+    #[arg(short = 'c', long = "config")]
+    pub config: Option<PathBuf>,
+}
+
+impl Render {
+    /// The options for viewing the same file, which the rest of the settings get loaded for
+    pub fn to_view(&self) -> View {
+        View {
+            file_path: self.input.clone(),
+            theme: self.theme,
+            scale: self.scale,
+            config: self.config.clone(),
+            ..Default::default()
+        }
+    }
 }
 
 /// Configuration related things
@@ -155,4 +368,6 @@ pub struct View {
 pub enum ConfigCmd {
     /// Opens the configuration file in the default text editor
     Open,
+    /// Prints the configuration that's in effect when viewing a file with the given options
+    Print(View),
 }
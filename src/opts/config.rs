@@ -1,40 +1,75 @@
+use std::collections::HashMap;
+use std::fmt;
 use std::fs::{create_dir_all, read_to_string};
 use std::io::Write;
+use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
 
-use super::{Position, Size, ThemeType};
+use super::{PageWidth, Percent, Size, ThemeType, WindowPosition};
 use crate::color;
-use crate::keybindings::Keybindings;
+use crate::keybindings::action::Action;
+use crate::keybindings::{Keybindings, MouseBindings};
+use crate::link_command::LinkCommand;
+use crate::panic_hook::AutoOpen;
+use crate::renderer::DEFAULT_SCROLLBAR_WIDTH;
 
 use anyhow::Context;
-use serde::Deserialize;
+use serde::de::{IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer};
 use syntect::highlighting::Theme as SyntectTheme;
+use toml::Spanned;
 
 #[derive(Deserialize, Debug, PartialEq, Eq, Default, Clone)]
-#[serde(rename_all = "kebab-case")]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
 pub struct FontOptions {
     #[serde(default)]
     pub regular_font: Option<String>,
     #[serde(default)]
     pub monospace_font: Option<String>,
+    /// Families to look for glyphs in when the main font lacks them, tried in order
+    #[serde(default)]
+    pub fallback_families: Vec<String>,
+    /// Family that gets tried for emoji before any of the `fallback_families`
+    #[serde(default)]
+    pub emoji_family: Option<String>,
+    /// Makes up bold and italic faces for fonts that don't have them
+    #[serde(default)]
+    pub synthetic_emphasis: bool,
 }
 
-#[derive(Deserialize, Debug, Default, PartialEq)]
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
 #[serde(default, rename_all = "kebab-case")]
 pub struct OptionalTheme {
     pub text_color: Option<u32>,
     pub background_color: Option<u32>,
     pub code_color: Option<u32>,
+    pub code_border_color: Option<u32>,
+    pub line_number_color: Option<u32>,
+    pub kbd_background_color: Option<u32>,
+    pub kbd_border_color: Option<u32>,
     pub quote_block_color: Option<u32>,
     pub link_color: Option<u32>,
     pub select_color: Option<u32>,
     pub checkbox_color: Option<u32>,
+    pub search_highlight_color: Option<u32>,
+    pub scrollbar_color: Option<u32>,
+    pub alert_note_color: Option<u32>,
+    pub alert_tip_color: Option<u32>,
+    pub alert_important_color: Option<u32>,
+    pub alert_warning_color: Option<u32>,
+    pub alert_caution_color: Option<u32>,
     pub code_highlighter: Option<color::SyntaxTheme>,
 }
 
 impl OptionalTheme {
     pub fn merge(self, other: color::Theme) -> anyhow::Result<color::Theme> {
         let code_highlighter = match self.code_highlighter {
+            // A typo'd name shouldn't keep inlyne from starting
+            Some(theme @ color::SyntaxTheme::Unknown(_)) => {
+                let err = SyntectTheme::try_from(theme).unwrap_err();
+                tracing::warn!("{err}. Falling back to the default code highlighter");
+                other.code_highlighter
+            }
             Some(theme) => SyntectTheme::try_from(theme)?,
             None => other.code_highlighter,
         };
@@ -43,13 +78,165 @@ impl OptionalTheme {
             text_color: self.text_color.unwrap_or(other.text_color),
             background_color: self.background_color.unwrap_or(other.background_color),
             code_color: self.code_color.unwrap_or(other.code_color),
+            code_border_color: self.code_border_color.unwrap_or(other.code_border_color),
+            line_number_color: self.line_number_color.unwrap_or(other.line_number_color),
+            kbd_background_color: self
+                .kbd_background_color
+                .unwrap_or(other.kbd_background_color),
+            kbd_border_color: self.kbd_border_color.unwrap_or(other.kbd_border_color),
             quote_block_color: self.quote_block_color.unwrap_or(other.quote_block_color),
             link_color: self.link_color.unwrap_or(other.link_color),
             select_color: self.select_color.unwrap_or(other.select_color),
             checkbox_color: self.checkbox_color.unwrap_or(other.checkbox_color),
+            search_highlight_color: self
+                .search_highlight_color
+                .unwrap_or(other.search_highlight_color),
+            scrollbar_color: self.scrollbar_color.unwrap_or(other.scrollbar_color),
+            alert_note_color: self.alert_note_color.unwrap_or(other.alert_note_color),
+            alert_tip_color: self.alert_tip_color.unwrap_or(other.alert_tip_color),
+            alert_important_color: self
+                .alert_important_color
+                .unwrap_or(other.alert_important_color),
+            alert_warning_color: self
+                .alert_warning_color
+                .unwrap_or(other.alert_warning_color),
+            alert_caution_color: self
+                .alert_caution_color
+                .unwrap_or(other.alert_caution_color),
             code_highlighter,
         })
     }
+
+    /// Loads a standalone theme file, which holds the same keys as a `[light-theme]` or
+    /// `[dark-theme]` section
+    pub fn load_from_file(path: &Path) -> anyhow::Result<Self> {
+        let contents = read_to_string(path)
+            .with_context(|| format!("Failed to read theme file at '{}'", path.display()))?;
+        Self::load_from_str(&contents)
+            .with_context(|| format!("Invalid theme file at '{}'", path.display()))
+    }
+
+    pub fn load_from_str(s: &str) -> anyhow::Result<Self> {
+        let table: toml::Table = toml::from_str(s)?;
+        for key in Self::unknown_keys(&table) {
+            tracing::warn!("Ignoring unknown key in theme file: {key}");
+        }
+        Ok(table.try_into()?)
+    }
+
+    /// Keys that don't set anything, typically from typos
+    pub(super) fn unknown_keys(table: &toml::Table) -> Vec<&str> {
+        table
+            .iter()
+            .filter(|(key, value)| {
+                // Every field is optional, so a known key always leaves its mark. Known keys with
+                // invalid values are left for the error from deserializing the whole thing
+                let single = toml::Table::from_iter([(key.to_string(), (*value).clone())]);
+                single
+                    .try_into::<Self>()
+                    .map_or(false, |theme| theme == Self::default())
+            })
+            .map(|(key, _)| key.as_str())
+            .collect()
+    }
+}
+
+/// Where each key starts in the config's source, with keys within a table as `table.key`
+#[derive(Default)]
+struct KeyLocations(HashMap<String, usize>);
+
+impl KeyLocations {
+    fn find(source: &str) -> Self {
+        toml::from_str(source).unwrap_or_default()
+    }
+
+    fn line(&self, source: &str, path: &str) -> Option<usize> {
+        let offset = *self.0.get(path)?;
+        Some(source.get(..offset)?.matches('\n').count() + 1)
+    }
+}
+
+impl<'de> Deserialize<'de> for KeyLocations {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct TopLevel;
+
+        impl<'de> Visitor<'de> for TopLevel {
+            type Value = KeyLocations;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a table")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut locations = HashMap::new();
+                while let Some(key) = map.next_key::<Spanned<String>>()? {
+                    let SubKeys(sub_keys) = map.next_value()?;
+                    for (sub_key, offset) in sub_keys {
+                        locations.insert(format!("{}.{sub_key}", key.get_ref()), offset);
+                    }
+                    let offset = key.span().start;
+                    locations.insert(key.into_inner(), offset);
+                }
+                Ok(KeyLocations(locations))
+            }
+        }
+
+        deserializer.deserialize_map(TopLevel)
+    }
+}
+
+/// The keys of a table and where each one starts. Nothing for any other value
+struct SubKeys(Vec<(String, usize)>);
+
+impl<'de> Deserialize<'de> for SubKeys {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct AnyValue;
+
+        impl<'de> Visitor<'de> for AnyValue {
+            type Value = SubKeys;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("any value")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut keys = Vec::new();
+                while let Some(key) = map.next_key::<Spanned<String>>()? {
+                    map.next_value::<IgnoredAny>()?;
+                    let offset = key.span().start;
+                    keys.push((key.into_inner(), offset));
+                }
+                Ok(SubKeys(keys))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                while seq.next_element::<IgnoredAny>()?.is_some() {}
+                Ok(SubKeys(Vec::new()))
+            }
+
+            fn visit_bool<E>(self, _: bool) -> Result<Self::Value, E> {
+                Ok(SubKeys(Vec::new()))
+            }
+
+            fn visit_i64<E>(self, _: i64) -> Result<Self::Value, E> {
+                Ok(SubKeys(Vec::new()))
+            }
+
+            fn visit_u64<E>(self, _: u64) -> Result<Self::Value, E> {
+                Ok(SubKeys(Vec::new()))
+            }
+
+            fn visit_f64<E>(self, _: f64) -> Result<Self::Value, E> {
+                Ok(SubKeys(Vec::new()))
+            }
+
+            fn visit_str<E>(self, _: &str) -> Result<Self::Value, E> {
+                Ok(SubKeys(Vec::new()))
+            }
+        }
+
+        deserializer.deserialize_any(AnyValue)
+    }
 }
 
 #[derive(Deserialize, Debug, PartialEq)]
@@ -67,47 +254,207 @@ impl Default for LinesToScroll {
     }
 }
 
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct IndexFiles(pub Vec<String>);
+
+impl From<IndexFiles> for Vec<String> {
+    fn from(value: IndexFiles) -> Self {
+        value.0
+    }
+}
+
+impl Default for IndexFiles {
+    fn default() -> Self {
+        Self(vec!["README.md".into(), "index.md".into()])
+    }
+}
+
 #[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
 pub struct KeybindingsSection {
     #[serde(default)]
     pub base: Keybindings,
     pub extra: Option<Keybindings>,
+    #[serde(default)]
+    pub mouse: MouseBindings,
 }
 
 #[derive(Deserialize, Clone, Debug, PartialEq)]
 #[serde(rename_all = "kebab-case")]
 pub enum MetricsExporter {
     Log,
+    Prometheus,
     #[cfg(inlyne_tcp_metrics)]
     Tcp,
 }
 
+/// What dismissing does when there is no active mode to dismiss
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum EscapeFallback {
+    Nothing,
+    #[default]
+    Quit,
+}
+
+impl EscapeFallback {
+    pub fn action(self) -> Option<Action> {
+        match self {
+            Self::Nothing => None,
+            Self::Quit => Some(Action::Quit),
+        }
+    }
+}
+
+/// What gets drawn behind images to keep transparent ones visible on any theme
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum ImageBackground {
+    /// A solid color e.g. `0xffffff`
+    Color(u32),
+    Pattern(BackgroundPattern),
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum BackgroundPattern {
+    Checkerboard,
+}
+
+/// Whether images get drawn underneath or on top of text
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ImageLayer {
+    #[default]
+    BelowText,
+    AboveText,
+}
+
+/// What takes the place of an image that fails to load
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum OnImageError {
+    /// The broken image icon
+    #[default]
+    Placeholder,
+    /// Nothing at all
+    Hide,
+    /// The image's alt text
+    Alt,
+}
+
+/// Where the view ends up when the file gets replaced instead of edited in place (e.g. by an
+/// editor's atomic save)
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum OnFileReplaced {
+    /// Stay at the same spot like any other reload
+    #[default]
+    KeepPosition,
+    /// Go back to the top like opening a new file
+    ToTop,
+}
+
+/// What happens when the window's last tab gets closed
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum CloseLastTab {
+    /// Close the window along with it
+    #[default]
+    Quit,
+    /// Keep the window open with a placeholder in place of the document
+    Empty,
+}
+
 #[derive(Deserialize, Clone, Debug, Default, PartialEq)]
-#[serde(default, rename_all = "kebab-case")]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
 pub struct DebugSection {
     pub metrics: Option<MetricsExporter>,
+    pub metrics_address: Option<SocketAddr>,
     pub render_element_bounds: bool,
 }
 
 #[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ScrollbarSection {
+    pub width: f32,
+    /// Overrides the theme's `scrollbar-color`
+    pub color: Option<u32>,
+}
+
+impl Default for ScrollbarSection {
+    fn default() -> Self {
+        Self {
+            width: DEFAULT_SCROLLBAR_WIDTH,
+            color: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
 pub struct Window {
-    pub position: Option<Position>,
+    pub position: Option<WindowPosition>,
     pub size: Option<Size>,
 }
 
 #[derive(Deserialize, Debug, Default, PartialEq)]
-#[serde(default, rename_all = "kebab-case")]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
 pub struct Config {
     pub theme: Option<ThemeType>,
+    pub theme_file: Option<PathBuf>,
     pub scale: Option<f32>,
-    pub page_width: Option<f32>,
+    pub page_width: Option<PageWidth>,
+    pub max_line_chars: Option<usize>,
     pub lines_to_scroll: LinesToScroll,
+    pub smooth_scroll: bool,
+    pub quote_block_background: Option<bool>,
     pub light_theme: Option<OptionalTheme>,
     pub dark_theme: Option<OptionalTheme>,
+    pub mono_theme: Option<OptionalTheme>,
     pub font_options: Option<FontOptions>,
     pub keybindings: KeybindingsSection,
     pub debug: DebugSection,
     pub window: Option<Window>,
+    pub scrollbar: ScrollbarSection,
+    pub open_crash_report: AutoOpen,
+    pub index_files: IndexFiles,
+    pub minimap: bool,
+    pub status_bar: bool,
+    pub toc: bool,
+    pub compact_kv_tables: bool,
+    pub reload_flash: bool,
+    pub watch: Option<bool>,
+    pub on_file_replaced: OnFileReplaced,
+    pub close_last_tab: CloseLastTab,
+    pub fit_single_image: bool,
+    pub show_whitespace: bool,
+    pub toggle_checkboxes: bool,
+    pub code_block_border: Option<f32>,
+    pub code_block_wrap: Option<bool>,
+    pub code_line_numbers: bool,
+    pub image_background: Option<ImageBackground>,
+    pub image_layer: ImageLayer,
+    pub default_size: Option<Percent>,
+    pub remember_window: bool,
+    pub allow_remote_images: Option<bool>,
+    pub max_image_bytes: Option<usize>,
+    pub max_cached_images: Option<usize>,
+    pub image_batch_ms: Option<u64>,
+    pub lazy_image_distance: Option<f32>,
+    pub math: bool,
+    pub pause_when_unfocused: bool,
+    pub max_nesting_depth: Option<usize>,
+    pub max_table_rows: Option<usize>,
+    pub max_table_columns: Option<usize>,
+    pub inline_image_baseline: bool,
+    pub preserve_blank_lines: bool,
+    pub preserve_spaces: bool,
+    pub on_image_error: OnImageError,
+    pub link_references: bool,
+    pub anchors_in_new_window: bool,
+    pub link_command: Option<LinkCommand>,
+    pub escape_fallback: EscapeFallback,
 }
 
 impl Config {
@@ -116,13 +463,106 @@ impl Config {
         Ok(config)
     }
 
+    /// Loads everything that it can from the config, skipping (and warning about) any keys that
+    /// are unknown or hold invalid values instead of failing altogether
+    ///
+    /// Only a config that isn't valid TOML at all is an error
+    pub fn load_from_str_lenient(s: &str) -> anyhow::Result<Self> {
+        let mut table: toml::Table = toml::from_str(s)?;
+        for problem in Self::remove_invalid_keys(s, &mut table) {
+            tracing::warn!("Ignoring part of the config: {problem}");
+        }
+        Ok(table.try_into()?)
+    }
+
+    /// Removes the keys that fail to load, returning a description of each one
+    ///
+    /// Each top-level key gets loaded on its own since every field has a default. Sections that
+    /// fail get the same treatment for each of their keys, so that one bad key in a section
+    /// doesn't take the rest of it down too
+    fn remove_invalid_keys(source: &str, table: &mut toml::Table) -> Vec<String> {
+        fn load_error(table: toml::Table) -> Option<String> {
+            let err = table.try_into::<Config>().err()?.to_string();
+            let err = err.trim();
+            // The list of every possible key is more noise than help
+            Some(match err.strip_prefix("unknown field ") {
+                Some(rest) => {
+                    let key = rest.split_once(", expected").map_or(rest, |(key, _)| key);
+                    format!("unknown key {key}")
+                }
+                None => err.to_owned(),
+            })
+        }
+
+        let locations = KeyLocations::find(source);
+        let describe = |path: &str, err: &str| match locations.line(source, path) {
+            Some(line) => format!("`{path}` (line {line}): {err}"),
+            None => format!("`{path}`: {err}"),
+        };
+
+        let mut problems = Vec::new();
+        let keys: Vec<_> = table.keys().cloned().collect();
+        for key in keys {
+            let single = |value: toml::Value| toml::Table::from_iter([(key.clone(), value)]);
+            let value = &table[&key];
+
+            if let (toml::Value::Table(section), "light-theme" | "dark-theme" | "mono-theme") =
+                (value, key.as_str())
+            {
+                // Themes ignore unknown keys on their own, so they need calling out separately
+                let unknown: Vec<_> = OptionalTheme::unknown_keys(section)
+                    .into_iter()
+                    .map(ToOwned::to_owned)
+                    .collect();
+                for sub_key in unknown {
+                    let path = format!("{key}.{sub_key}");
+                    problems.push(describe(&path, &format!("unknown key `{sub_key}`")));
+                    if let Some(toml::Value::Table(section)) = table.get_mut(&key) {
+                        section.remove(&sub_key);
+                    }
+                }
+            }
+
+            let value = table[&key].clone();
+            let Some(err) = load_error(single(value.clone())) else {
+                continue;
+            };
+            let toml::Value::Table(mut section) = value else {
+                problems.push(describe(&key, &err));
+                table.remove(&key);
+                continue;
+            };
+            let sub_keys: Vec<_> = section.keys().cloned().collect();
+            for sub_key in sub_keys {
+                let sub_value = section[&sub_key].clone();
+                let sub_table = toml::Table::from_iter([(sub_key.clone(), sub_value)]);
+                if let Some(err) = load_error(single(toml::Value::Table(sub_table))) {
+                    problems.push(describe(&format!("{key}.{sub_key}"), &err));
+                    section.remove(&sub_key);
+                }
+            }
+            // Some sections only make sense as a whole
+            match load_error(single(toml::Value::Table(section.clone()))) {
+                Some(err) => {
+                    problems.push(describe(&key, &err));
+                    table.remove(&key);
+                }
+                None => {
+                    table.insert(key, toml::Value::Table(section));
+                }
+            }
+        }
+        problems
+    }
+
     pub fn load_from_file(path: &Path) -> anyhow::Result<Self> {
         let config_content = read_to_string(path).context(format!(
             "Failed to read configuration file at '{}'",
             path.display()
         ))?;
 
-        Self::load_from_str(&config_content)
+        Self::load_from_str_lenient(&config_content)
+            .with_context(|| format!("Invalid configuration file at '{}'", path.display()))
     }
 
     pub fn load_from_system() -> anyhow::Result<Self> {
@@ -157,8 +597,9 @@ mod tests {
     #[test]
     fn defaults_file_is_in_sync() {
         // Load the provided default toml file and compare with what we generate to make sure the
-        // defaults stay in sync
-        let mut config = Config::load_from_file(Path::new("inlyne.default.toml")).unwrap();
+        // defaults stay in sync. Loading strictly catches any keys that don't exist
+        let default_toml = read_to_string("inlyne.default.toml").unwrap();
+        let mut config = Config::load_from_str(&default_toml).unwrap();
 
         // Swap out some of the values to compare
         let theme = config.theme.take().unwrap();
@@ -177,4 +618,80 @@ mod tests {
             color::Theme::light_default()
         );
     }
+
+    #[test]
+    fn bad_keys_dont_discard_the_rest() {
+        let source = r#"
+minmap = true
+minimap = true
+page-width = "wide"
+lines-to-scroll = 5.0
+
+[dark-theme]
+txet-color = 0x123456
+link-color = 0xABCDEF
+code-highlighter = "doesnt-exist"
+
+[window]
+positon = [1, 2]
+size = [300, 400]
+
+[scrollbar]
+width = "thin"
+"#;
+        // Loading strictly fails on the very first problem
+        assert!(Config::load_from_str(source).is_err());
+
+        let mut table = toml::from_str(source).unwrap();
+        let mut problems = Config::remove_invalid_keys(source, &mut table);
+        problems.sort();
+        let config: Config = table.try_into().unwrap();
+        assert!(config.minimap);
+        assert_eq!(config.lines_to_scroll, LinesToScroll(5.));
+        let dark_theme = config.dark_theme.unwrap();
+        assert_eq!(dark_theme.link_color, Some(0xABCDEF));
+        assert_eq!(
+            dark_theme.code_highlighter,
+            Some(color::SyntaxTheme::Unknown("doesnt-exist".into()))
+        );
+        let (theme, warnings) = crate::test_utils::log::capture_warnings(|| {
+            dark_theme.merge(color::Theme::dark_default()).unwrap()
+        });
+        assert_eq!(theme.link_color, 0xABCDEF);
+        assert_eq!(
+            theme.code_highlighter,
+            color::Theme::dark_default().code_highlighter
+        );
+        assert_eq!(warnings.len(), 1, "{warnings:?}");
+        assert!(
+            warnings[0].starts_with("\"doesnt-exist\" didn't match any of the expected variants")
+                && warnings[0].ends_with("Falling back to the default code highlighter"),
+            "{warnings:?}"
+        );
+        let window = config.window.unwrap();
+        assert_eq!((window.position, window.size.unwrap().width), (None, 300));
+        assert_eq!(config.scrollbar, ScrollbarSection::default());
+
+        let problem_keys: Vec<_> = problems
+            .iter()
+            .map(|problem| problem.split_once(':').unwrap().0)
+            .collect();
+        assert_eq!(
+            problem_keys,
+            [
+                "`dark-theme.txet-color` (line 8)",
+                "`minmap` (line 2)",
+                "`page-width` (line 4)",
+                "`scrollbar.width` (line 17)",
+                "`window.positon` (line 13)",
+            ]
+        );
+        assert!(
+            problems[1].ends_with("unknown key `minmap`"),
+            "{problems:?}"
+        );
+
+        // Broken TOML can't be salvaged
+        assert!(Config::load_from_str_lenient("minimap = ").is_err());
+    }
 }
@@ -1,18 +1,35 @@
 mod cli;
 mod config;
+mod print;
 #[cfg(test)]
 mod tests;
 
 use std::{
+    net::SocketAddr,
     path::Path,
     sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
 };
 
 use crate::color;
-pub use cli::{Cli, Commands, ConfigCmd, Position, Size, ThemeType, View};
-pub use config::{Config, DebugSection, FontOptions, KeybindingsSection, MetricsExporter};
+use crate::image;
+use crate::image_batch;
+use crate::interpreter::DEFAULT_MAX_NESTING_DEPTH;
+use crate::link_command::LinkCommand;
+use crate::metrics;
+use crate::table::{self, TableLimits};
+pub use cli::{
+    Cli, Commands, ConfigCmd, PageWidth, Percent, Placement, Position, Render, Size, ThemeType,
+    View, WindowPosition,
+};
+pub use config::{
+    BackgroundPattern, CloseLastTab, Config, DebugSection, EscapeFallback, FontOptions,
+    ImageBackground, ImageLayer, KeybindingsSection, MetricsExporter, OnFileReplaced, OnImageError,
+    OptionalTheme, ScrollbarSection,
+};
 
 use crate::history::History;
+use crate::panic_hook::AutoOpen;
 use anyhow::Result;
 use clap::Parser;
 use serde::Deserialize;
@@ -34,6 +51,7 @@ pub enum ResolvedTheme {
     Dark,
     #[default]
     Light,
+    Mono,
 }
 
 impl ResolvedTheme {
@@ -42,32 +60,131 @@ impl ResolvedTheme {
             ThemeType::Auto => Self::try_detect(),
             ThemeType::Dark => Some(Self::Dark),
             ThemeType::Light => Some(Self::Light),
+            ThemeType::Mono => Some(Self::Mono),
         }
     }
 
-    fn try_detect() -> Option<Self> {
+    pub fn try_detect() -> Option<Self> {
         match dark_light::detect() {
             dark_light::Mode::Default => None,
             dark_light::Mode::Dark => Some(Self::Dark),
             dark_light::Mode::Light => Some(Self::Light),
         }
     }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Dark => "dark",
+            Self::Light => "light",
+            Self::Mono => "mono",
+        }
+    }
+}
+
+/// The light and dark themes (including any configured overrides) to switch between when
+/// following the system's color scheme
+#[derive(Clone, PartialEq)]
+pub struct SystemThemes {
+    pub light: color::Theme,
+    pub dark: color::Theme,
+}
+
+impl SystemThemes {
+    pub fn get(&self, color_scheme: Option<ResolvedTheme>) -> &color::Theme {
+        match color_scheme {
+            Some(ResolvedTheme::Dark) => &self.dark,
+            None | Some(ResolvedTheme::Light | ResolvedTheme::Mono) => &self.light,
+        }
+    }
 }
 
 #[derive(SmartDebug, PartialEq)]
 pub struct Opts {
     pub history: History,
+    /// The histories of any other files opened in tabs
+    pub tabs: Vec<History>,
     #[debug(skip)]
     pub theme: color::Theme,
     pub scale: Option<f32>,
-    pub page_width: Option<f32>,
+    pub page_width: Option<PageWidth>,
+    /// Caps the page's width so that lines wrap after roughly this many characters
+    pub max_line_chars: Option<usize>,
     pub lines_to_scroll: f32,
+    pub smooth_scroll: bool,
+    pub quote_block_background: bool,
     pub font_opts: FontOptions,
     pub keybindings: KeybindingsSection,
+    pub scrollbar: ScrollbarSection,
     pub color_scheme: Option<ResolvedTheme>,
+    /// Set when the theme follows the system's color scheme, so that it can change at runtime
+    #[debug(skip)]
+    pub system_themes: Option<SystemThemes>,
     pub metrics: Option<MetricsExporter>,
-    pub position: Option<Position>,
+    /// Where the Prometheus exporter listens
+    pub metrics_address: SocketAddr,
+    pub position: Option<WindowPosition>,
     pub size: Option<Size>,
+    pub open_crash_report: AutoOpen,
+    pub index_files: Vec<String>,
+    pub minimap: bool,
+    pub status_bar: bool,
+    pub toc: bool,
+    pub compact_kv_tables: bool,
+    pub reload_flash: bool,
+    pub watch: bool,
+    pub on_file_replaced: OnFileReplaced,
+    pub close_last_tab: CloseLastTab,
+    pub fit_single_image: bool,
+    pub show_whitespace: bool,
+    /// Clicking a task list checkbox toggles it in the file
+    pub toggle_checkboxes: bool,
+    /// Width of the border drawn around code blocks, if any
+    pub code_block_border: Option<f32>,
+    /// Whether long lines in code blocks wrap instead of running past the edge of the window
+    pub code_block_wrap: bool,
+    /// Whether code blocks get a gutter of line numbers
+    pub code_line_numbers: bool,
+    pub image_background: Option<ImageBackground>,
+    pub image_layer: ImageLayer,
+    /// Initial window size relative to the monitor, when there's no explicit `size`
+    pub default_size: Option<Percent>,
+    pub remember_window: bool,
+    /// Whether images get downloaded at all. Blocked ones are treated as failing to load
+    pub allow_remote_images: bool,
+    /// Remote images larger than this are rejected instead of downloaded
+    pub max_image_bytes: usize,
+    pub max_cached_images: usize,
+    /// Images that load within this long of each other share a single reposition
+    pub image_batch: Duration,
+    /// Images only start loading once they're within this many (logical) pixels of the screen.
+    /// `None` loads every image right away
+    pub lazy_image_distance: Option<f32>,
+    /// Render `$...$` and `$$...$$` as math
+    pub math: bool,
+    /// Hold off on redraws from reloads, images, etc. while the window is unfocused
+    pub pause_when_unfocused: bool,
+    /// Lists and blockquotes nested deeper than this stop being indented further
+    pub max_nesting_depth: usize,
+    /// How many rows and columns of a table get rendered
+    pub table_limits: TableLimits,
+    /// Align small inline images with the surrounding text instead of giving them their own row
+    pub inline_image_baseline: bool,
+    /// Keep runs of blank lines as extra spacing instead of collapsing them
+    pub preserve_blank_lines: bool,
+    /// Keep runs of spaces in regular text together by making them non-breaking
+    pub preserve_spaces: bool,
+    pub on_image_error: OnImageError,
+    /// Number links and list their URLs at the end of the document
+    pub link_references: bool,
+    /// Shift-clicking an in-document link opens the document in a new window at that anchor
+    pub anchors_in_new_window: bool,
+    /// Opens external links in place of the system's default handler
+    pub link_command: Option<LinkCommand>,
+    /// Anchor to scroll to once it's been positioned
+    pub anchor: Option<String>,
+    pub dump_html: bool,
+    pub dump_text: bool,
+    pub escape_fallback: EscapeFallback,
 }
 
 impl Opts {
@@ -101,55 +218,143 @@ impl Opts {
     ) -> Result<Self> {
         let Config {
             theme: config_theme,
+            theme_file: config_theme_file,
             scale: config_scale,
             page_width: config_page_width,
+            max_line_chars,
             lines_to_scroll,
+            smooth_scroll,
+            quote_block_background,
             light_theme,
             dark_theme,
+            mono_theme,
             font_options,
             keybindings,
             debug,
             window,
+            scrollbar,
+            open_crash_report,
+            index_files,
+            minimap,
+            status_bar,
+            toc,
+            compact_kv_tables,
+            reload_flash,
+            watch,
+            on_file_replaced,
+            close_last_tab,
+            fit_single_image,
+            show_whitespace,
+            toggle_checkboxes,
+            code_block_border,
+            code_block_wrap,
+            code_line_numbers,
+            image_background,
+            image_layer,
+            default_size,
+            remember_window,
+            allow_remote_images,
+            max_image_bytes,
+            max_cached_images,
+            image_batch_ms,
+            lazy_image_distance,
+            math,
+            pause_when_unfocused,
+            max_nesting_depth,
+            max_table_rows,
+            max_table_columns,
+            inline_image_baseline,
+            preserve_blank_lines,
+            preserve_spaces,
+            on_image_error,
+            link_references,
+            anchors_in_new_window,
+            link_command,
+            escape_fallback,
         } = config;
 
         let View {
             file_path,
+            extra_files,
             theme: args_theme,
+            theme_file: args_theme_file,
             scale: args_scale,
             config: _,
             page_width: args_page_width,
             size: v_size,
             position: v_position,
+            no_watch,
+            dump_html,
+            dump_text,
+            anchor,
+            metrics_address: args_metrics_address,
         } = args;
 
         let DebugSection {
             metrics,
+            metrics_address: config_metrics_address,
             render_element_bounds,
         } = debug;
 
         set_render_element_bounds(render_element_bounds);
 
         let history = History::new(&file_path)?;
-        let resolved_theme = args_theme
-            .or(config_theme)
-            .and_then(ResolvedTheme::new)
-            .or(fallback_theme);
-        let theme = {
-            let (maybe_theme, fallback_values) = match resolved_theme {
-                Some(ResolvedTheme::Dark) => (dark_theme, color::Theme::dark_default()),
-                None | Some(ResolvedTheme::Light) => (light_theme, color::Theme::light_default()),
+        let tabs = extra_files
+            .iter()
+            .map(|path| History::new(path))
+            .collect::<anyhow::Result<_>>()?;
+        let theme_ty = args_theme.or(config_theme);
+        let resolved_theme = theme_ty.and_then(ResolvedTheme::new).or(fallback_theme);
+        // The theme file takes the place of the built-in themes, so the config's `[light-theme]`
+        // and `[dark-theme]` can still tweak it
+        let theme_file = args_theme_file
+            .or(config_theme_file)
+            .map(|path| OptionalTheme::load_from_file(&path))
+            .transpose()?;
+        let theme_for = |color_scheme| {
+            let (maybe_theme, default_values) = match color_scheme {
+                Some(ResolvedTheme::Dark) => (&dark_theme, color::Theme::dark_default()),
+                None | Some(ResolvedTheme::Light) => (&light_theme, color::Theme::light_default()),
+                Some(ResolvedTheme::Mono) => (&mono_theme, color::Theme::mono_default()),
+            };
+            // The theme file is meant for colorful themes, so mono sticks to its own palette
+            let fallback_values = match &theme_file {
+                Some(theme) if color_scheme != Some(ResolvedTheme::Mono) => {
+                    theme.clone().merge(default_values)?
+                }
+                _ => default_values,
             };
 
             match maybe_theme {
-                Some(theme) => theme.merge(fallback_values)?,
-                None => fallback_values,
+                Some(theme) => theme.clone().merge(fallback_values),
+                None => Ok(fallback_values),
             }
         };
+        let theme = theme_for(resolved_theme)?;
+        let system_themes = match theme_ty {
+            None | Some(ThemeType::Auto) => Some(SystemThemes {
+                light: theme_for(Some(ResolvedTheme::Light))?,
+                dark: theme_for(Some(ResolvedTheme::Dark))?,
+            }),
+            Some(ThemeType::Dark | ThemeType::Light | ThemeType::Mono) => None,
+        };
 
+        // Passing an address is enough to opt into serving metrics
+        let metrics = match args_metrics_address {
+            Some(_) => Some(MetricsExporter::Prometheus),
+            None => metrics,
+        };
+        let metrics_address = args_metrics_address
+            .or(config_metrics_address)
+            .unwrap_or(metrics::DEFAULT_PROMETHEUS_ADDRESS);
         let scale = args_scale.or(config_scale);
         let font_opts = font_options.unwrap_or_default();
         let page_width = args_page_width.or(config_page_width);
         let lines_to_scroll = lines_to_scroll.into();
+        let index_files = index_files.into();
+        let quote_block_background = quote_block_background.unwrap_or(true);
+        let code_block_wrap = code_block_wrap.unwrap_or(true);
+        let watch = !no_watch && watch.unwrap_or(true);
 
         let (position, size) = if let Some(window) = window {
             (v_position.or(window.position), v_size.or(window.size))
@@ -159,31 +364,106 @@ impl Opts {
 
         Ok(Self {
             history,
+            tabs,
             theme,
             scale,
             page_width,
+            max_line_chars,
             lines_to_scroll,
+            smooth_scroll,
+            quote_block_background,
             font_opts,
             keybindings,
+            scrollbar,
             color_scheme: resolved_theme,
+            system_themes,
             metrics,
+            metrics_address,
             position,
             size,
+            open_crash_report,
+            index_files,
+            minimap,
+            status_bar,
+            toc,
+            compact_kv_tables,
+            reload_flash,
+            watch,
+            on_file_replaced,
+            close_last_tab,
+            fit_single_image,
+            show_whitespace,
+            toggle_checkboxes,
+            code_block_border,
+            code_block_wrap,
+            code_line_numbers,
+            image_background,
+            image_layer,
+            default_size,
+            remember_window,
+            allow_remote_images: allow_remote_images.unwrap_or(true),
+            max_image_bytes: max_image_bytes.unwrap_or(image::DEFAULT_MAX_IMAGE_BYTES),
+            max_cached_images: max_cached_images.unwrap_or(image::DEFAULT_MAX_CACHED_IMAGES),
+            image_batch: image_batch_ms
+                .map(Duration::from_millis)
+                .unwrap_or(image_batch::DEFAULT_IMAGE_BATCH),
+            lazy_image_distance: lazy_image_distance.map(|distance| distance.max(0.)),
+            math,
+            pause_when_unfocused,
+            max_nesting_depth: max_nesting_depth.unwrap_or(DEFAULT_MAX_NESTING_DEPTH),
+            table_limits: TableLimits {
+                rows: max_table_rows.unwrap_or(table::DEFAULT_MAX_ROWS),
+                columns: max_table_columns.unwrap_or(table::DEFAULT_MAX_COLUMNS),
+            },
+            inline_image_baseline,
+            preserve_blank_lines,
+            preserve_spaces,
+            on_image_error,
+            link_references,
+            anchors_in_new_window,
+            link_command,
+            // Accept the anchor with or without its leading `#`
+            anchor: anchor.map(|anchor| {
+                if anchor.starts_with('#') {
+                    anchor
+                } else {
+                    format!("#{anchor}")
+                }
+            }),
+            dump_html,
+            dump_text,
+            escape_fallback,
         })
     }
 
     /// Arguments to supply to program that are opened externally.
-    pub fn program_args(file_path: &Path) -> Vec<String> {
+    pub fn program_args(file_path: &Path, anchor: Option<&str>) -> Vec<String> {
         let current_args = Cli::parse().into_view().expect("Should contain an view!");
+        Self::program_args_from(current_args, file_path, anchor)
+    }
 
+    fn program_args_from(
+        current_args: View,
+        file_path: &Path,
+        anchor: Option<&str>,
+    ) -> Vec<String> {
         let mut args = Vec::new();
 
         args.push(file_path.display().to_string());
+        if let Some(anchor) = anchor {
+            args.push("--anchor".to_owned());
+            args.push(anchor.to_owned());
+        }
         if let Some(theme) = current_args.theme {
             args.push("--theme".to_owned());
             args.push(theme.as_str().to_owned());
         }
 
+        if let Some(theme_file) = current_args.theme_file {
+            args.push("--theme-file".to_owned());
+            args.push(theme_file.display().to_string());
+        }
+
         if let Some(scale) = current_args.scale {
             args.push("--scale".to_owned());
             args.push(scale.to_string());
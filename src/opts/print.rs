@@ -0,0 +1,298 @@
+//! Printing the effective configuration (`inlyne config print`)
+//!
+//! The output is laid out like the config file, so that it can be compared against (or even used
+//! as) one. Keybindings are the exception and get listed in the same notation as the logs
+
+use std::fmt::{self, Debug, Write};
+
+use super::{ImageBackground, Opts, PageWidth, ResolvedTheme, WindowPosition};
+use crate::color::Theme;
+use crate::keybindings::Keybindings;
+
+/// TOML's form of a string, quotes and all
+fn quoted(s: &str) -> String {
+    toml::Value::from(s).to_string()
+}
+
+fn hex(color: u32) -> String {
+    format!("0x{color:06X}")
+}
+
+/// A unit variant the way it's written in the config e.g. `KeepPosition` as `"keep-position"`
+fn variant(value: impl Debug) -> String {
+    let mut kebab = String::new();
+    for (i, c) in format!("{value:?}").chars().enumerate() {
+        if c.is_ascii_uppercase() {
+            if i > 0 {
+                kebab.push('-');
+            }
+            kebab.push(c.to_ascii_lowercase());
+        } else {
+            kebab.push(c);
+        }
+    }
+    quoted(&kebab)
+}
+
+fn section(out: &mut String, name: &str) {
+    let _ = writeln!(out, "\n[{name}]");
+}
+
+fn line(out: &mut String, key: &str, value: impl fmt::Display) {
+    let _ = writeln!(out, "{key} = {value}");
+}
+
+fn maybe_line(out: &mut String, key: &str, value: Option<impl fmt::Display>) {
+    if let Some(value) = value {
+        line(out, key, value);
+    }
+}
+
+fn theme_lines(out: &mut String, theme: &Theme) {
+    let Theme {
+        text_color,
+        background_color,
+        code_color,
+        code_border_color,
+        line_number_color,
+        kbd_background_color,
+        kbd_border_color,
+        quote_block_color,
+        link_color,
+        select_color,
+        checkbox_color,
+        search_highlight_color,
+        scrollbar_color,
+        alert_note_color,
+        alert_tip_color,
+        alert_important_color,
+        alert_warning_color,
+        alert_caution_color,
+        code_highlighter: _,
+    } = theme;
+    for (key, color) in [
+        ("text-color", text_color),
+        ("background-color", background_color),
+        ("code-color", code_color),
+        ("code-border-color", code_border_color),
+        ("line-number-color", line_number_color),
+        ("kbd-background-color", kbd_background_color),
+        ("kbd-border-color", kbd_border_color),
+        ("quote-block-color", quote_block_color),
+        ("link-color", link_color),
+        ("select-color", select_color),
+        ("checkbox-color", checkbox_color),
+        ("search-highlight-color", search_highlight_color),
+        ("scrollbar-color", scrollbar_color),
+        ("alert-note-color", alert_note_color),
+        ("alert-tip-color", alert_tip_color),
+        ("alert-important-color", alert_important_color),
+        ("alert-warning-color", alert_warning_color),
+        ("alert-caution-color", alert_caution_color),
+    ] {
+        line(out, key, hex(*color));
+    }
+}
+
+impl Opts {
+    /// The settings that are in effect after merging the CLI args, config file, and defaults
+    pub fn to_toml(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "# Effective configuration for {}",
+            self.history.get_path().display()
+        );
+
+        maybe_line(
+            &mut out,
+            "theme",
+            // The config's theme names are capitalized unlike everything else
+            self.color_scheme.map(|theme| quoted(&format!("{theme:?}"))),
+        );
+        maybe_line(
+            &mut out,
+            "scale",
+            self.scale.map(|scale| format!("{scale:?}")),
+        );
+        maybe_line(
+            &mut out,
+            "page-width",
+            self.page_width.map(|width| match width {
+                PageWidth::Pixels(width) => format!("{width:?}"),
+                PageWidth::Relative(percent) => quoted(&percent.to_string()),
+            }),
+        );
+        maybe_line(&mut out, "max-line-chars", self.max_line_chars);
+        line(
+            &mut out,
+            "lines-to-scroll",
+            format!("{:?}", self.lines_to_scroll),
+        );
+        line(&mut out, "smooth-scroll", self.smooth_scroll);
+        line(
+            &mut out,
+            "quote-block-background",
+            self.quote_block_background,
+        );
+        line(
+            &mut out,
+            "open-crash-report",
+            variant(self.open_crash_report),
+        );
+        let index_files: Vec<_> = self.index_files.iter().map(|f| quoted(f)).collect();
+        line(
+            &mut out,
+            "index-files",
+            format!("[{}]", index_files.join(", ")),
+        );
+        line(&mut out, "minimap", self.minimap);
+        line(&mut out, "status-bar", self.status_bar);
+        line(&mut out, "toc", self.toc);
+        line(&mut out, "compact-kv-tables", self.compact_kv_tables);
+        line(&mut out, "reload-flash", self.reload_flash);
+        line(&mut out, "watch", self.watch);
+        line(&mut out, "on-file-replaced", variant(self.on_file_replaced));
+        line(&mut out, "close-last-tab", variant(self.close_last_tab));
+        line(&mut out, "fit-single-image", self.fit_single_image);
+        line(&mut out, "show-whitespace", self.show_whitespace);
+        line(&mut out, "toggle-checkboxes", self.toggle_checkboxes);
+        maybe_line(
+            &mut out,
+            "code-block-border",
+            self.code_block_border.map(|width| format!("{width:?}")),
+        );
+        line(&mut out, "code-block-wrap", self.code_block_wrap);
+        line(&mut out, "code-line-numbers", self.code_line_numbers);
+        maybe_line(
+            &mut out,
+            "image-background",
+            self.image_background.map(|background| match background {
+                ImageBackground::Color(color) => hex(color),
+                ImageBackground::Pattern(pattern) => variant(pattern),
+            }),
+        );
+        line(&mut out, "image-layer", variant(self.image_layer));
+        maybe_line(
+            &mut out,
+            "default-size",
+            self.default_size
+                .map(|percent| quoted(&percent.to_string())),
+        );
+        line(&mut out, "remember-window", self.remember_window);
+        line(&mut out, "allow-remote-images", self.allow_remote_images);
+        line(&mut out, "max-image-bytes", self.max_image_bytes);
+        line(&mut out, "max-cached-images", self.max_cached_images);
+        line(&mut out, "image-batch-ms", self.image_batch.as_millis());
+        maybe_line(&mut out, "lazy-image-distance", self.lazy_image_distance);
+        line(&mut out, "math", self.math);
+        line(&mut out, "pause-when-unfocused", self.pause_when_unfocused);
+        line(&mut out, "max-nesting-depth", self.max_nesting_depth);
+        line(&mut out, "max-table-rows", self.table_limits.rows);
+        line(&mut out, "max-table-columns", self.table_limits.columns);
+        line(
+            &mut out,
+            "inline-image-baseline",
+            self.inline_image_baseline,
+        );
+        line(&mut out, "preserve-blank-lines", self.preserve_blank_lines);
+        line(&mut out, "preserve-spaces", self.preserve_spaces);
+        line(&mut out, "on-image-error", variant(self.on_image_error));
+        line(&mut out, "link-references", self.link_references);
+        line(
+            &mut out,
+            "anchors-in-new-window",
+            self.anchors_in_new_window,
+        );
+        maybe_line(
+            &mut out,
+            "link-command",
+            self.link_command
+                .as_ref()
+                .map(|command| quoted(&command.to_string())),
+        );
+        line(&mut out, "escape-fallback", variant(self.escape_fallback));
+
+        let theme_section = match self.color_scheme.unwrap_or_default() {
+            ResolvedTheme::Dark => "dark-theme",
+            ResolvedTheme::Light => "light-theme",
+            ResolvedTheme::Mono => "mono-theme",
+        };
+        section(&mut out, theme_section);
+        theme_lines(&mut out, &self.theme);
+
+        section(&mut out, "font-options");
+        maybe_line(
+            &mut out,
+            "regular-font",
+            self.font_opts.regular_font.as_deref().map(quoted),
+        );
+        maybe_line(
+            &mut out,
+            "monospace-font",
+            self.font_opts.monospace_font.as_deref().map(quoted),
+        );
+        let fallback_families: Vec<_> = self
+            .font_opts
+            .fallback_families
+            .iter()
+            .map(|f| quoted(f))
+            .collect();
+        line(
+            &mut out,
+            "fallback-families",
+            format!("[{}]", fallback_families.join(", ")),
+        );
+        maybe_line(
+            &mut out,
+            "emoji-family",
+            self.font_opts.emoji_family.as_deref().map(quoted),
+        );
+        line(
+            &mut out,
+            "synthetic-emphasis",
+            self.font_opts.synthetic_emphasis,
+        );
+
+        section(&mut out, "window");
+        maybe_line(
+            &mut out,
+            "position",
+            self.position.as_ref().map(|pos| match pos {
+                WindowPosition::At(pos) => format!("{{ x = {}, y = {} }}", pos.x, pos.y),
+                WindowPosition::Placed(placement) => variant(placement),
+            }),
+        );
+        maybe_line(
+            &mut out,
+            "size",
+            self.size
+                .as_ref()
+                .map(|size| format!("{{ width = {}, height = {} }}", size.width, size.height)),
+        );
+
+        section(&mut out, "scrollbar");
+        line(&mut out, "width", format!("{:?}", self.scrollbar.width));
+        maybe_line(&mut out, "color", self.scrollbar.color.map(hex));
+
+        section(&mut out, "debug");
+        maybe_line(&mut out, "metrics", self.metrics.as_ref().map(variant));
+        line(
+            &mut out,
+            "metrics-address",
+            quoted(&self.metrics_address.to_string()),
+        );
+
+        let _ = writeln!(out, "\n# Keybindings (after merging `base` and `extra`)");
+        let keybindings: Keybindings = self.keybindings.clone().into();
+        for (action, combo) in keybindings.iter() {
+            let _ = writeln!(out, "# {combo}: {action:?}");
+        }
+        let _ = writeln!(out, "\n# Mouse bindings");
+        for (action, button) in self.keybindings.mouse.iter() {
+            let _ = writeln!(out, "# {button}: {action:?}");
+        }
+
+        out
+    }
+}
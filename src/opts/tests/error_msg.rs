@@ -1,3 +1,5 @@
+use syntect::highlighting::Theme as SyntectTheme;
+
 use crate::keybindings::KeyCombos;
 use crate::opts::Config;
 
@@ -20,7 +22,6 @@ macro_rules! snapshot_config_parse_error {
     }
 }
 
-const UNKNOWN_THEME: &str = r#"light-theme.code-highlighter = "doesnt-exist""#;
 const INVALID_THEME_TY: &str = "light-theme.code-highlighter = []";
 
 const FIX_THIS_SUCKY_ERROR_MESSAGE: &str = r#"
@@ -32,12 +33,27 @@ base = [
 "#;
 
 snapshot_config_parse_error!(
-    (unknown_theme, UNKNOWN_THEME),
     (invalid_theme_ty, INVALID_THEME_TY),
     // FIXME: vv
     (fix_this_sucky_error_message, FIX_THIS_SUCKY_ERROR_MESSAGE),
 );
 
+#[test]
+fn unknown_theme() {
+    crate::test_utils::log::init();
+
+    let text = r#"light-theme.code-highlighter = "doesnt-exist""#;
+    let light_theme = Config::load_from_str(text).unwrap().light_theme.unwrap();
+    let code_highlighter = light_theme.code_highlighter.unwrap();
+    let err = SyntectTheme::try_from(code_highlighter).unwrap_err();
+
+    insta::with_settings!({
+        description => text,
+    }, {
+        insta::assert_snapshot!(err);
+    });
+}
+
 fn keycombo_conflict_from_config(s: &str) -> anyhow::Result<anyhow::Error> {
     let Config { keybindings, .. } = Config::load_from_str(s)?;
     let err = KeyCombos::new(keybindings).unwrap_err();
@@ -7,8 +7,14 @@ use tempfile::NamedTempFile;
 
 use crate::color::{SyntaxTheme, Theme, ThemeDefaults};
 use crate::history::History;
-use crate::opts::config::{self, FontOptions, LinesToScroll};
-use crate::opts::{Cli, Opts, Position, ResolvedTheme, Size, ThemeType};
+use crate::opts::config::{self, FontOptions, IndexFiles, LinesToScroll};
+use crate::opts::{
+    Cli, CloseLastTab, Commands, EscapeFallback, ImageLayer, OnFileReplaced, OnImageError, Opts,
+    PageWidth, Percent, Placement, Position, ResolvedTheme, Size, SystemThemes, ThemeType, View,
+    WindowPosition,
+};
+use crate::panic_hook::AutoOpen;
+use crate::table::TableLimits;
 use crate::test_utils::log;
 
 fn gen_args(args: Vec<&str>) -> Vec<OsString> {
@@ -32,16 +38,66 @@ impl Opts {
     fn mostly_default(file_path: impl AsRef<Path>) -> Self {
         Self {
             history: History::new(file_path.as_ref()).unwrap(),
+            tabs: Vec::new(),
             theme: ResolvedTheme::Light.as_theme(),
             scale: None,
             page_width: None,
+            max_line_chars: None,
             font_opts: FontOptions::default(),
             lines_to_scroll: LinesToScroll::default().0,
+            smooth_scroll: false,
+            quote_block_background: true,
             keybindings: Default::default(),
+            scrollbar: Default::default(),
             color_scheme: None,
+            system_themes: Some(SystemThemes {
+                light: Theme::light_default(),
+                dark: Theme::dark_default(),
+            }),
             metrics: Default::default(),
+            metrics_address: crate::metrics::DEFAULT_PROMETHEUS_ADDRESS,
             size: None,
             position: None,
+            open_crash_report: AutoOpen::Never,
+            index_files: IndexFiles::default().0,
+            minimap: false,
+            status_bar: false,
+            toc: false,
+            compact_kv_tables: false,
+            reload_flash: false,
+            watch: true,
+            on_file_replaced: OnFileReplaced::KeepPosition,
+            close_last_tab: CloseLastTab::Quit,
+            fit_single_image: false,
+            show_whitespace: false,
+            toggle_checkboxes: false,
+            code_block_border: None,
+            code_block_wrap: true,
+            code_line_numbers: false,
+            image_background: None,
+            image_layer: ImageLayer::BelowText,
+            default_size: None,
+            remember_window: false,
+            allow_remote_images: true,
+            max_image_bytes: crate::image::DEFAULT_MAX_IMAGE_BYTES,
+            max_cached_images: crate::image::DEFAULT_MAX_CACHED_IMAGES,
+            image_batch: crate::image_batch::DEFAULT_IMAGE_BATCH,
+            lazy_image_distance: None,
+            math: false,
+            pause_when_unfocused: false,
+            max_nesting_depth: crate::interpreter::DEFAULT_MAX_NESTING_DEPTH,
+            table_limits: TableLimits::default(),
+            inline_image_baseline: false,
+            preserve_blank_lines: false,
+            preserve_spaces: false,
+            on_image_error: OnImageError::Placeholder,
+            link_references: false,
+            anchors_in_new_window: false,
+            link_command: None,
+            anchor: None,
+            dump_html: false,
+            dump_text: false,
+            escape_fallback: EscapeFallback::Quit,
         }
     }
 }
@@ -51,6 +107,7 @@ impl ResolvedTheme {
         match &self {
             Self::Dark => Theme::dark_default(),
             Self::Light => Theme::light_default(),
+            Self::Mono => Theme::mono_default(),
         }
     }
 }
@@ -106,6 +163,7 @@ fn config_overrides_default() {
         Opts {
             theme: ResolvedTheme::Dark.as_theme(),
             color_scheme: Some(ResolvedTheme::Dark),
+            system_themes: None,
             ..Opts::mostly_default(&md_file)
         }
     );
@@ -128,6 +186,39 @@ fn config_overrides_default() {
         Opts {
             theme: ResolvedTheme::Light.as_theme(),
             color_scheme: Some(ResolvedTheme::Light),
+            system_themes: None,
+            ..Opts::mostly_default(&md_file)
+        }
+    );
+
+    // Mono from the command line ignores the system theme along with the theme file
+    let theme_file = NamedTempFile::new().unwrap();
+    std::fs::write(&theme_file, "text-color = 0x123456").unwrap();
+    let config = config::Config {
+        theme_file: Some(theme_file.path().to_owned()),
+        mono_theme: Some(config::OptionalTheme {
+            link_color: Some(0x0000EE),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    assert_eq!(
+        Opts::parse_and_load_with_system_theme(
+            Cli::try_parse_from(gen_args(vec!["--theme", "mono", &md_file]))
+                .unwrap()
+                .into_view()
+                .unwrap(),
+            config,
+            Some(ResolvedTheme::Dark),
+        )
+        .unwrap(),
+        Opts {
+            theme: Theme {
+                link_color: 0x0000EE,
+                ..ResolvedTheme::Mono.as_theme()
+            },
+            color_scheme: Some(ResolvedTheme::Mono),
+            system_themes: None,
             ..Opts::mostly_default(&md_file)
         }
     );
@@ -172,6 +263,7 @@ fn from_cli() {
         Opts {
             theme: ResolvedTheme::Dark.as_theme(),
             color_scheme: Some(ResolvedTheme::Dark),
+            system_themes: None,
             ..Opts::mostly_default(&md_file)
         }
     );
@@ -196,6 +288,7 @@ fn from_cli() {
             theme: ResolvedTheme::Dark.as_theme(),
             scale: Some(1.5),
             color_scheme: Some(ResolvedTheme::Dark),
+            system_themes: None,
             ..Opts::mostly_default(&md_file)
         }
     );
@@ -223,15 +316,46 @@ fn cli_kitchen_sink() {
         )
         .unwrap(),
         Opts {
-            page_width: Some(500.0),
+            page_width: Some(PageWidth::Pixels(500.0)),
             scale: Some(1.5),
             theme: ResolvedTheme::Dark.as_theme(),
             color_scheme: Some(ResolvedTheme::Dark),
+            system_themes: None,
             ..Opts::mostly_default(&md_file)
         }
     );
 }
 
+#[test]
+fn system_themes_keep_overrides() {
+    log::init();
+
+    let (_tmp, md_file) = temp_md_file();
+
+    let mut config = config::Config::default();
+    config.dark_theme = Some(config::OptionalTheme {
+        text_color: Some(0x123456),
+        ..Default::default()
+    });
+    let opts = Opts::parse_and_load_with_system_theme(
+        Cli::try_parse_from(gen_args(vec![&md_file]))
+            .unwrap()
+            .into_view()
+            .unwrap(),
+        config,
+        Some(ResolvedTheme::Light),
+    )
+    .unwrap();
+    assert_eq!(opts.theme, Theme::light_default());
+    // Switching the system over to dark should pick up the configured dark theme
+    let system_themes = opts.system_themes.unwrap();
+    assert_eq!(system_themes.get(Some(ResolvedTheme::Light)), &opts.theme);
+    assert_eq!(
+        system_themes.get(Some(ResolvedTheme::Dark)).text_color,
+        0x123456
+    );
+}
+
 #[test]
 fn builtin_syntax_theme() {
     log::init();
@@ -258,6 +382,39 @@ fn builtin_syntax_theme() {
         opts.theme.code_highlighter.name.unwrap(),
         "Solarized (light)"
     );
+
+    // `syntect`'s names for the themes work too
+    let config =
+        config::Config::load_from_str("[light-theme]\ncode-highlighter = \"base16-ocean.dark\"")
+            .unwrap();
+    let theme = config.light_theme.unwrap().code_highlighter.unwrap();
+    assert_eq!(theme, SyntaxTheme::Defaults(ThemeDefaults::Base16OceanDark));
+}
+
+#[test]
+fn unknown_syntax_theme() {
+    log::init();
+
+    // Unknown names fall back to the theme's default highlighter once the theme gets used
+    let unknown = SyntaxTheme::Unknown("doesnt-exist".into());
+    let config =
+        config::Config::load_from_str("[light-theme]\ncode-highlighter = \"doesnt-exist\"")
+            .unwrap();
+    let light_theme = config.light_theme.unwrap();
+    assert_eq!(light_theme.code_highlighter, Some(unknown.clone()));
+    let (theme, warnings) =
+        log::capture_warnings(|| light_theme.merge(Theme::light_default()).unwrap());
+    assert_eq!(theme, Theme::light_default());
+    assert_eq!(warnings.len(), 1, "{warnings:?}");
+    // Standalone theme files get the same treatment and don't call it an unknown key
+    let (theme, warnings) = log::capture_warnings(|| {
+        config::OptionalTheme::load_from_str("code-highlighter = \"doesnt-exist\"")
+    });
+    assert_eq!(theme.unwrap().code_highlighter, Some(unknown));
+    assert!(warnings.is_empty(), "{warnings:?}");
+
+    // Values that aren't a theme at all are still an error
+    assert!(config::OptionalTheme::load_from_str("code-highlighter = []").is_err());
 }
 
 #[test]
@@ -304,6 +461,68 @@ fn custom_syntax_theme() {
     );
 }
 
+#[test]
+fn theme_file() {
+    log::init();
+
+    let (_tmp, md_file) = temp_md_file();
+    let mut theme_file = tempfile::Builder::new()
+        .prefix("inlyne-tests-")
+        .suffix(".toml")
+        .tempfile()
+        .unwrap();
+    std::io::Write::write_all(
+        &mut theme_file,
+        b"text-color = 0x123456\nlink-color = 0x654321\ntxet-color = 0xFFFFFF\n",
+    )
+    .unwrap();
+    let theme_path = theme_file.path().to_str().unwrap();
+
+    let mut config = config::Config::default();
+    // The config's own theme sections still apply on top
+    config.dark_theme = Some(config::OptionalTheme {
+        link_color: Some(0xABCDEF),
+        ..Default::default()
+    });
+    let opts = Opts::parse_and_load_with_system_theme(
+        Cli::try_parse_from(gen_args(vec!["--theme-file", theme_path, &md_file]))
+            .unwrap()
+            .into_view()
+            .unwrap(),
+        config,
+        Some(ResolvedTheme::Light),
+    )
+    .unwrap();
+
+    let system_themes = opts.system_themes.unwrap();
+    let light = system_themes.get(Some(ResolvedTheme::Light));
+    assert_eq!((light.text_color, light.link_color), (0x123456, 0x654321));
+    assert_eq!(
+        light.background_color,
+        Theme::light_default().background_color
+    );
+    let dark = system_themes.get(Some(ResolvedTheme::Dark));
+    assert_eq!((dark.text_color, dark.link_color), (0x123456, 0xABCDEF));
+
+    // Also works from the config, and typos get flagged
+    let config = config::Config::load_from_str(&format!("theme-file = {theme_path:?}")).unwrap();
+    let theme = config::OptionalTheme::load_from_file(&config.theme_file.unwrap()).unwrap();
+    assert_eq!(theme.text_color, Some(0x123456));
+    let table = toml::from_str(&std::fs::read_to_string(theme_path).unwrap()).unwrap();
+    assert_eq!(config::OptionalTheme::unknown_keys(&table), ["txet-color"]);
+
+    let missing = Cli::try_parse_from(gen_args(vec!["--theme-file", "missing.toml", &md_file]))
+        .unwrap()
+        .into_view()
+        .unwrap();
+    let res = Opts::parse_and_load_with_system_theme(
+        missing,
+        config::Config::default(),
+        Some(ResolvedTheme::Light),
+    );
+    assert!(res.is_err());
+}
+
 #[test]
 fn missing_file_arg() {
     log::init();
@@ -333,7 +552,7 @@ fn win_pos_and_size() {
         )
         .unwrap(),
         Opts {
-            position: Some(Position { x: 100, y: 200 }),
+            position: Some(WindowPosition::At(Position { x: 100, y: 200 })),
             size: Some(Size {
                 width: 300,
                 height: 400
@@ -342,3 +561,332 @@ fn win_pos_and_size() {
         }
     );
 }
+
+#[test]
+fn render_command() {
+    let args = gen_args(vec![
+        "render",
+        "doc.md",
+        "-o",
+        "doc.png",
+        "--width",
+        "1000",
+        "--full-height",
+        "--theme",
+        "light",
+    ]);
+    let Commands::Render(render) = Cli::try_parse_from(args).unwrap().into_commands() else {
+        panic!("Expected the render command");
+    };
+    assert_eq!(render.output, Path::new("doc.png"));
+    assert_eq!((render.width, render.height), (1000, 600));
+    assert!(render.full_height);
+    // Everything else gets loaded the same way as when viewing the file
+    assert_eq!(
+        render.to_view(),
+        View {
+            file_path: "doc.md".into(),
+            theme: Some(ThemeType::Light),
+            ..Default::default()
+        }
+    );
+
+    let missing_output = gen_args(vec!["render", "doc.md"]);
+    assert!(Cli::try_parse_from(missing_output).is_err());
+}
+
+#[test]
+fn win_pos_placement() {
+    log::init();
+
+    let (_tmp, md_file) = temp_md_file();
+    let parse = |args: Vec<&str>, config| {
+        Opts::parse_and_load_with_system_theme(
+            Cli::try_parse_from(gen_args(args))
+                .unwrap()
+                .into_view()
+                .unwrap(),
+            config,
+            None,
+        )
+        .unwrap()
+        .position
+    };
+
+    let center = parse(
+        vec!["--win-pos", "center", &md_file],
+        config::Config::default(),
+    );
+    assert_eq!(center, Some(WindowPosition::Placed(Placement::Center)));
+
+    let config = config::Config::load_from_str("[window]\nposition = \"cursor\"").unwrap();
+    let cursor = parse(vec![&md_file], config);
+    assert_eq!(cursor, Some(WindowPosition::Placed(Placement::Cursor)));
+    // Explicit coordinates still work from the config too
+    let config = config::Config::load_from_str("[window]\nposition = [500, 200]").unwrap();
+    let at = parse(vec![&md_file], config);
+    assert_eq!(at, Some(WindowPosition::At(Position { x: 500, y: 200 })));
+
+    assert!(Cli::try_parse_from(gen_args(vec!["--win-pos", "middle", &md_file])).is_err());
+    assert!(config::Config::load_from_str("[window]\nposition = \"middle\"").is_err());
+}
+
+#[test]
+fn no_watch() {
+    log::init();
+
+    let (_tmp, md_file) = temp_md_file();
+    let parse = |args: Vec<&str>, config| {
+        Opts::parse_and_load_with_system_theme(
+            Cli::try_parse_from(gen_args(args))
+                .unwrap()
+                .into_view()
+                .unwrap(),
+            config,
+            None,
+        )
+        .unwrap()
+    };
+    let no_watch = Opts {
+        watch: false,
+        ..Opts::mostly_default(&md_file)
+    };
+
+    assert_eq!(
+        parse(vec!["--no-watch", &md_file], config::Config::default()),
+        no_watch
+    );
+    let config = config::Config {
+        watch: Some(false),
+        ..Default::default()
+    };
+    assert_eq!(parse(vec![&md_file], config), no_watch);
+    // The flag can only turn watching off
+    let config = config::Config {
+        watch: Some(true),
+        ..Default::default()
+    };
+    assert_eq!(parse(vec!["--no-watch", &md_file], config), no_watch);
+}
+
+#[test]
+fn dump_html() {
+    log::init();
+
+    let (_tmp, md_file) = temp_md_file();
+    let opts = Opts::parse_and_load_with_system_theme(
+        Cli::try_parse_from(gen_args(vec!["--dump-html", &md_file]))
+            .unwrap()
+            .into_view()
+            .unwrap(),
+        config::Config::default(),
+        None,
+    )
+    .unwrap();
+    assert_eq!(
+        opts,
+        Opts {
+            dump_html: true,
+            ..Opts::mostly_default(&md_file)
+        }
+    );
+}
+
+#[test]
+fn dump_text() {
+    log::init();
+
+    let (_tmp, md_file) = temp_md_file();
+    let opts = Opts::parse_and_load_with_system_theme(
+        Cli::try_parse_from(gen_args(vec!["--dump-text", &md_file]))
+            .unwrap()
+            .into_view()
+            .unwrap(),
+        config::Config::default(),
+        None,
+    )
+    .unwrap();
+    assert_eq!(
+        opts,
+        Opts {
+            dump_text: true,
+            ..Opts::mostly_default(&md_file)
+        }
+    );
+}
+
+#[test]
+fn anchor() {
+    log::init();
+
+    let (_tmp, md_file) = temp_md_file();
+    let parse = |args: Vec<&str>| {
+        Opts::parse_and_load_with_system_theme(
+            Cli::try_parse_from(gen_args(args))
+                .unwrap()
+                .into_view()
+                .unwrap(),
+            config::Config::default(),
+            None,
+        )
+        .unwrap()
+    };
+    let with_anchor = Opts {
+        anchor: Some("#usage".to_owned()),
+        ..Opts::mostly_default(&md_file)
+    };
+
+    assert_eq!(parse(vec!["--anchor", "#usage", &md_file]), with_anchor);
+    assert_eq!(parse(vec!["--anchor", "usage", &md_file]), with_anchor);
+}
+
+#[test]
+fn extra_files_open_in_tabs() {
+    log::init();
+
+    let (_tmp1, first) = temp_md_file();
+    let (_tmp2, second) = temp_md_file();
+    let (_tmp3, third) = temp_md_file();
+    let opts = Opts::parse_and_load_with_system_theme(
+        Cli::try_parse_from(gen_args(vec![&first, &second, "--no-watch", &third]))
+            .unwrap()
+            .into_view()
+            .unwrap(),
+        config::Config::default(),
+        None,
+    )
+    .unwrap();
+
+    assert_eq!(
+        opts,
+        Opts {
+            tabs: vec![
+                History::new(Path::new(&second)).unwrap(),
+                History::new(Path::new(&third)).unwrap(),
+            ],
+            watch: false,
+            ..Opts::mostly_default(&first)
+        }
+    );
+}
+
+#[test]
+fn program_args_with_anchor() {
+    log::init();
+
+    let current_args = Cli::try_parse_from(gen_args(vec![
+        "--theme",
+        "dark",
+        "--anchor",
+        "#old",
+        "current.md",
+    ]))
+    .unwrap()
+    .into_view()
+    .unwrap();
+    let file_path = Path::new("current.md");
+
+    // The anchor is for the new window and isn't passed along from the current one
+    assert_eq!(
+        Opts::program_args_from(current_args.clone(), file_path, None),
+        ["current.md", "--theme", "dark"]
+    );
+    assert_eq!(
+        Opts::program_args_from(current_args, file_path, Some("#usage")),
+        ["current.md", "--anchor", "#usage", "--theme", "dark"]
+    );
+}
+
+#[test]
+fn printed_config_round_trips() {
+    log::init();
+
+    let (_tmp, md_file) = temp_md_file();
+    let parse = |config| {
+        Opts::parse_and_load_with_system_theme(
+            Cli::try_parse_from(gen_args(vec!["--theme", "dark", "-w", "800", &md_file]))
+                .unwrap()
+                .into_view()
+                .unwrap(),
+            config,
+            None,
+        )
+        .unwrap()
+    };
+    let config = config::Config {
+        minimap: true,
+        image_layer: ImageLayer::AboveText,
+        default_size: Some("70%".parse().unwrap()),
+        font_options: Some(FontOptions {
+            fallback_families: vec!["Noto Sans CJK JP".into(), "Noto Sans Arabic".into()],
+            emoji_family: Some("Twemoji".into()),
+            ..Default::default()
+        }),
+        dark_theme: Some(config::OptionalTheme {
+            link_color: Some(0xFF00FF),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let opts = parse(config);
+
+    // Loading the printed config gives back the same settings
+    let printed = opts.to_toml();
+    let reloaded = config::Config::load_from_str(&printed).unwrap();
+    assert_eq!(parse(reloaded), opts);
+    assert!(printed.contains("link-color = 0xFF00FF\n"), "{printed}");
+    assert!(
+        printed.contains("image-layer = \"above-text\"\n"),
+        "{printed}"
+    );
+    assert!(
+        printed.contains("fallback-families = [\"Noto Sans CJK JP\", \"Noto Sans Arabic\"]\n"),
+        "{printed}"
+    );
+}
+
+#[test]
+fn default_size_percent() {
+    let monitor = Size {
+        width: 2560,
+        height: 1440,
+    };
+    let percent: Percent = "70%".parse().unwrap();
+    assert_eq!(
+        percent.of(monitor.clone()),
+        Size {
+            width: 1792,
+            height: 1008,
+        }
+    );
+    assert_eq!(
+        "100%".parse::<Percent>().unwrap().of(monitor.clone()),
+        monitor
+    );
+    for invalid in ["70", "0%", "150%", "abc%"] {
+        assert!(invalid.parse::<Percent>().is_err(), "{invalid}");
+    }
+
+    let config = config::Config::load_from_str("default-size = \"50%\"").unwrap();
+    assert_eq!(config.default_size, Some("50%".parse().unwrap()));
+}
+
+#[test]
+fn relative_page_width() {
+    let width: PageWidth = "60%".parse().unwrap();
+    assert_eq!(width.resolve(2000.), 1200.);
+    assert_eq!("800".parse::<PageWidth>().unwrap().resolve(2000.), 800.);
+    for invalid in ["wide", "0%", "150%", "800px"] {
+        assert!(invalid.parse::<PageWidth>().is_err(), "{invalid}");
+    }
+
+    for (toml, expected) in [
+        ("page-width = 800", PageWidth::Pixels(800.)),
+        ("page-width = 812.5", PageWidth::Pixels(812.5)),
+        ("page-width = \"60%\"", width),
+    ] {
+        let config = config::Config::load_from_str(toml).unwrap();
+        assert_eq!(config.page_width, Some(expected), "{toml}");
+    }
+    assert!(config::Config::load_from_str("page-width = \"wide\"").is_err());
+}
@@ -12,6 +12,7 @@ use std::{
     io,
     panic::PanicHookInfo,
     path::{Path, PathBuf},
+    sync::OnceLock,
 };
 
 use human_panic::report::Method;
@@ -19,6 +20,41 @@ use serde::Deserialize;
 
 const PKG_NAME: &str = env!("CARGO_PKG_NAME");
 const PKG_VERSION: &str = env!("CARGO_PKG_VERSION");
+const NEW_ISSUE_URL: &str =
+    "https://github.com/Inlyne-Project/inlyne/issues/new?labels=C-crash-report";
+
+static AUTO_OPEN: OnceLock<AutoOpen> = OnceLock::new();
+
+/// What (if anything) gets opened automatically after a crash report is generated
+///
+/// This is strictly opt-in. We never send anything anywhere ourselves, and the default leaves it
+/// entirely up to the user to go looking for the report
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum AutoOpen {
+    #[default]
+    Never,
+    /// The directory containing the generated report
+    ReportDir,
+    /// The page for filing a new crash report issue
+    NewIssue,
+}
+
+impl AutoOpen {
+    fn target(self, report_path: Option<&Path>) -> Option<String> {
+        match self {
+            Self::Never => None,
+            Self::ReportDir => report_path
+                .and_then(Path::parent)
+                .map(|dir| dir.display().to_string()),
+            Self::NewIssue => Some(NEW_ISSUE_URL.to_owned()),
+        }
+    }
+}
+
+pub fn set_auto_open(auto_open: AutoOpen) {
+    let _ = AUTO_OPEN.set(auto_open);
+}
 
 #[macro_export]
 macro_rules! setup_panic {
@@ -30,6 +66,7 @@ macro_rules! setup_panic {
                         eprintln!("{info}");
                         let file_path = $crate::panic_hook::handle_dump(info);
                         $crate::panic_hook::print_msg(file_path.as_deref()).unwrap();
+                        $crate::panic_hook::auto_open(file_path.as_deref());
                     },
                 ));
             }
@@ -170,6 +207,16 @@ pub fn print_msg(file_path: Option<&Path>) -> Option<()> {
     Some(())
 }
 
+pub fn auto_open(file_path: Option<&Path>) {
+    let auto_open = AUTO_OPEN.get().copied().unwrap_or_default();
+    if let Some(target) = auto_open.target(file_path) {
+        eprintln!("\n\nOpening {target}");
+        if let Err(err) = open::that(&target) {
+            eprintln!("Failed opening {target}: {err}");
+        }
+    }
+}
+
 fn write_msg(buffer: &mut impl io::Write, file_path: Option<&Path>) -> Option<()> {
     let report_path = match file_path {
         Some(fp) => format!("{}", fp.display()),
@@ -190,7 +237,7 @@ for issues with similar explanations at the following url:
 and you can submit a new crash report using the report file as a template if there are no existing
 issues matching your own (the following link has the crash report label)
 
-- https://github.com/Inlyne-Project/inlyne/issues/new?labels=C-crash-report
+- {NEW_ISSUE_URL}
 
 We take privacy seriously, and do not preform any auotmated error collection. In order to improve
 the software we, rely on people to submit reports.
@@ -208,6 +255,24 @@ mod tests {
 
     use human_panic::report::Method;
 
+    #[test]
+    fn auto_open_target() {
+        let report_path = std::env::temp_dir().join("inlyne-report-1234.md");
+
+        assert_eq!(AutoOpen::default(), AutoOpen::Never);
+        assert_eq!(AutoOpen::Never.target(Some(&report_path)), None);
+        assert_eq!(
+            AutoOpen::ReportDir.target(Some(&report_path)),
+            Some(std::env::temp_dir().display().to_string()),
+        );
+        // Nothing to open when the report failed to persist
+        assert_eq!(AutoOpen::ReportDir.target(None), None);
+        assert_eq!(
+            AutoOpen::NewIssue.target(None).as_deref(),
+            Some(NEW_ISSUE_URL)
+        );
+    }
+
     #[test]
     fn snap_report() {
         let mut report = Report::new(
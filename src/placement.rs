@@ -0,0 +1,181 @@
+//! Working out where to open the window for `--win-pos center` and `--win-pos cursor`
+//!
+//! This all happens before the window exists, so the window's size is whatever was requested, or
+//! what `winit` would pick by default otherwise
+
+use crate::opts::Placement;
+
+use winit::dpi::{LogicalSize, PhysicalPosition, PhysicalSize};
+use winit::monitor::MonitorHandle;
+
+/// The size `winit` gives windows that don't ask for one
+const DEFAULT_SIZE: LogicalSize<f64> = LogicalSize::new(800., 600.);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Monitor {
+    pub position: PhysicalPosition<i32>,
+    pub size: PhysicalSize<u32>,
+    pub scale_factor: f64,
+}
+
+impl From<&MonitorHandle> for Monitor {
+    fn from(monitor: &MonitorHandle) -> Self {
+        Self {
+            position: monitor.position(),
+            size: monitor.size(),
+            scale_factor: monitor.scale_factor(),
+        }
+    }
+}
+
+impl Monitor {
+    fn contains(&self, point: PhysicalPosition<i32>) -> bool {
+        let x = i64::from(point.x) - i64::from(self.position.x);
+        let y = i64::from(point.y) - i64::from(self.position.y);
+        (0..i64::from(self.size.width)).contains(&x)
+            && (0..i64::from(self.size.height)).contains(&y)
+    }
+}
+
+/// The top-left corner of a window that's `size` big (when known) placed by `placement`
+///
+/// `primary` is the primary monitor, and `cursor` is where the mouse cursor is, when the platform
+/// can tell us
+pub fn window_position(
+    placement: Placement,
+    monitors: &[Monitor],
+    primary: Option<Monitor>,
+    cursor: Option<PhysicalPosition<i32>>,
+    size: Option<PhysicalSize<u32>>,
+) -> Option<PhysicalPosition<i32>> {
+    let fallback = primary.or_else(|| monitors.first().copied());
+    match (placement, cursor) {
+        (Placement::Center, _) => {
+            let monitor = fallback?;
+            let size = size.unwrap_or_else(|| DEFAULT_SIZE.to_physical(monitor.scale_factor));
+            Some(centered_on(monitor, size))
+        }
+        (Placement::Cursor, Some(cursor)) => {
+            let monitor = monitors
+                .iter()
+                .copied()
+                .find(|monitor| monitor.contains(cursor))
+                .or(fallback)?;
+            let size = size.unwrap_or_else(|| DEFAULT_SIZE.to_physical(monitor.scale_factor));
+            Some(around(cursor, monitor, size))
+        }
+        (Placement::Cursor, None) => {
+            tracing::warn!("Couldn't find the mouse cursor. Centering the window instead");
+            window_position(Placement::Center, monitors, primary, None, size)
+        }
+    }
+}
+
+fn centered_on(monitor: Monitor, size: PhysicalSize<u32>) -> PhysicalPosition<i32> {
+    let center = |start: i32, monitor_len: u32, len: u32| {
+        start + ((i64::from(monitor_len) - i64::from(len)) / 2) as i32
+    };
+    PhysicalPosition::new(
+        center(monitor.position.x, monitor.size.width, size.width),
+        center(monitor.position.y, monitor.size.height, size.height),
+    )
+}
+
+/// Centers the window on `point` while keeping as much of it on `monitor` as possible
+fn around(
+    point: PhysicalPosition<i32>,
+    monitor: Monitor,
+    size: PhysicalSize<u32>,
+) -> PhysicalPosition<i32> {
+    let clamp = |point: i32, start: i32, monitor_len: u32, len: u32| {
+        let ideal = i64::from(point) - i64::from(len) / 2;
+        let end = i64::from(start) + i64::from(monitor_len) - i64::from(len);
+        // A window that's bigger than the monitor starts at its edge
+        ideal.min(end).max(i64::from(start)) as i32
+    };
+    PhysicalPosition::new(
+        clamp(point.x, monitor.position.x, monitor.size.width, size.width),
+        clamp(
+            point.y,
+            monitor.position.y,
+            monitor.size.height,
+            size.height,
+        ),
+    )
+}
+
+/// Where the mouse cursor is on the screen, if the platform lets us know
+///
+/// Wayland doesn't give out the cursor's position at all, and `winit` only reports it for events
+/// within a window, so this asks the X server directly
+#[cfg(all(feature = "x11", not(any(target_os = "macos", windows))))]
+pub fn cursor_position() -> Option<PhysicalPosition<i32>> {
+    use x11rb::connection::Connection;
+    use x11rb::protocol::xproto::ConnectionExt;
+
+    let (conn, screen) = x11rb::connect(None).ok()?;
+    let root = conn.setup().roots.get(screen)?.root;
+    let pointer = conn.query_pointer(root).ok()?.reply().ok()?;
+    Some(PhysicalPosition::new(
+        pointer.root_x.into(),
+        pointer.root_y.into(),
+    ))
+}
+
+#[cfg(not(all(feature = "x11", not(any(target_os = "macos", windows)))))]
+pub fn cursor_position() -> Option<PhysicalPosition<i32>> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor(x: i32, width: u32, scale_factor: f64) -> Monitor {
+        Monitor {
+            position: PhysicalPosition::new(x, 0),
+            size: PhysicalSize::new(width, 1_000),
+            scale_factor,
+        }
+    }
+
+    #[test]
+    fn centering() {
+        let left = monitor(-1_000, 1_000, 1.);
+        let main = monitor(0, 2_000, 2.);
+        let monitors = [left, main];
+        let size = PhysicalSize::new(600, 400);
+
+        let pos = window_position(Placement::Center, &monitors, Some(main), None, Some(size));
+        assert_eq!(pos, Some(PhysicalPosition::new(700, 300)));
+        // Without a primary monitor the first one gets used. The default size depends on its scale
+        let pos = window_position(Placement::Center, &monitors, None, None, None);
+        assert_eq!(pos, Some(PhysicalPosition::new(-900, 200)));
+        assert_eq!(
+            window_position(Placement::Center, &[], None, None, None),
+            None
+        );
+    }
+
+    #[test]
+    fn around_the_cursor() {
+        let left = monitor(-1_000, 1_000, 1.);
+        let main = monitor(0, 2_000, 1.);
+        let monitors = [left, main];
+        let size = Some(PhysicalSize::new(600, 400));
+        let at_cursor = |x, y| {
+            let cursor = Some(PhysicalPosition::new(x, y));
+            window_position(Placement::Cursor, &monitors, Some(main), cursor, size)
+        };
+
+        assert_eq!(at_cursor(1_000, 500), Some(PhysicalPosition::new(700, 300)));
+        // Kept within the monitor that the cursor is on
+        assert_eq!(at_cursor(-10, 990), Some(PhysicalPosition::new(-600, 600)));
+        assert_eq!(at_cursor(10, 10), Some(PhysicalPosition::new(0, 0)));
+        // Falls back to centering when the cursor can't be found
+        assert_eq!(
+            window_position(Placement::Cursor, &monitors, Some(main), None, size),
+            Some(PhysicalPosition::new(700, 300))
+        );
+    }
+}
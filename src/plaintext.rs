@@ -0,0 +1,146 @@
+//! Extracting the text of a rendered document in reading order (`--dump-text`)
+
+use crate::positioner::Positioned;
+use crate::table::Table;
+use crate::text::TextBox;
+use crate::Element;
+
+/// The text within `elements` with paragraphs separated by blank lines
+///
+/// Tables come out row by row with their cells separated by tabs. The contents of collapsed
+/// sections are only included with `include_hidden`
+pub fn extract(elements: &[Positioned<Element>], include_hidden: bool) -> String {
+    let mut lines = Vec::new();
+    push_elements(&mut lines, elements, include_hidden);
+    while lines.last().map_or(false, String::is_empty) {
+        lines.pop();
+    }
+
+    let mut text = lines.join("\n");
+    if !text.is_empty() {
+        text.push('\n');
+    }
+    text
+}
+
+fn push_elements(lines: &mut Vec<String>, elements: &[Positioned<Element>], include_hidden: bool) {
+    for element in elements {
+        match &element.inner {
+            Element::TextBox(text_box) => lines.extend(text_box_lines(text_box)),
+            // Spacers are what separate paragraphs, but runs of them shouldn't pile up
+            Element::Spacer(_) => {
+                if lines.last().map_or(false, |line| !line.is_empty()) {
+                    lines.push(String::new());
+                }
+            }
+            Element::Image(_) => {}
+            Element::Table(table) => lines.extend(table_lines(table)),
+            Element::Row(row) => push_elements(lines, &row.elements, include_hidden),
+            Element::Section(section) => {
+                if let Some(summary) = section.summary.as_ref() {
+                    push_elements(lines, std::slice::from_ref(summary), include_hidden);
+                }
+                if include_hidden || !*section.hidden.borrow() {
+                    push_elements(lines, &section.elements, include_hidden);
+                }
+            }
+        }
+    }
+}
+
+fn text_box_lines(text_box: &TextBox) -> Vec<String> {
+    let mut text = match text_box.is_checkbox {
+        Some(true) => String::from("[x] "),
+        Some(false) => String::from("[ ] "),
+        None => String::new(),
+    };
+    text.extend(text_box.texts.iter().map(|text| text.text.as_str()));
+    let text = text.strip_suffix('\n').unwrap_or(&text);
+    text.lines().map(str::to_owned).collect()
+}
+
+fn table_lines(table: &Table) -> Vec<String> {
+    let cell_text = |cell: &TextBox| text_box_lines(cell).join(" ");
+    let row_text = |row: &[TextBox]| row.iter().map(cell_text).collect::<Vec<_>>().join("\t");
+    std::iter::once(table.headers.as_slice())
+        .chain(table.rows.iter().map(Vec::as_slice))
+        .filter(|row| !row.is_empty())
+        .map(row_text)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::positioner::{Row, Section, Spacer};
+    use crate::text::Text;
+
+    fn text_box(texts: &[&str]) -> TextBox {
+        let texts = texts
+            .iter()
+            .map(|text| Text::new(text.to_string(), 1., [0.; 4]))
+            .collect();
+        TextBox::new(texts, 1.)
+    }
+
+    fn section(hidden: bool, elements: Vec<Positioned<Element>>) -> Element {
+        Element::Section(Section {
+            elements,
+            hidpi_scale: 1.,
+            hidden: hidden.into(),
+            summary: Box::new(Some(Positioned::new(Element::TextBox(text_box(&[
+                "Summary",
+            ]))))),
+        })
+    }
+
+    #[test]
+    fn reading_order() {
+        let mut checkbox = text_box(&["Done"]);
+        checkbox.is_checkbox = Some(true);
+        let table = Table {
+            headers: vec![text_box(&["Key"]), text_box(&["Value"])],
+            rows: vec![vec![text_box(&["a"]), text_box(&["1"])]],
+            ..Table::new()
+        };
+        let row = Row {
+            elements: vec![Positioned::new(Element::TextBox(text_box(&["In a row"])))],
+            hidpi_scale: 1.,
+            baseline: false,
+        };
+        let elements: Vec<_> = [
+            Element::TextBox(text_box(&["Hello ", "world"])),
+            Element::Spacer(Spacer::invisible()),
+            Element::Spacer(Spacer::visible()),
+            Element::TextBox(text_box(&["fn main() {}\n", "// code\n"])),
+            Element::Spacer(Spacer::invisible()),
+            Element::TextBox(checkbox),
+            Element::Table(table),
+            Element::Row(row),
+            section(
+                true,
+                vec![Positioned::new(Element::TextBox(text_box(&["Hidden"])))],
+            ),
+            Element::Spacer(Spacer::invisible()),
+        ]
+        .into_iter()
+        .map(Positioned::new)
+        .collect();
+
+        let visible = "\
+Hello world
+
+fn main() {}
+// code
+
+[x] Done
+Key\tValue
+a\t1
+In a row
+Summary
+";
+        assert_eq!(extract(&elements, false), visible);
+        assert_eq!(extract(&elements, true), format!("{visible}Hidden\n"));
+        assert_eq!(extract(&[], false), "");
+    }
+}
@@ -7,11 +7,14 @@ use taffy::Taffy;
 
 use crate::image::Image;
 use crate::text::TextSystem;
+use crate::toc::Heading;
 use crate::utils::{Align, Point, Rect, Size};
 use crate::{debug_impls, Element};
 
 pub const DEFAULT_PADDING: f32 = 5.;
 pub const DEFAULT_MARGIN: f32 = 100.;
+/// How many lines of text tall an image can be while still sharing a baseline with the text
+pub const MAX_BASELINE_IMAGE_LINES: f32 = 2.;
 
 #[derive(Debug, PartialEq)]
 pub struct Positioned<T> {
@@ -42,9 +45,15 @@ impl<T> Positioned<T> {
 pub struct Positioner {
     pub screen_size: Size,
     pub reserved_height: f32,
+    /// Space kept clear above the document (e.g. for the tab strip)
+    pub top_inset: f32,
+    /// Space kept clear to the right of the document (e.g. for the table of contents)
+    pub right_inset: f32,
     pub hidpi_scale: f32,
     pub page_width: f32,
     pub anchors: HashMap<String, f32>,
+    /// The header level and text for each anchor that came from a header
+    pub headings: HashMap<String, Heading>,
     pub taffy: Taffy,
 }
 
@@ -54,14 +63,22 @@ impl Positioner {
         taffy.disable_rounding();
         Self {
             reserved_height: DEFAULT_PADDING * hidpi_scale,
+            top_inset: 0.,
+            right_inset: 0.,
             hidpi_scale,
             page_width,
             screen_size,
             anchors: HashMap::new(),
+            headings: HashMap::new(),
             taffy,
         }
     }
 
+    /// Width of the window that's left over for the document
+    pub fn content_width(&self) -> f32 {
+        (self.screen_size.0 - self.right_inset).max(0.)
+    }
+
     // Positions the element but does not update reserved_height
     pub fn position(
         &mut self,
@@ -69,24 +86,40 @@ impl Positioner {
         element: &mut Positioned<Element>,
         zoom: f32,
     ) -> anyhow::Result<()> {
-        let centering = (self.screen_size.0 - self.page_width).max(0.) / 2.;
+        let centering = (self.content_width() - self.page_width).max(0.) / 2.;
+
+        if let Element::Image(image) = &mut element.inner {
+            if let Some(alt) = image.take_failed_alt() {
+                element.inner = alt.into();
+            }
+        }
 
         let bounds = match &mut element.inner {
             Element::TextBox(text_box) => {
                 let indent = text_box.indent;
                 let pos = (DEFAULT_MARGIN + indent + centering, self.reserved_height);
 
-                let size = text_box.size(
+                // The text gets laid out to the right of the line number gutter
+                let gutter = text_box.gutter_width(zoom);
+                let mut size = text_box.size(
                     text_system,
                     (
-                        (self.screen_size.0 - pos.0 - DEFAULT_MARGIN - centering).max(0.),
+                        (self.content_width() - pos.0 - gutter - DEFAULT_MARGIN - centering)
+                            .max(0.),
                         f32::INFINITY,
                     ),
                     zoom,
                 );
+                size.0 += gutter;
 
-                if let Some(ref anchor_name) = text_box.is_anchor {
-                    let _ = self.anchors.insert(anchor_name.clone(), pos.1);
+                for anchor_name in text_box.anchors() {
+                    // Scrolling to an anchor should leave it just below the inset
+                    let _ = self
+                        .anchors
+                        .insert(anchor_name.clone(), pos.1 - self.top_inset);
+                }
+                if let (Some(anchor), Some(heading)) = (&text_box.is_anchor, &text_box.heading) {
+                    let _ = self.headings.insert(anchor.clone(), heading.clone());
                 }
 
                 Rect::new(pos, size)
@@ -96,15 +129,32 @@ impl Positioner {
                 (0., spacer.space * self.hidpi_scale * zoom),
             ),
             Element::Image(image) => {
+                let page_size = (
+                    self.content_width().min(self.page_width),
+                    self.screen_size.1,
+                );
                 let size = image
-                    .size(
-                        (self.screen_size.0.min(self.page_width), self.screen_size.1),
-                        zoom,
-                    )
-                    .unwrap_or_default();
+                    .size(page_size, zoom)
+                    .or_else(|| image.placeholder_size(page_size, zoom));
+                let size = match (size, image.loading_alt()) {
+                    (Some(size), _) => size,
+                    // Hold the alt text's space until the image shows up, using whichever of the
+                    // `width` and `height` is set over the text's
+                    (None, Some(alt)) => {
+                        let (width, height) = image.placeholder_sides(page_size, zoom);
+                        let max_width =
+                            width.unwrap_or((page_size.0 - 2. * DEFAULT_MARGIN).max(0.));
+                        let alt_size = alt.size(text_system, (max_width, f32::INFINITY), zoom);
+                        (width.unwrap_or(alt_size.0), height.unwrap_or(alt_size.1))
+                    }
+                    (None, None) => Size::default(),
+                };
                 match image.is_aligned {
                     Some(Align::Center) => Rect::new(
-                        (self.screen_size.0 / 2. - size.0 / 2., self.reserved_height),
+                        (
+                            self.content_width() / 2. - size.0 / 2.,
+                            self.reserved_height,
+                        ),
                         size,
                     ),
                     _ => Rect::new((DEFAULT_MARGIN + centering, self.reserved_height), size),
@@ -112,15 +162,9 @@ impl Positioner {
             }
             Element::Table(table) => {
                 let pos = (DEFAULT_MARGIN + centering, self.reserved_height);
-                let layout = table.layout(
-                    text_system,
-                    &mut self.taffy,
-                    (
-                        self.screen_size.0 - pos.0 - DEFAULT_MARGIN - centering,
-                        f32::INFINITY,
-                    ),
-                    zoom,
-                )?;
+                let width = self.content_width() - pos.0 - DEFAULT_MARGIN - centering;
+                let layout =
+                    table.layout(text_system, &mut self.taffy, (width, f32::INFINITY), zoom)?;
                 Rect::new(
                     (DEFAULT_MARGIN + centering, self.reserved_height),
                     layout.size,
@@ -131,18 +175,40 @@ impl Positioner {
                 let mut inner_reserved_height: f32 = 0.;
                 let mut max_height: f32 = 0.;
                 let mut max_width: f32 = 0.;
+                // Images that are much taller than the text they're in line with get a line to
+                // themselves instead of stretching the text's line around them
+                let max_inline_height = row
+                    .baseline
+                    .then(|| {
+                        row.elements
+                            .iter()
+                            .filter_map(|element| match &element.inner {
+                                Element::TextBox(text_box) => Some(text_box.line_height(zoom)),
+                                _ => None,
+                            })
+                            .reduce(f32::max)
+                    })
+                    .flatten()
+                    .map(|line_height| line_height * MAX_BASELINE_IMAGE_LINES);
+                let mut after_own_line = false;
                 for element in &mut row.elements {
                     self.position(text_system, element, zoom)?;
+                    let is_image = matches!(element.inner, Element::Image(_));
                     let element_bounds = element
                         .bounds
                         .as_mut()
                         .context("Element didn't have bounds")?;
 
+                    let own_line = is_image
+                        && max_inline_height.is_some_and(|max| element_bounds.size.1 > max);
+                    let line_has_elements = reserved_width > DEFAULT_MARGIN + centering;
                     let target_width = reserved_width
                         + DEFAULT_PADDING * self.hidpi_scale * zoom
                         + element_bounds.size.0;
                     // Row would be too long with this element so add another line
-                    if target_width > self.screen_size.0 - DEFAULT_MARGIN - centering {
+                    if target_width > self.content_width() - DEFAULT_MARGIN - centering
+                        || (line_has_elements && (own_line || after_own_line))
+                    {
                         max_width = max_width.max(reserved_width);
                         reserved_width = DEFAULT_MARGIN
                             + centering
@@ -158,9 +224,14 @@ impl Positioner {
                         reserved_width = target_width;
                     }
                     element_bounds.pos.1 = self.reserved_height + inner_reserved_height;
+                    after_own_line = own_line;
                 }
                 max_width = max_width.max(reserved_width);
                 inner_reserved_height += max_height + DEFAULT_PADDING * self.hidpi_scale * zoom;
+                if row.baseline {
+                    inner_reserved_height =
+                        self.align_row_baselines(text_system, &mut row.elements, zoom);
+                }
                 Rect::new(
                     (DEFAULT_MARGIN + centering, self.reserved_height),
                     (
@@ -208,6 +279,46 @@ impl Positioner {
         Ok(())
     }
 
+    /// Re-stacks the lines of an already positioned row so that each line shares a baseline,
+    /// returning the row's new height
+    fn align_row_baselines(
+        &self,
+        text_system: &mut TextSystem,
+        elements: &mut [Positioned<Element>],
+        zoom: f32,
+    ) -> f32 {
+        let centering = (self.content_width() - self.page_width).max(0.) / 2.;
+        // Elements on the same line were all given the same top
+        let mut lines: Vec<Vec<(f32, f32)>> = Vec::new();
+        let mut last_top = None;
+        for element in elements.iter() {
+            let Some(bounds) = &element.bounds else {
+                continue;
+            };
+            let baseline = match &element.inner {
+                Element::TextBox(text_box) => {
+                    let width =
+                        (self.content_width() - bounds.pos.0 - DEFAULT_MARGIN - centering).max(0.);
+                    text_box.baseline(text_system, (width, f32::INFINITY), zoom)
+                }
+                _ => bounds.size.1,
+            };
+            if last_top != Some(bounds.pos.1) {
+                lines.push(Vec::new());
+                last_top = Some(bounds.pos.1);
+            }
+            lines.last_mut().unwrap().push((baseline, bounds.size.1));
+        }
+
+        let line_gap = DEFAULT_PADDING * self.hidpi_scale * zoom;
+        let (offsets, height) = align_baselines(&lines, line_gap);
+        let positioned = elements.iter_mut().filter_map(|e| e.bounds.as_mut());
+        for (bounds, offset) in positioned.zip(offsets.into_iter().flatten()) {
+            bounds.pos.1 = self.reserved_height + offset;
+        }
+        height
+    }
+
     // Resets reserved height and positions every element again
     pub fn reposition(
         &mut self,
@@ -215,7 +326,7 @@ impl Positioner {
         elements: &mut [Positioned<Element>],
         zoom: f32,
     ) -> anyhow::Result<()> {
-        self.reserved_height = DEFAULT_PADDING * self.hidpi_scale * zoom;
+        self.reserved_height = self.top_inset + DEFAULT_PADDING * self.hidpi_scale * zoom;
 
         for element in elements {
             self.position(text_system, element, zoom)?;
@@ -261,6 +372,8 @@ impl fmt::Debug for Spacer {
 pub struct Row {
     pub elements: Vec<Positioned<Element>>,
     pub hidpi_scale: f32,
+    /// Whether elements on the same line share a baseline instead of being aligned to the top
+    pub baseline: bool,
 }
 
 impl Row {
@@ -268,8 +381,40 @@ impl Row {
         Self {
             elements: vec![Positioned::new(image)],
             hidpi_scale,
+            baseline: false,
         }
     }
+
+    /// An empty row for mixing text and inline images
+    pub fn baseline(hidpi_scale: f32) -> Self {
+        Self {
+            elements: Vec::new(),
+            hidpi_scale,
+            baseline: true,
+        }
+    }
+}
+
+/// Vertically aligns the elements on each line of a row to a shared baseline
+///
+/// Takes the `(baseline, height)` of each element grouped by line, where the baseline is the
+/// offset from the element's top (the bottom for images). Returns each element's offset from the
+/// top of the row along with the row's total height
+pub fn align_baselines(lines: &[Vec<(f32, f32)>], line_gap: f32) -> (Vec<Vec<f32>>, f32) {
+    let mut line_top = 0.;
+    let offsets = lines
+        .iter()
+        .map(|line| {
+            let baseline = line.iter().fold(0., |max: f32, &(b, _)| max.max(b));
+            let height = line
+                .iter()
+                .fold(0., |max: f32, &(b, h)| max.max(baseline - b + h));
+            let offsets = line.iter().map(|&(b, _)| line_top + baseline - b).collect();
+            line_top += height + line_gap;
+            offsets
+        })
+        .collect();
+    (offsets, line_top)
 }
 
 #[derive(Debug, PartialEq)]
@@ -290,3 +435,19 @@ impl Section {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn baseline_alignment() {
+        // A line of text (baseline 14 of 20) with a taller and a shorter badge
+        let lines = [vec![(14., 20.), (30., 30.), (10., 10.)], vec![(14., 20.)]];
+        let (offsets, height) = align_baselines(&lines, 5.);
+        // Everything's bottom (or baseline for text) sits at 30 down from the top
+        assert_eq!(offsets, [vec![16., 0., 20.], vec![41.]]);
+        // The text's descent hangs below the tallest badge
+        assert_eq!(height, 36. + 5. + 20. + 5.);
+    }
+}
@@ -0,0 +1,72 @@
+//! A user-placed marker for the current reading position
+//!
+//! The mark is stored as a position within the document (rather than on the screen), so it stays
+//! put while scrolling around. It's drawn as a thin rule across the window whenever it's in view
+
+use crate::utils::{Rect, Size};
+
+/// Height of the rule (before hidpi scaling)
+const RULE_HEIGHT: f32 = 2.;
+
+#[derive(Debug, Default)]
+pub struct ReadingMark(Option<f32>);
+
+impl ReadingMark {
+    /// Marks the line at the top of the window
+    pub fn set(&mut self, scroll_y: f32) {
+        self.0 = Some(scroll_y);
+    }
+
+    pub fn clear(&mut self) {
+        self.0 = None;
+    }
+
+    /// The scroll position that brings the mark back to the top of the window
+    pub fn get(&self) -> Option<f32> {
+        self.0
+    }
+
+    /// The mark's rule in screen coordinates if it's currently visible
+    pub fn rule(&self, scroll_y: f32, screen_size: Size, hidpi_scale: f32) -> Option<Rect> {
+        let y = self.0? - scroll_y;
+        let height = RULE_HEIGHT * hidpi_scale;
+        (-height..screen_size.1)
+            .contains(&y)
+            .then(|| Rect::new((0., y), (screen_size.0, height)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_and_goto_mark() {
+        let screen = (800., 600.);
+        let mut mark = ReadingMark::default();
+        assert_eq!(mark.get(), None);
+        assert_eq!(mark.rule(0., screen, 1.), None);
+
+        mark.set(1_250.);
+        // Scrolled well past the mark, so it's out of view
+        assert_eq!(mark.rule(3_000., screen, 1.), None);
+        // Jumping back puts the rule at the top of the window
+        let scroll_y = mark.get().unwrap();
+        assert_eq!(scroll_y, 1_250.);
+        assert_eq!(
+            mark.rule(scroll_y, screen, 2.),
+            Some(Rect::new((0., 0.), (800., 4.)))
+        );
+        // and it follows along while scrolling
+        assert_eq!(
+            mark.rule(scroll_y - 100., screen, 1.),
+            Some(Rect::new((0., 100.), (800., 2.)))
+        );
+
+        // Setting it again moves it
+        mark.set(0.);
+        assert_eq!(mark.get(), Some(0.));
+        mark.clear();
+        assert_eq!(mark.get(), None);
+    }
+}
@@ -0,0 +1,75 @@
+//! A brief flash around the window's border to confirm that the file was reloaded
+//!
+//! The flash is drawn as a border that shrinks away over [`DURATION`]. While it's active we keep
+//! requesting redraws to drive the animation
+
+use std::time::{Duration, Instant};
+
+pub const DURATION: Duration = Duration::from_millis(400);
+/// Border width at the start of the flash (before hidpi scaling)
+pub const WIDTH: f32 = 6.;
+
+#[derive(Debug, Default)]
+pub struct ReloadFlash {
+    enabled: bool,
+    started: Option<Instant>,
+}
+
+impl ReloadFlash {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            started: None,
+        }
+    }
+
+    /// Starts (or restarts) the flash if it's enabled
+    pub fn trigger(&mut self, now: Instant) {
+        if self.enabled {
+            self.started = Some(now);
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.started.is_some()
+    }
+
+    /// How much of the flash is left at `now` in the range `(0, 1]`
+    ///
+    /// Returns `None` and resets the flash once it's finished
+    pub fn remaining(&mut self, now: Instant) -> Option<f32> {
+        let elapsed = now.saturating_duration_since(self.started?);
+        if elapsed >= DURATION {
+            self.started = None;
+            None
+        } else {
+            Some(1. - elapsed.as_secs_f32() / DURATION.as_secs_f32())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_on_reload() {
+        let now = Instant::now();
+
+        let mut disabled = ReloadFlash::new(false);
+        disabled.trigger(now);
+        assert!(!disabled.is_active());
+        assert_eq!(disabled.remaining(now), None);
+
+        let mut flash = ReloadFlash::new(true);
+        assert!(!flash.is_active());
+        flash.trigger(now);
+        assert!(flash.is_active());
+        assert_eq!(flash.remaining(now), Some(1.));
+        assert_eq!(flash.remaining(now + DURATION / 2), Some(0.5));
+
+        // Finishing the animation clears it out
+        assert_eq!(flash.remaining(now + DURATION), None);
+        assert!(!flash.is_active());
+    }
+}
@@ -1,30 +1,49 @@
 use std::borrow::Cow;
+use std::ops::{Range, RangeInclusive};
 use std::sync::{Arc, Mutex};
 use std::time::Instant;
 
+use crate::anchor_jump::{self, AnchorList};
 use crate::color::{native_color, Theme};
 use crate::fonts::get_fonts;
 use crate::image::ImageRenderer;
 use crate::metrics::{histogram, HistTag};
-use crate::opts::FontOptions;
+use crate::minimap;
+use crate::opts::{
+    BackgroundPattern, FontOptions, ImageBackground, ImageLayer, PageWidth, ScrollbarSection,
+};
 use crate::positioner::{Positioned, Positioner, DEFAULT_MARGIN};
+use crate::reading_mark::ReadingMark;
+use crate::reload_flash::{self, ReloadFlash};
 use crate::selection::Selection;
-use crate::table::TABLE_ROW_GAP;
-use crate::text::{CachedTextArea, TextCache, TextSystem};
+use crate::status_bar::{self, StatusBar};
+use crate::tabs::{self, TabBar};
+use crate::text::{self, CachedTextArea, Text, TextBox, TextCache, TextSystem};
+use crate::toc::{self, TocEntry};
 use crate::utils::{Point, Rect, Size};
+use crate::whitespace;
 use crate::Element;
 
 use anyhow::{Context, Ok};
 use bytemuck::{Pod, Zeroable};
 use glyphon::{Resolution, SwashCache, TextArea, TextAtlas, TextRenderer};
+use image::RgbaImage;
 use lyon::geom::euclid::Point2D;
 use lyon::geom::Box2D;
 use lyon::path::Polygon;
 use lyon::tessellation::*;
 use wgpu::util::DeviceExt;
-use wgpu::{BindGroup, Buffer, IndexFormat, MultisampleState, TextureFormat};
+use wgpu::{
+    BindGroup, Buffer, CompositeAlphaMode, IndexFormat, MultisampleState, PresentMode,
+    TextureFormat,
+};
+use winit::dpi::PhysicalSize;
 use winit::window::Window;
 
+pub const DEFAULT_SCROLLBAR_WIDTH: f32 = DEFAULT_MARGIN / 4.;
+/// Horizontal padding around `<kbd>` text (before scaling)
+const KBD_PADDING: f32 = 2.;
+
 #[repr(C)]
 #[derive(Clone, Copy, Pod, Zeroable, Debug)]
 pub struct Vertex {
@@ -32,22 +51,56 @@ pub struct Vertex {
     pub color: [f32; 4],
 }
 
+/// What the renderer draws to
+pub enum RenderTarget<'a> {
+    Window(&'a Window),
+    /// An offscreen texture of the given size, for rendering without a window
+    Offscreen(PhysicalSize<u32>),
+}
+
 pub struct Renderer {
     pub config: wgpu::SurfaceConfiguration,
-    pub surface: wgpu::Surface,
+    /// The window's surface (`None` when rendering offscreen)
+    pub surface: Option<wgpu::Surface>,
     pub surface_format: TextureFormat,
     pub device: wgpu::Device,
     pub render_pipeline: wgpu::RenderPipeline,
     pub queue: wgpu::Queue,
     pub text_system: TextSystem,
     pub scroll_y: f32,
+    /// Where a smooth scroll is headed, if one is in progress
+    pub scroll_target: Option<f32>,
+    pub smooth_scroll: bool,
     pub lyon_buffer: VertexBuffers<Vertex, u16>,
     pub hidpi_scale: f32,
+    /// The page's width in pixels after resolving `max_page_width` and `max_line_chars`
     pub page_width: f32,
+    pub max_page_width: Option<PageWidth>,
+    pub max_line_chars: Option<usize>,
     pub image_renderer: ImageRenderer,
     pub theme: Theme,
     pub zoom: f32,
     pub positioner: Positioner,
+    pub quote_block_background: bool,
+    pub minimap: bool,
+    /// The active search query, if any, to highlight matches for
+    pub search_query: Option<String>,
+    pub reload_flash: ReloadFlash,
+    pub reading_mark: ReadingMark,
+    pub scrollbar: ScrollbarSection,
+    /// Whether to draw markers over the whitespace in code blocks
+    pub show_whitespace: bool,
+    /// Width of the border around code blocks (before scaling), if any
+    pub code_block_border: Option<f32>,
+    pub image_background: Option<ImageBackground>,
+    pub image_layer: ImageLayer,
+    /// Deferred images start loading once they're within this many (logical) pixels of the screen
+    pub lazy_image_distance: Option<f32>,
+    pub status_bar: Option<StatusBar>,
+    pub tab_bar: Option<TabBar>,
+    pub anchor_list: Option<AnchorList>,
+    /// Whether the table of contents is shown
+    pub toc: bool,
 }
 
 impl Renderer {
@@ -59,31 +112,63 @@ impl Renderer {
         self.positioner.screen_size
     }
 
+    /// The window's size without the room kept to the right of the document, which is what the
+    /// document gets laid out within
+    pub fn layout_size(&self) -> Size {
+        (self.positioner.content_width(), self.screen_height())
+    }
+
+    /// Height of the window that's left over for the document
+    pub fn content_height(&self) -> f32 {
+        status_bar::content_height(
+            self.screen_height(),
+            self.status_bar.as_ref(),
+            self.hidpi_scale,
+        )
+    }
+
     pub async fn new(
-        window: &Window,
+        target: RenderTarget<'_>,
         theme: Theme,
         hidpi_scale: f32,
-        page_width: f32,
+        max_page_width: Option<PageWidth>,
         font_opts: FontOptions,
+        quote_block_background: bool,
+        minimap: bool,
     ) -> anyhow::Result<Self> {
-        let size = window.inner_size();
         let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
             backends: wgpu::Backends::all(),
             dx12_shader_compiler: wgpu::Dx12Compiler::Fxc,
         });
-        let surface = unsafe {
-            instance
-                .create_surface(window)
-                .expect("Could not create surface")
+        let (surface, size) = match target {
+            RenderTarget::Window(window) => {
+                let surface = unsafe {
+                    instance
+                        .create_surface(window)
+                        .expect("Could not create surface")
+                };
+                (Some(surface), window.inner_size())
+            }
+            RenderTarget::Offscreen(size) => (None, size),
         };
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
+        let request_adapter = |force_fallback_adapter| {
+            instance.request_adapter(&wgpu::RequestAdapterOptions {
                 power_preference: wgpu::PowerPreference::default(),
-                force_fallback_adapter: false,
-                compatible_surface: Some(&surface),
+                force_fallback_adapter,
+                compatible_surface: surface.as_ref(),
             })
-            .await
-            .context("Failed to find an appropriate adapter")?;
+        };
+        let adapter = match request_adapter(false).await {
+            Some(adapter) => adapter,
+            None => {
+                tracing::warn!("No suitable GPU adapter found. Falling back to software rendering");
+                request_adapter(true).await.context(
+                    "Failed to find a graphics adapter to render with. inlyne needs a GPU (or a \
+                    software renderer like llvmpipe) with working Vulkan, Metal, DX12, or OpenGL \
+                    drivers. Updating your graphics drivers may help",
+                )?
+            }
+        };
 
         let (device, queue) = adapter
             .request_device(
@@ -107,13 +192,26 @@ impl Renderer {
             push_constant_ranges: &[],
         });
 
-        let caps = surface.get_capabilities(&adapter);
-        let surface_format = caps
-            .formats
-            .iter()
-            .copied()
-            .find(|f| f.is_srgb())
-            .unwrap_or(caps.formats[0]);
+        let (surface_format, present_mode, alpha_mode) = match &surface {
+            Some(surface) => {
+                let caps = surface.get_capabilities(&adapter);
+                let surface_format = caps
+                    .formats
+                    .iter()
+                    .copied()
+                    .find(|f| f.is_srgb())
+                    .unwrap_or(caps.formats[0]);
+                let present_mode = pick_present_mode(&caps.present_modes)?;
+                tracing::info!("Using present mode: {present_mode:?}");
+                (surface_format, present_mode, caps.alpha_modes[0])
+            }
+            // Matches the channel order of the image that gets read back
+            None => (
+                TextureFormat::Rgba8UnormSrgb,
+                PresentMode::Fifo,
+                CompositeAlphaMode::Opaque,
+            ),
+        };
 
         let vertex_buffers = [wgpu::VertexBufferLayout {
             array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
@@ -145,12 +243,14 @@ impl Renderer {
             format: surface_format,
             width: size.width,
             height: size.height,
-            present_mode: wgpu::PresentMode::Fifo,
-            alpha_mode: caps.alpha_modes[0],
+            present_mode,
+            alpha_mode,
             view_formats: vec![],
         };
 
-        surface.configure(&device, &config);
+        if let Some(surface) = &surface {
+            surface.configure(&device, &config);
+        }
         let image_renderer = ImageRenderer::new(&device, &surface_format);
 
         let font_system = Arc::new(Mutex::new(get_fonts(&font_opts)));
@@ -169,7 +269,9 @@ impl Renderer {
 
         let lyon_buffer: VertexBuffers<Vertex, u16> = VertexBuffers::new();
 
-        let positioner = Positioner::new(window.inner_size().into(), hidpi_scale, page_width);
+        let screen_size: Size = size.into();
+        let page_width = max_page_width.map_or(f32::MAX, |width| width.resolve(screen_size.0));
+        let positioner = Positioner::new(screen_size, hidpi_scale, page_width);
         Ok(Self {
             config,
             surface,
@@ -179,37 +281,465 @@ impl Renderer {
             queue,
             text_system,
             scroll_y: 0.,
+            scroll_target: None,
+            smooth_scroll: false,
             lyon_buffer,
             hidpi_scale,
             page_width,
+            max_page_width,
+            max_line_chars: None,
             zoom: 1.,
             image_renderer,
             theme,
             positioner,
+            quote_block_background,
+            minimap,
+            search_query: None,
+            reload_flash: ReloadFlash::default(),
+            reading_mark: ReadingMark::default(),
+            scrollbar: ScrollbarSection::default(),
+            show_whitespace: false,
+            code_block_border: None,
+            image_background: None,
+            image_layer: ImageLayer::default(),
+            lazy_image_distance: None,
+            status_bar: None,
+            tab_bar: None,
+            anchor_list: None,
+            toc: false,
         })
     }
 
     fn draw_scrollbar(&mut self) -> anyhow::Result<()> {
+        if self.content_height() > self.positioner.reserved_height {
+            return Ok(());
+        }
+        let color = self.scrollbar.color.unwrap_or(self.theme.scrollbar_color);
+        let color = native_color(color, &self.surface_format);
+        self.draw_rectangle(self.scrollbar_thumb(), color)
+    }
+
+    /// The strip along the right edge of the window that the scrollbar moves within
+    pub fn scrollbar_track(&self) -> Rect {
+        let screen_width = self.screen_size().0;
+        let width = self.scrollbar.width;
+        Rect::new((screen_width - width, 0.), (width, self.content_height()))
+    }
+
+    /// The draggable part of the scrollbar
+    pub fn scrollbar_thumb(&self) -> Rect {
+        let Rect { pos, size } = self.scrollbar_track();
+        let y = (self.scroll_y / self.positioner.reserved_height) * size.1;
+        Rect::new((pos.0, y), (size.0, self.scrollbar_height()))
+    }
+
+    /// Where the minimap gets drawn. There's nothing to show when it's off or when the whole
+    /// document fits in the window
+    pub fn minimap_bounds(&self) -> Option<Rect> {
+        let content_size = (self.screen_size().0, self.content_height());
+        (self.minimap && content_size.1 <= self.positioner.reserved_height).then(|| {
+            minimap::bounds(
+                content_size,
+                self.positioner.top_inset,
+                self.scrollbar.width,
+            )
+        })
+    }
+
+    /// The right edge of the table of contents' entries, which stop short of the minimap and
+    /// scrollbar
+    pub fn toc_right(&self) -> f32 {
+        self.minimap_bounds().map_or_else(
+            || self.screen_size().0 - self.scrollbar.width,
+            |strip| strip.pos.0,
+        )
+    }
+
+    fn draw_minimap(&mut self) -> anyhow::Result<()> {
+        let Some(strip) = self.minimap_bounds() else {
+            return Ok(());
+        };
+        let color = native_color(self.theme.link_color, &self.surface_format);
+        for marker in minimap::markers(
+            &self.positioner.anchors,
+            self.positioner.reserved_height,
+            &strip,
+        ) {
+            self.draw_rectangle(marker, color)?;
+        }
+        Ok(())
+    }
+
+    fn draw_reading_mark(&mut self) -> anyhow::Result<()> {
+        let Some(rule) =
+            self.reading_mark
+                .rule(self.scroll_y, self.screen_size(), self.hidpi_scale)
+        else {
+            return Ok(());
+        };
+        let color = native_color(self.theme.link_color, &self.surface_format);
+        self.draw_rectangle(rule, color)
+    }
+
+    fn draw_reload_flash(&mut self) -> anyhow::Result<()> {
+        let Some(remaining) = self.reload_flash.remaining(Instant::now()) else {
+            return Ok(());
+        };
+        let width = reload_flash::WIDTH * self.hidpi_scale * remaining;
+        let color = native_color(self.theme.link_color, &self.surface_format);
+        // Strokes are centered on the rect's edges, so inset to keep the full width on screen
         let (screen_width, screen_height) = self.screen_size();
-        if screen_height > self.positioner.reserved_height {
+        let inset = width / 2.;
+        self.stroke_rectangle(
+            Rect::from_min_max(
+                (inset, inset),
+                (screen_width - inset, screen_height - inset),
+            ),
+            color,
+            width,
+        )
+    }
+
+    /// Draws the table of contents (if shown) to the right of the document, cutting off any of
+    /// the document's text that runs past it
+    fn draw_toc(&mut self, text_areas: &mut Vec<CachedTextArea>) -> anyhow::Result<()> {
+        if !self.toc {
             return Ok(());
         }
-        let height = (screen_height / self.positioner.reserved_height) * screen_height;
+        let left = self.positioner.content_width();
+        let top = self.positioner.top_inset;
+        let bottom = self.content_height();
+        let screen_width = self.screen_size().0;
+        // The minimap and scrollbar get drawn over the panel's right edge
+        let right = self.toc_right();
+        let row_height = toc::row_height(self.hidpi_scale);
+        let padding = toc::PADDING * self.hidpi_scale;
+
+        for text_area in text_areas.iter_mut() {
+            text_area.clip_right(left);
+        }
+        let background = native_color(self.theme.quote_block_color, &self.surface_format);
         self.draw_rectangle(
-            Rect::new(
-                (
-                    screen_width - DEFAULT_MARGIN / 4.,
-                    ((self.scroll_y / self.positioner.reserved_height) * screen_height),
-                ),
-                (DEFAULT_MARGIN / 4., height),
-            ),
-            [0.3, 0.3, 0.3, 1.0],
+            Rect::from_min_max((left, top), (screen_width, bottom)),
+            background,
+        )?;
+
+        let (entries, visible) = self.toc_entries();
+        let current = toc::current(&entries, self.scroll_y);
+        let min_level = entries.iter().map(|entry| entry.level).min().unwrap_or(1);
+        let text_color = native_color(self.theme.text_color, &self.surface_format);
+        let current_color = native_color(self.theme.select_color, &self.surface_format);
+        for (row, i) in visible.enumerate() {
+            let entry = &entries[i];
+            let row_top = top + row_height * row as f32;
+            if current == Some(i) {
+                self.draw_rectangle(
+                    Rect::from_min_max((left, row_top), (right, row_top + row_height)),
+                    current_color,
+                )?;
+            }
+
+            let mut text_box = TextBox::new(
+                vec![Text::new(entry.title.clone(), self.hidpi_scale, text_color)],
+                self.hidpi_scale,
+            );
+            text_box.font_size = toc::FONT_SIZE;
+            text_box.no_wrap = true;
+            let indent = toc::indent(entry.level, min_level, self.hidpi_scale);
+            let pos = (left + padding + indent, row_top + padding);
+            let bounds = ((right - pos.0 - padding).max(0.), f32::INFINITY);
+            let mut text_area = text_box.text_areas(&mut self.text_system, pos, bounds, 1., 0.);
+            text_area.clip_right(right - padding);
+            text_area.clip_bottom(bottom);
+            text_areas.push(text_area);
+        }
+        Ok(())
+    }
+
+    /// Draws the status bar (if any) over the bottom of the window, cutting off the document's
+    /// text where the bar starts
+    fn draw_status_bar(&mut self, text_areas: &mut Vec<CachedTextArea>) -> anyhow::Result<()> {
+        let content_height = self.content_height();
+        let max_scroll_y = (self.positioner.reserved_height - content_height).max(0.);
+        let Some(status_bar) = &self.status_bar else {
+            return Ok(());
+        };
+        let status = status_bar.text(self.scroll_y, max_scroll_y, self.zoom);
+
+        for text_area in text_areas.iter_mut() {
+            text_area.clip_bottom(content_height);
+        }
+        let (screen_width, screen_height) = self.screen_size();
+        let background = native_color(self.theme.quote_block_color, &self.surface_format);
+        self.draw_rectangle(
+            Rect::from_min_max((0., content_height), (screen_width, screen_height)),
+            background,
+        )?;
+
+        let text_color = native_color(self.theme.text_color, &self.surface_format);
+        let mut text_box = TextBox::new(
+            vec![Text::new(status, self.hidpi_scale, text_color)],
+            self.hidpi_scale,
+        );
+        text_box.font_size = status_bar::FONT_SIZE;
+        let pos = (
+            DEFAULT_MARGIN / 2.,
+            content_height + status_bar::PADDING * self.hidpi_scale,
+        );
+        let bounds = ((screen_width - DEFAULT_MARGIN).max(0.), f32::INFINITY);
+        text_areas.push(text_box.text_areas(&mut self.text_system, pos, bounds, 1., 0.));
+        Ok(())
+    }
+
+    /// Draws the anchor jump list (if any) across the top of the document, cutting off the
+    /// document's text where the list ends
+    fn draw_anchor_list(&mut self, text_areas: &mut Vec<CachedTextArea>) -> anyhow::Result<()> {
+        let Some(anchor_list) = &self.anchor_list else {
+            return Ok(());
+        };
+        let top = self.positioner.top_inset;
+        let row_height = AnchorList::row_height(self.hidpi_scale);
+        let bottom = top + anchor_list.height(self.hidpi_scale);
+        let screen_width = self.screen_size().0;
+        let rows: Vec<_> = std::iter::once(format!("Go to: {}", anchor_list.query))
+            .chain(anchor_list.titles.iter().cloned())
+            .collect();
+        // The query takes up the first row
+        let selected = anchor_list.selected.map(|selected| selected + 1);
+
+        for text_area in text_areas.iter_mut() {
+            text_area.clip_top(bottom);
+        }
+        let background = native_color(self.theme.quote_block_color, &self.surface_format);
+        self.draw_rectangle(
+            Rect::from_min_max((0., top), (screen_width, bottom)),
+            background,
+        )?;
+        if let Some(selected) = selected {
+            let select_color = native_color(self.theme.select_color, &self.surface_format);
+            let row_top = top + row_height * selected as f32;
+            self.draw_rectangle(
+                Rect::from_min_max((0., row_top), (screen_width, row_top + row_height)),
+                select_color,
+            )?;
+        }
+
+        let text_color = native_color(self.theme.text_color, &self.surface_format);
+        for (i, row) in rows.into_iter().enumerate() {
+            let mut text_box = TextBox::new(
+                vec![Text::new(row, self.hidpi_scale, text_color)],
+                self.hidpi_scale,
+            );
+            text_box.font_size = anchor_jump::FONT_SIZE;
+            text_box.no_wrap = true;
+            let pos = (
+                DEFAULT_MARGIN / 2.,
+                top + row_height * i as f32 + anchor_jump::PADDING * self.hidpi_scale,
+            );
+            let bounds = ((screen_width - DEFAULT_MARGIN).max(0.), f32::INFINITY);
+            let mut text_area = text_box.text_areas(&mut self.text_system, pos, bounds, 1., 0.);
+            text_area.clip_bottom(bottom);
+            text_areas.push(text_area);
+        }
+        Ok(())
+    }
+
+    /// Draws the tab strip (if any) over the top of the window, cutting off the document's text
+    /// where the strip ends
+    fn draw_tab_bar(&mut self, text_areas: &mut Vec<CachedTextArea>) -> anyhow::Result<()> {
+        let Some(tab_bar) = &self.tab_bar else {
+            return Ok(());
+        };
+        let height = TabBar::height(self.hidpi_scale);
+        let screen_width = self.screen_size().0;
+        let tab_width = tab_bar.tab_width(screen_width);
+        let titles = tab_bar.titles.clone();
+        let active = tab_bar.active;
+
+        for text_area in text_areas.iter_mut() {
+            text_area.clip_top(height);
+        }
+        let background = native_color(self.theme.quote_block_color, &self.surface_format);
+        self.draw_rectangle(
+            Rect::from_min_max((0., 0.), (screen_width, height)),
+            background,
+        )?;
+        // The active tab blends in with the document below it
+        let active_background = native_color(self.theme.background_color, &self.surface_format);
+        let left = tab_width * active as f32;
+        self.draw_rectangle(
+            Rect::from_min_max((left, 0.), (left + tab_width, height)),
+            active_background,
         )?;
+
+        let text_color = native_color(self.theme.text_color, &self.surface_format);
+        for (i, title) in titles.into_iter().enumerate() {
+            let mut text_box = TextBox::new(
+                vec![Text::new(title, self.hidpi_scale, text_color)],
+                self.hidpi_scale,
+            );
+            text_box.font_size = tabs::FONT_SIZE;
+            let pos = (
+                tab_width * i as f32 + DEFAULT_MARGIN / 2.,
+                tabs::PADDING * self.hidpi_scale,
+            );
+            let bounds = ((tab_width - DEFAULT_MARGIN).max(0.), f32::INFINITY);
+            let mut text_area = text_box.text_areas(&mut self.text_system, pos, bounds, 1., 0.);
+            // Long titles get cut off instead of spilling into the document
+            text_area.clip_bottom(height);
+            text_areas.push(text_area);
+        }
+        Ok(())
+    }
+
+    /// The part of the window that the document shows through, i.e. everything that isn't
+    /// covered by the tab strip, anchor list, table of contents, or status bar
+    fn document_viewport(&self) -> Rect {
+        let mut top = self.positioner.top_inset;
+        if let Some(anchor_list) = &self.anchor_list {
+            top += anchor_list.height(self.hidpi_scale);
+        }
+        let right = if self.toc {
+            self.positioner.content_width()
+        } else {
+            self.screen_size().0
+        };
+        Rect::from_min_max((0., top), (right, self.content_height()))
+    }
+
+    fn draw_kbd_backgrounds(
+        &mut self,
+        text_box: &TextBox,
+        bounds: Size,
+        text_area: &CachedTextArea,
+    ) -> anyhow::Result<()> {
+        let background = native_color(self.theme.kbd_background_color, &self.surface_format);
+        let border = native_color(self.theme.kbd_border_color, &self.surface_format);
+        let scale = self.hidpi_scale * self.zoom;
+        for rect in
+            text_box.render_kbd_backgrounds(&mut self.text_system, bounds, self.zoom, text_area)
+        {
+            // A bit of breathing room around the key's text
+            let padding = KBD_PADDING * scale;
+            let rect = Rect::from_min_max(
+                (rect.pos.0 - padding, rect.pos.1),
+                (rect.max().0 + padding, rect.max().1),
+            );
+            self.draw_rectangle(rect.clone(), background)?;
+            self.stroke_rectangle(inset_border(&rect, scale), border, scale)?;
+        }
+        Ok(())
+    }
+
+    /// Draws the gutter and numbers for a text box with `line_numbers` that's positioned at `pos`
+    ///
+    /// `pos` is where the text starts, so the gutter sits just to the left of it
+    fn draw_line_numbers(
+        &mut self,
+        text_box: &TextBox,
+        pos: Point,
+        bounds: Size,
+        gutter: f32,
+        text_areas: &mut Vec<CachedTextArea>,
+    ) -> anyhow::Result<()> {
+        let scale = self.hidpi_scale * self.zoom;
+        let padding = text_box.gutter_padding(self.zoom);
+        let size = text_box.size(&mut self.text_system, bounds, self.zoom);
+        let scrolled_pos = (pos.0 - gutter, pos.1 - self.scroll_y);
+        let rect = block_background(scrolled_pos, (gutter - padding / 2., size.1), 1, scale);
+        let background = native_color(self.theme.quote_block_color, &self.surface_format);
+        self.draw_rectangle(rect, background)?;
+
+        let color = native_color(self.theme.line_number_color, &self.surface_format);
+        let numbers = text_box.line_number_gutter(&mut self.text_system, bounds, self.zoom, color);
+        let areas = numbers.text_areas(
+            &mut self.text_system,
+            (pos.0 - gutter, pos.1),
+            (gutter - padding, f32::INFINITY),
+            self.zoom,
+            self.scroll_y,
+        );
+        text_areas.push(areas);
+        Ok(())
+    }
+
+    fn draw_search_matches(
+        &mut self,
+        text_box: &TextBox,
+        bounds: Size,
+        text_area: &CachedTextArea,
+    ) -> anyhow::Result<()> {
+        let Some(query) = self.search_query.as_deref() else {
+            return Ok(());
+        };
+
+        let color = native_color(self.theme.search_highlight_color, &self.surface_format);
+        for rect in text_box.render_search_matches(
+            &mut self.text_system,
+            bounds,
+            self.zoom,
+            text_area,
+            query,
+        ) {
+            self.draw_rectangle(rect, color)?;
+        }
+        Ok(())
+    }
+
+    fn draw_whitespace(
+        &mut self,
+        text_box: &TextBox,
+        bounds: Size,
+        text_area: &CachedTextArea,
+    ) -> anyhow::Result<()> {
+        if !self.show_whitespace || !text_box.is_code_block {
+            return Ok(());
+        }
+
+        let marker_color = native_color(self.theme.code_color, &self.surface_format);
+        let trailing_color = native_color(self.theme.search_highlight_color, &self.surface_format);
+        for (rect, marker) in
+            text_box.render_whitespace(&mut self.text_system, bounds, self.zoom, text_area)
+        {
+            if marker.trailing {
+                self.draw_rectangle(rect.clone(), trailing_color)?;
+            }
+
+            let (center_x, center_y) =
+                (rect.pos.0 + rect.size.0 / 2., rect.pos.1 + rect.size.1 / 2.);
+            let dot = 2. * self.hidpi_scale * self.zoom;
+            match marker.kind {
+                whitespace::Kind::Space => self.draw_rectangle(
+                    Rect::new((center_x - dot / 2., center_y - dot / 2.), (dot, dot)),
+                    marker_color,
+                )?,
+                whitespace::Kind::Tab => {
+                    let (left, right) = (rect.pos.0 + dot, rect.max().0 - dot);
+                    let head = 2. * dot;
+                    self.draw_rectangle(
+                        Rect::from_min_max(
+                            (left, center_y - dot / 4.),
+                            (right - head, center_y + dot / 4.),
+                        ),
+                        marker_color,
+                    )?;
+                    self.draw_triangle(
+                        [
+                            (right, center_y),
+                            (right - head, center_y - head / 2.),
+                            (right - head, center_y + head / 2.),
+                        ],
+                        marker_color,
+                    )?;
+                }
+            }
+        }
         Ok(())
     }
 
     pub fn scrollbar_height(&self) -> f32 {
-        (self.screen_height() / self.positioner.reserved_height) * self.screen_height()
+        let content_height = self.content_height();
+        (content_height / self.positioner.reserved_height) * content_height
     }
 
     fn render_elements(
@@ -218,7 +748,8 @@ impl Renderer {
         selection: &mut Selection,
     ) -> anyhow::Result<Vec<CachedTextArea>> {
         let mut text_areas: Vec<CachedTextArea> = Vec::new();
-        let screen_size = self.screen_size();
+        let screen_size = self.layout_size();
+        let content_height = self.content_height();
         for element in elements.iter() {
             let Rect { mut pos, size } =
                 element.bounds.as_ref().context("Element not positioned")?;
@@ -226,7 +757,7 @@ impl Renderer {
             // Dont render off screen elements
             if scrolled_pos.1 + size.1 <= 0. {
                 continue;
-            } else if scrolled_pos.1 >= screen_size.1 {
+            } else if scrolled_pos.1 >= content_height {
                 break;
             }
 
@@ -240,6 +771,9 @@ impl Renderer {
                         pos.0 += box_size * 1.5;
                         scrolled_pos.0 += box_size * 1.5;
                     }
+                    let gutter = text_box.gutter_width(self.zoom);
+                    pos.0 += gutter;
+                    scrolled_pos.0 += gutter;
 
                     let bounds = (
                         (screen_size.0 - pos.0 - DEFAULT_MARGIN - centering).max(0.),
@@ -254,33 +788,52 @@ impl Renderer {
                         self.scroll_y,
                     );
                     text_areas.push(areas.clone());
-                    if text_box.is_code_block || text_box.is_quote_block.is_some() {
+                    text_areas.extend(text_box.script_areas(
+                        &mut self.text_system,
+                        bounds,
+                        self.zoom,
+                        &areas,
+                    ));
+                    let draw_quote_bg =
+                        text_box.is_quote_block.is_some() && self.quote_block_background;
+                    if text_box.is_code_block || draw_quote_bg {
                         let color = if let Some(bg_color) = text_box.background_color {
                             bg_color
                         } else {
                             native_color(self.theme.quote_block_color, &self.surface_format)
                         };
 
-                        let mut min = (
-                            (scrolled_pos.0 - 10.),
-                            scrolled_pos.1 - 5. * self.hidpi_scale * self.zoom,
+                        let width = bounds
+                            .0
+                            .max(text_box.size(&mut self.text_system, bounds, self.zoom).0);
+                        let rect = block_background(
+                            (scrolled_pos.0 - gutter, scrolled_pos.1),
+                            (width + gutter, size.1),
+                            text_box.is_quote_block.unwrap_or(1),
+                            self.hidpi_scale * self.zoom,
                         );
-                        let max = (
-                            min.0
-                                + bounds
-                                    .0
-                                    .max(text_box.size(&mut self.text_system, bounds, self.zoom).0)
-                                + 10.,
-                            min.1 + size.1 + 12. * self.hidpi_scale * self.zoom,
-                        );
-                        if let Some(nest) = text_box.is_quote_block {
-                            min.0 -= (nest - 1) as f32 * DEFAULT_MARGIN / 2.;
-                        }
-                        if min.0 < screen_size.0 - DEFAULT_MARGIN - centering {
-                            self.draw_rectangle(Rect::from_min_max(min, max), color)?;
+                        if rect.pos.0 < screen_size.0 - DEFAULT_MARGIN - centering {
+                            match self.code_block_border {
+                                Some(width) if text_box.is_code_block => {
+                                    let border_color = native_color(
+                                        self.theme.code_border_color,
+                                        &self.surface_format,
+                                    );
+                                    let width = width * self.hidpi_scale * self.zoom;
+                                    let border = inset_border(&rect, width);
+                                    self.draw_rectangle(rect, color)?;
+                                    self.stroke_rectangle(border, border_color, width)?;
+                                }
+                                _ => self.draw_rectangle(rect, color)?,
+                            }
                         }
                     }
+                    if text_box.line_numbers {
+                        self.draw_line_numbers(text_box, pos, bounds, gutter, &mut text_areas)?;
+                    }
                     if let Some(nest) = text_box.is_quote_block {
+                        let select_color =
+                            native_color(self.theme.select_color, &self.surface_format);
                         for n in 0..nest {
                             let nest_indent = n as f32 * DEFAULT_MARGIN / 2.;
                             let min = (
@@ -296,10 +849,12 @@ impl Renderer {
                                     .min(screen_size.0 - DEFAULT_MARGIN - centering),
                                 min.1 + size.1 + 5. * self.hidpi_scale * self.zoom,
                             );
-                            self.draw_rectangle(
-                                Rect::from_min_max(min, max),
-                                native_color(self.theme.select_color, &self.surface_format),
-                            )?;
+                            // The innermost bar is the one that an alert colors
+                            let color = match text_box.quote_bar_color {
+                                Some(color) if n == 0 => color,
+                                _ => select_color,
+                            };
+                            self.draw_rectangle(Rect::from_min_max(min, max), color)?;
                         }
                     }
                     if let Some(is_checked) = text_box.is_checkbox {
@@ -332,6 +887,7 @@ impl Renderer {
                             )?;
                         }
                     }
+                    self.draw_kbd_backgrounds(text_box, bounds, &areas)?;
                     for line in text_box.render_lines(
                         &mut self.text_system,
                         scrolled_pos,
@@ -343,6 +899,8 @@ impl Renderer {
                         let max = (line.max.0, line.max.1 + 2. * self.hidpi_scale * self.zoom);
                         self.draw_rectangle(Rect::from_min_max(min, max), line.color)?;
                     }
+                    self.draw_whitespace(text_box, bounds, &areas)?;
+                    self.draw_search_matches(text_box, bounds, &areas)?;
                     if let Some(selection_rects) = text_box.render_selection(
                         &mut self.text_system,
                         pos,
@@ -375,13 +933,25 @@ impl Renderer {
 
                     for (col, node) in layout.headers.iter().enumerate() {
                         if let Some(text_box) = table.headers.get(col) {
-                            text_areas.push(text_box.text_areas(
+                            let areas = text_box.text_areas(
                                 &mut self.text_system,
                                 (pos.0 + node.location.x, pos.1 + node.location.y),
                                 (node.size.width, f32::MAX),
                                 self.zoom,
                                 self.scroll_y,
+                            );
+                            text_areas.push(areas.clone());
+                            text_areas.extend(text_box.script_areas(
+                                &mut self.text_system,
+                                (node.size.width, f32::MAX),
+                                self.zoom,
+                                &areas,
                             ));
+                            self.draw_search_matches(
+                                text_box,
+                                (node.size.width, f32::MAX),
+                                &areas,
+                            )?;
                             if let Some(selection_rects) = text_box.render_selection(
                                 &mut self.text_system,
                                 (pos.0 + node.location.x, pos.1 + node.location.y),
@@ -407,17 +977,13 @@ impl Renderer {
                         .map(|last_header_node| {
                             last_header_node.location.y
                                 + last_header_node.size.height
-                                + TABLE_ROW_GAP / 2.0
+                                + table.row_gap() / 2.0
                         })
                         .unwrap_or(0.0);
-                    let x = layout
-                        .headers
-                        .last()
-                        .map(|f| f.location.x + f.size.width)
-                        .unwrap_or(0.);
+                    let x = layout.content_width();
                     {
                         let min = (
-                            scrolled_pos.0.max(DEFAULT_MARGIN + centering),
+                            scrolled_pos.0.max(DEFAULT_MARGIN + centering) + layout.offset,
                             scrolled_pos.1 + y,
                         );
                         let max = (
@@ -434,13 +1000,25 @@ impl Renderer {
                         for (col, node) in node_row.iter().enumerate() {
                             if let Some(row) = table.rows.get(row) {
                                 if let Some(text_box) = row.get(col) {
-                                    text_areas.push(text_box.text_areas(
+                                    let areas = text_box.text_areas(
                                         &mut self.text_system,
                                         (pos.0 + node.location.x, pos.1 + node.location.y),
                                         (node.size.width, f32::MAX),
                                         self.zoom,
                                         self.scroll_y,
+                                    );
+                                    text_areas.push(areas.clone());
+                                    text_areas.extend(text_box.script_areas(
+                                        &mut self.text_system,
+                                        (node.size.width, f32::MAX),
+                                        self.zoom,
+                                        &areas,
                                     ));
+                                    self.draw_search_matches(
+                                        text_box,
+                                        (node.size.width, f32::MAX),
+                                        &areas,
+                                    )?;
 
                                     if let Some(selection_rects) = text_box.render_selection(
                                         &mut self.text_system,
@@ -468,14 +1046,14 @@ impl Renderer {
                         let last_row_node = node_row.last().unwrap();
                         let y = last_row_node.location.y
                             + last_row_node.size.height
-                            + TABLE_ROW_GAP / 2.;
+                            + table.row_gap() / 2.;
                         let x = node_row
                             .last()
                             .map(|f| f.location.x + f.size.width)
                             .unwrap_or(0.);
                         {
                             let min = (
-                                scrolled_pos.0.max(DEFAULT_MARGIN + centering),
+                                scrolled_pos.0.max(DEFAULT_MARGIN + centering) + layout.offset,
                                 scrolled_pos.1 + y,
                             );
                             let max = (
@@ -489,7 +1067,27 @@ impl Renderer {
                         }
                     }
                 }
-                Element::Image(_) => {}
+                Element::Image(image) => {
+                    if let Some(alt) = image.loading_alt() {
+                        let bounds = (size.0, f32::INFINITY);
+                        let areas = alt.text_areas(
+                            &mut self.text_system,
+                            pos,
+                            bounds,
+                            self.zoom,
+                            self.scroll_y,
+                        );
+                        text_areas.push(areas);
+                    } else if let Some(background) = self.image_background {
+                        let tile = CHECKERBOARD_TILE * self.hidpi_scale;
+                        let bounds = Rect::new(scrolled_pos, *size);
+                        for (rect, color) in
+                            image_background(&bounds, background, tile, screen_size.1)
+                        {
+                            self.draw_rectangle(rect, native_color(color, &self.surface_format))?;
+                        }
+                    }
+                }
                 Element::Spacer(spacer) => {
                     if spacer.visible {
                         self.draw_rectangle(
@@ -544,7 +1142,12 @@ impl Renderer {
                 let _ = self.stroke_rectangle(rect, native_color(color, &self.surface_format), 1.0);
             }
         }
+        self.draw_reading_mark()?;
         self.draw_scrollbar()?;
+        if self.minimap {
+            self.draw_minimap()?;
+        }
+        self.draw_reload_flash()?;
         Ok(text_areas)
     }
 
@@ -555,19 +1158,25 @@ impl Renderer {
         color: [f32; 4],
         hidden: bool,
     ) -> anyhow::Result<()> {
-        let points = if hidden {
+        let corners = if hidden {
             [
-                point(pos.0, pos.1, self.screen_size()).into(),
-                point(pos.0 - size, pos.1 + size, self.screen_size()).into(),
-                point(pos.0 - size, pos.1 - size, self.screen_size()).into(),
+                pos,
+                (pos.0 - size, pos.1 + size),
+                (pos.0 - size, pos.1 - size),
             ]
         } else {
             [
-                point(pos.0, pos.1 - size / 2., self.screen_size()).into(),
-                point(pos.0 - size * 2., pos.1 - size / 2., self.screen_size()).into(),
-                point(pos.0 - size, pos.1 + size / 2., self.screen_size()).into(),
+                (pos.0, pos.1 - size / 2.),
+                (pos.0 - size * 2., pos.1 - size / 2.),
+                (pos.0 - size, pos.1 + size / 2.),
             ]
         };
+        self.draw_triangle(corners, color)
+    }
+
+    fn draw_triangle(&mut self, corners: [Point; 3], color: [f32; 4]) -> anyhow::Result<()> {
+        let screen_size = self.screen_size();
+        let points = corners.map(|(x, y)| point(x, y, screen_size).into());
         let triangle = Polygon {
             points: &points,
             closed: true,
@@ -645,6 +1254,25 @@ impl Renderer {
         Ok(())
     }
 
+    /// Starts loading the deferred images that are close enough to the screen. Anything further out
+    /// is left alone until it's scrolled near
+    fn load_nearby_images(&self, elements: &mut [Positioned<Element>]) {
+        let Some(distance) = self.lazy_image_distance else {
+            return;
+        };
+        let distance = distance * self.hidpi_scale;
+        let near = (self.scroll_y - distance)..=(self.scroll_y + self.screen_height() + distance);
+        for element in elements.iter_mut() {
+            let Some(Rect { pos, .. }) = element.bounds else {
+                continue;
+            };
+            if pos.1 > *near.end() {
+                break;
+            }
+            load_nearby(element, &near);
+        }
+    }
+
     fn image_bindgroups(
         &mut self,
         elements: &mut [Positioned<Element>],
@@ -667,6 +1295,7 @@ impl Renderer {
                             &self.queue,
                             &self.image_renderer.sampler,
                             &self.image_renderer.bindgroup_layout,
+                            &mut self.image_renderer.texture_cache,
                         )
                     }) {
                         let vertex_buf =
@@ -685,6 +1314,7 @@ impl Renderer {
                                     &self.queue,
                                     &self.image_renderer.sampler,
                                     &self.image_renderer.bindgroup_layout,
+                                    &mut self.image_renderer.texture_cache,
                                 )
                             }) {
                                 let vertex_buf = ImageRenderer::vertex_buf(
@@ -712,6 +1342,7 @@ impl Renderer {
                                     &self.queue,
                                     &self.image_renderer.sampler,
                                     &self.image_renderer.bindgroup_layout,
+                                    &mut self.image_renderer.texture_cache,
                                 )
                             }) {
                                 let vertex_buf = ImageRenderer::vertex_buf(
@@ -736,14 +1367,100 @@ impl Renderer {
         elements: &mut [Positioned<Element>],
         selection: &mut Selection,
     ) -> anyhow::Result<()> {
-        selection.text.clear();
         let frame = self
             .surface
+            .as_ref()
+            .context("Can't redraw without a window")?
             .get_current_texture()
             .context("Failed to acquire next swap chain texture")?;
         let view = frame
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
+        self.draw(&view, elements, selection)?;
+        frame.present();
+        self.text_system.text_atlas.trim();
+
+        Ok(())
+    }
+
+    /// Draws a frame to an offscreen texture and reads it back
+    pub fn render_to_image(
+        &mut self,
+        elements: &mut [Positioned<Element>],
+        selection: &mut Selection,
+    ) -> anyhow::Result<RgbaImage> {
+        let (width, height) = (self.config.width, self.config.height);
+        let extent = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Offscreen Texture"),
+            size: extent,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.surface_format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        self.draw(&view, elements, selection)?;
+
+        // Each row gets padded out to the alignment that copies require
+        let row_bytes = width * 4;
+        let padded_row_bytes = row_bytes.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Readback Buffer"),
+            size: u64::from(padded_row_bytes) * u64::from(height),
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row_bytes),
+                    rows_per_image: None,
+                },
+            },
+            extent,
+        );
+        self.queue.submit(Some(encoder.finish()));
+
+        let slice = buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |res| {
+            let _ = sender.send(res);
+        });
+        self.device.poll(wgpu::Maintain::Wait);
+        receiver
+            .recv()?
+            .context("Failed reading back the rendered image")?;
+        let pixels = slice
+            .get_mapped_range()
+            .chunks(padded_row_bytes as usize)
+            .flat_map(|row| &row[..row_bytes as usize])
+            .copied()
+            .collect();
+        buffer.unmap();
+
+        RgbaImage::from_raw(width, height, pixels).context("Rendered image has invalid dimensions")
+    }
+
+    fn draw(
+        &mut self,
+        view: &wgpu::TextureView,
+        elements: &mut [Positioned<Element>],
+        selection: &mut Selection,
+    ) -> anyhow::Result<()> {
+        selection.clear_text();
         let mut encoder = self
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
@@ -751,7 +1468,11 @@ impl Renderer {
         // Prepare and render elements that use lyon
         self.lyon_buffer.indices.clear();
         self.lyon_buffer.vertices.clear();
-        let cached_text_areas = self.render_elements(elements, selection)?;
+        let mut cached_text_areas = self.render_elements(elements, selection)?;
+        self.draw_toc(&mut cached_text_areas)?;
+        self.draw_status_bar(&mut cached_text_areas)?;
+        self.draw_anchor_list(&mut cached_text_areas)?;
+        self.draw_tab_bar(&mut cached_text_areas)?;
         let vertex_buf = self
             .device
             .create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -768,7 +1489,14 @@ impl Renderer {
             });
 
         // Prepare image bind groups for drawing
+        self.load_nearby_images(elements);
         let image_bindgroups = self.image_bindgroups(elements);
+        // The overlays are drawn along with the rest of the shapes, so images get clipped to keep
+        // them from drawing over the overlays
+        let image_scissor = scissor_rect(
+            &self.document_viewport(),
+            (self.config.width, self.config.height),
+        );
 
         {
             let mut text_cache = self.text_system.text_cache.lock().unwrap();
@@ -805,7 +1533,7 @@ impl Renderer {
             let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: None,
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
+                    view,
                     resolve_target: None,
                     ops: wgpu::Operations {
                         load: wgpu::LoadOp::Clear(background_color),
@@ -815,34 +1543,56 @@ impl Renderer {
                 depth_stencil_attachment: None,
             });
 
-            // Draw lyon elements
-            rpass.set_pipeline(&self.render_pipeline);
-            rpass.set_vertex_buffer(0, vertex_buf.slice(..));
-            rpass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint16);
-            rpass.draw_indexed(0..self.lyon_buffer.indices.len() as u32, 0, 0..1);
-
-            // Draw images
-            rpass.set_pipeline(&self.image_renderer.render_pipeline);
-            rpass.set_index_buffer(self.image_renderer.index_buf.slice(..), IndexFormat::Uint16);
-            for (bindgroup, vertex_buf) in image_bindgroups.iter() {
-                rpass.set_bind_group(0, bindgroup, &[]);
-                rpass.set_vertex_buffer(0, vertex_buf.slice(..));
-                rpass.draw_indexed(0..6, 0, 0..1);
+            for layer in draw_order(self.image_layer) {
+                match layer {
+                    DrawLayer::Shapes => {
+                        rpass.set_pipeline(&self.render_pipeline);
+                        rpass.set_vertex_buffer(0, vertex_buf.slice(..));
+                        rpass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+                        rpass.draw_indexed(0..self.lyon_buffer.indices.len() as u32, 0, 0..1);
+                    }
+                    DrawLayer::Images => {
+                        let Some((x, y, width, height)) = image_scissor else {
+                            continue;
+                        };
+                        rpass.set_scissor_rect(x, y, width, height);
+                        rpass.set_pipeline(&self.image_renderer.render_pipeline);
+                        rpass.set_index_buffer(
+                            self.image_renderer.index_buf.slice(..),
+                            IndexFormat::Uint16,
+                        );
+                        for (bindgroup, vertex_buf) in image_bindgroups.iter() {
+                            rpass.set_bind_group(0, bindgroup, &[]);
+                            rpass.set_vertex_buffer(0, vertex_buf.slice(..));
+                            rpass.draw_indexed(0..6, 0, 0..1);
+                        }
+                        rpass.set_scissor_rect(0, 0, self.config.width, self.config.height);
+                    }
+                    DrawLayer::Text => self
+                        .text_system
+                        .text_renderer
+                        .render(&self.text_system.text_atlas, &mut rpass)
+                        .unwrap(),
+                }
             }
-
-            self.text_system
-                .text_renderer
-                .render(&self.text_system.text_atlas, &mut rpass)
-                .unwrap();
         }
 
         self.queue.submit(Some(encoder.finish()));
-        frame.present();
-        self.text_system.text_atlas.trim();
 
         Ok(())
     }
 
+    /// Changes the size of what gets drawn to. The document needs to be repositioned afterwards
+    pub fn resize(&mut self, size: PhysicalSize<u32>) {
+        self.config.width = size.width;
+        self.config.height = size.height;
+        self.positioner.screen_size = size.into();
+        self.update_page_width();
+        if let Some(surface) = &self.surface {
+            surface.configure(&self.device, &self.config);
+        }
+    }
+
     pub fn reposition(&mut self, elements: &mut [Positioned<Element>]) -> anyhow::Result<()> {
         let start = Instant::now();
         let res = self
@@ -852,14 +1602,270 @@ impl Renderer {
         res
     }
 
+    /// Shows or hides the table of contents, making room for it to the right of the document
+    ///
+    /// The document needs to be repositioned afterwards
+    pub fn set_toc(&mut self, toc: bool) {
+        self.toc = toc;
+        self.positioner.right_inset = if toc {
+            toc::width(self.hidpi_scale)
+        } else {
+            0.
+        };
+        self.update_page_width();
+    }
+
+    /// The entries of the table of contents that are currently shown
+    pub fn toc_entries(&self) -> (Vec<TocEntry>, Range<usize>) {
+        let entries = toc::entries(&self.positioner.anchors, &self.positioner.headings);
+        let current = toc::current(&entries, self.scroll_y);
+        let rows =
+            (self.content_height() - self.positioner.top_inset) / toc::row_height(self.hidpi_scale);
+        let visible = toc::visible(entries.len(), current, rows.max(0.) as usize);
+        (entries, visible)
+    }
+
+    /// Re-resolves the page's width for the current window size, scale, and zoom
+    ///
+    /// Relative page widths follow the window's width, and `max_line_chars` caps the page so that
+    /// lines of text wrap after roughly that many characters
+    pub fn update_page_width(&mut self) {
+        let screen_width = self.positioner.content_width();
+        let mut page_width = self
+            .max_page_width
+            .map_or(f32::MAX, |width| width.resolve(screen_width));
+        if let Some(chars) = self.max_line_chars {
+            let text_width = text::line_width_for_chars(
+                &self.text_system.text_cache,
+                &self.text_system.font_system,
+                chars,
+                self.hidpi_scale,
+                self.zoom,
+            );
+            page_width = page_width.min(text_width + 2. * DEFAULT_MARGIN);
+        }
+        self.page_width = page_width;
+        self.positioner.page_width = page_width;
+    }
+
+    /// Jumps straight to `scroll_y`, cutting off any smooth scroll that's in progress
     pub fn set_scroll_y(&mut self, scroll_y: f32) {
-        self.scroll_y = scroll_y.clamp(
+        self.scroll_target = None;
+        self.scroll_y = self.clamp_scroll_y(scroll_y);
+    }
+
+    /// Heads towards `scroll_y`, gradually when smooth scrolling is enabled
+    pub fn scroll_to(&mut self, scroll_y: f32) {
+        if self.smooth_scroll {
+            self.scroll_target = Some(self.clamp_scroll_y(scroll_y));
+        } else {
+            self.set_scroll_y(scroll_y);
+        }
+    }
+
+    /// Where scrolling will end up once any smooth scroll finishes
+    ///
+    /// Relative scrolls should build on this, so that a quick series of them adds up
+    pub fn scroll_destination(&self) -> f32 {
+        self.scroll_target.unwrap_or(self.scroll_y)
+    }
+
+    /// Moves a frame's worth towards the smooth scroll's target. Returns whether there's still
+    /// more to go
+    pub fn step_scroll(&mut self) -> bool {
+        let Some(target) = self.scroll_target else {
+            // Still keep things in bounds for when the document shrinks
+            self.scroll_y = self.clamp_scroll_y(self.scroll_y);
+            return false;
+        };
+        // The document may have changed size since the target was set
+        let target = self.clamp_scroll_y(target);
+        let next = smooth_scroll_step(self.scroll_y, target);
+        if next == target {
+            self.set_scroll_y(target);
+            false
+        } else {
+            self.scroll_y = next;
+            self.scroll_target = Some(target);
+            true
+        }
+    }
+
+    fn clamp_scroll_y(&self, scroll_y: f32) -> f32 {
+        scroll_y.clamp(
             0.,
-            (self.positioner.reserved_height - self.screen_height()).max(0.),
+            (self.positioner.reserved_height - self.content_height()).max(0.),
         )
     }
 }
 
+/// The fraction of the remaining distance covered each frame while smooth scrolling
+const SMOOTH_SCROLL_EASING: f32 = 0.3;
+
+/// Eases out towards `target` by covering a fixed fraction of the remaining distance, snapping to
+/// it once it's within a pixel
+fn smooth_scroll_step(current: f32, target: f32) -> f32 {
+    let remaining = target - current;
+    if remaining.abs() < 1. {
+        target
+    } else {
+        current + remaining * SMOOTH_SCROLL_EASING
+    }
+}
+
+/// Picks the first supported present mode, preferring vsync'd modes
+///
+/// Some surfaces don't support `Fifo` (or report nothing at all) despite it being guaranteed, so
+/// configuring it blindly can panic
+pub fn pick_present_mode(supported: &[PresentMode]) -> anyhow::Result<PresentMode> {
+    const PREFERRED: [PresentMode; 4] = [
+        PresentMode::Fifo,
+        PresentMode::FifoRelaxed,
+        PresentMode::Mailbox,
+        PresentMode::Immediate,
+    ];
+
+    PREFERRED
+        .into_iter()
+        .find(|mode| supported.contains(mode))
+        .or_else(|| supported.first().copied())
+        .context("The window's surface doesn't support any present modes")
+}
+
+/// A group of things that get drawn together in a single step
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DrawLayer {
+    /// Everything drawn with lyon e.g. backgrounds, borders, and selections
+    Shapes,
+    Images,
+    Text,
+}
+
+/// The order that each layer gets drawn in, from bottom to top
+pub fn draw_order(image_layer: ImageLayer) -> [DrawLayer; 3] {
+    match image_layer {
+        ImageLayer::BelowText => [DrawLayer::Shapes, DrawLayer::Images, DrawLayer::Text],
+        ImageLayer::AboveText => [DrawLayer::Shapes, DrawLayer::Text, DrawLayer::Images],
+    }
+}
+
+/// Starts loading any deferred images within `element` that overlap the `near` range
+fn load_nearby(element: &mut Positioned<Element>, near: &RangeInclusive<f32>) {
+    let Some(Rect { pos, size }) = element.bounds else {
+        return;
+    };
+    if pos.1 + size.1 < *near.start() || pos.1 > *near.end() {
+        return;
+    }
+    match &mut element.inner {
+        Element::Image(image) => image.start_loading(),
+        Element::Row(row) => {
+            for element in &mut row.elements {
+                load_nearby(element, near);
+            }
+        }
+        Element::Section(section) if !*section.hidden.borrow() => {
+            for element in &mut section.elements {
+                load_nearby(element, near);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The background drawn behind a code or quote block
+///
+/// Nested quote blocks extend back to the outermost quote's indent, so each level of nesting
+/// stacks on top of its parent's background
+pub fn block_background(scrolled_pos: Point, size: Size, nest: usize, scale: f32) -> Rect {
+    let nest_indent = nest.saturating_sub(1) as f32 * DEFAULT_MARGIN / 2.;
+    let min = (
+        scrolled_pos.0 - 10. - nest_indent,
+        scrolled_pos.1 - 5. * scale,
+    );
+    let max = (
+        scrolled_pos.0 + size.0,
+        scrolled_pos.1 + size.1 + 7. * scale,
+    );
+    Rect::from_min_max(min, max)
+}
+
+/// The rect to stroke for a `width` wide border around `background` (e.g. a code block's)
+///
+/// Strokes are centered on the rect's edges, so it's inset to keep the border within the background
+pub fn inset_border(background: &Rect, width: f32) -> Rect {
+    let inset = width / 2.;
+    Rect::from_min_max(
+        (background.pos.0 + inset, background.pos.1 + inset),
+        (background.max().0 - inset, background.max().1 - inset),
+    )
+}
+
+/// `viewport` as a scissor rect (x, y, width, height) that fits within a `target` sized texture
+///
+/// Returns `None` when nothing of it is left
+pub fn scissor_rect(
+    viewport: &Rect,
+    (target_width, target_height): (u32, u32),
+) -> Option<(u32, u32, u32, u32)> {
+    let clamp = |value: f32, max: u32| (value.max(0.) as u32).min(max);
+    let (x, y) = (
+        clamp(viewport.pos.0, target_width),
+        clamp(viewport.pos.1, target_height),
+    );
+    let max = viewport.max();
+    let (right, bottom) = (
+        clamp(max.0.ceil(), target_width),
+        clamp(max.1.ceil(), target_height),
+    );
+    (right > x && bottom > y).then(|| (x, y, right - x, bottom - y))
+}
+
+/// The (light, dark) tile colors of the checkerboard image background
+const CHECKERBOARD: (u32, u32) = (0xffffff, 0xcccccc);
+/// Side length of a checkerboard tile (before hidpi scaling)
+const CHECKERBOARD_TILE: f32 = 10.;
+
+/// The rects (and their colors) to draw behind an image, clipped to the visible part of the screen
+///
+/// The checkerboard is anchored to the image's corner, so the tiles don't shift while scrolling
+pub fn image_background(
+    bounds: &Rect,
+    background: ImageBackground,
+    tile: f32,
+    screen_height: f32,
+) -> Vec<(Rect, u32)> {
+    let min = (bounds.pos.0, bounds.pos.1.max(0.));
+    let max = (bounds.max().0, bounds.max().1.min(screen_height));
+    if min.0 >= max.0 || min.1 >= max.1 {
+        return Vec::new();
+    }
+    let visible = Rect::from_min_max(min, max);
+
+    match background {
+        ImageBackground::Color(color) => vec![(visible, color)],
+        ImageBackground::Pattern(BackgroundPattern::Checkerboard) => {
+            let (light, dark) = CHECKERBOARD;
+            let mut rects = vec![(visible, light)];
+            let first_row = ((min.1 - bounds.pos.1) / tile).floor() as usize;
+            let cols = (bounds.size.0 / tile).ceil() as usize;
+            for row in first_row.. {
+                let y = bounds.pos.1 + row as f32 * tile;
+                if y >= max.1 {
+                    break;
+                }
+                for col in ((row + 1) % 2..cols).step_by(2) {
+                    let x = bounds.pos.0 + col as f32 * tile;
+                    let tile_min = (x, y.max(min.1));
+                    let tile_max = ((x + tile).min(max.0), (y + tile).min(max.1));
+                    rects.push((Rect::from_min_max(tile_min, tile_max), dark));
+                }
+            }
+            rects
+        }
+    }
+}
+
 // Translates points from pixel coordinates to wgpu coordinates
 pub fn point(x: f32, y: f32, screen: Size) -> [f32; 2] {
     let scale_x = 2. / screen.0;
@@ -868,3 +1874,157 @@ pub fn point(x: f32, y: f32, screen: Size) -> [f32; 2] {
     let new_y = 1. - (y * scale_y);
     [new_x, new_y]
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn covers(outer: &Rect, inner: &Rect) -> bool {
+        outer.pos.0 <= inner.pos.0
+            && outer.pos.1 <= inner.pos.1
+            && outer.max().0 >= inner.max().0
+            && outer.max().1 >= inner.max().1
+    }
+
+    #[test]
+    fn quote_background_covers_content() {
+        let pos = (150., 40.);
+        let size = (300., 20.);
+        let content = Rect::new(pos, size);
+
+        let single = block_background(pos, size, 1, 1.0);
+        assert!(covers(&single, &content), "{single:?} vs {content:?}");
+
+        // Nested quotes are indented further, but the background reaches back to the outer quote
+        let nested_pos = (pos.0 + DEFAULT_MARGIN / 2., pos.1);
+        let nested_content = Rect::new(nested_pos, size);
+        let nested = block_background(nested_pos, size, 2, 1.0);
+        assert!(covers(&nested, &nested_content));
+        assert_eq!(nested.pos.0, single.pos.0);
+
+        // Scaling only grows the vertical padding
+        let scaled = block_background(pos, size, 1, 2.0);
+        assert!(covers(&scaled, &single));
+    }
+
+    #[test]
+    fn code_border_matches_background() {
+        let pos = (150., 40.);
+        let size = (300., 20.);
+        for scale in [1., 2.] {
+            let background = block_background(pos, size, 1, scale);
+            let width = 1.5 * scale;
+            let border = inset_border(&background, width);
+            // The outer edge of the stroke lines up exactly with the background
+            let half = width / 2.;
+            let outer = Rect::from_min_max(
+                (border.pos.0 - half, border.pos.1 - half),
+                (border.max().0 + half, border.max().1 + half),
+            );
+            assert_eq!(outer, background);
+        }
+    }
+
+    #[test]
+    fn present_mode_fallback() {
+        use PresentMode::*;
+
+        assert_eq!(pick_present_mode(&[Immediate, Fifo]).unwrap(), Fifo);
+        assert_eq!(pick_present_mode(&[Immediate, Mailbox]).unwrap(), Mailbox);
+        assert_eq!(pick_present_mode(&[AutoNoVsync]).unwrap(), AutoNoVsync);
+        assert!(pick_present_mode(&[]).is_err());
+    }
+
+    #[test]
+    fn image_layer_draw_order() {
+        let position = |image_layer, layer| {
+            draw_order(image_layer)
+                .iter()
+                .position(|&l| l == layer)
+                .unwrap()
+        };
+        let (images, text) = (DrawLayer::Images, DrawLayer::Text);
+
+        assert!(position(ImageLayer::BelowText, images) < position(ImageLayer::BelowText, text));
+        assert!(position(ImageLayer::AboveText, images) > position(ImageLayer::AboveText, text));
+        // Shapes always sit at the very bottom since they include backgrounds
+        for image_layer in [ImageLayer::BelowText, ImageLayer::AboveText] {
+            assert_eq!(draw_order(image_layer)[0], DrawLayer::Shapes);
+        }
+    }
+
+    #[test]
+    fn image_scissor() {
+        let target = (800, 600);
+        let viewport = Rect::from_min_max((0., 30.5), (650., 580.));
+        assert_eq!(scissor_rect(&viewport, target), Some((0, 30, 650, 550)));
+        // Kept within the target
+        let oversized = Rect::from_min_max((-10., -10.), (900., 700.));
+        assert_eq!(scissor_rect(&oversized, target), Some((0, 0, 800, 600)));
+        // Overlays can cover everything on a tiny window
+        let covered = Rect::from_min_max((0., 40.), (800., 20.));
+        assert_eq!(scissor_rect(&covered, target), None);
+    }
+
+    #[test]
+    fn image_background_rects() {
+        let bounds = Rect::new((10., 100.), (40., 20.));
+        let solid = ImageBackground::Color(0xffffff);
+        assert_eq!(
+            image_background(&bounds, solid, 10., 500.),
+            [(bounds.clone(), 0xffffff)]
+        );
+        // Nothing to draw for images that are off screen
+        assert!(image_background(&bounds, solid, 10., 50.).is_empty());
+
+        let checkerboard = ImageBackground::Pattern(BackgroundPattern::Checkerboard);
+        let rects = image_background(&bounds, checkerboard, 10., 500.);
+        let (light, dark) = CHECKERBOARD;
+        assert_eq!(rects[0], (bounds.clone(), light));
+        let dark_tiles: Vec<_> = rects[1..]
+            .iter()
+            .map(|(rect, color)| {
+                assert_eq!(*color, dark);
+                rect.pos
+            })
+            .collect();
+        assert_eq!(
+            dark_tiles,
+            [(20., 100.), (40., 100.), (10., 110.), (30., 110.)]
+        );
+
+        // Tiles get clipped to the top of the screen
+        let scrolled = Rect::new((0., -15.), (20., 30.));
+        let rects = image_background(&scrolled, checkerboard, 10., 500.);
+        let tiles: Vec<_> = rects.iter().map(|(rect, _)| rect.clone()).collect();
+        assert_eq!(
+            tiles,
+            [
+                Rect::new((0., 0.), (20., 15.)),
+                Rect::new((0., 0.), (10., 5.)),
+                Rect::new((10., 5.), (10., 10.)),
+            ]
+        );
+    }
+
+    #[test]
+    fn smooth_scroll_eases_out() {
+        let target = 1_000.;
+        let mut scroll_y = 0.;
+        let mut steps = Vec::new();
+        while scroll_y != target {
+            let next = smooth_scroll_step(scroll_y, target);
+            steps.push(next - scroll_y);
+            scroll_y = next;
+            assert!(steps.len() < 60, "Smooth scrolling never finished");
+        }
+
+        // Starts off quick then slows down until it's close enough to snap into place
+        let (_snap, easing) = steps.split_last().unwrap();
+        assert!(easing.len() > 1);
+        assert!(easing.windows(2).all(|pair| pair[0] > pair[1]));
+        // Works the same going back up
+        assert!(smooth_scroll_step(target, 0.) < target);
+        assert_eq!(smooth_scroll_step(0.5, 0.), 0.);
+    }
+}
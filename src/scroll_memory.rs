@@ -0,0 +1,62 @@
+//! Remembering where the reader was in each file
+//!
+//! Positions are stored as a fraction of the document's height since the height changes whenever
+//! the file gets edited. Restoring waits until the reloaded document is fully positioned
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Default)]
+pub struct ScrollMemory {
+    positions: HashMap<PathBuf, f32>,
+    pending: Option<f32>,
+}
+
+impl ScrollMemory {
+    pub fn save(&mut self, path: &Path, scroll_y: f32, doc_height: f32) {
+        let fraction = if doc_height > 0. {
+            (scroll_y / doc_height).clamp(0., 1.)
+        } else {
+            0.
+        };
+        self.positions.insert(path.to_owned(), fraction);
+    }
+
+    /// Queues up restoring `path`'s position (if we have one) for when it finishes loading
+    pub fn restore(&mut self, path: &Path) {
+        self.pending = self.positions.get(path).copied();
+    }
+
+    /// The scroll position to restore within the newly loaded document, if any
+    pub fn take_pending(&mut self, doc_height: f32) -> Option<f32> {
+        self.pending.take().map(|fraction| fraction * doc_height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restores_relative_position() {
+        let a = Path::new("a.md");
+        let b = Path::new("b.md");
+        let mut memory = ScrollMemory::default();
+
+        memory.save(a, 250., 1_000.);
+        // Nothing to restore until asked
+        assert_eq!(memory.take_pending(2_000.), None);
+
+        // The document grew from an edit, so the position scales with it
+        memory.restore(a);
+        assert_eq!(memory.take_pending(2_000.), Some(500.));
+        assert_eq!(memory.take_pending(2_000.), None);
+
+        // Unseen files don't have a position to restore
+        memory.restore(b);
+        assert_eq!(memory.take_pending(2_000.), None);
+        memory.save(b, 10., 0.);
+        memory.restore(b);
+        assert_eq!(memory.take_pending(2_000.), Some(0.));
+    }
+}
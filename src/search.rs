@@ -0,0 +1,272 @@
+//! Incremental in-document search
+//!
+//! Matches are found by walking the positioned elements and scanning each [`Text`]'s contents.
+//! Each match remembers where it sits in the document, so that we can jump between them
+
+use std::ops::Range;
+
+use crate::keybindings::action::HistDirection;
+use crate::positioner::Positioned;
+use crate::text::{Text, TextBox};
+use crate::Element;
+
+/// Finds all of the case-insensitive matches of `needle` in `haystack`
+///
+/// The returned ranges are byte ranges into `haystack`
+pub fn find_matches(haystack: &str, needle: &str) -> Vec<Range<usize>> {
+    let needle: Vec<char> = needle.chars().flat_map(char::to_lowercase).collect();
+    if needle.is_empty() {
+        return Vec::new();
+    }
+
+    let mut matches = Vec::new();
+    let mut search_from = 0;
+    for (start, _) in haystack.char_indices() {
+        if start < search_from {
+            continue;
+        }
+
+        let mut needle_chars = needle.iter();
+        let mut end = None;
+        for (offset, c) in haystack[start..].char_indices() {
+            let all_matched = c
+                .to_lowercase()
+                .all(|lower| needle_chars.next() == Some(&lower));
+            if !all_matched {
+                break;
+            }
+            if needle_chars.as_slice().is_empty() {
+                end = Some(start + offset + c.len_utf8());
+                break;
+            }
+        }
+
+        if let Some(end) = end {
+            matches.push(start..end);
+            search_from = end;
+        }
+    }
+
+    matches
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchMatch {
+    /// Index of the top-level element containing the match
+    pub element: usize,
+    /// Index of the [`Text`] within its [`TextBox`]
+    pub text: usize,
+    /// Byte range of the match within the [`Text`]
+    pub range: Range<usize>,
+    /// Vertical position in the document to jump to for this match
+    pub y: f32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Search {
+    pub query: String,
+    /// Whether the query is still being typed out
+    pub editing: bool,
+    /// Scroll position when the search was started. Incremental updates jump relative to here
+    origin: f32,
+    matches: Vec<SearchMatch>,
+    current: Option<usize>,
+    /// The (number of elements, document height) from the last time matches were collected
+    last_run: Option<(usize, f32)>,
+}
+
+impl Search {
+    pub fn new(origin: f32) -> Self {
+        Self {
+            query: String::new(),
+            editing: true,
+            origin,
+            matches: Vec::new(),
+            current: None,
+            last_run: None,
+        }
+    }
+
+    pub fn matches(&self) -> &[SearchMatch] {
+        &self.matches
+    }
+
+    /// Re-runs the search against `elements`
+    pub fn update(&mut self, elements: &[Positioned<Element>], reserved_height: f32) {
+        self.matches.clear();
+        self.current = None;
+        collect_matches(elements, None, &self.query, &mut self.matches);
+        self.last_run = Some((elements.len(), reserved_height));
+    }
+
+    /// Re-runs the search if the elements have changed since the last run (e.g. from a reload)
+    ///
+    /// Returns whether the search was re-run
+    pub fn refresh(&mut self, elements: &[Positioned<Element>], reserved_height: f32) -> bool {
+        let is_stale = self.last_run != Some((elements.len(), reserved_height));
+        if is_stale {
+            self.update(elements, reserved_height);
+        }
+        is_stale
+    }
+
+    /// Selects the first match at or below where the search started (wrapping around to the top),
+    /// returning the position to scroll to
+    pub fn jump_from_origin(&mut self) -> Option<f32> {
+        let index = self
+            .matches
+            .iter()
+            .position(|m| m.y >= self.origin)
+            .or_else(|| (!self.matches.is_empty()).then_some(0))?;
+        self.current = Some(index);
+        Some(self.matches[index].y)
+    }
+
+    /// Selects the next or previous match (wrapping around), returning the position to scroll to
+    pub fn cycle(&mut self, direction: HistDirection) -> Option<f32> {
+        let len = self.matches.len();
+        if len == 0 {
+            return None;
+        }
+
+        let index = match (self.current, direction) {
+            (None, _) => return self.jump_from_origin(),
+            (Some(current), HistDirection::Next) => (current + 1) % len,
+            (Some(current), HistDirection::Prev) => (current + len - 1) % len,
+        };
+        self.current = Some(index);
+        Some(self.matches[index].y)
+    }
+}
+
+fn collect_matches(
+    elements: &[Positioned<Element>],
+    top_level: Option<usize>,
+    query: &str,
+    matches: &mut Vec<SearchMatch>,
+) {
+    for (i, element) in elements.iter().enumerate() {
+        let index = top_level.unwrap_or(i);
+        let y = element.bounds.as_ref().map_or(0., |bounds| bounds.pos.1);
+        match &element.inner {
+            Element::TextBox(text_box) => {
+                collect_text_box_matches(text_box, index, y, query, matches)
+            }
+            Element::Table(table) => {
+                for text_box in table.headers.iter().chain(table.rows.iter().flatten()) {
+                    collect_text_box_matches(text_box, index, y, query, matches);
+                }
+            }
+            Element::Row(row) => collect_matches(&row.elements, Some(index), query, matches),
+            Element::Section(section) => {
+                if let Some(summary) = &*section.summary {
+                    collect_matches(std::slice::from_ref(summary), Some(index), query, matches);
+                }
+                // Hidden contents can't be jumped to
+                if !*section.hidden.borrow() {
+                    collect_matches(&section.elements, Some(index), query, matches);
+                }
+            }
+            Element::Image(_) | Element::Spacer(_) => {}
+        }
+    }
+}
+
+fn collect_text_box_matches(
+    text_box: &TextBox,
+    element: usize,
+    y: f32,
+    query: &str,
+    matches: &mut Vec<SearchMatch>,
+) {
+    for (text_index, Text { text, .. }) in text_box.texts.iter().enumerate() {
+        matches.extend(
+            find_matches(text, query)
+                .into_iter()
+                .map(|range| SearchMatch {
+                    element,
+                    text: text_index,
+                    range,
+                    y,
+                }),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::Rect;
+
+    fn text_box(y: f32, texts: &[&str]) -> Positioned<Element> {
+        let texts = texts
+            .iter()
+            .map(|&text| Text::new(text.to_owned(), 1., [0.; 4]))
+            .collect();
+        Positioned {
+            inner: Element::TextBox(TextBox::new(texts, 1.)),
+            bounds: Some(Rect::new((0., y), (100., 20.))),
+        }
+    }
+
+    #[test]
+    fn case_insensitive_matches() {
+        assert_eq!(find_matches("Foo foo FOO", "foo"), [0..3, 4..7, 8..11]);
+        assert_eq!(find_matches("aaaa", "aa"), [0..2, 2..4]);
+        assert_eq!(
+            find_matches("Straße STRASSE straße", "straße"),
+            [0..7, 16..23]
+        );
+        assert_eq!(find_matches("ÀB àb", "àB"), [0..3, 4..7]);
+        assert!(find_matches("anything", "").is_empty());
+    }
+
+    #[test]
+    fn jump_and_cycle() {
+        let elements = vec![
+            text_box(0., &["Needle in a ", "haystack"]),
+            text_box(100., &["nothing here"]),
+            text_box(200., &["another NEEDLE"]),
+            text_box(300., &["last needle"]),
+        ];
+
+        let mut search = Search::new(150.);
+        search.query = "needle".into();
+        search.update(&elements, 400.);
+
+        let found: Vec<_> = search.matches().iter().map(|m| (m.element, m.y)).collect();
+        assert_eq!(found, [(0, 0.), (2, 200.), (3, 300.)]);
+        assert_eq!(search.matches()[1].range, 8..14);
+
+        // Starts with the first match below where we were
+        assert_eq!(search.jump_from_origin(), Some(200.));
+        assert_eq!(search.cycle(HistDirection::Next), Some(300.));
+        assert_eq!(search.cycle(HistDirection::Next), Some(0.));
+        assert_eq!(search.cycle(HistDirection::Prev), Some(300.));
+
+        // Wraps back to the top when there's nothing further down
+        let mut search = Search::new(350.);
+        search.query = "needle".into();
+        search.update(&elements, 400.);
+        assert_eq!(search.jump_from_origin(), Some(0.));
+    }
+
+    #[test]
+    fn survives_reload() {
+        let mut search = Search::new(0.);
+        search.query = "needle".into();
+        let elements = vec![text_box(0., &["needle"])];
+        search.update(&elements, 100.);
+        assert_eq!(search.matches().len(), 1);
+        assert!(!search.refresh(&elements, 100.));
+
+        // Reloading the file swaps out all of the elements
+        let reloaded = vec![
+            text_box(0., &["no match"]),
+            text_box(50., &["needle and another needle"]),
+        ];
+        assert!(search.refresh(&reloaded, 200.));
+        let found: Vec<_> = search.matches().iter().map(|m| (m.element, m.y)).collect();
+        assert_eq!(found, [(1, 50.), (1, 50.)]);
+    }
+}
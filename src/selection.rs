@@ -25,6 +25,8 @@ pub enum SelectionKind {
         position: Point,
         time: Instant,
     },
+    /// Everything in the document
+    All,
     #[default]
     None,
 }
@@ -33,6 +35,8 @@ pub enum SelectionKind {
 pub struct Selection {
     pub selection: SelectionKind,
     pub text: String,
+    /// The same text as markdown, keeping its inline formatting
+    pub markdown: String,
 }
 
 impl Selection {
@@ -40,11 +44,19 @@ impl Selection {
         Self {
             selection: SelectionKind::None,
             text: String::new(),
+            markdown: String::new(),
         }
     }
     pub fn is_none(&self) -> bool {
         matches!(self.selection, SelectionKind::None)
     }
+    pub fn select_all(&mut self) {
+        self.clear_text();
+        self.selection = SelectionKind::All;
+    }
+    pub fn is_all(&self) -> bool {
+        matches!(self.selection, SelectionKind::All)
+    }
     pub fn start(&mut self, position: Point) {
         self.selection = SelectionKind::Start {
             position,
@@ -54,7 +66,7 @@ impl Selection {
 
     #[must_use]
     pub fn handle_drag(&mut self, new_position: Point) -> bool {
-        self.text.clear();
+        self.clear_text();
         match &mut self.selection {
             SelectionKind::Start { position, .. } => {
                 self.selection = SelectionKind::Drag {
@@ -69,7 +81,7 @@ impl Selection {
     }
 
     pub fn add_position(&mut self, new_position: Point) {
-        self.text.clear();
+        self.clear_text();
 
         match &self.selection {
             SelectionKind::Click {
@@ -107,8 +119,16 @@ impl Selection {
         }
     }
 
-    pub fn add_line(&mut self, str: &str) {
+    /// Drops the collected text, which gets gathered again while drawing
+    pub fn clear_text(&mut self) {
+        self.text.clear();
+        self.markdown.clear();
+    }
+
+    pub fn add_line(&mut self, str: &str, markdown: &str) {
         self.text.push_str(str);
         self.text.push('\n');
+        self.markdown.push_str(markdown);
+        self.markdown.push('\n');
     }
 }
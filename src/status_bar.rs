@@ -0,0 +1,114 @@
+//! A strip along the bottom of the window with info on what's being viewed
+//!
+//! The strip takes its height away from the document's viewport instead of drawing over the
+//! bottom of the document
+
+use std::path::{Path, PathBuf};
+
+use crate::opts::ResolvedTheme;
+
+/// Font size of the status text (before hidpi scaling)
+pub const FONT_SIZE: f32 = 13.;
+/// Space above and below the status text (before hidpi scaling)
+pub const PADDING: f32 = 4.;
+
+#[derive(Debug)]
+pub struct StatusBar {
+    pub file_path: PathBuf,
+    pub theme: ResolvedTheme,
+}
+
+impl StatusBar {
+    pub fn new(file_path: &Path, theme: ResolvedTheme) -> Self {
+        Self {
+            file_path: file_path.to_owned(),
+            theme,
+        }
+    }
+
+    /// Shows the status bar when it's hidden and hides it when it's shown
+    pub fn toggle(status_bar: &mut Option<Self>, file_path: &Path, theme: ResolvedTheme) {
+        *status_bar = match status_bar.take() {
+            Some(_) => None,
+            None => Some(Self::new(file_path, theme)),
+        };
+    }
+
+    pub fn height(hidpi_scale: f32) -> f32 {
+        (FONT_SIZE * 1.1 + 2. * PADDING) * hidpi_scale
+    }
+
+    /// The status line for the current view of the document
+    pub fn text(&self, scroll_y: f32, max_scroll_y: f32, zoom: f32) -> String {
+        let scrolled = if max_scroll_y > 0. {
+            (scroll_y / max_scroll_y).clamp(0., 1.)
+        } else {
+            1.
+        };
+        format!(
+            "{}  |  {:.0}%  |  Zoom {:.0}%  |  {}",
+            self.file_path.display(),
+            scrolled * 100.,
+            zoom * 100.,
+            self.theme.as_str(),
+        )
+    }
+}
+
+/// Height of the window that's left for the document itself
+pub fn content_height(screen_height: f32, status_bar: Option<&StatusBar>, hidpi_scale: f32) -> f32 {
+    match status_bar {
+        Some(_) => (screen_height - StatusBar::height(hidpi_scale)).max(0.),
+        None => screen_height,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_area_excludes_status_bar() {
+        let status_bar = StatusBar::new(Path::new("README.md"), ResolvedTheme::Dark);
+
+        assert_eq!(content_height(600., None, 2.), 600.);
+        let bar_height = StatusBar::height(2.);
+        assert!(bar_height > 2. * FONT_SIZE);
+        assert_eq!(
+            content_height(600., Some(&status_bar), 2.),
+            600. - bar_height
+        );
+        // Tiny windows don't go negative
+        assert_eq!(content_height(10., Some(&status_bar), 2.), 0.);
+    }
+
+    #[test]
+    fn toggling() {
+        let mut status_bar = None;
+        StatusBar::toggle(&mut status_bar, Path::new("a.md"), ResolvedTheme::Dark);
+        let shown = status_bar.as_ref().unwrap();
+        assert_eq!(shown.file_path, Path::new("a.md"));
+        assert_eq!(
+            content_height(600., status_bar.as_ref(), 1.),
+            600. - StatusBar::height(1.)
+        );
+
+        StatusBar::toggle(&mut status_bar, Path::new("a.md"), ResolvedTheme::Dark);
+        assert!(status_bar.is_none());
+        assert_eq!(content_height(600., status_bar.as_ref(), 1.), 600.);
+    }
+
+    #[test]
+    fn status_text() {
+        let status_bar = StatusBar::new(Path::new("docs/README.md"), ResolvedTheme::Light);
+        assert_eq!(
+            status_bar.text(250., 1_000., 1.5),
+            "docs/README.md  |  25%  |  Zoom 150%  |  light"
+        );
+        // Documents that fit on screen are always fully scrolled
+        assert_eq!(
+            status_bar.text(0., 0., 1.),
+            "docs/README.md  |  100%  |  Zoom 100%  |  light"
+        );
+    }
+}
@@ -1,28 +1,80 @@
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
-use crate::text::{Text, TextBox, TextBoxMeasure, TextSystem};
-use crate::utils::{default, Point, Rect, Size};
+use crate::text::{Text, TextBox, TextBoxMeasure, TextCache, TextSystem};
+use crate::utils::{default, Align, Point, Rect, Size};
 
+use glyphon::FontSystem;
 use taffy::node::MeasureFunc;
 use taffy::prelude::{
     auto, line, points, AvailableSpace, Display, Layout, Size as TaffySize, Style, Taffy,
 };
-use taffy::style::JustifyContent;
+use taffy::style::{JustifyContent, JustifySelf};
 
 pub const TABLE_ROW_GAP: f32 = 20.;
 pub const TABLE_COL_GAP: f32 = 20.;
+pub const COMPACT_TABLE_ROW_GAP: f32 = 8.;
+pub const COMPACT_TABLE_COL_GAP: f32 = 12.;
+/// How many rows of a table get rendered by default
+pub const DEFAULT_MAX_ROWS: usize = 1_000;
+/// How many columns of a table get rendered by default
+pub const DEFAULT_MAX_COLUMNS: usize = 64;
+
+/// Caps on how much of a table gets laid out. Everything past them is dropped while the table is
+/// built, so huge tables can't bog down their layout
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TableLimits {
+    pub rows: usize,
+    pub columns: usize,
+}
+
+impl Default for TableLimits {
+    fn default() -> Self {
+        Self {
+            rows: DEFAULT_MAX_ROWS,
+            columns: DEFAULT_MAX_COLUMNS,
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct TableLayout {
     pub headers: Vec<Layout>,
     pub rows: Vec<Vec<Layout>>,
     pub size: Size,
+    /// The left edge of the table's grid within its bounds (non-zero for centered or right-aligned
+    /// tables)
+    pub offset: f32,
+}
+
+impl TableLayout {
+    /// The right edge of the rightmost cell
+    ///
+    /// Cells only span as much of their column as their text does, so this isn't always the
+    /// last header's right edge
+    pub fn content_width(&self) -> f32 {
+        self.headers
+            .iter()
+            .chain(self.rows.iter().flatten())
+            .map(|cell| cell.location.x + cell.size.width)
+            .fold(0., f32::max)
+    }
 }
 
 #[derive(Default, Debug, PartialEq)]
 pub struct Table {
     pub headers: Vec<TextBox>,
     pub rows: Vec<Vec<TextBox>>,
+    /// Tighter spacing used for key/value style tables
+    pub compact: bool,
+    /// Each column's alignment as set on its header (e.g. markdown's `---:` delimiters)
+    pub column_aligns: Vec<Option<Align>>,
+    /// Where the whole table sits within the available width
+    pub align: Align,
+    pub limits: TableLimits,
+    /// Rows past the limit that were dropped
+    pub hidden_rows: usize,
+    /// Columns past the limit that were dropped
+    pub hidden_columns: usize,
 }
 
 impl Table {
@@ -30,6 +82,48 @@ impl Table {
         Table::default()
     }
 
+    pub fn max_columns(&self) -> usize {
+        self.rows
+            .iter()
+            .fold(self.headers.len(), |max, row| std::cmp::max(row.len(), max))
+    }
+
+    /// Two-column tables are treated as key/value pairs
+    pub fn is_key_value(&self) -> bool {
+        self.max_columns() == 2
+    }
+
+    /// Switches to the compact layout with bolded keys
+    pub fn make_compact(&mut self) {
+        self.compact = true;
+        for key in self.rows.iter_mut().filter_map(|row| row.first_mut()) {
+            for text in &mut key.texts {
+                text.is_bold = true;
+            }
+        }
+    }
+
+    /// Cells without their own alignment fall back to their column's
+    pub fn column_align(&self, column: usize) -> Option<Align> {
+        self.column_aligns.get(column).copied().flatten()
+    }
+
+    pub fn row_gap(&self) -> f32 {
+        if self.compact {
+            COMPACT_TABLE_ROW_GAP
+        } else {
+            TABLE_ROW_GAP
+        }
+    }
+
+    pub fn col_gap(&self) -> f32 {
+        if self.compact {
+            COMPACT_TABLE_COL_GAP
+        } else {
+            TABLE_COL_GAP
+        }
+    }
+
     pub fn find_hoverable<'a>(
         &'a self,
         text_system: &mut TextSystem,
@@ -84,10 +178,24 @@ impl Table {
         bounds: Size,
         zoom: f32,
     ) -> anyhow::Result<TableLayout> {
-        let max_columns = self
-            .rows
-            .iter()
-            .fold(self.headers.len(), |max, row| std::cmp::max(row.len(), max));
+        self.layout_with(
+            &text_system.font_system,
+            &text_system.text_cache,
+            taffy,
+            bounds,
+            zoom,
+        )
+    }
+
+    fn layout_with(
+        &self,
+        font_system: &Arc<Mutex<FontSystem>>,
+        text_cache: &Arc<Mutex<TextCache>>,
+        taffy: &mut Taffy,
+        bounds: Size,
+        zoom: f32,
+    ) -> anyhow::Result<TableLayout> {
+        let max_columns = self.max_columns();
 
         // Setup the grid
         let root_style = Style {
@@ -96,15 +204,19 @@ impl Table {
                 width: points(bounds.0),
                 height: auto(),
             },
-            justify_content: Some(JustifyContent::Start),
+            justify_content: Some(match self.align {
+                Align::Left => JustifyContent::Start,
+                Align::Center => JustifyContent::Center,
+                Align::Right => JustifyContent::End,
+            }),
             ..default()
         };
 
         let grid_style = Style {
             display: Display::Grid,
             gap: TaffySize {
-                width: points(TABLE_COL_GAP),
-                height: points(TABLE_ROW_GAP),
+                width: points(self.col_gap()),
+                height: points(self.row_gap()),
             },
             grid_template_columns: vec![auto(); max_columns],
             ..default()
@@ -115,8 +227,8 @@ impl Table {
         // Define the child nodes
         for (x, header) in self.headers.iter().enumerate() {
             let textbox_measure = TextBoxMeasure {
-                font_system: text_system.font_system.clone(),
-                text_cache: text_system.text_cache.clone(),
+                font_system: font_system.clone(),
+                text_cache: text_cache.clone(),
                 textbox: Arc::new(header.clone()),
                 zoom,
             };
@@ -124,6 +236,7 @@ impl Table {
                 Style {
                     grid_row: line(1),
                     grid_column: line(x as i16 + 1),
+                    justify_self: Some(justify(header.align)),
                     ..default()
                 },
                 MeasureFunc::Boxed(Box::new(move |known_dimensions, available_space| {
@@ -138,8 +251,8 @@ impl Table {
             for (x, item) in row.iter().enumerate() {
                 let item = item.clone();
                 let textbox_measure = TextBoxMeasure {
-                    font_system: text_system.font_system.clone(),
-                    text_cache: text_system.text_cache.clone(),
+                    font_system: font_system.clone(),
+                    text_cache: text_cache.clone(),
                     textbox: Arc::new(item.clone()),
                     zoom,
                 };
@@ -147,6 +260,7 @@ impl Table {
                     Style {
                         grid_row: line(1 + y as i16 + 1),
                         grid_column: line(x as i16 + 1),
+                        justify_self: Some(justify(item.align)),
                         ..default()
                     },
                     MeasureFunc::Boxed(Box::new(move |known_dimensions, available_space| {
@@ -174,16 +288,23 @@ impl Table {
             },
         )?;
 
+        // Cells are laid out relative to the grid, so shift them by wherever the grid ended up
+        let offset = taffy.layout(grid)?.location.x;
+        let cell_layout = |node: &taffy::node::Node| {
+            let mut layout = *taffy.layout(*node).unwrap();
+            layout.location.x += offset;
+            layout
+        };
         let mut rows = nodes.into_iter();
         let header_layout = rows
             .next()
             .unwrap_or_default()
             .iter()
-            .map(|n| *taffy.layout(*n).unwrap())
+            .map(cell_layout)
             .collect();
 
         let rows_layout: Vec<Vec<Layout>> = rows
-            .map(|row| row.iter().map(|n| *taffy.layout(*n).unwrap()).collect())
+            .map(|row| row.iter().map(cell_layout).collect())
             .collect();
         let size = taffy.layout(root)?.size;
 
@@ -191,14 +312,179 @@ impl Table {
             headers: header_layout,
             rows: rows_layout,
             size: (size.width, size.height),
+            offset,
         })
     }
 
     pub fn push_header(&mut self, header: TextBox) {
-        self.headers.push(header);
+        if self.headers.len() < self.limits.columns {
+            self.headers.push(header);
+        } else {
+            // The headers come before any rows, so they're all that's been counted so far
+            self.hidden_columns += 1;
+        }
     }
 
-    pub fn push_row(&mut self, row: Vec<TextBox>) {
+    pub fn push_row(&mut self, mut row: Vec<TextBox>) {
+        if self.rows.len() == self.limits.rows {
+            self.hidden_rows += 1;
+            return;
+        }
+        let hidden = row.len().saturating_sub(self.limits.columns);
+        self.hidden_columns = self.hidden_columns.max(hidden);
+        row.truncate(self.limits.columns);
         self.rows.push(row);
     }
+
+    /// A note on what got dropped from the table, if anything did
+    pub fn truncation_note(&self) -> Option<String> {
+        let plural = |count: usize, what: &str| {
+            let s = if count == 1 { "" } else { "s" };
+            format!("{count} more {what}{s}")
+        };
+        let note = match (self.hidden_rows, self.hidden_columns) {
+            (0, 0) => return None,
+            (rows, 0) => plural(rows, "row"),
+            (0, columns) => plural(columns, "column"),
+            (rows, columns) => format!("{} and {}", plural(rows, "row"), plural(columns, "column")),
+        };
+        Some(format!("... {note}"))
+    }
+}
+
+/// Places a cell within its column according to its alignment
+fn justify(align: Align) -> JustifySelf {
+    match align {
+        Align::Left => JustifySelf::Start,
+        Align::Center => JustifySelf::Center,
+        Align::Right => JustifySelf::End,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cell(text: &str, align: Align) -> TextBox {
+        let mut text_box = TextBox::new(vec![Text::new(text.to_owned(), 1., [0.; 4])], 1.);
+        text_box.set_align(align);
+        text_box
+    }
+
+    #[test]
+    fn right_aligned_column() {
+        let mut table = Table::new();
+        table.column_aligns = vec![None, Some(Align::Right)];
+        table.push_header(cell("Fruit", Align::Left));
+        table.push_header(cell("Count", Align::Right));
+        for (fruit, count) in [("apples", "1"), ("pears", "1000")] {
+            table.push_row(vec![cell(fruit, Align::Left), cell(count, Align::Right)]);
+        }
+
+        let font_system = Arc::new(Mutex::new(FontSystem::new()));
+        let text_cache = Arc::new(Mutex::new(TextCache::new()));
+        let layout = table
+            .layout_with(
+                &font_system,
+                &text_cache,
+                &mut Taffy::new(),
+                (800., 600.),
+                1.,
+            )
+            .unwrap();
+
+        // Cells hug their text, so the text ends right at the column's right edge
+        let right_edge = |layout: &Layout| layout.location.x + layout.size.width;
+        let count_column = [&layout.headers[1], &layout.rows[0][1], &layout.rows[1][1]];
+        let column_edge = right_edge(count_column[0]);
+        for count in count_column {
+            assert_eq!(right_edge(count), column_edge);
+        }
+        assert!(layout.rows[0][1].location.x > layout.rows[1][1].location.x);
+        // While left aligned ones all start together
+        assert_eq!(layout.rows[0][0].location.x, layout.rows[1][0].location.x);
+        assert_eq!(layout.content_width(), column_edge);
+    }
+
+    #[test]
+    fn huge_table_stays_bounded() {
+        let mut table = Table::new();
+        for column in 0..70 {
+            table.push_header(cell(&format!("Column {column}"), Align::Left));
+        }
+        for row in 0..10_000 {
+            let row = (0..3)
+                .map(|column| cell(&format!("{row}x{column}"), Align::Left))
+                .collect();
+            table.push_row(row);
+        }
+        assert_eq!(table.rows.len(), DEFAULT_MAX_ROWS);
+        assert_eq!(table.max_columns(), DEFAULT_MAX_COLUMNS);
+        assert_eq!(
+            table.truncation_note().unwrap(),
+            "... 9000 more rows and 6 more columns"
+        );
+
+        let font_system = Arc::new(Mutex::new(FontSystem::new()));
+        let text_cache = Arc::new(Mutex::new(TextCache::new()));
+        let start = std::time::Instant::now();
+        let layout = table
+            .layout_with(
+                &font_system,
+                &text_cache,
+                &mut Taffy::new(),
+                (800., 600.),
+                1.,
+            )
+            .unwrap();
+        assert_eq!(layout.rows.len(), DEFAULT_MAX_ROWS);
+        let elapsed = start.elapsed();
+        assert!(
+            elapsed < std::time::Duration::from_secs(10),
+            "Laying out the table took {elapsed:?}"
+        );
+    }
+
+    #[test]
+    fn centered_table() {
+        let mut table = Table::new();
+        table.push_header(cell("Key", Align::Left));
+        table.push_header(cell("Value", Align::Left));
+        table.push_row(vec![cell("a", Align::Left), cell("1", Align::Left)]);
+
+        let font_system = Arc::new(Mutex::new(FontSystem::new()));
+        let text_cache = Arc::new(Mutex::new(TextCache::new()));
+        let mut layout_with_align = |align| {
+            table.align = align;
+            table
+                .layout_with(
+                    &font_system,
+                    &text_cache,
+                    &mut Taffy::new(),
+                    (800., 600.),
+                    1.,
+                )
+                .unwrap()
+        };
+
+        let left = layout_with_align(Align::Left);
+        assert_eq!(left.offset, 0.);
+        let table_width = left.content_width();
+        assert!(table_width < 800.);
+
+        // Taffy rounds everything to whole pixels
+        let roughly_eq = |a: f32, b: f32| (a - b).abs() <= 1.;
+
+        // Equal gaps on either side of the table
+        let centered = layout_with_align(Align::Center);
+        assert!(roughly_eq(centered.offset, (800. - table_width) / 2.));
+        assert_eq!(centered.headers[0].location.x, centered.offset);
+        assert!(roughly_eq(
+            centered.content_width(),
+            centered.offset + table_width
+        ));
+
+        let right = layout_with_align(Align::Right);
+        assert!(roughly_eq(right.content_width(), 800.));
+    }
 }
@@ -0,0 +1,367 @@
+//! Viewing several files in one window with a strip of tabs along the top
+//!
+//! Only the active tab's document gets interpreted and positioned. Every tab keeps its own file
+//! watcher though, and holds on to its document and scroll position while it's in the background,
+//! so switching back to it doesn't have to re-read anything unless its file changed in the meantime
+
+use std::mem;
+use std::path::Path;
+
+use crate::file_watcher::Watcher;
+use crate::history::History;
+use crate::opts::CloseLastTab;
+use crate::positioner::Positioned;
+use crate::Element;
+
+/// Shown in place of the document after the last tab gets closed with `close-last-tab = "empty"`
+pub const EMPTY_PLACEHOLDER: &str = "\
+# No file open
+
+Drop a markdown file onto the window to open it
+";
+
+/// Font size of the tab titles (before hidpi scaling)
+pub const FONT_SIZE: f32 = 13.;
+/// Space above and below the tab titles (before hidpi scaling)
+pub const PADDING: f32 = 4.;
+
+/// The files open in the window
+///
+/// The active tab's history lives in `Opts::history` while it's active, so the slot for it here is
+/// out of date until the tab gets switched away from. The same goes for its document
+pub struct Tabs {
+    tabs: Vec<Tab>,
+    active: usize,
+    /// The last tab was closed, leaving the window showing a placeholder
+    emptied: bool,
+}
+
+/// Identifies a tab for as long as it's open, unlike its index which shifts as other tabs close
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TabId(usize);
+
+struct Tab {
+    id: TabId,
+    history: History,
+    watcher: Watcher,
+    /// The document from when the tab was last switched away from
+    doc: Option<TabDoc>,
+}
+
+/// A tab's positioned document along with how far it was scrolled
+pub struct TabDoc {
+    pub elements: Vec<Positioned<Element>>,
+    pub scroll_y: f32,
+}
+
+/// What closing the active tab leads to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CloseOutcome {
+    /// Another tab takes its place
+    Switch,
+    /// The window sticks around with a placeholder instead of a document
+    Empty,
+    Quit,
+}
+
+impl Tabs {
+    /// `watch` sets up the watcher for each tab's file
+    pub fn new(
+        current: &History,
+        others: Vec<History>,
+        mut watch: impl FnMut(TabId, &Path) -> Watcher,
+    ) -> Self {
+        let tabs = std::iter::once(current.clone())
+            .chain(others)
+            .enumerate()
+            .map(|(i, history)| {
+                let id = TabId(i);
+                Tab {
+                    id,
+                    watcher: watch(id, history.get_path()),
+                    history,
+                    doc: None,
+                }
+            })
+            .collect();
+        Self {
+            tabs,
+            active: 0,
+            emptied: false,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.tabs.len()
+    }
+
+    pub fn active(&self) -> usize {
+        self.active
+    }
+
+    pub fn active_id(&self) -> TabId {
+        self.tabs[self.active].id
+    }
+
+    /// The watcher for the active tab's file
+    pub fn watcher(&self) -> &Watcher {
+        &self.tabs[self.active].watcher
+    }
+
+    /// Holds on to the document of the tab with `id` until it's shown again. Does nothing once
+    /// that tab is closed
+    pub fn save_doc(&mut self, id: TabId, doc: TabDoc) {
+        if let Some(tab) = self.tabs.iter_mut().find(|tab| tab.id == id) {
+            tab.doc = Some(doc);
+        }
+    }
+
+    /// The active tab's document from when it was last shown, if it's still up to date
+    pub fn take_doc(&mut self) -> Option<TabDoc> {
+        self.tabs[self.active].doc.take()
+    }
+
+    /// Drops the saved document of the tab with `id` (e.g. because its file changed)
+    pub fn forget_doc(&mut self, id: TabId) {
+        if let Some(tab) = self.tabs.iter_mut().find(|tab| tab.id == id) {
+            tab.doc = None;
+        }
+    }
+
+    /// Drops every saved document (e.g. because the theme changed)
+    pub fn forget_docs(&mut self) {
+        for tab in &mut self.tabs {
+            tab.doc = None;
+        }
+    }
+
+    /// Makes the tab at `index` active, swapping `current` over to its history
+    ///
+    /// Returns `false` when that tab is already active or doesn't exist
+    pub fn switch(&mut self, index: usize, current: &mut History) -> bool {
+        if index == self.active || index >= self.len() {
+            return false;
+        }
+        mem::swap(&mut self.tabs[self.active].history, current);
+        mem::swap(&mut self.tabs[index].history, current);
+        self.active = index;
+        true
+    }
+
+    /// Switches to the tab after the active one, wrapping around at the end
+    pub fn switch_next(&mut self, current: &mut History) -> bool {
+        let next = (self.active + 1) % self.len();
+        self.switch(next, current)
+    }
+
+    /// What closing the active tab should do given the `close-last-tab` setting
+    ///
+    /// Closing the placeholder that's left after the last tab always quits
+    pub fn close_outcome(&self, close_last_tab: CloseLastTab) -> CloseOutcome {
+        if self.len() > 1 {
+            CloseOutcome::Switch
+        } else if self.emptied || close_last_tab == CloseLastTab::Quit {
+            CloseOutcome::Quit
+        } else {
+            CloseOutcome::Empty
+        }
+    }
+
+    pub fn is_emptied(&self) -> bool {
+        self.emptied
+    }
+
+    pub fn set_emptied(&mut self, emptied: bool) {
+        self.emptied = emptied;
+    }
+
+    /// Drops the active tab (along with its watcher) and switches `current` over to the tab that
+    /// takes its place
+    ///
+    /// Returns `false` when it's the last tab, which is left open
+    pub fn close(&mut self, current: &mut History) -> bool {
+        if self.len() == 1 {
+            return false;
+        }
+        self.tabs.remove(self.active);
+        self.active = self.active.min(self.len() - 1);
+        mem::swap(&mut self.tabs[self.active].history, current);
+        true
+    }
+
+    /// The title of each tab, in order
+    pub fn titles(&self, current: &History) -> Vec<String> {
+        self.tabs
+            .iter()
+            .enumerate()
+            .map(|(i, tab)| {
+                let path = if i == self.active {
+                    current.get_path()
+                } else {
+                    tab.history.get_path()
+                };
+                title(path)
+            })
+            .collect()
+    }
+}
+
+fn title(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// What the renderer needs to draw the tab strip
+#[derive(Debug)]
+pub struct TabBar {
+    pub titles: Vec<String>,
+    pub active: usize,
+}
+
+impl TabBar {
+    pub fn height(hidpi_scale: f32) -> f32 {
+        (FONT_SIZE * 1.1 + 2. * PADDING) * hidpi_scale
+    }
+
+    /// Every tab gets an equal share of the window's width
+    pub fn tab_width(&self, screen_width: f32) -> f32 {
+        screen_width / self.titles.len().max(1) as f32
+    }
+
+    /// The index of the tab at `(x, y)`, if there is one
+    pub fn tab_at(&self, (x, y): (f32, f32), screen_width: f32, hidpi_scale: f32) -> Option<usize> {
+        if !(0. ..Self::height(hidpi_scale)).contains(&y) || x < 0. {
+            return None;
+        }
+        let index = (x / self.tab_width(screen_width)) as usize;
+        (index < self.titles.len()).then_some(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+
+    fn histories(dir: &Path, names: &[&str]) -> Vec<History> {
+        names
+            .iter()
+            .map(|name| {
+                let path = dir.join(name);
+                fs::write(&path, "").unwrap();
+                History::new(&path).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn switching_and_closing() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut histories = histories(dir.path(), &["a.md", "b.md", "c.md"]);
+        let mut current = histories.remove(0);
+        let mut tabs = Tabs::new(&current, histories, |_, _| Watcher::detached());
+        let titles = |tabs: &Tabs, current: &History| tabs.titles(current).join(" ");
+        assert_eq!(titles(&tabs, &current), "a.md b.md c.md");
+
+        // Navigating within a tab sticks with that tab
+        current.make_next(dir.path().join("b.md"));
+        assert_eq!(titles(&tabs, &current), "b.md b.md c.md");
+        assert!(!tabs.switch(0, &mut current));
+        assert!(!tabs.switch(3, &mut current));
+
+        assert!(tabs.switch(2, &mut current));
+        assert_eq!(current.get_path().file_name().unwrap(), "c.md");
+        assert!(tabs.switch_next(&mut current));
+        assert_eq!(tabs.active(), 0);
+        assert_eq!(current.get_path().file_name().unwrap(), "b.md");
+        current.previous().unwrap();
+        assert_eq!(current.get_path().file_name().unwrap(), "a.md");
+
+        assert!(tabs.switch(2, &mut current));
+        assert!(tabs.close(&mut current));
+        assert_eq!(tabs.active(), 1);
+        assert_eq!(titles(&tabs, &current), "a.md b.md");
+        assert!(tabs.close(&mut current));
+        assert_eq!(titles(&tabs, &current), "a.md");
+        assert!(!tabs.close(&mut current));
+    }
+
+    #[test]
+    fn closing_the_last_tab() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut histories = histories(dir.path(), &["a.md", "b.md"]);
+        let mut current = histories.remove(0);
+        let mut tabs = Tabs::new(&current, histories, |_, _| Watcher::detached());
+
+        // Other tabs are always switched to first
+        for close_last_tab in [CloseLastTab::Quit, CloseLastTab::Empty] {
+            assert_eq!(tabs.close_outcome(close_last_tab), CloseOutcome::Switch);
+        }
+        assert!(tabs.close(&mut current));
+
+        assert_eq!(tabs.close_outcome(CloseLastTab::Quit), CloseOutcome::Quit);
+        assert_eq!(tabs.close_outcome(CloseLastTab::Empty), CloseOutcome::Empty);
+        // There's nothing left to close after that
+        tabs.set_emptied(true);
+        assert_eq!(tabs.close_outcome(CloseLastTab::Empty), CloseOutcome::Quit);
+        // Until another file gets opened
+        tabs.set_emptied(false);
+        assert_eq!(tabs.close_outcome(CloseLastTab::Empty), CloseOutcome::Empty);
+    }
+
+    #[test]
+    fn background_docs() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut histories = histories(dir.path(), &["a.md", "b.md", "c.md"]);
+        let mut current = histories.remove(0);
+        let mut tabs = Tabs::new(&current, histories, |_, _| Watcher::detached());
+        let doc = |scroll_y| TabDoc {
+            elements: Vec::new(),
+            scroll_y,
+        };
+        let take_scroll = |tabs: &mut Tabs| tabs.take_doc().map(|doc| doc.scroll_y);
+
+        // Nothing's been shown yet
+        let a = tabs.active_id();
+        assert!(tabs.switch(1, &mut current));
+        assert_eq!(take_scroll(&mut tabs), None);
+        tabs.save_doc(a, doc(10.));
+
+        let b = tabs.active_id();
+        assert!(tabs.switch(0, &mut current));
+        assert_eq!(take_scroll(&mut tabs), Some(10.));
+        tabs.save_doc(tabs.active_id(), doc(20.));
+        tabs.save_doc(b, doc(30.));
+        // The file changed while the tab was in the background
+        tabs.forget_doc(b);
+        assert!(tabs.switch(1, &mut current));
+        assert_eq!(take_scroll(&mut tabs), None);
+
+        // Closed tabs take their document with them
+        assert!(tabs.switch(2, &mut current));
+        let c = tabs.active_id();
+        assert!(tabs.close(&mut current));
+        tabs.save_doc(c, doc(40.));
+        assert_eq!(take_scroll(&mut tabs), None);
+        assert!(tabs.switch(0, &mut current));
+        assert_eq!(take_scroll(&mut tabs), Some(20.));
+    }
+
+    #[test]
+    fn clicking_tabs() {
+        let tab_bar = TabBar {
+            titles: vec!["a.md".to_owned(), "b.md".to_owned()],
+            active: 0,
+        };
+        let height = TabBar::height(2.);
+
+        assert_eq!(tab_bar.tab_at((10., 1.), 400., 2.), Some(0));
+        assert_eq!(tab_bar.tab_at((250., height - 1.), 400., 2.), Some(1));
+        // Below the strip is the document
+        assert_eq!(tab_bar.tab_at((250., height), 400., 2.), None);
+        assert_eq!(tab_bar.tab_at((400., 1.), 400., 2.), None);
+    }
+}
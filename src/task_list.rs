@@ -0,0 +1,135 @@
+//! Toggling task list checkboxes (`- [ ]`) by editing the markdown file they came from
+//!
+//! Each rendered checkbox keeps the byte offset of its `[ ]` within the source. Clicking it flips
+//! the marker in the file, and the file watcher takes care of reloading the document
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::utils::comrak_options;
+
+use anyhow::Context;
+use comrak::nodes::NodeValue;
+use comrak::{parse_document, Arena};
+
+/// The byte offsets of the `[ ]` markers for every task list item in `md`, keyed by the
+/// `(line, column)` that each item starts at (the same as its `data-sourcepos`)
+pub fn markers(md: &str) -> HashMap<(usize, usize), usize> {
+    let arena = Arena::new();
+    let root = parse_document(&arena, md, &comrak_options());
+
+    // Positions are relative to the end of the front matter, if there is one
+    let front_matter_len = root
+        .children()
+        .find_map(|node| match &node.data.borrow().value {
+            NodeValue::FrontMatter(front_matter) => Some(front_matter.len()),
+            _ => None,
+        })
+        .unwrap_or(0);
+    let line_starts: Vec<usize> = std::iter::once(front_matter_len)
+        .chain(
+            md[front_matter_len..]
+                .match_indices('\n')
+                .map(|(i, _)| front_matter_len + i + 1),
+        )
+        .collect();
+    root.descendants()
+        .filter_map(|node| {
+            let ast = node.data.borrow();
+            let NodeValue::TaskItem(_) = ast.value else {
+                return None;
+            };
+            // The item starts at its bullet, so look for the marker after it on the same line
+            let start = ast.sourcepos.start;
+            let line_start = *line_starts.get(start.line.checked_sub(1)?)?;
+            let item_start = line_start + start.column.checked_sub(1)?;
+            let line_end = md[item_start..]
+                .find('\n')
+                .map_or(md.len(), |i| item_start + i);
+            let marker = md[item_start..line_end].find('[')?;
+            Some(((start.line, start.column), item_start + marker))
+        })
+        .collect()
+}
+
+/// `md` with the marker at `offset` flipped from `checked`, or `None` when there's no such marker
+/// (e.g. the file changed since it was rendered)
+fn toggled(md: &str, offset: usize, checked: bool) -> Option<String> {
+    let marker = md.get(offset..offset + 3)?;
+    let expected = if checked {
+        matches!(marker, "[x]" | "[X]")
+    } else {
+        marker == "[ ]"
+    };
+    if !expected {
+        return None;
+    }
+
+    let replacement = if checked { "[ ]" } else { "[x]" };
+    let mut toggled = md.to_owned();
+    toggled.replace_range(offset..offset + 3, replacement);
+    Some(toggled)
+}
+
+/// Flips the checkbox at `offset` within the file at `path`
+pub fn toggle(path: &Path, offset: usize, checked: bool) -> anyhow::Result<()> {
+    let md =
+        fs::read_to_string(path).with_context(|| format!("Failed reading {}", path.display()))?;
+    match toggled(&md, offset, checked) {
+        Some(toggled) => {
+            fs::write(path, toggled).with_context(|| format!("Failed writing {}", path.display()))
+        }
+        None => {
+            tracing::warn!(
+                "Skipping toggling a checkbox since {} no longer matches what's rendered",
+                path.display()
+            );
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn marker_offsets() {
+        let md = "\
+---
+title: Tasks
+---
+- [ ] First
+- [x] Second
+  1. [X] Nested
+- Not a task [ ]
+
+Not a list [ ]
+";
+        let mut offsets: Vec<_> = markers(md).into_iter().collect();
+        offsets.sort();
+        let markers: Vec<_> = offsets.iter().map(|&(_, i)| &md[i..i + 3]).collect();
+        assert_eq!(markers, ["[ ]", "[x]", "[X]"]);
+        // Lines are counted from the end of the front matter
+        assert_eq!(offsets[0], ((1, 1), md.find("[ ] First").unwrap()));
+        assert_eq!(offsets[2], ((3, 3), md.find("[X] Nested").unwrap()));
+    }
+
+    #[test]
+    fn toggling() {
+        let md = "- [ ] Todo\n- [x] Done\n";
+        let todo = md.find("[ ]").unwrap();
+        let done = md.find("[x]").unwrap();
+
+        assert_eq!(
+            toggled(md, todo, false).unwrap(),
+            "- [x] Todo\n- [x] Done\n"
+        );
+        assert_eq!(toggled(md, done, true).unwrap(), "- [ ] Todo\n- [ ] Done\n");
+        // Things have changed since the checkbox was rendered
+        assert_eq!(toggled(md, todo, true), None);
+        assert_eq!(toggled(md, 0, false), None);
+        assert_eq!(toggled(md, md.len(), false), None);
+    }
+}
@@ -8,14 +8,18 @@ use std::sync::{Arc, Mutex};
 use fxhash::{FxHashMap, FxHashSet};
 use glyphon::{
     Affinity, Attrs, AttrsList, BufferLine, Color, Cursor, FamilyOwned, FontSystem, LayoutGlyph,
-    Shaping, Style, SwashCache, TextArea, TextBounds, Weight,
+    Shaping, Style, SwashCache, TextArea, TextBounds, Weight, Wrap,
 };
 use smart_debug::SmartDebug;
 use taffy::prelude::{AvailableSpace, Size as TaffySize};
+use unicode_segmentation::UnicodeSegmentation;
 
 use crate::debug_impls::{self, DebugInline, DebugInlineMaybeF32Color};
+use crate::search;
 use crate::selection::{Selection, SelectionKind, SelectionMode};
+use crate::toc::Heading;
 use crate::utils::{Align, Line, Point, Rect, Size};
+use crate::whitespace;
 
 type KeyHash = u64;
 type HashBuilder = twox_hash::RandomXxHashBuilder64;
@@ -63,12 +67,28 @@ pub struct TextBox {
     #[debug(wrapper = DebugInlineMaybeF32Color)]
     pub background_color: Option<[f32; 4]>,
     pub is_code_block: bool,
+    /// Lines run on past the bounds instead of wrapping
+    pub no_wrap: bool,
+    /// Draws a gutter of line numbers to the left of the text (`code-line-numbers`)
+    pub line_numbers: bool,
     #[debug(wrapper = DebugInline)]
     pub is_quote_block: Option<usize>,
+    /// Overrides the color of the innermost quote block's bar (used for alerts)
+    #[debug(wrapper = DebugInlineMaybeF32Color)]
+    pub quote_bar_color: Option<[f32; 4]>,
     #[debug(wrapper = DebugInline)]
     pub is_checkbox: Option<bool>,
+    /// Where the checkbox's `[ ]` is in the markdown source, for toggling it
+    #[debug(skip)]
+    pub checkbox_source: Option<usize>,
     #[debug(wrapper = DebugInline)]
     pub is_anchor: Option<String>,
+    /// Anchors that were replaced by a later `set_anchor()` (e.g. several footnote references
+    /// within a single paragraph). These all still link to this text box
+    pub other_anchors: Vec<String>,
+    /// The header's level and text when this is a header, for listing it in an outline
+    #[debug(skip)]
+    pub heading: Option<Heading>,
     #[debug(no_skip)]
     pub texts: Vec<Text>,
     #[debug(skip)]
@@ -82,9 +102,15 @@ impl Default for TextBox {
             font_size: 16.0,
             texts: Vec::new(),
             is_code_block: false,
+            no_wrap: false,
+            line_numbers: false,
             is_quote_block: None,
+            quote_bar_color: None,
             is_checkbox: None,
+            checkbox_source: None,
             is_anchor: None,
+            other_anchors: Vec::new(),
+            heading: None,
             align: Align::default(),
             hidpi_scale: 1.0,
             padding_height: 0.0,
@@ -103,6 +129,21 @@ pub struct CachedTextArea {
 }
 
 impl CachedTextArea {
+    /// Cuts off anything drawn above `top`
+    pub fn clip_top(&mut self, top: f32) {
+        self.bounds.top = self.bounds.top.max(top as i32);
+    }
+
+    /// Cuts off anything drawn right of `right`
+    pub fn clip_right(&mut self, right: f32) {
+        self.bounds.right = self.bounds.right.min(right as i32);
+    }
+
+    /// Cuts off anything drawn below `bottom`
+    pub fn clip_bottom(&mut self, bottom: f32) {
+        self.bounds.bottom = self.bounds.bottom.min(bottom as i32);
+    }
+
     pub fn text_area<'a>(&self, cache: &'a TextCache) -> TextArea<'a> {
         TextArea {
             buffer: cache.get(&self.key).expect("Get cached buffer"),
@@ -134,6 +175,7 @@ impl TextBox {
 
     pub fn clear_quote_block(&mut self) {
         self.is_quote_block = None;
+        self.quote_bar_color = None;
     }
 
     pub fn set_checkbox(&mut self, is_checked: bool) {
@@ -141,7 +183,13 @@ impl TextBox {
     }
 
     pub fn set_anchor(&mut self, anchor: String) {
-        self.is_anchor = Some(anchor);
+        if let Some(prev) = self.is_anchor.replace(anchor) {
+            self.other_anchors.push(prev);
+        }
+    }
+
+    pub fn anchors(&self) -> impl Iterator<Item = &String> {
+        self.is_anchor.iter().chain(&self.other_anchors)
     }
 
     pub fn set_background_color(&mut self, color: [f32; 4]) {
@@ -165,6 +213,86 @@ impl TextBox {
         self.font_size * 1.1 * self.hidpi_scale * zoom
     }
 
+    /// Where the checkbox gets drawn for a checkbox text box positioned at `pos`
+    pub fn checkbox_bounds(&self, pos: Point, zoom: f32) -> Option<Rect> {
+        self.is_checkbox?;
+        let box_size = self.font_size * self.hidpi_scale * zoom * 0.75;
+        let line_height = self.line_height(zoom);
+        Some(Rect::new(
+            (pos.0, pos.1 + line_height / 2. - box_size / 2.),
+            (box_size, box_size),
+        ))
+    }
+
+    /// The number of lines before any wrapping
+    fn line_count(&self) -> usize {
+        let newlines = self
+            .texts
+            .iter()
+            .filter(|text| text.text.ends_with('\n'))
+            .count();
+        let trailing = self
+            .texts
+            .last()
+            .map_or(false, |text| !text.text.ends_with('\n'));
+        newlines + usize::from(trailing)
+    }
+
+    /// The space on either side of the line numbers, which is about one digit wide
+    pub fn gutter_padding(&self, zoom: f32) -> f32 {
+        // Digits are roughly 0.6em wide in most monospace fonts
+        0.6 * self.font_size * self.hidpi_scale * zoom
+    }
+
+    /// The width of the line number gutter, which is enough for the widest number plus padding
+    pub fn gutter_width(&self, zoom: f32) -> f32 {
+        if !self.line_numbers {
+            return 0.;
+        }
+        let digits = self.line_count().max(1).ilog10() + 1;
+        (digits as f32 + 2.) * self.gutter_padding(zoom)
+    }
+
+    /// A right-aligned text box numbering each of the lines in this one
+    ///
+    /// Lines that wrap only get numbered on their first row. This is kept separate from the text
+    /// itself so that the numbers never end up in a selection
+    pub fn line_number_gutter(
+        &self,
+        text_system: &mut TextSystem,
+        bounds: Size,
+        zoom: f32,
+        color: [f32; 4],
+    ) -> TextBox {
+        let mut cache = text_system.text_cache.lock().unwrap();
+        let (_, buffer) = cache.allocate(
+            text_system.font_system.lock().unwrap().borrow_mut(),
+            self.key(bounds, zoom),
+        );
+
+        let mut prev_line = None;
+        let texts = buffer
+            .layout_runs()
+            .map(|run| {
+                let number = if prev_line == Some(run.line_i) {
+                    String::new()
+                } else {
+                    (run.line_i + 1).to_string()
+                };
+                prev_line = Some(run.line_i);
+                Text::new(format!("{number}\n"), self.hidpi_scale, color)
+                    .with_family(FamilyOwned::Monospace)
+            })
+            .collect();
+
+        TextBox {
+            font_size: self.font_size,
+            align: Align::Right,
+            no_wrap: true,
+            ..TextBox::new(texts, self.hidpi_scale)
+        }
+    }
+
     pub fn key(&self, bounds: Size, zoom: f32) -> Key<'_> {
         let mut lines = Vec::new();
         let mut sections = Vec::new();
@@ -185,6 +313,7 @@ impl TextBox {
             size: self.font_size * self.hidpi_scale * zoom,
             line_height: self.line_height(zoom),
             bounds,
+            no_wrap: self.no_wrap,
         }
     }
 
@@ -221,6 +350,34 @@ impl TextBox {
         }
     }
 
+    /// Offset from the top of the text box to the baseline of its first line
+    pub fn baseline(&self, text_system: &mut TextSystem, bounds: Size, zoom: f32) -> f32 {
+        self.baseline_without_system(
+            &text_system.text_cache,
+            &text_system.font_system,
+            bounds,
+            zoom,
+        )
+    }
+
+    pub fn baseline_without_system(
+        &self,
+        text_cache: &Mutex<TextCache>,
+        font_system: &Mutex<FontSystem>,
+        bounds: Size,
+        zoom: f32,
+    ) -> f32 {
+        let mut cache = text_cache.lock().unwrap();
+        let (_, buffer) = cache.allocate(
+            font_system.lock().unwrap().borrow_mut(),
+            self.key(bounds, zoom),
+        );
+        buffer
+            .layout_runs()
+            .next()
+            .map_or_else(|| self.line_height(zoom), |run| run.line_y)
+    }
+
     pub fn size(&self, text_system: &mut TextSystem, bounds: Size, zoom: f32) -> Size {
         self.size_without_system(
             &text_system.text_cache,
@@ -408,6 +565,231 @@ impl TextBox {
         lines
     }
 
+    /// Rects (in screen coordinates) covering all of the search matches for `query`
+    pub fn render_search_matches(
+        &self,
+        text_system: &mut TextSystem,
+        bounds: Size,
+        zoom: f32,
+        text_area: &CachedTextArea,
+        query: &str,
+    ) -> Vec<Rect> {
+        let line_height = self.line_height(zoom);
+        let mut rects = Vec::new();
+
+        let mut cache = text_system.text_cache.lock().unwrap();
+
+        let (_, buffer) = cache.allocate(
+            text_system.font_system.lock().unwrap().borrow_mut(),
+            self.key(bounds, zoom),
+        );
+
+        let mut y = text_area.top;
+        for line in buffer.layout_runs() {
+            for range in search::find_matches(line.text, query) {
+                let start_cursor = Cursor::new(line.line_i, range.start);
+                let end_cursor = Cursor::new(line.line_i, range.end);
+                // Matches that wrapped onto a different run won't have a highlight in this one
+                if let Some((highlight_x, highlight_w)) = line.highlight(start_cursor, end_cursor) {
+                    let x = text_area.left + highlight_x;
+                    rects.push(Rect::from_min_max(
+                        (x.floor(), y),
+                        ((x + highlight_w).ceil(), y + line_height),
+                    ));
+                }
+            }
+            y += line_height;
+        }
+
+        rects
+    }
+
+    /// Rects (in screen coordinates) behind each run of `<kbd>` text
+    pub fn render_kbd_backgrounds(
+        &self,
+        text_system: &mut TextSystem,
+        bounds: Size,
+        zoom: f32,
+        text_area: &CachedTextArea,
+    ) -> Vec<Rect> {
+        if !self.texts.iter().any(|text| text.is_kbd) {
+            return Vec::new();
+        }
+
+        let line_height = self.line_height(zoom);
+        let mut rects = Vec::new();
+
+        let mut cache = text_system.text_cache.lock().unwrap();
+
+        let (_, buffer) = cache.allocate(
+            text_system.font_system.lock().unwrap().borrow_mut(),
+            self.key(bounds, zoom),
+        );
+
+        let mut y = text_area.top;
+        for line in buffer.layout_runs() {
+            // Consecutive glyphs from the same text make up a single key
+            let mut keys: Vec<(usize, Range<usize>)> = Vec::new();
+            for glyph in line.glyphs {
+                if !self.texts[glyph.metadata].is_kbd {
+                    continue;
+                }
+                match keys.last_mut() {
+                    Some((text_index, range)) if *text_index == glyph.metadata => {
+                        range.end = glyph.end;
+                    }
+                    _ => keys.push((glyph.metadata, glyph.start..glyph.end)),
+                }
+            }
+            for (_, range) in keys {
+                let start_cursor = Cursor::new(line.line_i, range.start);
+                let end_cursor = Cursor::new(line.line_i, range.end);
+                if let Some((highlight_x, highlight_w)) = line.highlight(start_cursor, end_cursor) {
+                    let x = text_area.left + highlight_x;
+                    rects.push(Rect::from_min_max(
+                        (x.floor(), y),
+                        ((x + highlight_w).ceil(), y + line_height),
+                    ));
+                }
+            }
+            y += line_height;
+        }
+
+        rects
+    }
+
+    /// The superscripts and subscripts within this text box, each as a smaller text box along with
+    /// its offset from the top left of this one
+    pub fn script_boxes(
+        &self,
+        text_cache: &Mutex<TextCache>,
+        font_system: &Mutex<FontSystem>,
+        bounds: Size,
+        zoom: f32,
+    ) -> Vec<(TextBox, Point)> {
+        if !self.texts.iter().any(|text| text.script.is_some()) {
+            return Vec::new();
+        }
+
+        // Where each run of script glyphs sits within the laid out text
+        let mut runs: Vec<(usize, String, Point)> = Vec::new();
+        {
+            let mut cache = text_cache.lock().unwrap();
+            let (_, buffer) = cache.allocate(
+                font_system.lock().unwrap().borrow_mut(),
+                self.key(bounds, zoom),
+            );
+            for line in buffer.layout_runs() {
+                let mut line_runs: Vec<(usize, Range<usize>, f32)> = Vec::new();
+                for glyph in line.glyphs {
+                    if self.texts[glyph.metadata].script.is_none() {
+                        continue;
+                    }
+                    match line_runs.last_mut() {
+                        Some((text_index, range, _)) if *text_index == glyph.metadata => {
+                            range.start = range.start.min(glyph.start);
+                            range.end = range.end.max(glyph.end);
+                        }
+                        _ => line_runs.push((glyph.metadata, glyph.start..glyph.end, glyph.x)),
+                    }
+                }
+                runs.extend(line_runs.into_iter().map(|(text_index, range, x)| {
+                    (text_index, line.text[range].to_owned(), (x, line.line_y))
+                }));
+            }
+        }
+
+        runs.into_iter()
+            .map(|(text_index, content, (x, baseline))| {
+                let text = &self.texts[text_index];
+                let script = text.script.expect("Only script glyphs are kept");
+                let script_box = TextBox {
+                    font_size: self.font_size * Script::SCALE,
+                    no_wrap: true,
+                    ..TextBox::new(
+                        vec![Text {
+                            text: content,
+                            script: None,
+                            ..text.clone()
+                        }],
+                        self.hidpi_scale,
+                    )
+                };
+                let script_baseline = script_box.baseline_without_system(
+                    text_cache,
+                    font_system,
+                    (f32::INFINITY, f32::INFINITY),
+                    zoom,
+                );
+                let shift = script.baseline_shift() * self.font_size * self.hidpi_scale * zoom;
+                (script_box, (x, baseline - script_baseline + shift))
+            })
+            .collect()
+    }
+
+    /// Text areas for drawing the superscripts and subscripts over their placeholders in `text_area`
+    pub fn script_areas(
+        &self,
+        text_system: &mut TextSystem,
+        bounds: Size,
+        zoom: f32,
+        text_area: &CachedTextArea,
+    ) -> Vec<CachedTextArea> {
+        let text_cache = &text_system.text_cache;
+        let font_system = &text_system.font_system;
+        self.script_boxes(text_cache, font_system, bounds, zoom)
+            .into_iter()
+            .map(|(script_box, (x, y))| {
+                let mut cache = text_cache.lock().unwrap();
+                let (key, _) = cache.allocate(
+                    font_system.lock().unwrap().borrow_mut(),
+                    script_box.key((f32::INFINITY, f32::INFINITY), zoom),
+                );
+                CachedTextArea {
+                    key,
+                    left: text_area.left + x,
+                    top: text_area.top + y,
+                    ..text_area.clone()
+                }
+            })
+            .collect()
+    }
+
+    /// Rects (in screen coordinates) covering each space and tab, paired with their markers
+    pub fn render_whitespace(
+        &self,
+        text_system: &mut TextSystem,
+        bounds: Size,
+        zoom: f32,
+        text_area: &CachedTextArea,
+    ) -> Vec<(Rect, whitespace::Marker)> {
+        let line_height = self.line_height(zoom);
+        let mut rects = Vec::new();
+
+        let mut cache = text_system.text_cache.lock().unwrap();
+
+        let (_, buffer) = cache.allocate(
+            text_system.font_system.lock().unwrap().borrow_mut(),
+            self.key(bounds, zoom),
+        );
+
+        let mut y = text_area.top;
+        for line in buffer.layout_runs() {
+            for marker in whitespace::markers(line.text) {
+                let start_cursor = Cursor::new(line.line_i, marker.range.start);
+                let end_cursor = Cursor::new(line.line_i, marker.range.end);
+                if let Some((highlight_x, highlight_w)) = line.highlight(start_cursor, end_cursor) {
+                    let x = text_area.left + highlight_x;
+                    let rect = Rect::new((x, y), (highlight_w, line_height));
+                    rects.push((rect, marker));
+                }
+            }
+            y += line_height;
+        }
+
+        rects
+    }
+
     pub fn render_selection(
         &self,
         text_system: &mut TextSystem,
@@ -418,6 +800,8 @@ impl TextBox {
     ) -> Option<Vec<Rect>> {
         let mut rects = Vec::new();
         let mut selected_text = String::new();
+        // The selected text split up by which of `self.texts` it came from
+        let mut pieces: Vec<(usize, String)> = Vec::new();
 
         let line_height = self.line_height(zoom);
         let mut cache = text_system.text_cache.lock().unwrap();
@@ -442,8 +826,14 @@ impl TextBox {
                     buffer.hit(end.0 - screen_position.0, end.1 - screen_position.1)?;
                 (start_cursor, end_cursor, start.1, end.1)
             }
+            SelectionKind::All => {
+                let last_line = buffer.lines.len().checked_sub(1)?;
+                let start_cursor = Cursor::new(0, 0);
+                let end_cursor = Cursor::new(last_line, buffer.lines[last_line].text().len());
+                (start_cursor, end_cursor, f32::NEG_INFINITY, f32::INFINITY)
+            }
             SelectionKind::Click { mode, position, .. } => {
-                let mut cursor = buffer.hit(
+                let cursor = buffer.hit(
                     position.0 - screen_position.0,
                     position.1 - screen_position.1,
                 )?;
@@ -453,61 +843,19 @@ impl TextBox {
                 match mode {
                     SelectionMode::Word => {
                         let text = line.text();
-
-                        let mut start_index = None;
-                        let mut end_index = None;
-
-                        match cursor.affinity {
-                            Affinity::Before => {
-                                if cursor.index == 0 {
-                                    return None;
-                                }
-                                if text
-                                    .get(cursor.index - 1..cursor.index)?
-                                    .contains(|c: char| c.is_whitespace())
-                                {
-                                    cursor.index += 1;
-                                } else if cursor.index == text.len()
-                                    || text
-                                        .get(cursor.index..cursor.index + 1)?
-                                        .contains(|c: char| c.is_whitespace())
-                                {
-                                    end_index = Some(cursor.index);
-                                }
-                            }
-                            Affinity::After => {
-                                if text
-                                    .get(cursor.index..cursor.index + 1)?
-                                    .contains(|c: char| c.is_whitespace())
-                                {
-                                    cursor.index -= 1;
-                                } else if cursor.index == 0
-                                    || text
-                                        .get(cursor.index - 1..cursor.index)?
-                                        .contains(|c: char| c.is_whitespace())
-                                {
-                                    start_index = Some(cursor.index)
-                                }
-                            }
-                        }
-
-                        if end_index.is_none() {
-                            let end_text = text
-                                .get(cursor.index..)
-                                .and_then(|str| str.split_whitespace().next())?;
-                            end_index = Some(end_text.len() + cursor.index);
-                        }
-                        if start_index.is_none() {
-                            let start_text = text
-                                .get(..cursor.index)
-                                .and_then(|str| str.split_whitespace().next_back())?;
-                            start_index = Some(cursor.index - start_text.len());
-                        }
-
-                        let start =
-                            Cursor::new(cursor.line, start_index.expect("Should have an value"));
-                        let end =
-                            Cursor::new(cursor.line, end_index.expect("Should have an value"));
+                        // The clicked character is the one before the cursor when the click
+                        // landed on its trailing half
+                        let index = match cursor.affinity {
+                            Affinity::Before => text
+                                .get(..cursor.index)?
+                                .chars()
+                                .next_back()
+                                .map_or(0, |c| cursor.index - c.len_utf8()),
+                            Affinity::After => cursor.index,
+                        };
+                        let word = word_range(text, index);
+                        let start = Cursor::new(cursor.line, word.start);
+                        let end = Cursor::new(cursor.line, word.end);
 
                         (start, end, position.1, position.1)
                     }
@@ -522,6 +870,19 @@ impl TextBox {
                 return None;
             }
         };
+        // Fonts lacking a ligature for a multi-codepoint cluster draw it as several glyphs. Don't
+        // let the selection split those apart
+        let start_cursor = Cursor {
+            index: grapheme_start(
+                buffer.lines.get(start_cursor.line)?.text(),
+                start_cursor.index,
+            ),
+            ..start_cursor
+        };
+        let end_cursor = Cursor {
+            index: grapheme_end(buffer.lines.get(end_cursor.line)?.text(), end_cursor.index),
+            ..end_cursor
+        };
 
         let mut y = screen_position.1;
         for line in buffer.layout_runs() {
@@ -552,28 +913,194 @@ impl TextBox {
                     if (left_glyph_cursor >= start_cursor && left_glyph_cursor <= end_cursor)
                         && (right_glyph_cursor >= start_cursor && right_glyph_cursor <= end_cursor)
                     {
-                        selected_text.push_str(&line.text[glyph.start..glyph.end]);
+                        let content = &line.text[glyph.start..glyph.end];
+                        selected_text.push_str(content);
+                        match pieces.last_mut() {
+                            Some((index, piece)) if *index == glyph.metadata => {
+                                piece.push_str(content)
+                            }
+                            _ => pieces.push((glyph.metadata, content.to_owned())),
+                        }
                     }
                 }
                 if end_y > y + line_height {
-                    selected_text.push(' ')
+                    selected_text.push(' ');
+                    if let Some((_, piece)) = pieces.last_mut() {
+                        piece.push(' ');
+                    }
                 }
             }
             y += line_height;
         }
 
-        selection.add_line(&selected_text);
+        let pieces: Vec<_> = pieces
+            .iter()
+            .map(|(index, piece)| (&self.texts[*index], piece.as_str()))
+            .collect();
+        let markdown = inline_markdown(&pieces, self.is_code_block);
+        selection.add_line(&selected_text, &markdown);
 
         Some(rects)
     }
 }
 
+/// The inline formatting of a [`Text`] that markdown has a way of writing
+#[derive(Clone, Copy, Default, PartialEq)]
+struct InlineFormat<'a> {
+    link: Option<&'a str>,
+    code: bool,
+    kbd: bool,
+    bold: bool,
+    italic: bool,
+    striked: bool,
+}
+
+impl<'a> InlineFormat<'a> {
+    fn new(text: &'a Text, in_code_block: bool) -> Self {
+        // Everything in a code block is monospace, so there's nothing to mark up there
+        if in_code_block {
+            return Self::default();
+        }
+        let monospace = text.font_family == FamilyOwned::Monospace;
+        Self {
+            link: text.link.as_deref(),
+            code: monospace && !text.is_kbd,
+            kbd: text.is_kbd,
+            bold: text.is_bold,
+            italic: text.is_italic,
+            striked: text.is_striked,
+        }
+    }
+
+    fn wrap(&self, content: &str) -> String {
+        let mut wrapped = if self.code {
+            code_span(content)
+        } else if self.kbd {
+            format!("<kbd>{content}</kbd>")
+        } else {
+            content.to_owned()
+        };
+        let emphasis = match (self.bold, self.italic) {
+            (true, true) => "***",
+            (true, false) => "**",
+            (false, true) => "*",
+            (false, false) => "",
+        };
+        wrapped = format!("{emphasis}{wrapped}{emphasis}");
+        if self.striked {
+            wrapped = format!("~~{wrapped}~~");
+        }
+        if let Some(link) = self.link {
+            wrapped = format!("[{wrapped}]({link})");
+        }
+        wrapped
+    }
+}
+
+/// Markdown for selected `pieces` of text, keeping their inline formatting (bold, italic, code,
+/// links, etc.)
+///
+/// Neighboring pieces that share the same formatting get joined up first so that they don't each
+/// get marked up separately
+fn inline_markdown(pieces: &[(&Text, &str)], in_code_block: bool) -> String {
+    let mut runs: Vec<(InlineFormat<'_>, String)> = Vec::new();
+    for &(text, piece) in pieces {
+        let format = InlineFormat::new(text, in_code_block);
+        match runs.last_mut() {
+            Some((last, run)) if *last == format => run.push_str(piece),
+            _ => runs.push((format, piece.to_owned())),
+        }
+    }
+
+    let mut markdown = String::new();
+    for (format, run) in runs {
+        // Emphasis can't start or end with whitespace, so that gets left outside of it
+        let content = run.trim();
+        if format == InlineFormat::default() || content.is_empty() {
+            markdown.push_str(&run);
+            continue;
+        }
+        let start = run.len() - run.trim_start().len();
+        markdown.push_str(&run[..start]);
+        markdown.push_str(&format.wrap(content));
+        markdown.push_str(&run[start + content.len()..]);
+    }
+    markdown
+}
+
+/// An inline code span for `code` using enough backticks to fit any that it contains
+fn code_span(code: &str) -> String {
+    let longest_run = code
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or_default();
+    let fence = "`".repeat(longest_run + 1);
+    let padding = if code.starts_with('`') || code.ends_with('`') {
+        " "
+    } else {
+        ""
+    };
+    format!("{fence}{padding}{code}{padding}{fence}")
+}
+
+/// The range of the word containing the character at `index` using Unicode's word boundaries
+///
+/// Punctuation and runs of whitespace are words of their own, so they don't get tacked onto the
+/// neighboring word
+fn word_range(text: &str, index: usize) -> Range<usize> {
+    text.split_word_bound_indices()
+        .map(|(start, word)| start..start + word.len())
+        .find(|word| index < word.end)
+        .unwrap_or(text.len()..text.len())
+}
+
+/// Moves `index` back to the start of the grapheme cluster containing it
+fn grapheme_start(text: &str, index: usize) -> usize {
+    text.grapheme_indices(true)
+        .map(|(start, _)| start)
+        .take_while(|&start| start <= index)
+        .last()
+        .unwrap_or(0)
+}
+
+/// Moves `index` forward to the end of the grapheme cluster containing it
+fn grapheme_end(text: &str, index: usize) -> usize {
+    text.grapheme_indices(true)
+        .map(|(start, grapheme)| start + grapheme.len())
+        .find(|&end| end >= index)
+        .unwrap_or(text.len())
+}
+
 #[derive(Clone)]
 struct ThinLine {
     range: Range<usize>,
     color: [f32; 4],
 }
 
+/// Text that's raised or lowered and drawn smaller than the text around it (`<sup>` and `<sub>`)
+///
+/// The text is still laid out at full size (just invisibly) alongside everything else, so that
+/// selecting, searching, and clicking it all keep working. The visible text gets drawn over it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Script {
+    Super,
+    Sub,
+}
+
+impl Script {
+    /// Font size relative to the surrounding text
+    pub const SCALE: f32 = 0.75;
+
+    /// How far the baseline moves relative to the surrounding font size (down is positive)
+    pub fn baseline_shift(self) -> f32 {
+        match self {
+            Self::Super => -0.35,
+            Self::Sub => 0.2,
+        }
+    }
+}
+
 #[derive(Clone, PartialEq)]
 pub struct Text {
     pub text: String,
@@ -583,6 +1110,9 @@ pub struct Text {
     pub is_italic: bool,
     pub is_underlined: bool,
     pub is_striked: bool,
+    /// Drawn as a keyboard key, with a bordered background
+    pub is_kbd: bool,
+    pub script: Option<Script>,
     pub font_family: FamilyOwned,
     pub hidpi_scale: f32,
     pub default_color: [f32; 4],
@@ -606,6 +1136,8 @@ impl Text {
             is_italic: false,
             is_underlined: false,
             is_striked: false,
+            is_kbd: false,
+            script: None,
             font_family: FamilyOwned::SansSerif,
         }
     }
@@ -640,6 +1172,16 @@ impl Text {
         self
     }
 
+    pub fn make_kbd(mut self, kbd: bool) -> Self {
+        self.is_kbd = kbd;
+        self
+    }
+
+    pub fn with_script(mut self, script: Option<Script>) -> Self {
+        self.script = script;
+        self
+    }
+
     pub fn with_family(mut self, family: FamilyOwned) -> Self {
         self.font_family = family;
         self
@@ -667,11 +1209,13 @@ impl Text {
 
     pub fn section_keys(&self, index: usize) -> Vec<SectionKey<'_>> {
         let color = self.color();
+        // Scripts only hold their place here. They get drawn separately
+        let alpha = if self.script.is_some() { 0. } else { color[3] };
         let color = Color::rgba(
             (color[0] * 255.) as u8,
             (color[1] * 255.) as u8,
             (color[2] * 255.) as u8,
-            (color[3] * 255.) as u8,
+            (alpha * 255.) as u8,
         );
         let font = Font {
             family: self.font_family.as_family(),
@@ -711,6 +1255,7 @@ pub struct Key<'a> {
     size: f32,
     line_height: f32,
     bounds: Size,
+    no_wrap: bool,
 }
 
 #[derive(Default)]
@@ -742,6 +1287,7 @@ impl TextCache {
             key.line_height.to_bits().hash(&mut hasher);
             key.bounds.0.to_bits().hash(&mut hasher);
             key.bounds.1.to_bits().hash(&mut hasher);
+            key.no_wrap.hash(&mut hasher);
 
             hasher.finish()
         };
@@ -751,6 +1297,9 @@ impl TextCache {
             let mut buffer = glyphon::Buffer::new(font_system, metrics);
 
             buffer.set_size(font_system, key.bounds.0, key.bounds.1.max(key.line_height));
+            if key.no_wrap {
+                buffer.set_wrap(font_system, Wrap::None);
+            }
 
             buffer.lines.clear();
 
@@ -793,6 +1342,27 @@ impl TextCache {
     }
 }
 
+/// The width of a line of `chars` characters in the regular font at the given `zoom`
+///
+/// Like CSS's `ch` unit this goes off of the width of `0`, which is a reasonable stand-in for the
+/// average character width
+pub fn line_width_for_chars(
+    text_cache: &Mutex<TextCache>,
+    font_system: &Mutex<FontSystem>,
+    chars: usize,
+    hidpi_scale: f32,
+    zoom: f32,
+) -> f32 {
+    let line = Text::new("0".repeat(chars), hidpi_scale, [0.; 4]);
+    let (width, _) = TextBox::new(vec![line], hidpi_scale).size_without_system(
+        text_cache,
+        font_system,
+        (f32::INFINITY, f32::INFINITY),
+        zoom,
+    );
+    width
+}
+
 pub struct TextSystem {
     pub font_system: Arc<Mutex<FontSystem>>,
     pub text_renderer: glyphon::TextRenderer,
@@ -800,3 +1370,182 @@ pub struct TextSystem {
     pub text_cache: Arc<Mutex<TextCache>>,
     pub swash_cache: SwashCache,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zwj_sequence_is_one_cluster() {
+        // Family emoji: man ZWJ woman ZWJ girl
+        let family = "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}";
+        let text = format!("a {family} b");
+        let (start, end) = (2, 2 + family.len());
+
+        // Landing anywhere within the sequence selects all of it
+        for index in start + 1..end {
+            assert_eq!(grapheme_start(&text, index), start);
+            assert_eq!(grapheme_end(&text, index), end);
+        }
+        // Cluster boundaries are left alone
+        assert_eq!(grapheme_start(&text, start), start);
+        assert_eq!(grapheme_end(&text, end), end);
+        assert_eq!(grapheme_end(&text, text.len()), text.len());
+    }
+
+    #[test]
+    fn words_stop_at_punctuation_and_spaces() {
+        let text = "Hello, wörld  (x_y) 日本";
+        let word_at = |needle: &str| {
+            let index = text.find(needle).unwrap();
+            &text[word_range(text, index)]
+        };
+
+        assert_eq!(word_at("ello"), "Hello");
+        assert_eq!(word_at(","), ",");
+        assert_eq!(word_at("ö"), "wörld");
+        assert_eq!(word_at("rld"), "wörld");
+        assert_eq!(word_at("  "), "  ");
+        assert_eq!(word_at("_y"), "x_y");
+        assert_eq!(word_at("("), "(");
+        assert_eq!(word_at("本"), "本");
+        assert_eq!(word_range(text, text.len()), text.len()..text.len());
+    }
+
+    #[test]
+    fn selection_as_markdown() {
+        let plain = Text::new(String::new(), 1., [0.; 4]);
+        let bold = plain.clone().make_bold(true);
+        let code = plain.clone().with_family(FamilyOwned::Monospace);
+        let link = plain.clone().with_link("https://example.com".into());
+        let bold_italic = bold.clone().make_italic(true);
+
+        let pieces = [
+            (&plain, "Some "),
+            // Neighbors with the same formatting are marked up together
+            (&bold, "bold "),
+            (&bold, "text "),
+            (&code, "let x = `1`;"),
+            (&plain, " and "),
+            (&link, "a link"),
+            (&plain, ", "),
+            (&bold_italic, "both"),
+        ];
+        assert_eq!(
+            inline_markdown(&pieces, false),
+            "Some **bold text** ``let x = `1`;`` and [a link](https://example.com), ***both***",
+        );
+        // Code blocks are left as is
+        assert_eq!(inline_markdown(&pieces[3..4], true), "let x = `1`;");
+        assert_eq!(code_span("`tick"), "`` `tick ``");
+    }
+
+    #[test]
+    fn wraps_at_line_char_cap() {
+        let font_system = Mutex::new(FontSystem::new());
+        let text_cache = Mutex::new(TextCache::new());
+        let max_chars = 20;
+        let max_width = line_width_for_chars(&text_cache, &font_system, max_chars, 1., 1.);
+        assert!(max_width > 0.);
+        // Zooming in makes the characters, and so the line, wider
+        let zoomed = line_width_for_chars(&text_cache, &font_system, max_chars, 1., 2.);
+        assert!(zoomed > 1.9 * max_width && zoomed < 2.1 * max_width);
+
+        let word = "0".repeat(9);
+        let paragraph = vec![word; 20].join(" ");
+        let text_box = TextBox::new(vec![Text::new(paragraph, 1., [0.; 4])], 1.);
+        let lines = |width| {
+            let mut cache = text_cache.lock().unwrap();
+            let (_, buffer) = cache.allocate(
+                font_system.lock().unwrap().borrow_mut(),
+                text_box.key((width, f32::INFINITY), 1.),
+            );
+            buffer
+                .layout_runs()
+                .map(|run| run.glyphs.len())
+                .collect::<Vec<_>>()
+        };
+
+        // A very wide window fits everything on one line
+        assert_eq!(lines(100_000.).len(), 1);
+        // While capping the width wraps after two words (19 chars) at most
+        let capped = lines(max_width);
+        assert_eq!(capped.len(), 10);
+        assert!(capped.iter().all(|&glyphs| glyphs <= max_chars));
+    }
+
+    #[test]
+    fn no_wrap_overflows_bounds() {
+        let font_system = Mutex::new(FontSystem::new());
+        let text_cache = Mutex::new(TextCache::new());
+        let width = line_width_for_chars(&text_cache, &font_system, 20, 1., 1.);
+
+        let line = vec!["0".repeat(9); 20].join(" ");
+        let mut text_box = TextBox::new(vec![Text::new(line, 1., [0.; 4])], 1.);
+        let wrapped = text_box.size_without_system(&text_cache, &font_system, (width, 1e6), 1.);
+        text_box.no_wrap = true;
+        let unwrapped = text_box.size_without_system(&text_cache, &font_system, (width, 1e6), 1.);
+
+        assert!(wrapped.0 <= width);
+        assert!(unwrapped.0 > width);
+        assert_eq!(unwrapped.1, text_box.line_height(1.));
+    }
+
+    #[test]
+    fn scripts_are_smaller_and_shifted() {
+        let font_system = Mutex::new(FontSystem::new());
+        let text_cache = Mutex::new(TextCache::new());
+
+        let texts = vec![
+            Text::new("x".into(), 1., [0.; 4]),
+            Text::new("2".into(), 1., [0.; 4])
+                .make_bold(true)
+                .with_script(Some(Script::Super)),
+            Text::new(" H".into(), 1., [0.; 4]),
+            Text::new("2".into(), 1., [0.; 4]).with_script(Some(Script::Sub)),
+        ];
+        let text_box = TextBox::new(texts, 1.);
+        let bounds = (f32::INFINITY, f32::INFINITY);
+        let baseline = text_box.baseline_without_system(&text_cache, &font_system, bounds, 1.);
+        let boxes = text_box.script_boxes(&text_cache, &font_system, bounds, 1.);
+        let [(sup, sup_pos), (sub, sub_pos)] = boxes.as_slice() else {
+            panic!("Expected two scripts: {boxes:?}");
+        };
+
+        for script in [sup, sub] {
+            assert_eq!(script.font_size, text_box.font_size * Script::SCALE);
+            assert_eq!(script.texts[0].text, "2");
+            assert_eq!(script.texts[0].script, None);
+        }
+        // Styling carries over to the script
+        assert!(sup.texts[0].is_bold);
+        // Each one's baseline lands above or below the regular one
+        let script_baseline = |script: &TextBox, pos: Point| {
+            pos.1 + script.baseline_without_system(&text_cache, &font_system, bounds, 1.)
+        };
+        assert!(script_baseline(sup, *sup_pos) < baseline);
+        assert!(script_baseline(sub, *sub_pos) > baseline);
+        assert!(sup_pos.0 > 0. && sub_pos.0 > sup_pos.0);
+    }
+
+    #[test]
+    fn gutter_fits_widest_line_number() {
+        let lines = |count: usize| {
+            let texts = (0..count)
+                .map(|i| Text::new(format!("line {i}\n"), 1., [0.; 4]))
+                .collect();
+            let mut text_box = TextBox::new(texts, 1.);
+            text_box.line_numbers = true;
+            text_box
+        };
+        let digit = lines(1).gutter_padding(1.);
+
+        assert_eq!(lines(9).gutter_width(1.), 3. * digit);
+        assert_eq!(lines(10).gutter_width(1.), 4. * digit);
+        assert_eq!(lines(1_000).gutter_width(2.), 2. * 6. * digit);
+        // No numbers means no gutter
+        let mut text_box = lines(10);
+        text_box.line_numbers = false;
+        assert_eq!(text_box.gutter_width(1.), 0.);
+    }
+}
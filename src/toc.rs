@@ -0,0 +1,162 @@
+//! A table of contents panel along the right of the window (`ToggleToc`)
+//!
+//! Headers get collected while interpreting the document. The panel lists them as an outline
+//! indented by level and takes its width away from the document. Clicking an entry scrolls to its
+//! header, and the entry for the section that's currently scrolled to gets highlighted
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// Width of the panel (before hidpi scaling)
+pub const WIDTH: f32 = 220.;
+/// Font size of the entries (before hidpi scaling)
+pub const FONT_SIZE: f32 = 13.;
+/// Space around each entry (before hidpi scaling)
+pub const PADDING: f32 = 3.;
+/// Extra indent for each level below the top-most one (before hidpi scaling)
+pub const INDENT: f32 = 12.;
+
+/// A header's level (`1` for `<h1>` through `6` for `<h6>`) and text
+#[derive(Clone, Debug, PartialEq)]
+pub struct Heading {
+    pub level: u8,
+    pub title: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TocEntry {
+    pub level: u8,
+    pub title: String,
+    pub y: f32,
+}
+
+/// All of the headers in document order
+pub fn entries(
+    anchors: &HashMap<String, f32>,
+    headings: &HashMap<String, Heading>,
+) -> Vec<TocEntry> {
+    let mut entries: Vec<_> = headings
+        .iter()
+        .filter_map(|(anchor, heading)| {
+            let y = *anchors.get(anchor)?;
+            Some(TocEntry {
+                level: heading.level,
+                title: heading.title.clone(),
+                y,
+            })
+        })
+        .collect();
+    entries.sort_by(|a, b| a.y.total_cmp(&b.y).then_with(|| a.title.cmp(&b.title)));
+    entries
+}
+
+/// The entry for the section that `scroll_y` is in, which is the last header at or above it
+pub fn current(entries: &[TocEntry], scroll_y: f32) -> Option<usize> {
+    // Jumping to a header can land a hair short of it
+    entries.iter().rposition(|entry| entry.y <= scroll_y + 1.)
+}
+
+pub fn width(hidpi_scale: f32) -> f32 {
+    WIDTH * hidpi_scale
+}
+
+pub fn row_height(hidpi_scale: f32) -> f32 {
+    (FONT_SIZE * 1.1 + 2. * PADDING) * hidpi_scale
+}
+
+/// How far an entry gets indented when the shallowest level in the outline is `min_level`
+pub fn indent(level: u8, min_level: u8, hidpi_scale: f32) -> f32 {
+    f32::from(level.saturating_sub(min_level)) * INDENT * hidpi_scale
+}
+
+/// The entries that fit within `rows`, scrolled along to keep the current one in view
+pub fn visible(len: usize, current: Option<usize>, rows: usize) -> Range<usize> {
+    if len <= rows {
+        return 0..len;
+    }
+    let start = current
+        .map_or(0, |current| current.saturating_sub(rows / 2))
+        .min(len - rows);
+    start..start + rows
+}
+
+/// The index of the entry at `y` (in screen coordinates) when the panel starts at `top`
+pub fn entry_at(y: f32, top: f32, visible: Range<usize>, hidpi_scale: f32) -> Option<usize> {
+    if y < top {
+        return None;
+    }
+    let index = visible.start + ((y - top) / row_height(hidpi_scale)) as usize;
+    visible.contains(&index).then_some(index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outline() -> Vec<TocEntry> {
+        let anchors = HashMap::from([
+            ("#intro".to_owned(), 0.),
+            ("#install".to_owned(), 400.),
+            ("#from-source".to_owned(), 600.),
+            ("#fn-1".to_owned(), 700.),
+            ("#usage".to_owned(), 900.),
+        ]);
+        let headings = [
+            ("#usage", 2, "Usage"),
+            ("#intro", 1, "Intro"),
+            ("#from-source", 3, "From Source"),
+            ("#install", 2, "Install"),
+        ]
+        .into_iter()
+        .map(|(anchor, level, title)| {
+            let title = title.to_owned();
+            (anchor.to_owned(), Heading { level, title })
+        })
+        .collect();
+        entries(&anchors, &headings)
+    }
+
+    #[test]
+    fn outline_in_document_order() {
+        let entries = outline();
+        let outline: Vec<_> = entries
+            .iter()
+            .map(|e| (e.level, e.title.as_str()))
+            .collect();
+        // Anchors that aren't from headers get left out
+        assert_eq!(
+            outline,
+            [
+                (1, "Intro"),
+                (2, "Install"),
+                (3, "From Source"),
+                (2, "Usage")
+            ]
+        );
+        assert_eq!(indent(3, 1, 2.), 2. * INDENT * 2.);
+        assert_eq!(indent(2, 2, 2.), 0.);
+    }
+
+    #[test]
+    fn highlights_current_section() {
+        let entries = outline();
+        assert_eq!(current(&entries, 0.), Some(0));
+        assert_eq!(current(&entries, 399.5), Some(1));
+        assert_eq!(current(&entries, 650.), Some(2));
+        assert_eq!(current(&entries, 5_000.), Some(3));
+        assert_eq!(current(&[], 100.), None);
+    }
+
+    #[test]
+    fn scrolling_and_clicking_entries() {
+        assert_eq!(visible(4, Some(3), 10), 0..4);
+        assert_eq!(visible(20, None, 6), 0..6);
+        assert_eq!(visible(20, Some(10), 6), 7..13);
+        assert_eq!(visible(20, Some(19), 6), 14..20);
+
+        let row = row_height(1.);
+        assert_eq!(entry_at(30. + row * 1.5, 30., 7..13, 1.), Some(8));
+        assert_eq!(entry_at(29., 30., 7..13, 1.), None);
+        assert_eq!(entry_at(30. + row * 6.5, 30., 7..13, 1.), None);
+    }
+}
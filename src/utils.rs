@@ -1,14 +1,17 @@
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::io;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex, OnceLock};
 
-use crate::image::ImageData;
+use crate::image::LoadedImages;
 
 use comrak::adapters::SyntaxHighlighterAdapter;
+use comrak::nodes::{Ast, AstNode, NodeHtmlBlock, NodeValue};
 use comrak::plugins::syntect::{SyntectAdapter, SyntectAdapterBuilder};
-use comrak::{markdown_to_html_with_plugins, ComrakOptions};
+use comrak::{format_html_with_plugins, parse_document, Anchorizer, Arena, ComrakOptions};
 use indexmap::IndexMap;
+use percent_encoding::percent_decode_str;
 use serde::Deserialize;
 use syntect::highlighting::{Theme as SyntectTheme, ThemeSet as SyntectThemeSet};
 use syntect::parsing::SyntaxSet;
@@ -54,6 +57,145 @@ fn root_filepath_to_vcs_dir(path: &Path) -> Option<PathBuf> {
     }
 }
 
+/// Resolves a link to a local directory to the first of `index_files` that exists within it
+///
+/// Returns `None` when `path` isn't a directory or it has no matching index file
+pub fn resolve_dir_index(path: &Path, index_files: &[String]) -> Option<PathBuf> {
+    if !path.is_dir() {
+        return None;
+    }
+
+    index_files
+        .iter()
+        .map(|name| path.join(name))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Checks that `path` is a file that we can read, describing why not otherwise
+pub fn check_readable(path: &Path) -> Result<(), String> {
+    let display = path.display();
+    if path.is_dir() {
+        return Err(format!("Can't view '{display}': is a directory"));
+    }
+
+    std::fs::File::open(path).map(drop).map_err(|err| {
+        let reason = match err.kind() {
+            io::ErrorKind::NotFound => "file not found".to_owned(),
+            io::ErrorKind::PermissionDenied => "permission denied".to_owned(),
+            _ => err.to_string(),
+        };
+        format!("Can't view '{display}': {reason}")
+    })
+}
+
+/// Where following a link leads
+#[derive(Debug, PartialEq)]
+pub enum LinkTarget {
+    /// A local markdown file to open within inlyne
+    Markdown(PathBuf),
+    /// Any other local file, resolved to where it lives on disk
+    File(PathBuf),
+    /// The position of an anchor in the current document
+    Anchor(f32),
+    /// An in-document link (`#...`) to an anchor that doesn't exist
+    MissingAnchor,
+    /// Anything else is handed off to the system (e.g. opened in a browser)
+    External,
+}
+
+/// Normalizes an anchor (e.g. `#What's-New`) the same way that header anchors get generated, so
+/// that links and the anchors they point to always agree on casing and punctuation
+pub fn normalize_anchor(anchor: &str) -> String {
+    let name = anchor.strip_prefix('#').unwrap_or(anchor);
+    // A fresh anchorizer never dedupes, so this is the plain normalization
+    let normalized = Anchorizer::new().anchorize(name.to_owned());
+    format!("#{normalized}")
+}
+
+pub fn classify_link(
+    link: &str,
+    anchors: &HashMap<String, f32>,
+    index_files: &[String],
+) -> LinkTarget {
+    // In-document links never go anywhere else, even when they're dangling
+    if link.starts_with('#') {
+        return match anchors.get(&normalize_anchor(link)) {
+            Some(pos) => LinkTarget::Anchor(*pos),
+            None => LinkTarget::MissingAnchor,
+        };
+    }
+
+    let (link, _) = split_anchor(trim_link_delimiters(link));
+    let path = match link_scheme(link) {
+        // Paths relative to the current file (which is also our current dir)
+        None => resolve_link_path(link),
+        Some(scheme) if scheme.eq_ignore_ascii_case("file") => {
+            let path = &link[scheme.len() + 1..];
+            resolve_link_path(path.strip_prefix("//").unwrap_or(path))
+        }
+        // `http(s)`, `mailto`, `tel`, etc. are all for the system to handle
+        Some(_) => return LinkTarget::External,
+    };
+    // Links to a directory open its index (e.g. a README) instead
+    let path = resolve_dir_index(&path, index_files).unwrap_or(path);
+    if path.extension().map_or(false, |ext| ext == "md") {
+        LinkTarget::Markdown(path)
+    } else {
+        LinkTarget::File(path)
+    }
+}
+
+/// Strips the `<>` that markdown allows around link destinations (e.g. `[x](<a (1).pdf>)`)
+pub fn trim_link_delimiters(link: &str) -> &str {
+    link.strip_prefix('<')
+        .and_then(|link| link.strip_suffix('>'))
+        .unwrap_or(link)
+}
+
+/// The file that the path part of a local link points to
+///
+/// Links come from the rendered HTML, so things like spaces are percent-encoded while parentheses
+/// may or may not be. Escapes that don't decode to UTF-8 are left as-is. Paths that exist get
+/// canonicalized against the current dir
+fn resolve_link_path(link: &str) -> PathBuf {
+    let decoded = percent_decode_str(link)
+        .decode_utf8()
+        .unwrap_or(Cow::Borrowed(link));
+    let path = PathBuf::from(decoded.as_ref());
+    path.canonicalize().unwrap_or(path)
+}
+
+/// Splits a link like `other.md#section` into the part that points to a file and its anchor
+/// (including the `#`), if it has one
+pub fn split_anchor(link: &str) -> (&str, Option<&str>) {
+    match link.find('#') {
+        Some(i) => (&link[..i], Some(&link[i..])),
+        None => (link, None),
+    }
+}
+
+/// The URI scheme of `link` (e.g. `mailto` for `mailto:me@example.org`), if it has one
+///
+/// Single letter schemes are left out since they're really Windows drive letters (`C:\...`)
+fn link_scheme(link: &str) -> Option<&str> {
+    let (scheme, _) = link.split_once(':')?;
+    let mut chars = scheme.chars();
+    let valid = chars.next()?.is_ascii_alphabetic()
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    (valid && scheme.len() > 1).then_some(scheme)
+}
+
+/// `path` as an absolute path for showing to users (e.g. copying it to the clipboard)
+pub fn absolute_display(path: &Path) -> String {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_owned());
+    let path = path.display().to_string();
+    // Canonicalized paths on Windows get the verbatim prefix that most programs don't understand
+    match path.strip_prefix(r"\\?\") {
+        Some(stripped) => stripped.to_owned(),
+        None => path,
+    }
+}
+
 pub(crate) fn default<T: Default>() -> T {
     Default::default()
 }
@@ -69,7 +211,7 @@ pub fn dist_between_points(p1: &Point, p2: &Point) -> f32 {
 }
 
 pub type Size = (f32, f32);
-pub type ImageCache = Arc<Mutex<HashMap<String, Arc<Mutex<Option<ImageData>>>>>>;
+pub type ImageCache = Arc<Mutex<LoadedImages>>;
 
 #[derive(Debug, Clone)]
 pub struct Line {
@@ -147,6 +289,36 @@ impl From<CursorIcon> for HoverInfo {
     }
 }
 
+/// Common shorthands for languages that the syntax set doesn't know by that name
+const LANGUAGE_ALIASES: &[(&str, &str)] = &[
+    ("sh", "bash"),
+    ("shell", "bash"),
+    ("zsh", "bash"),
+    ("console", "bash"),
+    ("rs", "rust"),
+    ("py", "python"),
+    ("python3", "python"),
+    ("js", "javascript"),
+    ("ts", "typescript"),
+    ("yml", "yaml"),
+    ("golang", "go"),
+    ("c++", "cpp"),
+];
+
+/// Picks the language out of a code fence's info string e.g. `rust` from `rust,ignore` or
+/// `python` from `{.python .numberLines}`
+fn fence_language(info: &str) -> Option<&str> {
+    let lang = info
+        .split(|c: char| c.is_whitespace() || matches!(c, ',' | '{' | '}'))
+        .map(|token| token.strip_prefix('.').unwrap_or(token))
+        .find(|token| !token.is_empty())?;
+    let alias = LANGUAGE_ALIASES
+        .iter()
+        .find(|(alias, _)| alias.eq_ignore_ascii_case(lang))
+        .map(|&(_, lang)| lang);
+    Some(alias.unwrap_or(lang))
+}
+
 // TODO(cosmic): Remove after `comrak` supports code block info strings that have a comma
 //     (like ```rust,ignore)
 //     https://github.com/kivikakk/comrak/issues/246
@@ -159,7 +331,7 @@ impl SyntaxHighlighterAdapter for CustomSyntectAdapter {
         lang: Option<&str>,
         code: &str,
     ) -> io::Result<()> {
-        let norm_lang = lang.map(|l| l.split_once(',').map(|(lang, _)| lang).unwrap_or(l));
+        let norm_lang = lang.and_then(fence_language);
         self.0.write_highlighted(output, norm_lang, code)
     }
 
@@ -180,17 +352,70 @@ impl SyntaxHighlighterAdapter for CustomSyntectAdapter {
     }
 }
 
-pub fn markdown_to_html(md: &str, syntax_theme: SyntectTheme) -> String {
+/// CommonMark collapses any run of blank lines between blocks, so this adds an empty paragraph for
+/// each blank line past the first one for the interpreter to turn back into spacing
+fn insert_blank_paragraphs<'a>(arena: &'a Arena<AstNode<'a>>, root: &'a AstNode<'a>) {
+    let blocks: Vec<_> = root.children().collect();
+    for pair in blocks.windows(2) {
+        let prev_end = pair[0].data.borrow().sourcepos.end.line;
+        let start = pair[1].data.borrow().sourcepos.start;
+        let blank_lines = start.line.saturating_sub(prev_end + 1);
+        for _ in 1..blank_lines {
+            let blank = NodeValue::HtmlBlock(NodeHtmlBlock {
+                block_type: 6,
+                literal: "<p></p>\n".to_owned(),
+            });
+            let node = arena.alloc(AstNode::new(Ast::new(blank, start).into()));
+            pair[1].insert_before(node);
+        }
+    }
+}
+
+/// The markdown extensions and such that documents get parsed with
+pub fn comrak_options<'c>() -> ComrakOptions<'c> {
     let mut options = ComrakOptions::default();
     options.extension.autolink = true;
     options.extension.table = true;
     options.extension.strikethrough = true;
     options.extension.tasklist = true;
-    // options.extension.footnotes = true;
+    options.extension.footnotes = true;
     options.extension.front_matter_delimiter = Some("---".to_owned());
     options.extension.shortcodes = true;
     options.parse.smart = true;
     options.render.unsafe_ = true;
+    options
+}
+
+pub fn markdown_to_html(
+    md: &str,
+    syntax_theme: SyntectTheme,
+    preserve_blank_lines: bool,
+    math: bool,
+) -> String {
+    markdown_to_html_inner(md, syntax_theme, preserve_blank_lines, math, false)
+}
+
+/// [`markdown_to_html`] with a `data-sourcepos` attribute on block elements that points back to
+/// where they start and end in `md`
+pub fn markdown_to_html_with_sourcepos(
+    md: &str,
+    syntax_theme: SyntectTheme,
+    preserve_blank_lines: bool,
+    math: bool,
+) -> String {
+    markdown_to_html_inner(md, syntax_theme, preserve_blank_lines, math, true)
+}
+
+fn markdown_to_html_inner(
+    md: &str,
+    syntax_theme: SyntectTheme,
+    preserve_blank_lines: bool,
+    math: bool,
+    sourcepos: bool,
+) -> String {
+    let mut options = comrak_options();
+    options.extension.math_dollars = math;
+    options.render.sourcepos = sourcepos;
 
     // TODO(cosmic): gonna send a PR so that a plugin can pass in a single theme too
     let dummy_name = "theme";
@@ -213,7 +438,14 @@ pub fn markdown_to_html(md: &str, syntax_theme: SyntectTheme) -> String {
     let custom = CustomSyntectAdapter(adapter);
     plugins.render.codefence_syntax_highlighter = Some(&custom);
 
-    let htmlified = markdown_to_html_with_plugins(md, &options, &plugins);
+    let arena = Arena::new();
+    let root = parse_document(&arena, md, &options);
+    if preserve_blank_lines {
+        insert_blank_paragraphs(&arena, root);
+    }
+    let mut htmlified = Vec::new();
+    format_html_with_plugins(root, &options, &mut htmlified, &plugins).unwrap();
+    let htmlified = String::from_utf8(htmlified).unwrap();
 
     // Comrak doesn't support converting the front matter to HTML, so we have to convert it to an
     // HTML table ourselves. Front matter is found like so
@@ -295,3 +527,219 @@ impl Cell {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Theme;
+
+    fn index_files() -> Vec<String> {
+        vec!["README.md".into(), "index.md".into()]
+    }
+
+    #[test]
+    fn dir_link_resolves_to_readme() {
+        let dir = tempfile::tempdir().unwrap();
+        let readme = dir.path().join("README.md");
+        std::fs::write(&readme, "# Hello").unwrap();
+        std::fs::write(dir.path().join("index.md"), "# Index").unwrap();
+
+        // Earlier entries take priority
+        assert_eq!(resolve_dir_index(dir.path(), &index_files()), Some(readme));
+        // A trailing slash is still the same directory
+        let with_slash = PathBuf::from(format!("{}/", dir.path().display()));
+        assert_eq!(
+            resolve_dir_index(&with_slash, &index_files()),
+            Some(with_slash.join("README.md")),
+        );
+    }
+
+    #[test]
+    fn in_document_links_stay_internal() {
+        let anchors = HashMap::from([("#section".to_owned(), 120.)]);
+        let classify = |link| classify_link(link, &anchors, &index_files());
+
+        assert_eq!(classify("#Section"), LinkTarget::Anchor(120.));
+        // Dangling anchors should never end up getting opened externally
+        assert_eq!(classify("#missing"), LinkTarget::MissingAnchor);
+        assert_eq!(classify("#missing.md"), LinkTarget::MissingAnchor);
+        assert_eq!(
+            classify("other.md"),
+            LinkTarget::Markdown(PathBuf::from("other.md"))
+        );
+        assert_eq!(classify("https://example.org/a.md"), LinkTarget::External);
+        assert_eq!(classify("https://example.org"), LinkTarget::External);
+    }
+
+    #[test]
+    fn links_by_scheme() {
+        let dir = tempfile::tempdir().unwrap();
+        let notes = dir.path().join("notes.md");
+        std::fs::write(&notes, "# Notes").unwrap();
+        let classify = |link: &str| classify_link(link, &HashMap::new(), &index_files());
+
+        // Never treated as paths, even when they look like markdown files
+        assert_eq!(classify("mailto:foo@bar.md"), LinkTarget::External);
+        assert_eq!(classify("tel:+1-555-0100"), LinkTarget::External);
+        assert_eq!(classify("http://example.org/a.md"), LinkTarget::External);
+        assert_eq!(classify("HTTPS://example.org/a.md"), LinkTarget::External);
+
+        let file_url = format!("file://{}", notes.display());
+        assert_eq!(classify(&file_url), LinkTarget::Markdown(notes.clone()));
+        let dir_url = format!("file://{}", dir.path().display());
+        assert_eq!(
+            classify(&dir_url),
+            LinkTarget::File(dir.path().canonicalize().unwrap())
+        );
+        assert_eq!(
+            classify("file:///no/such/image.png"),
+            LinkTarget::File(PathBuf::from("/no/such/image.png"))
+        );
+
+        // Relative paths and drive letters stay as paths
+        assert_eq!(
+            classify("docs/guide.md"),
+            LinkTarget::Markdown(PathBuf::from("docs/guide.md"))
+        );
+        assert_eq!(
+            classify("C:/docs/guide.md"),
+            LinkTarget::Markdown(PathBuf::from("C:/docs/guide.md"))
+        );
+    }
+
+    #[test]
+    fn absolute_paths_for_display() {
+        let cwd = std::env::current_dir().unwrap();
+        assert_eq!(
+            absolute_display(Path::new("docs/README.md")),
+            cwd.join("docs").join("README.md").display().to_string()
+        );
+        let already_absolute = cwd.join("README.md");
+        assert_eq!(
+            absolute_display(&already_absolute),
+            already_absolute.display().to_string()
+        );
+    }
+
+    #[test]
+    fn links_with_anchors() {
+        let dir = tempfile::tempdir().unwrap();
+        let notes = dir.path().join("notes.md");
+        std::fs::write(&notes, "# Notes").unwrap();
+        let classify = |link: &str| classify_link(link, &HashMap::new(), &index_files());
+
+        assert_eq!(split_anchor("other.md#usage"), ("other.md", Some("#usage")));
+        assert_eq!(split_anchor("other.md"), ("other.md", None));
+        assert_eq!(split_anchor("#usage"), ("", Some("#usage")));
+
+        // The anchor is left for after the file gets opened
+        assert_eq!(
+            classify("docs/guide.md#install"),
+            LinkTarget::Markdown(PathBuf::from("docs/guide.md"))
+        );
+        let file_url = format!("file://{}#notes", notes.display());
+        assert_eq!(classify(&file_url), LinkTarget::Markdown(notes));
+        assert_eq!(
+            classify("https://example.org/a.md#intro"),
+            LinkTarget::External
+        );
+    }
+
+    #[test]
+    fn dir_link_without_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("notes.md");
+        std::fs::write(&file, "# Notes").unwrap();
+
+        assert_eq!(resolve_dir_index(dir.path(), &index_files()), None);
+        assert_eq!(resolve_dir_index(dir.path(), &[]), None);
+        // Only directories get resolved
+        assert_eq!(resolve_dir_index(&file, &index_files()), None);
+    }
+
+    #[test]
+    fn links_with_spaces_and_parens() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        let pdf = dir.path().join("a (1).pdf");
+        let notes = dir.path().join("my notes (draft).md");
+        std::fs::write(&pdf, "").unwrap();
+        std::fs::write(&notes, "# Notes").unwrap();
+        let (pdf, notes) = (pdf.canonicalize().unwrap(), notes.canonicalize().unwrap());
+        // Reaching up from a sibling dir like `../a (1).pdf` does
+        let base = dir.path().join("sub").join("..").display().to_string();
+        let classify = |link: &str| classify_link(link, &HashMap::new(), &index_files());
+
+        for link in [
+            format!("{base}/a%20%281%29.pdf"),
+            format!("{base}/a%20(1).pdf"),
+            format!("{base}/a (1).pdf"),
+            format!("<{base}/a (1).pdf>"),
+            format!("file://{base}/a%20(1).pdf"),
+        ] {
+            assert_eq!(classify(&link), LinkTarget::File(pdf.clone()), "{link}");
+        }
+        for link in [
+            format!("{base}/my%20notes%20%28draft%29.md"),
+            format!("<{base}/my notes (draft).md#intro>"),
+        ] {
+            assert_eq!(
+                classify(&link),
+                LinkTarget::Markdown(notes.clone()),
+                "{link}"
+            );
+        }
+        assert_eq!(trim_link_delimiters("<a (1).pdf>"), "a (1).pdf");
+        assert_eq!(trim_link_delimiters("<a (1).pdf"), "<a (1).pdf");
+    }
+
+    #[test]
+    fn unreadable_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("notes.md");
+        std::fs::write(&file, "# Notes").unwrap();
+
+        assert_eq!(check_readable(&file), Ok(()));
+        let missing = dir.path().join("missing.md");
+        assert!(check_readable(&missing)
+            .unwrap_err()
+            .ends_with(": file not found"));
+        assert!(check_readable(dir.path())
+            .unwrap_err()
+            .ends_with(": is a directory"));
+    }
+
+    #[test]
+    fn fence_info_strings() {
+        assert_eq!(fence_language("rust"), Some("rust"));
+        assert_eq!(fence_language("rust,ignore"), Some("rust"));
+        assert_eq!(fence_language("{.python .numberLines}"), Some("python"));
+        assert_eq!(fence_language("{python}"), Some("python"));
+        assert_eq!(fence_language("  toml title=\"Cargo.toml\""), Some("toml"));
+        assert_eq!(fence_language("RS"), Some("rust"));
+        assert_eq!(fence_language("sh"), Some("bash"));
+        assert_eq!(fence_language("{}"), None);
+        assert_eq!(fence_language(""), None);
+    }
+
+    #[test]
+    fn highlighting_fence_variants() {
+        let to_html = |info: &str| {
+            let md = format!("```{info}\nfn main() {{}}\n```\n");
+            let html = markdown_to_html(&md, Theme::light_default().code_highlighter, false, false);
+            // Skip past the `<code>` tag since its class keeps the original info string
+            let (_, code) = html.split_once("<code").unwrap();
+            let (_, code) = code.split_once('>').unwrap();
+            code.to_owned()
+        };
+        let rust = to_html("rust");
+        for info in ["rs", "rust,ignore", "{.rust .numberLines}", "rust no_run"] {
+            assert_eq!(to_html(info), rust, "{info}");
+        }
+
+        // Unknown languages still come out as (unhighlighted) code
+        let unknown = to_html("not-a-language");
+        assert_ne!(unknown, rust);
+        assert!(unknown.contains("fn main() {}"), "{unknown}");
+    }
+}
@@ -0,0 +1,70 @@
+//! Visualizing whitespace within code blocks
+//!
+//! Markers are only ever drawn over the text, so the underlying text (and what gets copied) is left
+//! untouched
+
+use std::ops::Range;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    Space,
+    Tab,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Marker {
+    pub kind: Kind,
+    /// Byte range of the whitespace character within the line
+    pub range: Range<usize>,
+    /// Whether this is part of the whitespace at the end of the line
+    pub trailing: bool,
+}
+
+/// Finds all of the spaces and tabs within a single line of text
+pub fn markers(line: &str) -> Vec<Marker> {
+    let trailing_start = line.trim_end_matches([' ', '\t']).len();
+    line.char_indices()
+        .filter_map(|(i, c)| {
+            let kind = match c {
+                ' ' => Kind::Space,
+                '\t' => Kind::Tab,
+                _ => return None,
+            };
+            Some(Marker {
+                kind,
+                range: i..i + c.len_utf8(),
+                trailing: i >= trailing_start,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trailing_whitespace() {
+        let found: Vec<_> = markers("\tlet x = 1; \t ")
+            .into_iter()
+            .map(|Marker { kind, trailing, .. }| (kind, trailing))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (Kind::Tab, false),
+                (Kind::Space, false),
+                (Kind::Space, false),
+                (Kind::Space, false),
+                (Kind::Space, true),
+                (Kind::Tab, true),
+                (Kind::Space, true),
+            ]
+        );
+
+        assert!(markers("fn main() {}")
+            .iter()
+            .all(|marker| !marker.trailing));
+        assert!(markers("").is_empty());
+    }
+}
@@ -0,0 +1,97 @@
+//! Remembering the window's size and position between launches (`remember-window`)
+//!
+//! The geometry gets saved to a small file in the cache directory when the window closes and is
+//! used on the next launch unless a size or position is set explicitly
+
+use std::fs;
+use std::path::PathBuf;
+
+use crate::opts::{Position, Size};
+
+use anyhow::Context;
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug, Default, PartialEq)]
+pub struct WindowState {
+    pub position: Option<Position>,
+    pub size: Option<Size>,
+}
+
+impl WindowState {
+    fn path() -> anyhow::Result<PathBuf> {
+        let cache_dir = dirs::cache_dir().context("Failed to find the cache directory")?;
+        Ok(cache_dir.join("inlyne").join("window.toml"))
+    }
+
+    /// The last saved state, if there is one
+    pub fn load() -> Option<Self> {
+        let path = Self::path().ok()?;
+        let contents = fs::read_to_string(path).ok()?;
+        match Self::load_from_str(&contents) {
+            Ok(state) => Some(state),
+            Err(err) => {
+                tracing::warn!("Ignoring invalid saved window state: {err}");
+                None
+            }
+        }
+    }
+
+    fn load_from_str(s: &str) -> anyhow::Result<Self> {
+        Ok(toml::from_str(s)?)
+    }
+
+    pub fn save(&self) {
+        let save = || -> anyhow::Result<()> {
+            let path = Self::path()?;
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&path, self.to_toml())
+                .with_context(|| format!("Failed writing to {}", path.display()))
+        };
+        if let Err(err) = save() {
+            tracing::warn!("Failed saving the window's state: {err}");
+        }
+    }
+
+    fn to_toml(&self) -> String {
+        let mut out = String::new();
+        if let Some(Position { x, y }) = &self.position {
+            out.push_str(&format!("position = {{ x = {x}, y = {y} }}\n"));
+        }
+        if let Some(Size { width, height }) = &self.size {
+            out.push_str(&format!(
+                "size = {{ width = {width}, height = {height} }}\n"
+            ));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let state = WindowState {
+            position: Some(Position { x: -20, y: 40 }),
+            size: Some(Size {
+                width: 800,
+                height: 600,
+            }),
+        };
+        assert_eq!(WindowState::load_from_str(&state.to_toml()).unwrap(), state);
+
+        // Some platforms can't tell us where the window is
+        let size_only = WindowState {
+            position: None,
+            ..state
+        };
+        assert_eq!(
+            WindowState::load_from_str(&size_only.to_toml()).unwrap(),
+            size_only
+        );
+        assert!(WindowState::load_from_str("size = 5").is_err());
+    }
+}